@@ -0,0 +1,155 @@
+//! Recipient address parsing at the API boundary
+//!
+//! Recipients may be sent as a hex string or as a JSON byte array. Hex that
+//! mixes upper and lower case is taken to be EIP-55 checksummed and must
+//! match its checksum; all-lowercase or all-uppercase hex carries no
+//! checksum and is only length-checked. Byte arrays are length-checked.
+
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Length of an EVM address in bytes
+pub const ADDRESS_BYTES: usize = 20;
+
+/// Why a recipient address was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    #[error("Address is {got} bytes, expected {ADDRESS_BYTES}")]
+    WrongLength { got: usize },
+    #[error("Address {0} is not valid hex")]
+    InvalidHex(String),
+    #[error("Address {given} fails its EIP-55 checksum, expected {expected}")]
+    BadChecksum { given: String, expected: String },
+}
+
+/// Parse a hex address, verifying its checksum if it has one
+pub fn parse_address(value: &str) -> Result<Address, AddressError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if digits.len() != ADDRESS_BYTES * 2 {
+        // Odd lengths can't be whole bytes; report the width they'd round up to
+        return Err(AddressError::WrongLength {
+            got: digits.len().div_ceil(2),
+        });
+    }
+    let bytes = hex::decode(digits).map_err(|_| AddressError::InvalidHex(value.to_string()))?;
+    let address = Address::from_slice(&bytes);
+
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    if has_upper && has_lower {
+        let expected = to_checksum(&address, None);
+        if expected[2..] != *digits {
+            return Err(AddressError::BadChecksum {
+                given: value.to_string(),
+                expected,
+            });
+        }
+    }
+    Ok(address)
+}
+
+/// Take an address from raw bytes, checking the length
+pub fn address_from_bytes(bytes: &[u8]) -> Result<Address, AddressError> {
+    if bytes.len() != ADDRESS_BYTES {
+        return Err(AddressError::WrongLength { got: bytes.len() });
+    }
+    Ok(Address::from_slice(bytes))
+}
+
+/// A recipient address validated when the request is parsed
+///
+/// Serialized as checksummed hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Recipient(pub Address);
+
+impl Serialize for Recipient {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_checksum(&self.0, None))
+    }
+}
+
+impl<'de> Deserialize<'de> for Recipient {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RecipientVisitor)
+    }
+}
+
+struct RecipientVisitor;
+
+impl<'de> Visitor<'de> for RecipientVisitor {
+    type Value = Recipient;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a hex address or an array of {ADDRESS_BYTES} bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Recipient, E> {
+        parse_address(value).map(Recipient).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Recipient, E> {
+        address_from_bytes(value).map(Recipient).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Recipient, A::Error> {
+        let mut bytes = Vec::with_capacity(ADDRESS_BYTES);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_bad_checksum_rejected() {
+        let address = parse_address(CHECKSUMMED).unwrap();
+        assert_eq!(to_checksum(&address, None), CHECKSUMMED);
+
+        // No checksum to check without mixed case
+        assert_eq!(parse_address(&CHECKSUMMED.to_lowercase()).unwrap(), address);
+
+        // One letter's case flipped
+        let mistyped = CHECKSUMMED.replace("aAeb", "aaeb");
+        assert_eq!(
+            parse_address(&mistyped).unwrap_err(),
+            AddressError::BadChecksum {
+                given: mistyped.clone(),
+                expected: CHECKSUMMED.to_string(),
+            }
+        );
+
+        let err = serde_json::from_str::<Recipient>(&format!("\"{mistyped}\"")).unwrap_err();
+        assert!(err.to_string().contains("EIP-55"));
+    }
+
+    #[test]
+    fn test_wrong_length_rejected() {
+        assert_eq!(
+            parse_address(&CHECKSUMMED[..40]).unwrap_err(),
+            AddressError::WrongLength { got: 19 }
+        );
+        assert_eq!(
+            parse_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00").unwrap_err(),
+            AddressError::WrongLength { got: 21 }
+        );
+        assert!(matches!(
+            parse_address("0xzzaeb6053f3e94c9b9a09f33669435e7ef1beaed"),
+            Err(AddressError::InvalidHex(_))
+        ));
+
+        // Byte arrays are length-checked too
+        let bytes: Recipient = serde_json::from_str(&format!("{:?}", [0x33u8; 20])).unwrap();
+        assert_eq!(bytes.0, Address::repeat_byte(0x33));
+        let err = serde_json::from_str::<Recipient>(&format!("{:?}", [0x33u8; 32])).unwrap_err();
+        assert!(err.to_string().contains("32 bytes, expected 20"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Replay-resistant authentication for admin mutations
+//!
+//! The operator fetches a single-use nonce from `/admin/challenge`, then
+//! signs `"{METHOD} {path}\n{nonce}\n{timestamp}\n{keccak(body)}"` with the
+//! configured admin key (EIP-191). Each nonce is accepted at most once and
+//! only while fresh, so a captured request can't be replayed.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use ethers::types::{Address, Signature};
+use ethers::utils::keccak256;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::AppState;
+
+/// Header carrying the challenge nonce
+pub const NONCE_HEADER: &str = "x-admin-nonce";
+/// Header carrying the unix timestamp the request was signed at
+pub const TIMESTAMP_HEADER: &str = "x-admin-timestamp";
+/// Header carrying the hex EIP-191 signature
+pub const SIGNATURE_HEADER: &str = "x-admin-signature";
+
+/// Default lifetime of an issued challenge
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(60);
+
+/// Cap on unused challenges, since issuing them is unauthenticated
+const MAX_OUTSTANDING_CHALLENGES: usize = 1024;
+
+/// Largest admin request body that will be buffered for signing
+const MAX_ADMIN_BODY_BYTES: usize = 64 * 1024;
+
+/// Issues and redeems admin challenges
+pub struct AdminAuth {
+    /// Address whose signatures are accepted; `None` disables admin mutations
+    operator: Option<Address>,
+    ttl: Duration,
+    /// Unused nonces and when they were issued
+    issued: Mutex<HashMap<String, Instant>>,
+}
+
+impl AdminAuth {
+    pub fn new(operator: Option<Address>, ttl: Duration) -> Self {
+        Self {
+            operator,
+            ttl,
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issue a fresh nonce, or `None` if too many are outstanding
+    pub fn issue(&self) -> Option<String> {
+        let mut issued = self.issued.lock().unwrap();
+        issued.retain(|_, at| at.elapsed() <= self.ttl);
+        if issued.len() >= MAX_OUTSTANDING_CHALLENGES {
+            return None;
+        }
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let nonce = hex::encode(bytes);
+        issued.insert(nonce.clone(), Instant::now());
+        Some(nonce)
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Check a signed request, consuming its nonce on success
+    fn verify(
+        &self,
+        method: &Method,
+        path: &str,
+        nonce: &str,
+        timestamp: u64,
+        signature: &Signature,
+        body: &[u8],
+    ) -> Result<(), &'static str> {
+        let operator = self.operator.ok_or("Admin mutations are disabled")?;
+
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        if now.abs_diff(timestamp) > self.ttl.as_secs() {
+            return Err("Stale admin request timestamp");
+        }
+
+        let message = signing_message(method, path, nonce, timestamp, body);
+        signature
+            .verify(message, operator)
+            .map_err(|_| "Invalid admin signature")?;
+
+        // Removing the nonce makes it single-use, even under concurrent requests
+        let issued_at = self
+            .issued
+            .lock()
+            .unwrap()
+            .remove(nonce)
+            .ok_or("Unknown or already used nonce")?;
+        if issued_at.elapsed() > self.ttl {
+            return Err("Expired nonce");
+        }
+        Ok(())
+    }
+}
+
+/// Message the operator signs for an admin request
+pub fn signing_message(
+    method: &Method,
+    path: &str,
+    nonce: &str,
+    timestamp: u64,
+    body: &[u8],
+) -> String {
+    format!(
+        "{} {}\n{}\n{}\n0x{}",
+        method,
+        path,
+        nonce,
+        timestamp,
+        hex::encode(keccak256(body))
+    )
+}
+
+/// Require a signed challenge on every non-GET request under `/admin/`
+pub async fn require_challenge(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let is_mutation = request.uri().path().starts_with("/admin/")
+        && !matches!(*request.method(), Method::GET | Method::HEAD);
+    if !is_mutation {
+        return Ok(next.run(request).await);
+    }
+
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let unauthorized = |reason| (StatusCode::UNAUTHORIZED, reason);
+
+    let nonce = header(NONCE_HEADER).ok_or(unauthorized("Missing admin nonce"))?;
+    let timestamp = header(TIMESTAMP_HEADER)
+        .and_then(|v| v.parse::<u64>().ok())
+        .ok_or(unauthorized("Missing admin timestamp"))?;
+    let signature = header(SIGNATURE_HEADER)
+        .and_then(|v| v.parse::<Signature>().ok())
+        .ok_or(unauthorized("Missing admin signature"))?;
+
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, MAX_ADMIN_BODY_BYTES)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Admin request body too large",
+            )
+        })?;
+
+    state
+        .admin
+        .verify(
+            &parts.method,
+            parts.uri.path(),
+            &nonce,
+            timestamp,
+            &signature,
+            &body,
+        )
+        .map_err(unauthorized)?;
+
+    Ok(next.run(Request::from_parts(parts, Body::from(body))).await)
+}
@@ -0,0 +1,67 @@
+//! Request rate limits for public endpoints
+//!
+//! A token bucket shared by every caller of an endpoint: it holds up to a
+//! minute's allowance and refills continuously, so short bursts pass while
+//! sustained traffic is held to the configured rate.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket allowing `per_minute` requests a minute
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    /// Tokens left and when they were last topped up
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            bucket: Mutex::new((per_minute as f64, Instant::now())),
+        }
+    }
+
+    /// Take a token if one is left
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        let (tokens, topped_up) = &mut *bucket;
+        let refill = now.saturating_duration_since(*topped_up).as_secs_f64()
+            / Duration::from_secs(60).as_secs_f64()
+            * self.per_minute as f64;
+        *tokens = (*tokens + refill).min(self.per_minute as f64);
+        *topped_up = now;
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_drains_then_refills() {
+        let limiter = RateLimiter::per_minute(2);
+        let now = Instant::now();
+        assert!(limiter.try_acquire_at(now));
+        assert!(limiter.try_acquire_at(now));
+        assert!(!limiter.try_acquire_at(now));
+
+        // Half a minute earns one more request, never more than the allowance
+        assert!(limiter.try_acquire_at(now + Duration::from_secs(30)));
+        assert!(!limiter.try_acquire_at(now + Duration::from_secs(30)));
+        let later = now + Duration::from_secs(600);
+        assert!(limiter.try_acquire_at(later));
+        assert!(limiter.try_acquire_at(later));
+        assert!(!limiter.try_acquire_at(later));
+    }
+}
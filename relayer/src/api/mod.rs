@@ -0,0 +1,1702 @@
+//! HTTP API for the relayer node
+//!
+//! Serves health/status probes, relay submission and fee quotes, standalone
+//! proving and batch aggregation, header queries, plus operator-facing admin
+//! endpoints.
+
+pub mod address;
+pub mod admin;
+pub mod limit;
+pub mod types;
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Path, Query, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::metrics::{HTTP_REQUESTS, HTTP_REQUEST_DURATION, RELAY_REQUESTS};
+use admin::AdminAuth;
+use limit::RateLimiter;
+use types::{
+    AcceptedRoot, AggregateResponse, ApiError, ChainStatus, ChainSummary, HeadersResponse,
+    ProveRequest, ProveResponse, QuoteRequest, QuoteResponse, RelayRequest, RelayStatusResponse,
+    ResyncResponse, RootsResponse, TxStatusResponse, VerifyInclusionRequest,
+    VerifyInclusionResponse, VerifyReceiptRequest,
+};
+
+use crate::diagnostics::StartupReport;
+use crate::error::RelayerError;
+use crate::keys::{ActiveSigner, Rotation, RotationError};
+use crate::light_client::{FinalityHandle, ReorgRecord, ResyncHandle, StoredHeader};
+use crate::merkle::roots::PoolRoots;
+
+use crate::p2p::fetch::ProofIndex;
+use crate::p2p::peers::{PeerLatency, PeerTable};
+use crate::p2p::NodeIdentity;
+use crate::prover::circuits::{CircuitRegistry, MAX_SERVED_ARTIFACT_BYTES};
+use crate::prover::{ProofRequest, ProverError, ProverService};
+use crate::quote::{QuoteBook, QuoteError};
+use crate::relay::{
+    validate_relay_request, RelayContext, RelayRejection, RelayStatus, RelayTracker,
+};
+use crate::submitter::WithdrawalSubmitter;
+
+/// Shared state available to every handler
+#[derive(Clone)]
+pub struct AppState {
+    /// Identity of the local P2P node, kept current by the swarm
+    pub identity: Arc<RwLock<NodeIdentity>>,
+    /// Circuit artifacts loaded by the prover
+    pub circuits: Arc<CircuitRegistry>,
+    /// Challenge issuer guarding admin mutations
+    pub admin: Arc<AdminAuth>,
+    /// Status of accepted relays
+    pub relays: Arc<RelayTracker>,
+    /// Header views of the tracked chains, keyed by chain ID
+    pub chains: Arc<HashMap<u64, FinalityHandle>>,
+    /// Signed quotes and the relays bound to them
+    pub quotes: Arc<QuoteBook>,
+    /// Prover answering relays, for its liveness and load
+    pub prover: Arc<ProverService>,
+    /// Readiness report gathered at startup
+    pub diagnostics: Arc<StartupReport>,
+    /// When the node booted, for uptime
+    pub started: Instant,
+    /// Recent roots of each watched pool, keyed by chain ID and pool
+    pub roots: Arc<HashMap<(u64, ethers::types::Address), Arc<RwLock<PoolRoots>>>>,
+    /// Header store resync for each tracked chain, keyed by chain ID
+    pub resync: Arc<HashMap<u64, ResyncHandle>>,
+    /// State relay requests are validated against
+    pub validation: RelayContext,
+    /// Prover serving `/prove` (unset: proving isn't offered on its own)
+    pub proofs: Option<PublicProver>,
+    /// Latency of connected P2P peers
+    pub peers: Arc<PeerTable>,
+    /// Proofs of validated relays, served to P2P peers
+    pub served_proofs: Arc<ProofIndex>,
+    /// Transaction signer used for submissions
+    pub signer: Arc<ActiveSigner>,
+    /// Submits accepted relays (unset: they stay pending)
+    pub withdrawals: Option<Arc<WithdrawalSubmitter>>,
+    /// Hands accepted relays to the P2P node to gossip (unset: not gossiped)
+    pub gossip: Option<tokio::sync::mpsc::Sender<(String, RelayRequest)>>,
+}
+
+/// Prover behind `/prove`, with the rate limit shared by all its callers
+#[derive(Clone)]
+pub struct PublicProver {
+    pub prover: Arc<ProverService>,
+    pub limit: Arc<RateLimiter>,
+}
+
+/// Default hold time for `/relay/:id/wait`
+const DEFAULT_RELAY_WAIT: Duration = Duration::from_secs(30);
+
+/// Longest a client may hold a `/relay/:id/wait` connection open
+const MAX_RELAY_WAIT: Duration = Duration::from_secs(60);
+
+/// Default number of reorgs returned by `/admin/reorgs/:chain_id`
+const DEFAULT_REORG_LIMIT: usize = 50;
+
+/// Most reorgs a single `/admin/reorgs/:chain_id` request may return
+const MAX_REORG_LIMIT: usize = 500;
+
+/// Longest line a `/prove/aggregate/:chain_id` body may hold one member in
+const MAX_BATCH_MEMBER_BYTES: usize = 1024 * 1024;
+
+/// Build the API router
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/status", get(status_handler))
+        .route("/relay", post(relay_handler))
+        .route("/relay/:id", get(relay_status_handler))
+        .route("/relay/:id/wait", get(relay_wait_handler))
+        .route("/tx/:id", get(tx_status_handler))
+        .route("/quote", post(quote_handler))
+        .route("/prove", post(prove_handler))
+        .route("/prove/aggregate/:chain_id", post(aggregate_handler))
+        .route("/chains", get(chains_handler))
+        .route("/headers/:chain_id", get(headers_handler))
+        .route("/headers/:chain_id/:block_number", get(header_handler))
+        .route("/verify_inclusion", post(verify_inclusion_handler))
+        .route("/verify_receipt", post(verify_receipt_handler))
+        .route("/roots/:chain_id", get(roots_handler))
+        .route("/admin/identity", get(identity_handler))
+        .route("/admin/peers", get(peers_handler))
+        .route("/admin/challenge", get(challenge_handler))
+        .route("/admin/resync/:chain_id", post(resync_handler))
+        .route("/admin/reorgs/:chain_id", get(reorgs_handler))
+        .route("/admin/rotate-key", post(rotate_key_handler))
+        .route("/circuits/:proof_type/:version/vk", get(circuit_vk_handler))
+        .route(
+            "/circuits/:proof_type/:version/bytecode",
+            get(circuit_bytecode_handler),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin::require_challenge,
+        ))
+        .layer(middleware::from_fn(track_metrics))
+        .with_state(state)
+}
+
+/// Record request count and latency against the matched route template
+///
+/// Unmatched requests share a single label so arbitrary URLs can't grow
+/// the label set.
+async fn track_metrics(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&route])
+        .observe(start.elapsed().as_secs_f64());
+    HTTP_REQUESTS
+        .with_label_values(&[&route, response.status().as_str()])
+        .inc();
+
+    response
+}
+
+/// Blocks a chain may trail its head by and still count as synced
+pub const MAX_SYNC_LAG: u64 = 3;
+
+/// Ready only once the prover is up and every chain has caught up
+async fn health_handler(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    if !state.prover.worker_health().is_alive() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "prover worker down");
+    }
+    if !all_synced(&state) {
+        return (StatusCode::SERVICE_UNAVAILABLE, "syncing");
+    }
+    (StatusCode::OK, "ready")
+}
+
+/// Whether every tracked chain is within `MAX_SYNC_LAG` of its head
+fn all_synced(state: &AppState) -> bool {
+    state
+        .chains
+        .values()
+        .all(|chain| chain.is_synced(MAX_SYNC_LAG))
+}
+
+async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let peer_id = state.identity.read().unwrap().peer_id.clone();
+
+    let mut chains: Vec<ChainStatus> = state
+        .chains
+        .iter()
+        .map(|(chain_id, chain)| {
+            let head = chain.head();
+            let finalized = chain.finalized();
+            let finalized_timestamp = chain.header(finalized).map(|h| h.timestamp);
+            ChainStatus {
+                chain_id: *chain_id,
+                head: head.as_ref().map(|h| h.block_number),
+                head_timestamp: head.as_ref().map(|h| h.timestamp),
+                head_age_secs: head.as_ref().map(|h| age_secs(h.timestamp)),
+                finalized,
+                finalized_timestamp,
+                finalized_age_secs: finalized_timestamp.map(age_secs),
+                sync_lag: chain.sync_lag(),
+                synced: chain.is_synced(MAX_SYNC_LAG),
+            }
+        })
+        .collect();
+    chains.sort_by_key(|chain| chain.chain_id);
+    let synced = all_synced(&state);
+
+    Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "status": "running",
+        "sync": if synced { "ready" } else { "syncing" },
+        "chains_synced": synced,
+        "uptime": state.started.elapsed().as_secs(),
+        "peer_id": peer_id,
+        "peer_count": state.peers.connected_count(),
+        "signer": state.signer.address(),
+        "chains": chains,
+        "diagnostics": state.diagnostics.as_ref(),
+        "prover": state.prover.stats(),
+    }))
+}
+
+/// Seconds elapsed since a block timestamp (zero for future timestamps)
+fn age_secs(timestamp: u64) -> u64 {
+    (chrono::Utc::now().timestamp() as u64).saturating_sub(timestamp)
+}
+
+/// Accept a relay request and submit its withdrawal
+///
+/// The request must pass validation, which checks its root against the
+/// finalized pool roots before any gas is spent; a quote sent with it is
+/// verified and binds the relay's fee. The withdrawal is submitted in the
+/// background once its nullifier is reserved; poll `/relay/:id` or wait on
+/// `/relay/:id/wait` for the outcome. Failures carry a reason code.
+async fn relay_handler(
+    State(state): State<AppState>,
+    Json(request): Json<RelayRequest>,
+) -> Result<Json<RelayStatusResponse>, (StatusCode, Json<ApiError>)> {
+    validate_relay_request(&request, &state.validation, chrono::Utc::now().timestamp())
+        .await
+        .map_err(|e| {
+            RELAY_REQUESTS.with_label_values(&["api", "rejected"]).inc();
+            // Requests for pools this relayer doesn't serve are the
+            // caller's to fix, though not held against gossiping peers
+            let status = if e.is_sender_fault() || matches!(e, RelayRejection::PoolNotServed { .. })
+            {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, Json(ApiError::new(e.code(), &e)))
+        })?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    if let Some(quote) = &request.quote {
+        state
+            .quotes
+            .honor(quote, request.chain_id, &id)
+            .await
+            .map_err(|e| {
+                RELAY_REQUESTS.with_label_values(&["api", "rejected"]).inc();
+                let status = match e {
+                    QuoteError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                    _ => StatusCode::BAD_REQUEST,
+                };
+                (status, Json(ApiError::new(e.code(), &e)))
+            })?;
+    }
+
+    RELAY_REQUESTS.with_label_values(&["api", "accepted"]).inc();
+    tracing::debug!(id = %id, chain_id = request.chain_id, "Relay request accepted");
+    state
+        .served_proofs
+        .insert(request.proof.clone(), request.public_inputs.clone());
+    if let Some(gossip) = &state.gossip {
+        let accepted = (id.clone(), request.clone());
+        if let Err(e) = crate::channel::send(gossip, "relay_gossip", accepted).await {
+            tracing::warn!(id = %id, error = %e, "Accepted relay not gossiped");
+        }
+    }
+
+    let Some(withdrawals) = &state.withdrawals else {
+        state.relays.register(id.clone());
+        return Ok(Json(RelayStatusResponse::new(id, RelayStatus::Pending)));
+    };
+    let store = state.validation.store.clone();
+    if let Err(e) = withdrawals.dispatch(&id, request, store).await {
+        tracing::warn!(id = %id, error = %e, "Relay submission failed");
+        // Gas is only priced once submission runs in the background; a relay
+        // its quote can't cover fails there and hands the quote back
+        let status = match e.code() {
+            "shutting_down" => StatusCode::SERVICE_UNAVAILABLE,
+            "submission_failed" => StatusCode::BAD_GATEWAY,
+            "nullifier_spent" => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        return Err((status, Json(ApiError::new(e.code(), &e))));
+    }
+
+    let status = state.relays.status(&id).unwrap_or(RelayStatus::Pending);
+    Ok(Json(RelayStatusResponse::new(id, status)))
+}
+
+/// Generate a proof for the caller to submit themselves
+///
+/// Goes through the prover queue like any other request, so queue limits
+/// apply; nothing is relayed and no fee is charged. Requests beyond the
+/// configured rate are turned away before they reach the queue, so callers
+/// can't crowd out relays. The request holds the caller's secrets, so only
+/// its public summary is ever logged.
+async fn prove_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ProveRequest>,
+) -> Result<Json<ProveResponse>, (StatusCode, String)> {
+    let prover = public_prover(&state)?;
+    let proof = prover
+        .generate(request.request, request.chain_id)
+        .await
+        .map_err(prover_rejection)?;
+    tracing::debug!(
+        proof_type = %proof.proof_type,
+        chain_id = request.chain_id,
+        generation_time_ms = proof.generation_time_ms,
+        "Proof generated for client"
+    );
+
+    Ok(Json(proof.into()))
+}
+
+/// Aggregate a batch of proofs for the caller
+///
+/// The body is newline-delimited JSON, one `ProofRequest` per line. Members
+/// are parsed only as the prover pulls them, so the batch is never held
+/// whole; the prover's aggregation budget bounds how much of it is. The
+/// batch takes one slot of the `/prove` rate limit, and its members go
+/// through the prover queue like any other request.
+async fn aggregate_handler(
+    State(state): State<AppState>,
+    Path(chain_id): Path<u64>,
+    body: Body,
+) -> Result<Json<AggregateResponse>, (StatusCode, String)> {
+    let prover = public_prover(&state)?;
+    let aggregated = prover
+        .aggregate(batch_members(body), chain_id)
+        .await
+        .map_err(prover_rejection)?;
+    tracing::debug!(
+        chain_id = chain_id,
+        members = aggregated.members,
+        peak_bytes = aggregated.peak_working_set,
+        "Batch aggregated for client"
+    );
+
+    Ok(Json(AggregateResponse {
+        proof: aggregated.proof.into(),
+        members: aggregated.members,
+    }))
+}
+
+/// Prover behind the public proving endpoints, if offered, with a slot of
+/// its rate limit taken
+fn public_prover(state: &AppState) -> Result<&Arc<ProverService>, (StatusCode, String)> {
+    let PublicProver { prover, limit } = state
+        .proofs
+        .as_ref()
+        .filter(|proofs| proofs.prover.is_enabled())
+        .ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Proving is not offered by this relayer".to_string(),
+        ))?;
+    if !limit.try_acquire() {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Proof request rate limit reached".to_string(),
+        ));
+    }
+    Ok(prover)
+}
+
+/// Status a failed proving request is answered with
+fn prover_rejection(e: RelayerError) -> (StatusCode, String) {
+    let status = match &e {
+        RelayerError::Prover(ProverError::QueueFull) => StatusCode::TOO_MANY_REQUESTS,
+        RelayerError::Prover(ProverError::ShuttingDown) => StatusCode::SERVICE_UNAVAILABLE,
+        RelayerError::Prover(
+            ProverError::Timeout { .. } | ProverError::Failed { .. } | ProverError::Dropped,
+        ) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, e.to_string())
+}
+
+/// Proof requests read one line at a time from a newline-delimited JSON body
+fn batch_members(body: Body) -> impl Stream<Item = Result<ProofRequest, RelayerError>> {
+    let malformed = |reason: String| RelayerError::Validation(anyhow::anyhow!(reason));
+    let parse = move |line: &[u8]| {
+        serde_json::from_slice::<ProofRequest>(line)
+            .map_err(|e| malformed(format!("Malformed batch member: {}", e)))
+    };
+    let blank = |line: &[u8]| line.iter().all(|byte| byte.is_ascii_whitespace());
+    let state = (body.into_data_stream(), Vec::new(), false);
+    futures::stream::unfold(
+        state,
+        move |(mut chunks, mut buffer, mut done)| async move {
+            loop {
+                if let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    if !blank(&line) {
+                        return Some((parse(&line), (chunks, buffer, done)));
+                    }
+                    continue;
+                }
+                if done {
+                    // The last member needn't end in a newline
+                    let line = std::mem::take(&mut buffer);
+                    if blank(&line) {
+                        return None;
+                    }
+                    return Some((parse(&line), (chunks, buffer, done)));
+                }
+                if buffer.len() > MAX_BATCH_MEMBER_BYTES {
+                    let e = malformed(format!(
+                        "Batch member is over {} bytes",
+                        MAX_BATCH_MEMBER_BYTES
+                    ));
+                    return Some((Err(e), (chunks, Vec::new(), true)));
+                }
+                match chunks.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => {
+                        let e = malformed(format!("Failed to read batch: {}", e));
+                        return Some((Err(e), (chunks, Vec::new(), true)));
+                    }
+                    None => done = true,
+                }
+            }
+        },
+    )
+}
+
+/// Current status of a relay, without waiting
+async fn relay_status_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<RelayStatusResponse>, StatusCode> {
+    let status = state.relays.status(&id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(RelayStatusResponse::new(id, status)))
+}
+
+/// Outcome of a relay's withdrawal transaction, until it expires
+async fn tx_status_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TxStatusResponse>, StatusCode> {
+    let status = state
+        .withdrawals
+        .as_ref()
+        .and_then(|withdrawals| withdrawals.tx_status(&id))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(TxStatusResponse { id, status }))
+}
+
+#[derive(serde::Deserialize)]
+struct WaitParams {
+    /// Seconds to wait for a terminal status
+    timeout: Option<u64>,
+}
+
+/// Hold the request until the relay finishes or the timeout elapses
+async fn relay_wait_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<WaitParams>,
+) -> Result<Json<RelayStatusResponse>, StatusCode> {
+    let timeout = params
+        .timeout
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_RELAY_WAIT)
+        .min(MAX_RELAY_WAIT);
+
+    let status = state
+        .relays
+        .wait(&id, timeout)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(RelayStatusResponse::new(id, status)))
+}
+
+/// Fee (0.01 ETH in wei) required of relay requests without a quote
+pub(crate) const FLAT_FEE_WEI: u64 = 10_000_000_000_000_000;
+
+/// Signed fee quote the relayer will honor on `/relay`
+///
+/// Priced from the chain's current fees; the quote is valid for the quote
+/// validity from now. Refused once a shutdown drain has started.
+async fn quote_handler(
+    State(state): State<AppState>,
+    Json(request): Json<QuoteRequest>,
+) -> Result<Json<QuoteResponse>, (StatusCode, Json<ApiError>)> {
+    // A quote issued now could not be honored before the process exits
+    let quoted = if state.withdrawals.as_ref().is_some_and(|w| w.is_draining()) {
+        Err(QuoteError::ShuttingDown)
+    } else {
+        state.quotes.quote(request.chain_id).await
+    };
+    quoted.map(Json).map_err(|e| {
+        let status = match e {
+            QuoteError::UnknownChain(_) => StatusCode::NOT_FOUND,
+            QuoteError::GasPrice(_) | QuoteError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(ApiError::new(e.code(), &e)))
+    })
+}
+
+/// Head and finalized block of every tracked chain, by chain ID
+async fn chains_handler(State(state): State<AppState>) -> Json<Vec<ChainSummary>> {
+    let mut chains: Vec<ChainSummary> = state
+        .chains
+        .iter()
+        .map(|(chain_id, chain)| {
+            let head = chain.head();
+            ChainSummary {
+                chain_id: *chain_id,
+                head: head.as_ref().map(|h| h.block_number),
+                finalized: chain.finalized(),
+                head_hash: head.map(|h| h.block_hash),
+            }
+        })
+        .collect();
+    chains.sort_by_key(|chain| chain.chain_id);
+    Json(chains)
+}
+
+/// Latest and finalized headers for a chain
+async fn headers_handler(
+    State(state): State<AppState>,
+    Path(chain_id): Path<u64>,
+) -> Result<Json<HeadersResponse>, StatusCode> {
+    let chain = state.chains.get(&chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    let finalized_number = chain.finalized();
+    let head = chain.head();
+    let finalized = chain.header(finalized_number);
+
+    Ok(Json(HeadersResponse {
+        chain_id,
+        head_age_secs: head.as_ref().map(|h| age_secs(h.timestamp)),
+        finalized_age_secs: finalized.as_ref().map(|h| age_secs(h.timestamp)),
+        head,
+        finalized,
+        finalized_number,
+    }))
+}
+
+/// Stored header at a given height
+async fn header_handler(
+    State(state): State<AppState>,
+    Path((chain_id, block_number)): Path<(u64, u64)>,
+) -> Result<Json<StoredHeader>, StatusCode> {
+    state
+        .chains
+        .get(&chain_id)
+        .and_then(|chain| chain.header(block_number))
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(serde::Deserialize)]
+struct RootsParams {
+    /// Pool whose roots to list (the chain's default pool if unset)
+    pool: Option<ethers::types::Address>,
+}
+
+/// Pool roots the relayer currently accepts proofs against
+async fn roots_handler(
+    State(state): State<AppState>,
+    Path(chain_id): Path<u64>,
+    Query(params): Query<RootsParams>,
+) -> Result<Json<RootsResponse>, StatusCode> {
+    let pool = state
+        .validation
+        .pools
+        .get(&chain_id)
+        .and_then(|served| served.resolve(params.pool))
+        .and_then(|pool| state.roots.get(&(chain_id, pool)))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let finalized = state
+        .chains
+        .get(&chain_id)
+        .map_or(0, |chain| chain.finalized());
+
+    let roots = pool
+        .read()
+        .unwrap()
+        .roots()
+        .into_iter()
+        .map(|known| AcceptedRoot {
+            root: known.root,
+            block_number: known.block_number,
+            finalized: known.block_number <= finalized,
+        })
+        .collect();
+    Ok(Json(RootsResponse { chain_id, roots }))
+}
+
+/// Drop a chain's stored headers and reload them from its current head
+async fn resync_handler(
+    State(state): State<AppState>,
+    Path(chain_id): Path<u64>,
+) -> Result<Json<ResyncResponse>, (StatusCode, String)> {
+    let handle = state
+        .resync
+        .get(&chain_id)
+        .ok_or((StatusCode::NOT_FOUND, format!("Unknown chain {}", chain_id)))?;
+    let report = handle
+        .resync()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+    Ok(Json(ResyncResponse {
+        chain_id: report.chain_id,
+        headers_loaded: report.headers_loaded,
+        head: report.head,
+    }))
+}
+
+#[derive(serde::Deserialize)]
+struct ReorgParams {
+    /// Most recent reorgs to return
+    limit: Option<usize>,
+}
+
+/// Recorded reorgs on a chain, newest first
+async fn reorgs_handler(
+    State(state): State<AppState>,
+    Path(chain_id): Path<u64>,
+    Query(params): Query<ReorgParams>,
+) -> Result<Json<Vec<ReorgRecord>>, StatusCode> {
+    if !state.chains.contains_key(&chain_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_REORG_LIMIT)
+        .min(MAX_REORG_LIMIT);
+    state
+        .validation
+        .store
+        .reorgs(chain_id, limit)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Swap in a new transaction signer, loaded from the source in the body
+///
+/// Responds once the old signer's in-flight transactions have finished (or
+/// the drain timeout passed); the new signer is used from the moment it
+/// passes its checks.
+async fn rotate_key_handler(
+    State(state): State<AppState>,
+    Json(source): Json<crate::SignerConfig>,
+) -> Result<Json<Rotation>, (StatusCode, String)> {
+    let wallet = crate::keys::load_tx_signer(Some(&source), None)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    let rotation = state.signer.rotate(wallet).await.map_err(|e| {
+        let status = match e {
+            RotationError::Unchanged(_) | RotationError::InProgress => StatusCode::CONFLICT,
+            RotationError::Unfit { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, e.to_string())
+    })?;
+    Ok(Json(rotation))
+}
+
+/// Check a transaction inclusion proof against a stored header
+async fn verify_inclusion_handler(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyInclusionRequest>,
+) -> Result<Json<VerifyInclusionResponse>, StatusCode> {
+    let chain = state
+        .chains
+        .get(&request.chain_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(VerifyInclusionResponse {
+        included: chain.verify_inclusion(
+            request.block_hash,
+            request.tx_hash,
+            &request.proof,
+            &request.indices,
+        ),
+    }))
+}
+
+/// Check a receipt trie proof against a stored header's receipts root
+async fn verify_receipt_handler(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyReceiptRequest>,
+) -> Result<Json<VerifyInclusionResponse>, StatusCode> {
+    let chain = state
+        .chains
+        .get(&request.chain_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(VerifyInclusionResponse {
+        included: chain.verify_receipt_inclusion(
+            request.block_hash,
+            &request.receipt,
+            &request.proof,
+        ),
+    }))
+}
+
+/// Local peer identity, for configuring other nodes' bootstrap lists
+async fn identity_handler(State(state): State<AppState>) -> Json<NodeIdentity> {
+    Json(state.identity.read().unwrap().clone())
+}
+
+/// Connected peers with their latencies, fastest first
+async fn peers_handler(State(state): State<AppState>) -> Json<Vec<PeerLatency>> {
+    Json(state.peers.snapshot())
+}
+
+/// Single-use nonce for signing an admin mutation
+async fn challenge_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let nonce = state.admin.issue().ok_or(StatusCode::TOO_MANY_REQUESTS)?;
+
+    Ok(Json(serde_json::json!({
+        "nonce": nonce,
+        "expires_in_secs": state.admin.ttl().as_secs(),
+    })))
+}
+
+/// Verification key and integrity hashes for a loaded circuit
+async fn circuit_vk_handler(
+    State(state): State<AppState>,
+    Path((proof_type, version)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let circuit = state
+        .circuits
+        .get(&proof_type, &version)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "proof_type": circuit.proof_type,
+        "version": circuit.version,
+        "proof_system": circuit.proof_system,
+        "vk": format!("0x{}", hex::encode(&circuit.vk)),
+        "vk_hash": circuit.vk_hash,
+        "bytecode_hash": circuit.bytecode_hash,
+        "bytecode_size": circuit.bytecode_size,
+        "bytecode_available": circuit.bytecode.is_some(),
+    })))
+}
+
+/// Raw circuit bytecode, when small enough to serve
+async fn circuit_bytecode_handler(
+    State(state): State<AppState>,
+    Path((proof_type, version)): Path<(String, String)>,
+) -> Result<Vec<u8>, (StatusCode, String)> {
+    let circuit = state
+        .circuits
+        .get(&proof_type, &version)
+        .ok_or((StatusCode::NOT_FOUND, "Unknown circuit".to_string()))?;
+
+    match (&circuit.bytecode, circuit.bytecode_size) {
+        (Some(bytecode), _) => Ok(bytecode.clone()),
+        (None, Some(size)) => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Bytecode is {} bytes, above the {} byte serving limit",
+                size, MAX_SERVED_ARTIFACT_BYTES
+            ),
+        )),
+        (None, None) => Err((
+            StatusCode::NOT_FOUND,
+            "No bytecode loaded for this circuit".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use crate::submitter::TxStatus;
+    use crate::{CircuitConfig, P2PConfig};
+
+    fn test_identity() -> Arc<RwLock<NodeIdentity>> {
+        Arc::new(RwLock::new(NodeIdentity {
+            peer_id: "12D3KooWTest".to_string(),
+            public_key_fingerprint: "00".to_string(),
+            listen_addrs: vec![],
+            external_addrs: vec![],
+            protocols: vec![],
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }))
+    }
+
+    async fn test_validation() -> RelayContext {
+        crate::relay::validate::valid_relay_fixture(1).await.0
+    }
+
+    fn test_admin(operator: Option<ethers::types::Address>) -> Arc<AdminAuth> {
+        Arc::new(AdminAuth::new(operator, admin::DEFAULT_CHALLENGE_TTL))
+    }
+
+    /// State with nothing tracked or configured, for tests to override
+    async fn test_state() -> AppState {
+        // Relays are validated against the same quotes the API hands out
+        let validation = test_validation().await;
+        AppState {
+            identity: test_identity(),
+            circuits: Arc::new(CircuitRegistry::default()),
+            admin: test_admin(None),
+            relays: Arc::new(RelayTracker::default()),
+            chains: Arc::default(),
+            quotes: validation.quotes.clone(),
+            prover: Arc::new(ProverService::new(&crate::ProverConfig::placeholder()).unwrap()),
+            diagnostics: Arc::default(),
+            started: Instant::now(),
+            roots: Arc::default(),
+            resync: Arc::default(),
+            validation,
+            proofs: None,
+            peers: Arc::default(),
+            served_proofs: Arc::default(),
+            signer: crate::keys::rotation::test_signer(),
+            withdrawals: None,
+            gossip: None,
+        }
+    }
+
+    async fn get_json(app: Router, uri: &str) -> serde_json::Value {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identity_returns_local_peer_id() {
+        let config = P2PConfig {
+            listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(),
+            bootstrap_peers: vec![],
+            max_peers: 10,
+            identity_key_path: None,
+            slow_peer_rtt_ms: 500,
+            relay_dedup_window_secs: 600,
+            relay_dedup_capacity: 100_000,
+            compression: false,
+            compression_threshold_bytes: 1024,
+            reputation_threshold: -10,
+            reputation_interval_secs: 300,
+            dht_refresh_interval_secs: 300,
+        };
+        let node = crate::p2p::P2PNode::new(&config).await.unwrap();
+        let state = AppState {
+            identity: node.identity(),
+            peers: node.peers(),
+            served_proofs: node.proofs(),
+            ..test_state().await
+        };
+
+        let identity = get_json(router(state.clone()), "/admin/identity").await;
+        assert_eq!(identity["peer_id"], node.local_peer_id().to_string());
+        assert_eq!(identity["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!identity["public_key_fingerprint"]
+            .as_str()
+            .unwrap()
+            .is_empty());
+
+        // Nothing connected yet
+        let peers = get_json(router(state.clone()), "/admin/peers").await;
+        assert_eq!(peers, serde_json::json!([]));
+
+        let signer = state.signer.address();
+        let status = get_json(router(state), "/status").await;
+        assert_eq!(status["peer_id"], node.local_peer_id().to_string());
+        assert_eq!(status["signer"], serde_json::json!(signer));
+    }
+
+    #[tokio::test]
+    async fn test_health_request_counted_by_route() {
+        let state = test_state().await;
+        let counter = HTTP_REQUESTS.with_label_values(&["/health", "200"]);
+        let before = counter.get();
+
+        let response = router(state.clone())
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(counter.get() > before);
+
+        // Path parameters are reported by template, not raw URL
+        let templated =
+            HTTP_REQUESTS.with_label_values(&["/circuits/:proof_type/:version/vk", "404"]);
+        let before = templated.get();
+        router(state)
+            .oneshot(
+                Request::get("/circuits/withdrawal/v1/vk")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(templated.get() > before);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_vk_matches_loaded_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let vk_path = dir.path().join("vk");
+        std::fs::write(&vk_path, b"verification-key-bytes").unwrap();
+
+        let circuits = CircuitRegistry::load(&[CircuitConfig {
+            proof_type: "withdrawal".to_string(),
+            version: "v2".to_string(),
+            vk_path,
+            bytecode_path: None,
+            proof_system: crate::prover::ProofSystem::UltraHonk,
+        }])
+        .unwrap();
+        let loaded = circuits.get("withdrawal", "v2").unwrap();
+
+        let state = AppState {
+            circuits: Arc::new(circuits),
+            ..test_state().await
+        };
+
+        let body = get_json(router(state.clone()), "/circuits/withdrawal/v2/vk").await;
+        assert_eq!(
+            body["vk"],
+            format!("0x{}", hex::encode(b"verification-key-bytes"))
+        );
+        assert_eq!(
+            body["vk_hash"],
+            serde_json::to_value(loaded.vk_hash).unwrap()
+        );
+        assert_eq!(
+            loaded.vk_hash,
+            ethers::types::H256(ethers::utils::keccak256(b"verification-key-bytes"))
+        );
+        assert_eq!(body["bytecode_available"], false);
+        assert_eq!(body["proof_system"], "ultra_honk");
+
+        let response = router(state)
+            .oneshot(
+                Request::get("/circuits/withdrawal/v9/vk")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_head_age() {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let header = |block_number: u64, timestamp: u64| StoredHeader {
+            block_number,
+            block_hash: ethers::types::H256::from_low_u64_be(block_number),
+            parent_hash: ethers::types::H256::from_low_u64_be(block_number - 1),
+            state_root: Default::default(),
+            transactions_root: Default::default(),
+            receipts_root: Default::default(),
+            timestamp,
+        };
+        let state = AppState {
+            chains: Arc::new(HashMap::from([(
+                1,
+                FinalityHandle::with_headers(
+                    vec![header(100, now - 300), header(110, now - 120)],
+                    100,
+                ),
+            )])),
+            ..test_state().await
+        };
+
+        let status = get_json(router(state.clone()), "/status").await;
+        let chain = &status["chains"][0];
+        assert_eq!(chain["chain_id"], 1);
+        assert_eq!(chain["head"], 110);
+        assert_eq!(chain["head_timestamp"], now - 120);
+        let head_age = chain["head_age_secs"].as_u64().unwrap();
+        assert!((120..=125).contains(&head_age), "head age {}", head_age);
+        assert_eq!(chain["finalized"], 100);
+        let finalized_age = chain["finalized_age_secs"].as_u64().unwrap();
+        assert!((300..=305).contains(&finalized_age));
+
+        let headers = get_json(router(state), "/headers/1").await;
+        let head_age = headers["head_age_secs"].as_u64().unwrap();
+        assert!((120..=125).contains(&head_age));
+        assert_eq!(headers["head"]["timestamp"], now - 120);
+    }
+
+    #[tokio::test]
+    async fn test_status_uptime_advances() {
+        let peers = Arc::new(PeerTable::default());
+        peers.connected("peer-a");
+        let state = AppState {
+            chains: Arc::new(HashMap::from([(
+                1,
+                FinalityHandle::with_headers(vec![], 0),
+            )])),
+            peers,
+            ..test_state().await
+        };
+
+        let first = get_json(router(state.clone()), "/status").await;
+        tokio::time::sleep(Duration::from_millis(1_100)).await;
+        let second = get_json(router(state), "/status").await;
+        let uptime = |status: &serde_json::Value| status["uptime"].as_u64().unwrap();
+        assert!(uptime(&second) >= uptime(&first));
+        assert!(uptime(&second) >= 1);
+
+        assert_eq!(second["peer_count"], 1);
+        assert_eq!(second["prover"]["in_flight"], 0);
+        assert_eq!(second["prover"]["queued"], 0);
+        // No headers stored yet: alive but not synchronized
+        assert_eq!(second["chains_synced"], false);
+        assert_eq!(second["status"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_chains_lists_every_tracked_chain() {
+        let header = |block_number: u64| StoredHeader {
+            block_number,
+            block_hash: ethers::types::H256::from_low_u64_be(block_number),
+            parent_hash: ethers::types::H256::from_low_u64_be(block_number - 1),
+            state_root: Default::default(),
+            transactions_root: Default::default(),
+            receipts_root: Default::default(),
+            timestamp: 0,
+        };
+        let state = AppState {
+            chains: Arc::new(HashMap::from([
+                (
+                    42161,
+                    FinalityHandle::with_headers(vec![header(500), header(501)], 480),
+                ),
+                (1, FinalityHandle::with_headers(vec![], 0)),
+            ])),
+            ..test_state().await
+        };
+
+        let chains = get_json(router(state), "/chains").await;
+        assert_eq!(
+            chains,
+            serde_json::json!([
+                {
+                    "chain_id": 1,
+                    "head": null,
+                    "finalized": 0,
+                    "head_hash": null,
+                },
+                {
+                    "chain_id": 42161,
+                    "head": 501,
+                    "finalized": 480,
+                    "head_hash": ethers::types::H256::from_low_u64_be(501),
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roots_lists_recent_roots() {
+        use crate::merkle::{IncrementalMerkleTree, TREE_DEPTH};
+
+        let mut pool = PoolRoots::new(IncrementalMerkleTree::new(TREE_DEPTH), 2);
+        let mut produced = Vec::new();
+        for (leaf, block) in [(0u64, 100u64), (1, 105), (2, 110)] {
+            let commitment = ethers::types::H256::from_low_u64_be(leaf + 1);
+            produced.push(
+                pool.apply_deposit(commitment, leaf, block)
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+
+        let state = AppState {
+            chains: Arc::new(HashMap::from([(
+                1,
+                FinalityHandle::with_headers(vec![], 105),
+            )])),
+            roots: Arc::new(HashMap::from([(
+                (1, crate::relay::validate::FIXTURE_POOL),
+                Arc::new(RwLock::new(pool)),
+            )])),
+            ..test_state().await
+        };
+
+        let response = get_json(router(state.clone()), "/roots/1").await;
+        let roots: RootsResponse = serde_json::from_value(response).unwrap();
+        assert_eq!(
+            roots.roots,
+            vec![
+                AcceptedRoot {
+                    root: produced[2],
+                    block_number: 110,
+                    finalized: false,
+                },
+                AcceptedRoot {
+                    root: produced[1],
+                    block_number: 105,
+                    finalized: true,
+                },
+            ]
+        );
+        // The oldest root fell out of the history window
+        assert!(!roots.roots.iter().any(|r| r.root == produced[0]));
+
+        // Named explicitly, the default pool lists the same roots
+        let response = get_json(
+            router(state.clone()),
+            &format!("/roots/1?pool={:?}", crate::relay::validate::FIXTURE_POOL),
+        )
+        .await;
+        assert_eq!(
+            serde_json::from_value::<RootsResponse>(response).unwrap(),
+            roots
+        );
+
+        for uri in [
+            "/roots/5",
+            "/roots/1?pool=0x00000000000000000000000000000000000000a3",
+        ] {
+            let response = router(state.clone())
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    async fn signed_admin_request(
+        wallet: &ethers::signers::LocalWallet,
+        nonce: &str,
+        timestamp: u64,
+    ) -> Request<Body> {
+        use ethers::signers::Signer;
+
+        let body = br#"{"paused":true}"#;
+        let message = admin::signing_message(
+            &axum::http::Method::POST,
+            "/admin/pause",
+            nonce,
+            timestamp,
+            body,
+        );
+        let signature = wallet.sign_message(message).await.unwrap();
+        Request::post("/admin/pause")
+            .header(admin::NONCE_HEADER, nonce)
+            .header(admin::TIMESTAMP_HEADER, timestamp.to_string())
+            .header(admin::SIGNATURE_HEADER, signature.to_string())
+            .body(Body::from(body.to_vec()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_admin_request_rejected() {
+        use ethers::signers::Signer;
+
+        let wallet = ethers::signers::LocalWallet::new(&mut rand::thread_rng());
+        let state = AppState {
+            admin: test_admin(Some(wallet.address())),
+            ..test_state().await
+        };
+        // Stand-in mutation behind the same challenge layer as the real router
+        let app = Router::new()
+            .route("/admin/pause", post(|| async { "paused" }))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin::require_challenge,
+            ))
+            .with_state(state.clone());
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let challenge = get_json(router(state.clone()), "/admin/challenge").await;
+        let nonce = challenge["nonce"].as_str().unwrap().to_string();
+
+        let response = app
+            .clone()
+            .oneshot(signed_admin_request(&wallet, &nonce, now).await)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Same nonce again, even with a valid signature
+        let replay = app
+            .clone()
+            .oneshot(signed_admin_request(&wallet, &nonce, now).await)
+            .await
+            .unwrap();
+        assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+
+        // Fresh nonce but a stale timestamp
+        let nonce = state.admin.issue().unwrap();
+        let stale = app
+            .clone()
+            .oneshot(signed_admin_request(&wallet, &nonce, now - 3_600).await)
+            .await
+            .unwrap();
+        assert_eq!(stale.status(), StatusCode::UNAUTHORIZED);
+
+        // Nonce the server never issued
+        let forged = app
+            .clone()
+            .oneshot(signed_admin_request(&wallet, "00", now).await)
+            .await
+            .unwrap();
+        assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+        // Signed by someone other than the operator
+        let nonce = state.admin.issue().unwrap();
+        let stranger = ethers::signers::LocalWallet::new(&mut rand::thread_rng());
+        let response = app
+            .oneshot(signed_admin_request(&stranger, &nonce, now).await)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_relay_submits_withdrawal() {
+        let (mut validation, mut request) = crate::relay::validate::valid_relay_fixture(1).await;
+        // An allowlist of just the default pool serves no other
+        validation.pools = Arc::new(HashMap::from([(
+            1,
+            crate::relay::ServedPools {
+                default: Some(crate::relay::validate::FIXTURE_POOL),
+                allowed: vec![crate::relay::validate::FIXTURE_POOL],
+            },
+        )]));
+        let relays = Arc::new(RelayTracker::default());
+        let (withdrawals, _, broadcaster) = crate::submitter::withdraw::test_withdrawals(
+            1,
+            ethers::types::Address::repeat_byte(0x50),
+            crate::submitter::FeeSettings::default(),
+            FinalityHandle::with_headers(vec![], 0),
+            relays.clone(),
+        )
+        .await;
+        let (gossip, mut gossiped) = tokio::sync::mpsc::channel(8);
+        let state = AppState {
+            relays,
+            validation,
+            withdrawals: Some(withdrawals.clone()),
+            gossip: Some(gossip),
+            ..test_state().await
+        };
+        let post = |request: &RelayRequest| {
+            Request::post("/relay")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(request).unwrap()))
+                .unwrap()
+        };
+
+        let response = router(state.clone()).oneshot(post(&request)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let relay: RelayStatusResponse = serde_json::from_slice(&body).unwrap();
+        // Handed to the P2P node to publish, under the ID the caller got
+        assert_eq!(
+            gossiped.try_recv().unwrap(),
+            (relay.id.clone(), request.clone())
+        );
+        // Submission runs in the background; draining waits for the broadcast
+        withdrawals.drain().await;
+        // and from then on no quotes are handed out for relays to come
+        let response = router(state.clone())
+            .oneshot(
+                Request::post("/quote")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"chain_id":1}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let sent = broadcaster.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let tx_hash = ethers::types::H256(ethers::utils::keccak256(&sent[0]));
+        assert_eq!(
+            state.relays.status(&relay.id),
+            Some(RelayStatus::Submitted {
+                tx_hash: format!("{:?}", tx_hash)
+            })
+        );
+        assert!(state
+            .validation
+            .store
+            .has_nullifier(
+                1,
+                crate::relay::validate::FIXTURE_POOL,
+                request.public_inputs[1]
+            )
+            .await
+            .unwrap());
+        // The validated proof is now served to peers
+        assert!(!state
+            .served_proofs
+            .insert(request.proof.clone(), request.public_inputs.clone()));
+
+        // A root the relayer doesn't know is turned away before any gas is spent
+        request.public_inputs[0] = ethers::types::H256::repeat_byte(0x0f);
+        let response = router(state.clone()).oneshot(post(&request)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "unknown_root");
+
+        // So is a pool the relayer doesn't serve
+        request.pool = Some(ethers::types::Address::repeat_byte(0xa3));
+        let response = router(state).oneshot(post(&request)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "pool_not_served");
+        assert_eq!(broadcaster.sent.lock().unwrap().len(), 1);
+        // Rejected relays aren't gossiped
+        assert!(gossiped.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tx_status_follows_withdrawal_until_mined() {
+        let (validation, request) = crate::relay::validate::valid_relay_fixture(1).await;
+        let relays = Arc::new(RelayTracker::default());
+        let (withdrawals, _, broadcaster) = crate::submitter::withdraw::test_withdrawals(
+            1,
+            ethers::types::Address::repeat_byte(0x50),
+            crate::submitter::FeeSettings::default(),
+            FinalityHandle::with_headers(vec![], 0),
+            relays.clone(),
+        )
+        .await;
+        let state = AppState {
+            relays,
+            validation,
+            withdrawals: Some(withdrawals),
+            ..test_state().await
+        };
+        let tx_status = |id: String| {
+            let state = state.clone();
+            async move {
+                let response = router(state)
+                    .oneshot(
+                        Request::get(format!("/tx/{}", id))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                if response.status() == StatusCode::NOT_FOUND {
+                    return None;
+                }
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                Some(
+                    serde_json::from_slice::<TxStatusResponse>(&body)
+                        .unwrap()
+                        .status,
+                )
+            }
+        };
+
+        let response = router(state.clone())
+            .oneshot(
+                Request::post("/relay")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let id = serde_json::from_slice::<RelayStatusResponse>(&body)
+            .unwrap()
+            .id;
+        // Submitted in the background, so known once broadcast
+        let submitted = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match tx_status(id.clone()).await {
+                    None => tokio::time::sleep(Duration::from_millis(5)).await,
+                    status => break status,
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(submitted, Some(TxStatus::Pending));
+        assert_eq!(tx_status("unknown".to_string()).await, None);
+
+        // The chain includes the withdrawal in block 101
+        broadcaster.mine(101);
+        let tx_hash = ethers::types::H256(ethers::utils::keccak256(
+            &broadcaster.sent.lock().unwrap()[0],
+        ));
+        let mined = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match tx_status(id.clone()).await {
+                    Some(TxStatus::Pending) => tokio::time::sleep(Duration::from_millis(5)).await,
+                    status => break status,
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            mined,
+            Some(TxStatus::Mined {
+                block: 101,
+                tx_hash
+            })
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_relay_wait_returns_on_completion_or_timeout() {
+        let state = test_state().await;
+        state.relays.register("done");
+        state.relays.register("stuck");
+
+        let relays = state.relays.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            relays.update(
+                "done",
+                crate::relay::RelayStatus::Confirmed {
+                    tx_hash: "0xabc".to_string(),
+                },
+            );
+        });
+
+        // Completes as soon as the relay confirms, well before the timeout
+        let start = tokio::time::Instant::now();
+        let body = get_json(router(state.clone()), "/relay/done/wait?timeout=30").await;
+        assert_eq!(body["status"], "confirmed");
+        assert_eq!(body["tx_hash"], "0xabc");
+        assert_eq!(body["terminal"], true);
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        // A relay that never finishes is reported as-is once the timeout passes
+        let start = tokio::time::Instant::now();
+        let body = get_json(router(state.clone()), "/relay/stuck/wait?timeout=5").await;
+        assert_eq!(body["status"], "pending");
+        assert_eq!(body["terminal"], false);
+        assert!(start.elapsed() >= Duration::from_secs(5));
+
+        // Requested timeouts beyond the cap are clamped
+        let start = tokio::time::Instant::now();
+        get_json(router(state.clone()), "/relay/stuck/wait?timeout=3600").await;
+        assert!(start.elapsed() >= MAX_RELAY_WAIT);
+        assert!(start.elapsed() < MAX_RELAY_WAIT + Duration::from_secs(1));
+
+        let response = router(state)
+            .oneshot(
+                Request::get("/relay/missing/wait")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_prove_returns_verifiable_proof_without_relaying() {
+        use crate::prover::verifier::{PlaceholderVerifier, ProofVerifier};
+        use crate::prover::{ProofRequest, WithdrawalOutput};
+
+        let prover = Arc::new(ProverService::new(&crate::ProverConfig::placeholder()).unwrap());
+        let state = AppState {
+            prover: prover.clone(),
+            proofs: Some(PublicProver {
+                prover,
+                limit: Arc::new(RateLimiter::per_minute(2)),
+            }),
+            ..test_state().await
+        };
+
+        let secret = [0x5eu8; 32];
+        let request = ProveRequest {
+            chain_id: 1,
+            request: ProofRequest::Withdrawal {
+                merkle_root: [1u8; 32],
+                nullifier: [2u8; 32],
+                outputs: vec![WithdrawalOutput {
+                    recipient: vec![3u8; 20],
+                    amount: 500,
+                }],
+                amount: 500,
+                fee: 0,
+                note_value: 500,
+                change_commitment: None,
+                change_value: 0,
+                secret,
+                randomness: [0x7au8; 32],
+                merkle_path: vec![[0u8; 32]; crate::merkle::TREE_DEPTH],
+                merkle_indices: vec![0; crate::merkle::TREE_DEPTH],
+            },
+        };
+        let response = router(state.clone())
+            .oneshot(
+                Request::post("/prove")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let proof: ProveResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(proof.proof_type, "withdrawal");
+        let inputs: Vec<[u8; 32]> = proof.public_inputs.iter().map(|input| input.0).collect();
+        assert!(PlaceholderVerifier.verify(&proof.proof, &inputs));
+
+        // Nothing private comes back
+        let text = String::from_utf8_lossy(&body);
+        assert!(!text.contains("secret") && !text.contains(&hex::encode(secret)));
+
+        // Callers beyond the rate limit are turned away
+        let prove = || {
+            router(state.clone()).oneshot(
+                Request::post("/prove")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                    .unwrap(),
+            )
+        };
+        assert_eq!(prove().await.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            prove().await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // Without a prover the endpoint is unavailable
+        let response = router(AppState {
+            proofs: None,
+            ..state
+        })
+        .oneshot(
+            Request::post("/prove")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&request).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_streams_batch_from_body() {
+        let prover = Arc::new(ProverService::new(&crate::ProverConfig::placeholder()).unwrap());
+        let state = AppState {
+            proofs: Some(PublicProver {
+                prover: prover.clone(),
+                limit: Arc::new(RateLimiter::per_minute(10)),
+            }),
+            ..test_state().await
+        };
+        let member = |index: u8| {
+            let request = ProofRequest::Transfer {
+                merkle_root: [1u8; 32],
+                nullifier: [index; 32],
+                new_commitment_a: [3u8; 32],
+                new_commitment_b: [4u8; 32],
+                secret: [5u8; 32],
+                randomness: [6u8; 32],
+                merkle_path: vec![[7u8; 32]; crate::merkle::TREE_DEPTH],
+                merkle_indices: vec![0u8; crate::merkle::TREE_DEPTH],
+            };
+            serde_json::to_string(&request).unwrap()
+        };
+        let aggregate = |body: String| {
+            router(state.clone()).oneshot(
+                Request::post("/prove/aggregate/1")
+                    .header("content-type", "application/x-ndjson")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        // Blank lines are skipped and the last member needs no newline
+        let batch = format!("{}\n\n{}\n{}", member(1), member(2), member(3));
+        let response = aggregate(batch).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let aggregated: AggregateResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(aggregated.members, 3);
+        assert_eq!(aggregated.proof.proof_type, "aggregate");
+
+        // A bad member fails the whole batch
+        let batch = format!("{}\nnot json\n{}\n", member(1), member(2));
+        let response = aggregate(batch).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("Malformed batch member"));
+
+        let response = aggregate(String::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_health_syncing_until_caught_up() {
+        let header = |block_number: u64| StoredHeader {
+            block_number,
+            block_hash: ethers::types::H256::from_low_u64_be(block_number),
+            parent_hash: ethers::types::H256::from_low_u64_be(block_number - 1),
+            state_root: Default::default(),
+            transactions_root: Default::default(),
+            receipts_root: Default::default(),
+            timestamp: 1_700_000_000 + block_number * 12,
+        };
+        let chain = FinalityHandle::with_headers(vec![header(99), header(100)], 85);
+        let state = AppState {
+            chains: Arc::new(HashMap::from([(1, chain.clone())])),
+            ..test_state().await
+        };
+        let health = |state: AppState| async move {
+            let response = router(state)
+                .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+
+        // The head is 20 blocks past the stored tip: still backfilling
+        chain.set_network_head(120);
+        assert_eq!(
+            health(state.clone()).await,
+            (StatusCode::SERVICE_UNAVAILABLE, "syncing".to_string())
+        );
+        let status = get_json(router(state.clone()), "/status").await;
+        assert_eq!(status["sync"], "syncing");
+        assert_eq!(status["chains"][0]["sync_lag"], 20);
+        assert_eq!(status["chains"][0]["synced"], false);
+
+        // Within the allowed lag counts as caught up
+        chain.set_network_head(100 + MAX_SYNC_LAG);
+        assert_eq!(
+            health(state.clone()).await,
+            (StatusCode::OK, "ready".to_string())
+        );
+        let status = get_json(router(state), "/status").await;
+        assert_eq!(status["sync"], "ready");
+        assert_eq!(status["chains"][0]["synced"], true);
+    }
+}
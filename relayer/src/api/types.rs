@@ -0,0 +1,238 @@
+//! Request and response bodies shared by the HTTP API and its client
+
+use ethers::types::{Address, Bytes, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::light_client::StoredHeader;
+use crate::prover::{GeneratedProof, ProofRequest};
+use crate::relay::RelayStatus;
+use crate::submitter::TxStatus;
+
+pub use super::address::Recipient;
+pub use crate::quote::{Quote, SignedQuote};
+
+/// Body of `POST /quote`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    /// Chain the withdrawal will be submitted on
+    pub chain_id: u64,
+    /// Circuit the proof is generated for (defaults to withdrawal)
+    #[serde(default)]
+    pub proof_type: Option<String>,
+}
+
+/// Signed fee quote returned by `POST /quote`
+pub type QuoteResponse = SignedQuote;
+
+/// Body of `POST /relay`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayRequest {
+    /// Chain the withdrawal is submitted on
+    pub chain_id: u64,
+    pub proof: Bytes,
+    pub public_inputs: Vec<H256>,
+    /// Quote from `POST /quote` that the relayer must honor
+    #[serde(default)]
+    pub quote: Option<SignedQuote>,
+    /// Fee in wei offered to the relayer
+    #[serde(default)]
+    pub fee: U256,
+    /// Unix timestamp after which the relay must not be submitted
+    #[serde(default)]
+    pub deadline: Option<i64>,
+    /// Circuit the proof was generated with (defaults to the current one)
+    #[serde(default)]
+    pub circuit_version: Option<String>,
+    /// Intended recipient; if given, must match the proof's recipient input
+    #[serde(default)]
+    pub recipient: Option<Recipient>,
+    /// Hash of `public_inputs` for chains whose verifier takes one; if
+    /// given, must match the hash of the inputs
+    #[serde(default)]
+    pub public_inputs_hash: Option<H256>,
+    /// Pool contract the withdrawal targets (defaults to the chain's
+    /// configured pool)
+    #[serde(default)]
+    pub pool: Option<Address>,
+}
+
+/// Error body of `POST /relay` and `POST /quote`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    /// Stable, machine-readable reason code
+    pub code: String,
+    pub reason: String,
+}
+
+impl ApiError {
+    pub fn new(code: &str, reason: impl ToString) -> Self {
+        Self {
+            code: code.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Body of `POST /prove`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProveRequest {
+    /// Chain the proof will be submitted on; picks the public-input layout
+    pub chain_id: u64,
+    pub request: ProofRequest,
+}
+
+/// Proof returned by `POST /prove`; carries no private inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProveResponse {
+    pub proof_type: String,
+    pub proof: Bytes,
+    pub public_inputs: Vec<H256>,
+    pub generation_time_ms: u64,
+}
+
+impl From<GeneratedProof> for ProveResponse {
+    fn from(proof: GeneratedProof) -> Self {
+        Self {
+            proof_type: proof.proof_type,
+            proof: proof.proof_data.into(),
+            public_inputs: proof.public_inputs.into_iter().map(H256).collect(),
+            generation_time_ms: proof.generation_time_ms,
+        }
+    }
+}
+
+/// Aggregate proof returned by `POST /prove/aggregate/:chain_id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateResponse {
+    #[serde(flatten)]
+    pub proof: ProveResponse,
+    /// Batch members folded into the proof
+    pub members: usize,
+}
+
+/// Relay status returned by `POST /relay`, `GET /relay/:id` and `GET /relay/:id/wait`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayStatusResponse {
+    pub id: String,
+    #[serde(flatten)]
+    pub status: RelayStatus,
+    /// Whether the status will not change again
+    pub terminal: bool,
+}
+
+impl RelayStatusResponse {
+    pub fn new(id: impl Into<String>, status: RelayStatus) -> Self {
+        Self {
+            id: id.into(),
+            terminal: status.is_terminal(),
+            status,
+        }
+    }
+}
+
+/// Withdrawal outcome of a relay, returned by `GET /tx/:id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxStatusResponse {
+    pub id: String,
+    #[serde(flatten)]
+    pub status: TxStatus,
+}
+
+/// Latest headers for a chain, returned by `GET /headers/:chain_id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadersResponse {
+    pub chain_id: u64,
+    pub head: Option<StoredHeader>,
+    /// Finalized header, if still retained
+    pub finalized: Option<StoredHeader>,
+    pub finalized_number: u64,
+    /// Seconds between the head block's timestamp and now
+    pub head_age_secs: Option<u64>,
+    /// Seconds between the finalized block's timestamp and now
+    pub finalized_age_secs: Option<u64>,
+}
+
+/// Entry of `GET /chains`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainSummary {
+    pub chain_id: u64,
+    /// Latest stored header (unset before any are stored)
+    pub head: Option<u64>,
+    pub finalized: u64,
+    /// Hash of the latest stored header
+    pub head_hash: Option<H256>,
+}
+
+/// Per-chain view reported by `GET /status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    pub chain_id: u64,
+    pub head: Option<u64>,
+    pub head_timestamp: Option<u64>,
+    pub head_age_secs: Option<u64>,
+    pub finalized: u64,
+    pub finalized_timestamp: Option<u64>,
+    pub finalized_age_secs: Option<u64>,
+    /// Blocks the stored headers trail the chain head by (unset before any are stored)
+    pub sync_lag: Option<u64>,
+    /// Whether the chain is within `MAX_SYNC_LAG` blocks of its head
+    pub synced: bool,
+}
+
+/// Root a proof may be generated against
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptedRoot {
+    pub root: H256,
+    /// Block of the deposit that produced the root
+    pub block_number: u64,
+    /// Whether that block is finalized
+    pub finalized: bool,
+}
+
+/// Roots returned by `GET /roots/:chain_id`, newest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootsResponse {
+    pub chain_id: u64,
+    pub roots: Vec<AcceptedRoot>,
+}
+
+/// Result of `POST /admin/resync/:chain_id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResyncResponse {
+    pub chain_id: u64,
+    /// Headers now stored for the chain
+    pub headers_loaded: usize,
+    /// Head block the resync started from
+    pub head: u64,
+}
+
+/// Body of `POST /verify_inclusion`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyInclusionRequest {
+    pub chain_id: u64,
+    pub block_hash: H256,
+    pub tx_hash: H256,
+    /// Sibling hashes from the leaf up to the transactions root
+    pub proof: Vec<H256>,
+    /// Position at each level of `proof`: 0 for a left child, 1 for a right;
+    /// may be left out with an empty `proof`
+    #[serde(default)]
+    pub indices: Vec<u8>,
+}
+
+/// Body of `POST /verify_receipt`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReceiptRequest {
+    pub chain_id: u64,
+    pub block_hash: H256,
+    /// Receipt as stored in the trie (typed receipts keep their type byte)
+    pub receipt: Bytes,
+    /// Trie nodes from the receipts root down to the receipt's leaf
+    pub proof: Vec<Bytes>,
+}
+
+/// Result of `POST /verify_inclusion` or `POST /verify_receipt`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyInclusionResponse {
+    pub included: bool,
+}
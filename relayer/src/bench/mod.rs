@@ -0,0 +1,325 @@
+//! End-to-end relay throughput benchmark
+//!
+//! Drives synthetic relays through the stages a real one takes: the proof is
+//! generated by the configured prover backend, the resulting relay request
+//! goes through full validation (proof verification included), and it is
+//! handed to a nonce-managed submitter whose broadcast is mocked. Nothing
+//! touches a chain or the network, so it runs anywhere, against the
+//! placeholder backend in CI. Run with `relayer --bench <N>`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, H256, U256};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::api::types::RelayRequest;
+use crate::merkle::roots::PoolRoots;
+use crate::merkle::IncrementalMerkleTree;
+use crate::prover::{ProofRequest, ProverService, WithdrawalOutput};
+use crate::quote::{
+    FeePricing, QuoteBook, DEFAULT_ABSORB_BUFFER_BPS, DEFAULT_FEE_MARGIN_BPS,
+    DEFAULT_QUOTE_VALIDITY,
+};
+use crate::relay::{validate_relay_request, RelayContext, ServedPools};
+use crate::store::SqliteStore;
+use crate::submitter::route::BroadcastRoute;
+use crate::submitter::{Broadcast, ChainSubmitters, NonceManager, NonceSource};
+use crate::ProverConfig;
+
+/// Chain the synthetic relays target
+pub const BENCH_CHAIN_ID: u64 = 1;
+
+/// Pool the synthetic relays withdraw from
+const BENCH_POOL: Address = Address::repeat_byte(0x50);
+
+/// Default number of relays in flight at once
+pub const DEFAULT_BENCH_CONCURRENCY: usize = 16;
+
+/// Amount withdrawn by each synthetic relay
+const BENCH_AMOUNT: u64 = 1_000_000;
+
+/// Latency distribution of one stage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    fn of(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let at = |q: f64| {
+            samples
+                .get(((samples.len().saturating_sub(1)) as f64 * q).round() as usize)
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Outcome of a benchmark run
+#[derive(Debug)]
+pub struct BenchReport {
+    /// Relays that made it through every stage
+    pub completed: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+    /// End-to-end latency of completed relays
+    pub total: Percentiles,
+    /// Latency of each stage, in pipeline order
+    pub stages: Vec<(&'static str, Percentiles)>,
+}
+
+impl BenchReport {
+    /// Completed relays per second
+    pub fn throughput(&self) -> f64 {
+        self.completed as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Human-readable summary
+    pub fn render(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut lines = vec![
+            format!(
+                "relays: {} completed, {} failed in {:.2}s",
+                self.completed,
+                self.failed,
+                self.elapsed.as_secs_f64()
+            ),
+            format!("throughput: {:.1} relays/s", self.throughput()),
+        ];
+        for (stage, p) in std::iter::once(&("total", self.total)).chain(&self.stages) {
+            lines.push(format!(
+                "{:<8} p50 {:.2}ms  p90 {:.2}ms  p99 {:.2}ms  max {:.2}ms",
+                stage,
+                ms(p.p50),
+                ms(p.p90),
+                ms(p.p99),
+                ms(p.max)
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Stage timings of one relay
+struct Sample {
+    prove: Duration,
+    validate: Duration,
+    submit: Duration,
+}
+
+/// A submitter account with no transactions yet
+struct FreshAccount;
+
+#[async_trait]
+impl NonceSource for FreshAccount {
+    async fn pending_nonce(&self, _address: Address) -> Result<U256> {
+        Ok(U256::zero())
+    }
+}
+
+/// Everything the synthetic relays run through
+struct Pipeline {
+    prover: Arc<ProverService>,
+    ctx: RelayContext,
+    submitters: ChainSubmitters,
+    root: H256,
+    tree_depth: usize,
+}
+
+impl Pipeline {
+    async fn new(config: &ProverConfig, concurrency: usize) -> Result<Self> {
+        let prover = Arc::new(ProverService::new(config)?);
+        let tree_depth = prover.tree_depth(BENCH_CHAIN_ID);
+
+        // One finalized deposit whose root every relay proves against
+        let mut pool = PoolRoots::new(IncrementalMerkleTree::new(tree_depth), 1);
+        let root = pool
+            .apply_deposit(H256::repeat_byte(1), 0, 0)?
+            .ok_or_else(|| anyhow::anyhow!("Deposit produced no root"))?;
+
+        let ctx = RelayContext {
+            prover: prover.clone(),
+            circuits: prover.circuits(),
+            // Synthetic relays carry no quote, so none is ever checked
+            quotes: Arc::new(QuoteBook::new(
+                LocalWallet::new(&mut rand::thread_rng()),
+                DEFAULT_QUOTE_VALIDITY,
+                DEFAULT_ABSORB_BUFFER_BPS,
+                FeePricing::new(HashMap::new(), DEFAULT_FEE_MARGIN_BPS),
+                Arc::new(SqliteStore::open_in_memory().await?),
+            )),
+            roots: Arc::new(HashMap::from([(
+                (BENCH_CHAIN_ID, BENCH_POOL),
+                Arc::new(RwLock::new(pool)),
+            )])),
+            sources: Arc::default(),
+            chains: Arc::default(),
+            store: Arc::new(SqliteStore::open_in_memory().await?),
+            min_fee: U256::zero(),
+            input_commitments: Arc::default(),
+            pools: Arc::new(HashMap::from([(
+                BENCH_CHAIN_ID,
+                ServedPools {
+                    default: Some(BENCH_POOL),
+                    allowed: vec![],
+                },
+            )])),
+            dedup: Arc::default(),
+        };
+
+        let mut submitters = ChainSubmitters::default();
+        submitters.insert(NonceManager::new(
+            BENCH_CHAIN_ID,
+            Address::repeat_byte(0x42),
+            Arc::new(FreshAccount),
+            concurrency,
+        ));
+
+        Ok(Self {
+            prover,
+            ctx,
+            submitters,
+            root,
+            tree_depth,
+        })
+    }
+
+    /// Synthetic withdrawal, unique per `index` so none hit the proof cache
+    fn request(&self, index: u64) -> ProofRequest {
+        ProofRequest::Withdrawal {
+            merkle_root: self.root.0,
+            nullifier: H256::from_low_u64_be(index + 1).0,
+            outputs: vec![WithdrawalOutput {
+                recipient: Address::repeat_byte(0x33).as_bytes().to_vec(),
+                amount: BENCH_AMOUNT,
+            }],
+            amount: BENCH_AMOUNT,
+            fee: 0,
+            note_value: BENCH_AMOUNT,
+            change_commitment: None,
+            change_value: 0,
+            secret: [0u8; 32],
+            randomness: [0u8; 32],
+            merkle_path: vec![[0u8; 32]; self.tree_depth],
+            merkle_indices: vec![0; self.tree_depth],
+        }
+    }
+
+    async fn relay(&self, index: u64) -> Result<Sample> {
+        let started = Instant::now();
+        let proof = self
+            .prover
+            .generate(self.request(index), BENCH_CHAIN_ID)
+            .await?;
+        let proved = Instant::now();
+
+        let request = RelayRequest {
+            chain_id: BENCH_CHAIN_ID,
+            proof: proof.proof_data.into(),
+            public_inputs: proof.public_inputs.into_iter().map(H256).collect(),
+            quote: None,
+            fee: U256::zero(),
+            deadline: None,
+            circuit_version: None,
+            recipient: None,
+            public_inputs_hash: None,
+            pool: None,
+        };
+        validate_relay_request(&request, &self.ctx, chrono::Utc::now().timestamp()).await?;
+        let validated = Instant::now();
+
+        let in_flight = self
+            .submitters
+            .submit(BENCH_CHAIN_ID, |nonce| async move {
+                Ok(Broadcast {
+                    tx_hash: H256::from_low_u64_be(nonce.as_u64()),
+                    route: BroadcastRoute::Public,
+                    expires_at_block: None,
+                })
+            })
+            .await?;
+        drop(in_flight);
+
+        Ok(Sample {
+            prove: proved - started,
+            validate: validated - proved,
+            submit: validated.elapsed(),
+        })
+    }
+}
+
+/// Push `requests` synthetic relays through the pipeline, `concurrency` at
+/// a time
+pub async fn run(
+    config: &ProverConfig,
+    requests: usize,
+    concurrency: usize,
+) -> Result<BenchReport> {
+    let concurrency = concurrency.max(1);
+    let pipeline = Pipeline::new(config, concurrency).await?;
+
+    let started = Instant::now();
+    let results: Vec<Result<Sample>> = stream::iter(0..requests as u64)
+        .map(|index| pipeline.relay(index))
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let elapsed = started.elapsed();
+
+    let mut samples = Vec::with_capacity(results.len());
+    let mut failed = 0;
+    for result in results {
+        match result {
+            Ok(sample) => samples.push(sample),
+            Err(e) => {
+                if failed == 0 {
+                    warn!(error = %e, "Benchmark relay failed");
+                }
+                failed += 1;
+            }
+        }
+    }
+
+    let stage = |f: fn(&Sample) -> Duration| Percentiles::of(samples.iter().map(f).collect());
+    Ok(BenchReport {
+        completed: samples.len(),
+        failed,
+        elapsed,
+        total: stage(|s| s.prove + s.validate + s.submit),
+        stages: vec![
+            ("prove", stage(|s| s.prove)),
+            ("validate", stage(|s| s.validate)),
+            ("submit", stage(|s| s.submit)),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bench_completes_against_placeholder_backend() {
+        let report = run(&ProverConfig::placeholder(), 32, 4).await.unwrap();
+        assert_eq!(report.completed, 32);
+        assert_eq!(report.failed, 0);
+        assert!(report.throughput() > 0.0);
+        assert!(report.total.p50 <= report.total.max);
+        assert!(report.render().contains("throughput"));
+    }
+}
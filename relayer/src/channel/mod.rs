@@ -0,0 +1,91 @@
+//! Sends on the internal channels wiring components together
+//!
+//! Components hand events to each other over bounded mpsc channels. A full
+//! channel is backpressure: the sender waits, which is expected under load
+//! and only counted. A closed channel means its consumer is gone, usually a
+//! task that exited or panicked, and every later event on it would be lost,
+//! so it is counted and returned to the caller instead of being ignored.
+//! Callers log it, at a level depending on whether the consumer going away
+//! is expected there.
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::debug;
+
+use crate::metrics;
+
+/// The receiving end of an internal channel was dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Internal channel {0} is closed")]
+pub struct ChannelClosed(pub &'static str);
+
+/// Send `value` on the channel named `channel`, waiting while it is full
+pub async fn send<T>(
+    tx: &mpsc::Sender<T>,
+    channel: &'static str,
+    value: T,
+) -> Result<(), ChannelClosed> {
+    let value = match tx.try_send(value) {
+        Ok(()) => return Ok(()),
+        Err(TrySendError::Full(value)) => {
+            metrics::CHANNEL_SENDS_BLOCKED
+                .with_label_values(&[channel])
+                .inc();
+            debug!(channel = channel, "Internal channel full, waiting");
+            value
+        }
+        Err(TrySendError::Closed(_)) => return Err(closed(channel)),
+    };
+    tx.send(value).await.map_err(|_| closed(channel))
+}
+
+/// Count `channel` as closed, whichever end noticed
+pub fn closed(channel: &'static str) -> ChannelClosed {
+    metrics::CHANNEL_CLOSED.with_label_values(&[channel]).inc();
+    ChannelClosed(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_closed_receiver_reported_not_dropped() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+
+        let before = metrics::CHANNEL_CLOSED
+            .with_label_values(&["test_closed"])
+            .get();
+        assert_eq!(
+            send(&tx, "test_closed", 1u64).await,
+            Err(ChannelClosed("test_closed"))
+        );
+        assert_eq!(
+            metrics::CHANNEL_CLOSED
+                .with_label_values(&["test_closed"])
+                .get(),
+            before + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_channel_waits_and_is_counted_apart() {
+        let (tx, mut rx) = mpsc::channel(1);
+        send(&tx, "test_full", 1u64).await.unwrap();
+
+        let blocked = metrics::CHANNEL_SENDS_BLOCKED.with_label_values(&["test_full"]);
+        let closed = metrics::CHANNEL_CLOSED.with_label_values(&["test_full"]);
+        let before = blocked.get();
+        let sender = tokio::spawn(async move { send(&tx, "test_full", 2u64).await });
+        while blocked.get() == before {
+            tokio::task::yield_now().await;
+        }
+        assert!(!sender.is_finished());
+
+        // Backpressure clears once the consumer catches up, nothing is lost
+        assert_eq!(rx.recv().await, Some(1));
+        sender.await.unwrap().unwrap();
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(closed.get(), 0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Typed client for the relayer HTTP API
+//!
+//! Mirrors the routes served by `api::router` using the same request and
+//! response types, re-exported here, so integrators don't hand-roll JSON.
+
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+pub use crate::api::types;
+pub use crate::light_client::StoredHeader;
+pub use crate::prover::{ProofRequest, WithdrawalOutput};
+pub use crate::relay::RelayStatus;
+pub use crate::submitter::TxStatus;
+
+use types::{
+    ChainSummary, HeadersResponse, ProveRequest, ProveResponse, QuoteRequest, QuoteResponse,
+    RelayRequest, RelayStatusResponse, RootsResponse, TxStatusResponse, VerifyInclusionRequest,
+    VerifyInclusionResponse, VerifyReceiptRequest,
+};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Relayer returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Client for a single relayer node
+#[derive(Clone)]
+pub struct RelayerClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl RelayerClient {
+    /// Client for the relayer API at `base_url` (e.g. `http://localhost:8080`)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Client reusing an existing HTTP client (timeouts, proxies, TLS)
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fee quote for relaying a withdrawal
+    pub async fn quote(&self, request: &QuoteRequest) -> Result<QuoteResponse> {
+        self.post("/quote", request).await
+    }
+
+    /// Submit a withdrawal for relaying
+    pub async fn relay(&self, request: &RelayRequest) -> Result<RelayStatusResponse> {
+        self.post("/relay", request).await
+    }
+
+    /// Generate a proof without relaying it
+    pub async fn prove(&self, request: &ProveRequest) -> Result<ProveResponse> {
+        self.post("/prove", request).await
+    }
+
+    /// Current status of a relay, or `None` if the relayer doesn't know it
+    pub async fn status(&self, id: &str) -> Result<Option<RelayStatusResponse>> {
+        self.get_optional(&format!("/relay/{}", id)).await
+    }
+
+    /// Wait up to `timeout` (capped by the server) for a relay to finish
+    pub async fn wait(&self, id: &str, timeout: Duration) -> Result<Option<RelayStatusResponse>> {
+        self.get_optional(&format!("/relay/{}/wait?timeout={}", id, timeout.as_secs()))
+            .await
+    }
+
+    /// Outcome of a relay's withdrawal, or `None` if unknown or expired
+    pub async fn tx_status(&self, id: &str) -> Result<Option<TxStatusResponse>> {
+        self.get_optional(&format!("/tx/{}", id)).await
+    }
+
+    /// Head and finalized block of every tracked chain
+    pub async fn chains(&self) -> Result<Vec<ChainSummary>> {
+        self.get("/chains").await
+    }
+
+    /// Latest and finalized headers of a chain, or `None` if it isn't tracked
+    pub async fn headers(&self, chain_id: u64) -> Result<Option<HeadersResponse>> {
+        self.get_optional(&format!("/headers/{}", chain_id)).await
+    }
+
+    /// Stored header at `block_number`, or `None` if not retained
+    pub async fn header(&self, chain_id: u64, block_number: u64) -> Result<Option<StoredHeader>> {
+        self.get_optional(&format!("/headers/{}/{}", chain_id, block_number))
+            .await
+    }
+
+    /// Pool roots the relayer accepts proofs against, or `None` if no pool is watched
+    pub async fn roots(&self, chain_id: u64) -> Result<Option<RootsResponse>> {
+        self.get_optional(&format!("/roots/{}", chain_id)).await
+    }
+
+    /// Check a transaction inclusion proof against the relayer's headers
+    pub async fn verify_inclusion(&self, request: &VerifyInclusionRequest) -> Result<bool> {
+        let response: VerifyInclusionResponse = self.post("/verify_inclusion", request).await?;
+        Ok(response.included)
+    }
+
+    /// Check a receipt trie proof against the relayer's headers
+    pub async fn verify_receipt(&self, request: &VerifyReceiptRequest) -> Result<bool> {
+        let response: VerifyInclusionResponse = self.post("/verify_receipt", request).await?;
+        Ok(response.included)
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let response = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(body)
+            .send()
+            .await?;
+        decode(response).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await?;
+        decode(response).await
+    }
+
+    /// GET that maps 404 to `None`
+    async fn get_optional<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let response = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        decode(response).await.map(Some)
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(ClientError::Status { status, body });
+    }
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Bytes, H256};
+    use ethers::utils::keccak256;
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+
+    use crate::api::{self, admin::AdminAuth, AppState};
+    use crate::light_client::FinalityHandle;
+    use crate::p2p::NodeIdentity;
+    use crate::prover::circuits::CircuitRegistry;
+    use crate::relay::{RelayStatus, RelayTracker};
+
+    fn header(block_number: u64, transactions_root: H256) -> StoredHeader {
+        StoredHeader {
+            block_number,
+            block_hash: H256::from_low_u64_be(block_number),
+            parent_hash: H256::from_low_u64_be(block_number - 1),
+            state_root: H256::zero(),
+            transactions_root,
+            receipts_root: H256::zero(),
+            // Recent, so quotes valid from the head's timestamp are live
+            timestamp: chrono::Utc::now().timestamp() as u64 + block_number * 12 - 1_224,
+        }
+    }
+
+    /// Serve the real API router on an ephemeral port
+    async fn serve(state: AppState) -> RelayerClient {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, api::router(state)).await.unwrap() });
+        RelayerClient::new(url)
+    }
+
+    #[tokio::test]
+    async fn test_client_round_trips_against_server() {
+        let tx_hash = H256::repeat_byte(0x11);
+        let sibling = H256::repeat_byte(0x22);
+        let root = H256(keccak256([tx_hash.as_bytes(), sibling.as_bytes()].concat()));
+        // Single-receipt block: the receipts root is a leaf keyed by rlp(0)
+        let receipt = Bytes::from(vec![0xab; 40]);
+        let mut leaf = ethers::utils::rlp::RlpStream::new_list(2);
+        leaf.append(&[0x20u8, 0x80].as_slice())
+            .append(&receipt.as_ref());
+        let leaf = Bytes::from(leaf.out().to_vec());
+        let mut first = header(100, H256::zero());
+        first.receipts_root = H256(keccak256(&leaf));
+        let headers = vec![first, header(101, root), header(102, H256::zero())];
+
+        let (validation, mut request) = crate::relay::validate::valid_relay_fixture(1).await;
+        let state = AppState {
+            identity: Arc::new(RwLock::new(NodeIdentity {
+                peer_id: "12D3KooWTest".to_string(),
+                public_key_fingerprint: "00".to_string(),
+                listen_addrs: vec![],
+                external_addrs: vec![],
+                protocols: vec![],
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            })),
+            circuits: Arc::new(CircuitRegistry::default()),
+            admin: Arc::new(AdminAuth::new(None, api::admin::DEFAULT_CHALLENGE_TTL)),
+            relays: Arc::new(RelayTracker::default()),
+            chains: Arc::new(HashMap::from([(
+                1,
+                FinalityHandle::with_headers(headers.clone(), 101),
+            )])),
+            quotes: validation.quotes.clone(),
+            prover: Arc::default(),
+            diagnostics: Arc::default(),
+            started: std::time::Instant::now(),
+            roots: Arc::default(),
+            resync: Arc::default(),
+            validation: validation.clone(),
+            proofs: None,
+            peers: Arc::default(),
+            served_proofs: Arc::default(),
+            signer: crate::keys::rotation::test_signer(),
+            withdrawals: None,
+            gossip: None,
+        };
+        let client = serve(state.clone()).await;
+
+        let quote = client
+            .quote(&QuoteRequest {
+                chain_id: 1,
+                proof_type: None,
+            })
+            .await
+            .unwrap();
+        assert!(!quote.quote.fee.is_zero());
+
+        // Quoted requests must offer at least the quoted fee
+        request.quote = Some(quote.clone());
+        let err = client.relay(&request).await.unwrap_err();
+        assert!(err.to_string().contains("below the required"));
+
+        request.fee = quote.quote.fee;
+        let relay = client.relay(&request).await.unwrap();
+        assert_eq!(relay.status, RelayStatus::Pending);
+        assert!(!relay.terminal);
+        assert_eq!(state.quotes.binding(&relay.id), Some(quote.quote.fee));
+
+        state.relays.update(
+            &relay.id,
+            RelayStatus::Confirmed {
+                tx_hash: "0xabc".to_string(),
+            },
+        );
+        let status = client.status(&relay.id).await.unwrap().unwrap();
+        assert_eq!(
+            status.status,
+            RelayStatus::Confirmed {
+                tx_hash: "0xabc".to_string()
+            }
+        );
+        assert!(status.terminal);
+        let waited = client
+            .wait(&relay.id, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(waited, Some(status));
+        assert_eq!(client.status("unknown").await.unwrap(), None);
+
+        let chains = client.chains().await.unwrap();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].head, Some(102));
+        assert_eq!(chains[0].finalized, 101);
+        assert_eq!(chains[0].head_hash, Some(headers[2].block_hash));
+
+        let latest = client.headers(1).await.unwrap().unwrap();
+        assert_eq!(latest.head, Some(headers[2].clone()));
+        assert_eq!(latest.finalized, Some(headers[1].clone()));
+        assert_eq!(latest.finalized_number, 101);
+        assert_eq!(client.headers(5).await.unwrap(), None);
+        assert_eq!(
+            client.header(1, 100).await.unwrap(),
+            Some(headers[0].clone())
+        );
+        assert_eq!(client.header(1, 99).await.unwrap(), None);
+
+        let mut inclusion = VerifyInclusionRequest {
+            chain_id: 1,
+            block_hash: headers[1].block_hash,
+            tx_hash,
+            proof: vec![sibling],
+            indices: vec![0],
+        };
+        assert!(client.verify_inclusion(&inclusion).await.unwrap());
+        inclusion.tx_hash = H256::repeat_byte(0x33);
+        assert!(!client.verify_inclusion(&inclusion).await.unwrap());
+
+        // Unknown chains surface as a status error rather than `false`
+        inclusion.chain_id = 5;
+        assert!(matches!(
+            client.verify_inclusion(&inclusion).await,
+            Err(ClientError::Status {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+
+        let mut receipt_proof = VerifyReceiptRequest {
+            chain_id: 1,
+            block_hash: headers[0].block_hash,
+            receipt,
+            proof: vec![leaf],
+        };
+        assert!(client.verify_receipt(&receipt_proof).await.unwrap());
+        receipt_proof.block_hash = headers[1].block_hash;
+        assert!(!client.verify_receipt(&receipt_proof).await.unwrap());
+    }
+}
@@ -0,0 +1,269 @@
+//! Startup self-diagnostics
+//!
+//! After initialization the node gathers one readiness report covering every
+//! subsystem, logs it, and serves it at `/status`. Startup aborts with the
+//! report if a critical subsystem didn't come up.
+//!
+//! `--check` runs the same probes without starting the node and reports a
+//! pass/fail result per subsystem as a `CheckReport`.
+
+use anyhow::Result;
+use ethers::types::{Address, U256};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::prover::ProverService;
+
+/// RPC connectivity of a tracked chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainCheck {
+    pub chain_id: u64,
+    pub connected: bool,
+    /// Head block reported by the RPC endpoint
+    pub head: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl ChainCheck {
+    pub fn new(chain_id: u64, head: Result<u64>) -> Self {
+        match head {
+            Ok(head) => Self {
+                chain_id,
+                connected: true,
+                head: Some(head),
+                error: None,
+            },
+            Err(e) => Self {
+                chain_id,
+                connected: false,
+                head: None,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+/// Prover backend state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProverCheck {
+    pub enabled: bool,
+    pub backend: String,
+    pub worker_alive: bool,
+    /// Accepting work: enabled, worker up and a proving slot free
+    pub ready: bool,
+}
+
+/// Signer balance on one chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceCheck {
+    pub chain_id: u64,
+    /// Balance in wei, if the RPC call succeeded
+    pub balance: Option<U256>,
+    pub error: Option<String>,
+}
+
+impl BalanceCheck {
+    pub fn new(chain_id: u64, balance: Result<U256>) -> Self {
+        match balance {
+            Ok(balance) => Self {
+                chain_id,
+                balance: Some(balance),
+                error: None,
+            },
+            Err(e) => Self {
+                chain_id,
+                balance: None,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+/// Transaction signer and its balances
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SignerCheck {
+    pub address: Address,
+    pub balances: Vec<BalanceCheck>,
+}
+
+/// Consolidated readiness report built once at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StartupReport {
+    pub chains: Vec<ChainCheck>,
+    pub prover: ProverCheck,
+    pub peer_count: usize,
+    pub signer: SignerCheck,
+    /// Loaded circuits as `proof_type/version`
+    pub circuits: Vec<String>,
+}
+
+impl StartupReport {
+    /// Assemble the report from initialized components and RPC checks
+    pub fn new(
+        mut chains: Vec<ChainCheck>,
+        prover: &ProverService,
+        peer_count: usize,
+        signer: SignerCheck,
+    ) -> Self {
+        chains.sort_by_key(|chain| chain.chain_id);
+        Self {
+            chains,
+            prover: ProverCheck {
+                enabled: prover.is_enabled(),
+                backend: prover.backend().to_string(),
+                worker_alive: prover.worker_health().is_alive(),
+                ready: prover.is_available(),
+            },
+            peer_count,
+            signer,
+            circuits: prover.circuits().ids(),
+        }
+    }
+
+    /// Subsystems the node can't run without
+    ///
+    /// Unreachable chains and a dead prover worker are critical; a disabled
+    /// prover, missing peers or unknown balances are reported but tolerated.
+    pub fn critical_failures(&self) -> Vec<String> {
+        let mut failures: Vec<String> = self
+            .chains
+            .iter()
+            .filter(|chain| !chain.connected)
+            .map(|chain| {
+                format!(
+                    "chain {} unreachable: {}",
+                    chain.chain_id,
+                    chain.error.as_deref().unwrap_or("no head")
+                )
+            })
+            .collect();
+        if self.prover.enabled && !self.prover.worker_alive {
+            failures.push("prover worker not running".to_string());
+        }
+        failures
+    }
+
+    /// Log the report, failing if any critical subsystem is down
+    pub fn check(&self) -> Result<()> {
+        let summary = serde_json::to_string(self).unwrap_or_default();
+        let failures = self.critical_failures();
+        if failures.is_empty() {
+            info!(report = %summary, "Startup diagnostics passed");
+            return Ok(());
+        }
+
+        error!(report = %summary, failures = ?failures, "Startup diagnostics failed");
+        Err(anyhow::anyhow!(
+            "Startup diagnostics failed: {}",
+            failures.join("; ")
+        ))
+    }
+}
+
+/// Outcome of one subsystem probe in a `--check` run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubsystemResult {
+    pub subsystem: String,
+    pub passed: bool,
+    /// What was found, or why the probe failed
+    pub detail: String,
+}
+
+/// Per-subsystem results of a `--check` run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CheckReport {
+    pub results: Vec<SubsystemResult>,
+}
+
+impl CheckReport {
+    /// Record a probe: `Ok` carries a description of what was found
+    pub fn record(&mut self, subsystem: impl Into<String>, outcome: Result<String>) {
+        let (passed, detail) = match outcome {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, format!("{:#}", e)),
+        };
+        self.results.push(SubsystemResult {
+            subsystem: subsystem.into(),
+            passed,
+            detail,
+        });
+    }
+
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+
+    /// Result of `subsystem`, if it was probed
+    pub fn result(&self, subsystem: &str) -> Option<&SubsystemResult> {
+        self.results
+            .iter()
+            .find(|result| result.subsystem == subsystem)
+    }
+
+    /// One line per subsystem, for operators and deployment logs
+    pub fn render(&self) -> String {
+        self.results
+            .iter()
+            .map(|result| {
+                format!(
+                    "{} {}: {}",
+                    if result.passed { "PASS" } else { "FAIL" },
+                    result.subsystem,
+                    result.detail
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProverConfig;
+
+    #[tokio::test]
+    async fn test_report_reflects_initialized_components() {
+        let prover = ProverService::new(&ProverConfig::placeholder()).unwrap();
+        let signer = SignerCheck {
+            address: Address::repeat_byte(0x42),
+            balances: vec![
+                BalanceCheck::new(1, Ok(U256::from(10u64).pow(18.into()))),
+                BalanceCheck::new(42161, Err(anyhow::anyhow!("rate limited"))),
+            ],
+        };
+        let chains = vec![
+            ChainCheck::new(42161, Ok(2_000)),
+            ChainCheck::new(1, Ok(1_000)),
+        ];
+
+        let report = StartupReport::new(chains, &prover, 3, signer);
+        assert_eq!(report.chains[0].chain_id, 1);
+        assert_eq!(report.chains[0].head, Some(1_000));
+        assert!(report.chains.iter().all(|chain| chain.connected));
+        assert_eq!(
+            report.prover,
+            ProverCheck {
+                enabled: true,
+                backend: "placeholder".to_string(),
+                worker_alive: true,
+                ready: true,
+            }
+        );
+        assert_eq!(report.peer_count, 3);
+        assert_eq!(report.signer.address, Address::repeat_byte(0x42));
+        assert!(report.signer.balances[1].balance.is_none());
+        assert!(report.circuits.is_empty());
+
+        // A missing balance is tolerated
+        assert!(report.check().is_ok());
+
+        let mut unreachable = report.clone();
+        unreachable.chains[1] = ChainCheck::new(42161, Err(anyhow::anyhow!("connection refused")));
+        let failures = unreachable.critical_failures();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("42161"));
+        assert!(failures[0].contains("connection refused"));
+        assert!(unreachable.check().is_err());
+    }
+}
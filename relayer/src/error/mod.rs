@@ -0,0 +1,56 @@
+//! Errors returned by the relayer's services
+//!
+//! `LightClient`, `P2PNode` and `ProverService` fail with `RelayerError`, so
+//! callers can tell an unreachable RPC from a bad configuration or a failed
+//! proof without parsing messages. Internals keep their `anyhow` context in
+//! the variants that have no more specific type: the message includes it, and
+//! `source` leads to the underlying error for callers that need to inspect it.
+
+use crate::light_client::DeepReorg;
+use crate::prover::ProverError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RelayerError {
+    /// A chain's RPC endpoint failed or couldn't be reached
+    #[error("RPC error: {0:#}")]
+    Rpc(#[source] anyhow::Error),
+    /// Settings that can't be used as given
+    #[error("Invalid configuration: {0:#}")]
+    Config(#[source] anyhow::Error),
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+    /// The libp2p node couldn't start, publish or reach a peer
+    #[error("P2P error: {0:#}")]
+    P2p(#[source] anyhow::Error),
+    /// A chain reorganized further back than the retained headers reach
+    #[error(transparent)]
+    Reorg(#[from] DeepReorg),
+    /// A request rejected before any work was done
+    #[error("Invalid request: {0:#}")]
+    Validation(#[source] anyhow::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_cause_reachable_through_source() {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        let err = RelayerError::Rpc(
+            Err::<(), _>(io)
+                .context("Fetching head of chain 1")
+                .unwrap_err(),
+        );
+        assert_eq!(
+            err.to_string(),
+            "RPC error: Fetching head of chain 1: refused"
+        );
+
+        let cause = std::iter::successors(std::error::Error::source(&err), |e| e.source())
+            .find_map(|e| e.downcast_ref::<std::io::Error>())
+            .unwrap();
+        assert_eq!(cause.kind(), std::io::ErrorKind::ConnectionRefused);
+    }
+}
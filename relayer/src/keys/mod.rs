@@ -0,0 +1,243 @@
+//! Key material for the relayer node
+//!
+//! The libp2p identity key and the transaction signer are loaded from
+//! independent sources so either can be rotated without touching the other.
+//! The transaction signer can also be rotated while the node runs.
+
+pub mod rotation;
+
+use anyhow::{Context, Result};
+use ethers::signers::{LocalWallet, Signer};
+use libp2p::identity::Keypair;
+use std::path::Path;
+use tracing::{info, warn};
+use zeroize::Zeroizing;
+
+use crate::SignerConfig;
+
+pub use rotation::{ActiveSigner, Rotation, RotationError};
+
+/// Load the libp2p identity from `path`, creating and persisting one if missing
+///
+/// Without a path the identity is ephemeral and the peer ID changes on
+/// every restart.
+pub fn load_or_create_identity(path: Option<&Path>) -> Result<Keypair> {
+    let Some(path) = path else {
+        warn!("No identity_key_path configured, using an ephemeral P2P identity");
+        return Ok(Keypair::generate_ed25519());
+    };
+
+    if path.exists() {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read identity key {}", path.display()))?;
+        let keypair = Keypair::from_protobuf_encoding(&bytes)
+            .with_context(|| format!("Invalid identity key {}", path.display()))?;
+        info!(path = %path.display(), "Loaded P2P identity key");
+        return Ok(keypair);
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_private(path, &keypair.to_protobuf_encoding()?)?;
+    info!(path = %path.display(), "Generated new P2P identity key");
+    Ok(keypair)
+}
+
+/// Write a secret file readable only by the owner
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    use std::io::Write;
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?
+        .write_all(contents)?;
+    Ok(())
+}
+
+/// Load the transaction signer from its configured source
+///
+/// Falls back to the deprecated top-level `private_key` when no `[signer]`
+/// section is present. Keys and passwords read from the environment are
+/// removed from it and zeroed once the wallet is built.
+pub fn load_tx_signer(
+    signer: Option<&SignerConfig>,
+    legacy_key: Option<&str>,
+) -> Result<LocalWallet> {
+    let wallet = match (signer, legacy_key) {
+        (Some(SignerConfig::Env { var }), _) => {
+            let key = env_secret(var)?;
+            key.parse::<LocalWallet>()
+                .context("Invalid transaction signer key")?
+        }
+        (Some(SignerConfig::Keystore { path, password_env }), _) => {
+            let password = env_secret(password_env)?;
+            LocalWallet::decrypt_keystore(path, password.as_bytes())
+                .with_context(|| format!("Failed to decrypt keystore {}", path.display()))?
+        }
+        (None, Some(key)) => {
+            warn!(
+                "Loading the transaction signer from the plaintext `private_key` setting, \
+                 which is deprecated; configure a [signer] section instead"
+            );
+            Zeroizing::new(crate::expand_env(key)?)
+                .parse::<LocalWallet>()
+                .context("Invalid transaction signer key")?
+        }
+        (None, None) => anyhow::bail!("No transaction signer configured"),
+    };
+
+    info!(address = ?wallet.address(), "Loaded transaction signer");
+    Ok(wallet)
+}
+
+/// Take a secret out of the environment, so child processes and later
+/// reads of the environment don't see it
+fn env_secret(var: &str) -> Result<Zeroizing<String>> {
+    let secret = Zeroizing::new(
+        std::env::var(var)
+            .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", var))?,
+    );
+    std::env::remove_var(var);
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TX_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    /// PBKDF2 test vector from the Web3 Secret Storage definition, password
+    /// "testpassword"
+    const V3_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+    const V3_KEYSTORE_KEY: &str =
+        "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    #[test]
+    fn test_identity_persisted_across_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("identity.key");
+
+        let first = load_or_create_identity(Some(&path)).unwrap();
+        let second = load_or_create_identity(Some(&path)).unwrap();
+        assert_eq!(first.public(), second.public());
+
+        // Without a path every load is a fresh identity
+        let ephemeral = load_or_create_identity(None).unwrap();
+        assert_ne!(ephemeral.public(), first.public());
+    }
+
+    #[test]
+    fn test_identity_and_tx_keys_independent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        let var = "LAUNDRY_TEST_TX_SIGNER_KEY";
+        std::env::set_var(var, TX_KEY);
+        let signer_config = SignerConfig::Env {
+            var: var.to_string(),
+        };
+
+        let identity = load_or_create_identity(Some(&path)).unwrap();
+        let signer = load_tx_signer(Some(&signer_config), None).unwrap();
+
+        let identity_secret = identity
+            .clone()
+            .try_into_ed25519()
+            .unwrap()
+            .secret()
+            .as_ref()
+            .to_vec();
+        assert_ne!(identity_secret, signer.signer().to_bytes().to_vec());
+
+        // Rotating the identity leaves the signer untouched
+        std::fs::remove_file(&path).unwrap();
+        let rotated = load_or_create_identity(Some(&path)).unwrap();
+        assert_ne!(rotated.public(), identity.public());
+        std::env::set_var(var, TX_KEY);
+        assert_eq!(
+            load_tx_signer(Some(&signer_config), None)
+                .unwrap()
+                .address(),
+            signer.address()
+        );
+
+        // An explicit signer source takes precedence over the legacy key
+        let legacy = format!("{:064x}", 1);
+        std::env::set_var(var, TX_KEY);
+        assert_eq!(
+            load_tx_signer(Some(&signer_config), Some(&legacy))
+                .unwrap()
+                .address(),
+            signer.address()
+        );
+        assert_ne!(
+            load_tx_signer(None, Some(&legacy)).unwrap().address(),
+            signer.address()
+        );
+    }
+
+    #[test]
+    fn test_keystore_signer_decrypts_v3() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signer.json");
+        std::fs::write(&path, V3_KEYSTORE).unwrap();
+        let password_env = "LAUNDRY_TEST_KEYSTORE_PASSWORD";
+        let signer_config = SignerConfig::Keystore {
+            path: path.clone(),
+            password_env: password_env.to_string(),
+        };
+
+        std::env::set_var(password_env, "testpassword");
+        let signer = load_tx_signer(Some(&signer_config), None).unwrap();
+        assert_eq!(hex::encode(signer.signer().to_bytes()), V3_KEYSTORE_KEY);
+
+        std::env::set_var(password_env, "wrongpassword");
+        assert!(load_tx_signer(Some(&signer_config), None).is_err());
+    }
+
+    #[test]
+    fn test_env_signer() {
+        let var = "LAUNDRY_TEST_ENV_SIGNER_KEY";
+        let signer_config = SignerConfig::Env {
+            var: var.to_string(),
+        };
+
+        std::env::remove_var(var);
+        let err = load_tx_signer(Some(&signer_config), None).unwrap_err();
+        assert!(err.to_string().contains(var));
+
+        std::env::set_var(var, format!("0x{}", TX_KEY));
+        let signer = load_tx_signer(Some(&signer_config), None).unwrap();
+        assert_eq!(hex::encode(signer.signer().to_bytes()), TX_KEY);
+        // The key is gone from the environment once read
+        assert!(std::env::var(var).is_err());
+
+        std::env::set_var(var, "not a key");
+        assert!(load_tx_signer(Some(&signer_config), None).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//! Rotating the transaction signer without a restart
+//!
+//! A submission takes the active signer together with the nonce managers
+//! for its address, so a transaction is always signed by the key whose
+//! nonces it uses. A rotation checks the new key can submit on every chain
+//! (funded, and registered wherever the pool only accepts registered
+//! relayers), then swaps it in: submissions from that point use the new key
+//! immediately, while transactions already in flight on the old key are
+//! left to complete. The old key's managers are then drained and retired.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::abi::{decode, encode, ParamType, Token};
+use ethers::prelude::*;
+use ethers::utils::id;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::light_client::RpcProvider;
+use crate::submitter::{ChainSubmitters, InFlight};
+
+/// Default time a rotation waits for the old key's transactions to finish
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A signer and the nonce managers for its address
+pub struct SignerAccount {
+    pub wallet: LocalWallet,
+    pub submitters: ChainSubmitters,
+}
+
+impl SignerAccount {
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+}
+
+/// Builds the nonce managers for a signer address
+pub type SubmittersFactory = Box<dyn Fn(Address) -> Result<ChainSubmitters> + Send + Sync>;
+
+/// Whether an address is fit to submit relay transactions
+#[async_trait]
+pub trait SignerCheck: Send + Sync {
+    /// `Err` explaining why `address` can't submit
+    async fn check(&self, address: Address) -> Result<()>;
+}
+
+/// Why a rotation didn't happen
+#[derive(Debug, thiserror::Error)]
+pub enum RotationError {
+    #[error("{0:?} is already the active signer")]
+    Unchanged(Address),
+    #[error("Another key rotation is in progress")]
+    InProgress,
+    #[error("New signer {address:?} can't submit: {reason:#}")]
+    Unfit {
+        address: Address,
+        reason: anyhow::Error,
+    },
+}
+
+/// Outcome of a completed rotation
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Rotation {
+    pub previous: Address,
+    pub active: Address,
+    /// Whether the old key's in-flight transactions finished within the
+    /// drain timeout
+    pub drained: bool,
+}
+
+/// The transaction signer currently used for submissions
+pub struct ActiveSigner {
+    current: RwLock<Arc<SignerAccount>>,
+    submitters: SubmittersFactory,
+    check: Arc<dyn SignerCheck>,
+    drain_timeout: Duration,
+    rotating: tokio::sync::Mutex<()>,
+}
+
+impl ActiveSigner {
+    pub fn new(
+        wallet: LocalWallet,
+        submitters: SubmittersFactory,
+        check: Arc<dyn SignerCheck>,
+        drain_timeout: Duration,
+    ) -> Result<Self> {
+        let account = SignerAccount {
+            submitters: submitters(wallet.address())?,
+            wallet,
+        };
+        Ok(Self {
+            current: RwLock::new(Arc::new(account)),
+            submitters,
+            check,
+            drain_timeout,
+            rotating: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Address of the active signer
+    pub fn address(&self) -> Address {
+        self.current.read().unwrap().address()
+    }
+
+    /// Submit on `chain_id` with the active signer
+    ///
+    /// `send` signs with the given wallet and broadcasts at the given nonce.
+    pub async fn submit<F, Fut>(&self, chain_id: u64, send: F) -> Result<InFlight>
+    where
+        F: FnOnce(LocalWallet, U256) -> Fut,
+        Fut: Future<Output = Result<crate::submitter::Broadcast>>,
+    {
+        let account = self.current.read().unwrap().clone();
+        let wallet = account.wallet.clone();
+        account
+            .submitters
+            .submit(chain_id, move |nonce| send(wallet, nonce))
+            .await
+    }
+
+    /// Swap in `wallet` for future submissions, then wait for the old
+    /// key's in-flight transactions
+    pub async fn rotate(&self, wallet: LocalWallet) -> Result<Rotation, RotationError> {
+        let _rotating = self
+            .rotating
+            .try_lock()
+            .map_err(|_| RotationError::InProgress)?;
+        let address = wallet.address();
+        let previous = self.current.read().unwrap().clone();
+        if previous.address() == address {
+            return Err(RotationError::Unchanged(address));
+        }
+
+        let unfit = |reason| RotationError::Unfit { address, reason };
+        self.check.check(address).await.map_err(unfit)?;
+        let account = SignerAccount {
+            submitters: (self.submitters)(address).map_err(unfit)?,
+            wallet,
+        };
+        *self.current.write().unwrap() = Arc::new(account);
+        info!(previous = ?previous.address(), active = ?address, "Transaction signer rotated");
+
+        let drained = tokio::time::timeout(self.drain_timeout, previous.submitters.drain())
+            .await
+            .is_ok();
+        if !drained {
+            warn!(
+                previous = ?previous.address(),
+                "Old signer still has transactions in flight after the drain timeout"
+            );
+        }
+        Ok(Rotation {
+            previous: previous.address(),
+            active: address,
+            drained,
+        })
+    }
+}
+
+/// A chain a signer must be able to submit on
+pub struct SubmitChain {
+    pub chain_id: u64,
+    pub provider: RpcProvider,
+    /// Pool the relayer submits to, checked for relayer registration
+    pub pool: Option<Address>,
+}
+
+/// Checks a signer against each chain over RPC
+pub struct ChainSignerCheck {
+    pub chains: Vec<SubmitChain>,
+}
+
+#[async_trait]
+impl SignerCheck for ChainSignerCheck {
+    async fn check(&self, address: Address) -> Result<()> {
+        for chain in &self.chains {
+            let balance = chain.provider.get_balance(address, None).await?;
+            if balance.is_zero() {
+                anyhow::bail!("No balance on chain {}", chain.chain_id);
+            }
+            if let Some(pool) = chain.pool {
+                if !registered_if_required(&chain.provider, pool, address).await? {
+                    anyhow::bail!(
+                        "Not an active registered relayer for the pool on chain {}",
+                        chain.chain_id
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `relayer` may submit to `pool`: always, unless the pool requires
+/// registered relayers and the registry doesn't list it as active
+async fn registered_if_required(
+    provider: &RpcProvider,
+    pool: Address,
+    relayer: Address,
+) -> Result<bool> {
+    // Pools without a registry don't have the getter and revert
+    let required = match view(provider, pool, "requireRegisteredRelayer()", &[]).await {
+        Ok(output) => decode(&[ParamType::Bool], &output)?,
+        Err(e) if e.as_error_response().is_some() => return Ok(true),
+        Err(e) => return Err(e.into()),
+    };
+    if required.first() != Some(&Token::Bool(true)) {
+        return Ok(true);
+    }
+
+    let output = view(provider, pool, "relayerRegistry()", &[]).await?;
+    let Some(Token::Address(registry)) = decode(&[ParamType::Address], &output)?.pop() else {
+        anyhow::bail!("Malformed relayer registry address");
+    };
+    let output = view(
+        provider,
+        registry,
+        "isActiveRelayer(address)",
+        &[Token::Address(relayer)],
+    )
+    .await?;
+    Ok(decode(&[ParamType::Bool], &output)?.first() == Some(&Token::Bool(true)))
+}
+
+async fn view(
+    provider: &RpcProvider,
+    to: Address,
+    signature: &str,
+    args: &[Token],
+) -> Result<Bytes, ProviderError> {
+    let mut data = id(signature).to_vec();
+    data.extend(encode(args));
+    let call: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    provider.call(&call, None).await
+}
+
+/// Accepts every signer, for tests across the crate
+#[cfg(test)]
+pub(crate) struct AcceptAll;
+
+#[cfg(test)]
+#[async_trait]
+impl SignerCheck for AcceptAll {
+    async fn check(&self, _address: Address) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A random signer with no chains to submit on, for tests across the crate
+#[cfg(test)]
+pub(crate) fn test_signer() -> Arc<ActiveSigner> {
+    Arc::new(
+        ActiveSigner::new(
+            LocalWallet::new(&mut rand::thread_rng()),
+            Box::new(|_| Ok(ChainSubmitters::default())),
+            Arc::new(AcceptAll),
+            DEFAULT_DRAIN_TIMEOUT,
+        )
+        .unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::submitter::route::BroadcastRoute;
+    use crate::submitter::{Broadcast, NonceManager, NonceSource};
+
+    /// Each address's nonces start at its first byte
+    struct NoncePerAddress;
+
+    #[async_trait]
+    impl NonceSource for NoncePerAddress {
+        async fn pending_nonce(&self, address: Address) -> Result<U256> {
+            Ok(U256::from(address.0[0]))
+        }
+    }
+
+    struct Unfunded(Address);
+
+    #[async_trait]
+    impl SignerCheck for Unfunded {
+        async fn check(&self, address: Address) -> Result<()> {
+            if address == self.0 {
+                anyhow::bail!("No balance on chain 1");
+            }
+            Ok(())
+        }
+    }
+
+    fn signer(check: Arc<dyn SignerCheck>) -> ActiveSigner {
+        ActiveSigner::new(
+            LocalWallet::new(&mut rand::thread_rng()),
+            Box::new(|address| {
+                let mut submitters = ChainSubmitters::default();
+                submitters.insert(NonceManager::new(1, address, Arc::new(NoncePerAddress), 2));
+                Ok(submitters)
+            }),
+            check,
+            Duration::from_secs(10),
+        )
+        .unwrap()
+    }
+
+    /// Submit on chain 1, returning the in-flight guard and who signed
+    async fn submit(signer: &ActiveSigner) -> Result<(InFlight, Address)> {
+        let signed_by = Arc::new(std::sync::Mutex::new(Address::zero()));
+        let record = signed_by.clone();
+        let in_flight = signer
+            .submit(1, |wallet, nonce| async move {
+                *record.lock().unwrap() = wallet.address();
+                Ok(Broadcast {
+                    tx_hash: H256::from_low_u64_be(nonce.as_u64()),
+                    route: BroadcastRoute::Public,
+                    expires_at_block: None,
+                })
+            })
+            .await?;
+        let address = *signed_by.lock().unwrap();
+        Ok((in_flight, address))
+    }
+
+    #[tokio::test]
+    async fn test_rotation_switches_new_submissions_and_drains_old() {
+        let signer = Arc::new(signer(Arc::new(AcceptAll)));
+        let old = signer.address();
+        let (old_in_flight, signed_by) = submit(&signer).await.unwrap();
+        assert_eq!(signed_by, old);
+        assert_eq!(old_in_flight.nonce, U256::from(old.0[0]));
+
+        let new_wallet = LocalWallet::new(&mut rand::thread_rng());
+        let new = new_wallet.address();
+        let rotation = {
+            let signer = signer.clone();
+            tokio::spawn(async move { signer.rotate(new_wallet).await })
+        };
+        while signer.address() != new {
+            tokio::task::yield_now().await;
+        }
+
+        // New submissions use the new key and its nonces, while the rotation
+        // waits on the old key's transaction
+        let (new_in_flight, signed_by) = submit(&signer).await.unwrap();
+        assert_eq!(signed_by, new);
+        assert_eq!(new_in_flight.nonce, U256::from(new.0[0]));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!rotation.is_finished());
+
+        // The old transaction completes and the rotation finishes drained
+        drop(old_in_flight);
+        let rotation = rotation.await.unwrap().unwrap();
+        assert_eq!(
+            rotation,
+            Rotation {
+                previous: old,
+                active: new,
+                drained: true,
+            }
+        );
+        drop(new_in_flight);
+    }
+
+    #[tokio::test]
+    async fn test_unfit_signer_not_swapped_in() {
+        let unfunded = LocalWallet::new(&mut rand::thread_rng());
+        let signer = signer(Arc::new(Unfunded(unfunded.address())));
+        let old = signer.address();
+
+        assert!(matches!(
+            signer.rotate(unfunded).await,
+            Err(RotationError::Unfit { .. })
+        ));
+        assert_eq!(signer.address(), old);
+        let (_in_flight, signed_by) = submit(&signer).await.unwrap();
+        assert_eq!(signed_by, old);
+    }
+}
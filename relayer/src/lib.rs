@@ -0,0 +1,2258 @@
+//! Laundry Cash Relayer Node
+//!
+//! The relayer node performs the following functions:
+//! - Submits withdrawal transactions on behalf of users (gas-less withdrawals)
+//! - Synchronizes block headers across chains (light client)
+//! - Participates in the P2P relayer network
+//! - Generates ZK proofs (optional, with proper hardware)
+//!
+//! The node runs from the `relayer` binary through `run`. The library also
+//! exports the proof wire format helpers, and, with the `client` feature, a
+//! typed client for the node's HTTP API.
+
+mod api;
+mod bench;
+mod channel;
+#[cfg(feature = "client")]
+pub mod client;
+mod diagnostics;
+mod error;
+mod keys;
+mod light_client;
+mod merkle;
+mod metrics;
+mod p2p;
+mod prover;
+mod quote;
+mod relay;
+mod store;
+mod submitter;
+mod watcher;
+
+pub use prover::{
+    compress_proof, decompress_proof, deserialize_public_inputs, serialize_public_inputs,
+    ProofSystem, WireError,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use futures::future::{BoxFuture, FutureExt, TryFutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::{debug, info, warn, Level};
+use tracing_subscriber::FmtSubscriber;
+
+/// Laundry Cash Relayer Node
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to configuration file
+    #[arg(short, long, default_value = "config/relayer.toml")]
+    config: PathBuf,
+
+    /// Log level (trace, debug, info, warn, error)
+    #[arg(short, long, default_value = "info")]
+    log_level: String,
+
+    /// Enable metrics endpoint
+    #[arg(long, default_value = "false")]
+    metrics: bool,
+
+    /// Metrics port
+    #[arg(long, default_value = "9090")]
+    metrics_port: u16,
+
+    /// HTTP API port
+    #[arg(long, default_value = "8080")]
+    api_port: u16,
+
+    /// Probe every subsystem with this config, report, and exit without starting
+    #[arg(long, default_value = "false")]
+    check: bool,
+
+    /// Push this many synthetic relays through the pipeline, report
+    /// throughput and latency, and exit without starting
+    #[arg(long)]
+    bench: Option<usize>,
+
+    /// Relays in flight at once during `--bench`
+    #[arg(long, default_value_t = bench::DEFAULT_BENCH_CONCURRENCY)]
+    bench_concurrency: usize,
+}
+
+/// Run the node with the command-line arguments
+pub async fn run() -> Result<()> {
+    let args = Args::parse();
+
+    // Initialize logging
+    let log_level = match args.log_level.as_str() {
+        "trace" => Level::TRACE,
+        "debug" => Level::DEBUG,
+        "info" => Level::INFO,
+        "warn" => Level::WARN,
+        "error" => Level::ERROR,
+        _ => Level::INFO,
+    };
+
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(log_level)
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_file(true)
+        .with_line_number(true)
+        .json()
+        .finish();
+
+    tracing::subscriber::set_global_default(subscriber)?;
+    let started = std::time::Instant::now();
+
+    info!(
+        "Starting Laundry Cash Relayer v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    info!("Loading configuration from {:?}", args.config);
+
+    // Load configuration
+    let config = load_config(&args.config)?;
+
+    // Catch port clashes before anything binds
+    let metrics_port = args.metrics.then_some(args.metrics_port);
+
+    if args.check {
+        let report = run_config_check(&config, args.api_port, metrics_port).await;
+        println!("{}", report.render());
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(requests) = args.bench {
+        let report = bench::run(&config.prover, requests, args.bench_concurrency).await?;
+        println!("{}", report.render());
+        return Ok(());
+    }
+
+    validate_ports(args.api_port, metrics_port, &config.p2p.listen_addr)?;
+    check_ports_available(args.api_port, metrics_port, &config.p2p.listen_addr)?;
+
+    // Load the transaction signer up front so a bad key fails at startup
+    let tx_signer = keys::load_tx_signer(
+        config.signer.as_ref(),
+        config.private_key.as_ref().map(|key| key.as_str()),
+    )?;
+    let signer_address = ethers::signers::Signer::address(&tx_signer);
+    let signer = std::sync::Arc::new(active_signer(&config, tx_signer.clone())?);
+
+    // Open persistent storage
+    let store = store::open(&config.database_url).await?;
+
+    // Initialize components
+    let (light_client, p2p_node, prover) = initialize_components(&config, store.clone()).await?;
+
+    // Confirm everything came up before serving traffic
+    let diagnostics =
+        run_startup_diagnostics(&config, &light_client, &p2p_node, &prover, signer_address).await;
+    diagnostics.check()?;
+
+    // Shared by the event loop and the `/prove` endpoint
+    let prover = std::sync::Arc::new(prover);
+
+    // Recent roots of every watched pool, rebuilt from its past deposits
+    // and then fed by new ones
+    let (roots, watch_from) = pool_roots(&config, &light_client, &prover).await?;
+
+    let fees = submitter::FeeSettings {
+        strategy: config.transactions.fee_strategy,
+        max_priority_fee: config
+            .transactions
+            .max_priority_fee_gwei
+            .map(|gwei| ethers::types::U256::from(gwei) * ethers::types::U256::exp10(9)),
+        max_fee: None,
+    };
+
+    // Quotes are priced the way each chain's submitter prices withdrawals
+    let mut chain_pricing = HashMap::new();
+    for endpoints in &config.chains {
+        let pricing = quote::ChainPricing {
+            oracle: std::sync::Arc::new(light_client::http_provider(endpoints)?),
+            fees: fees.for_chain(&submitter::GasPolicy::from(endpoints)),
+            gas_limit: endpoints.withdraw_gas_limit,
+        };
+        chain_pricing.insert(endpoints.chain_id, pricing);
+    }
+    let pricing = quote::FeePricing::new(chain_pricing, config.quote.fee_margin_bps);
+    let quotes = std::sync::Arc::new(quote::QuoteBook::new(
+        tx_signer,
+        std::time::Duration::from_secs(config.quote.validity_secs),
+        config.quote.absorb_buffer_bps,
+        pricing,
+        store.clone(),
+    ));
+
+    // Shared by the HTTP and P2P relay paths
+    let validation = relay::RelayContext {
+        prover: prover.clone(),
+        circuits: prover.circuits(),
+        quotes: quotes.clone(),
+        roots: roots.clone(),
+        sources: std::sync::Arc::new(
+            config
+                .chains
+                .iter()
+                .map(|endpoints| {
+                    let source: std::sync::Arc<dyn watcher::LogSource> =
+                        std::sync::Arc::new(light_client::http_provider(endpoints)?);
+                    Ok((endpoints.chain_id, source))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        chains: std::sync::Arc::new(light_client.finality_handles()),
+        store: store.clone(),
+        min_fee: api::FLAT_FEE_WEI.into(),
+        input_commitments: std::sync::Arc::new(
+            config
+                .chains
+                .iter()
+                .map(|endpoints| (endpoints.chain_id, endpoints.public_input_commitment))
+                .collect(),
+        ),
+        pools: std::sync::Arc::new(
+            config
+                .chains
+                .iter()
+                .map(|endpoints| {
+                    let served = relay::ServedPools {
+                        default: endpoints.pool_address,
+                        allowed: endpoints
+                            .allowed_pools
+                            .iter()
+                            .map(|pool| pool.address)
+                            .collect(),
+                    };
+                    (endpoints.chain_id, served)
+                })
+                .collect(),
+        ),
+        dedup: std::sync::Arc::new(relay::RelayDedup::new(
+            std::time::Duration::from_secs(config.p2p.relay_dedup_window_secs),
+            config.p2p.relay_dedup_capacity,
+        )),
+    };
+
+    // Accepted relays are submitted as pool withdrawals
+    let relays = std::sync::Arc::new(relay::RelayTracker::default());
+    let finality = light_client.finality_handles();
+    let mut withdrawal_chains = HashMap::new();
+    for endpoints in &config.chains {
+        if let Some(chain) = finality.get(&endpoints.chain_id) {
+            let withdrawals = submitter::ChainWithdrawals::from_endpoints(
+                endpoints,
+                fees,
+                chain.clone(),
+                relays.clone(),
+                store.clone(),
+            )?;
+            withdrawal_chains.insert(endpoints.chain_id, withdrawals);
+        }
+    }
+    let withdrawals = std::sync::Arc::new(submitter::WithdrawalSubmitter::new(
+        signer.clone(),
+        relays.clone(),
+        withdrawal_chains,
+        std::sync::Arc::new(submitter::TxStatuses::new(std::time::Duration::from_secs(
+            config.transactions.status_ttl_secs,
+        ))),
+        quotes.clone(),
+    ));
+
+    // Start HTTP API server; relays it accepts are gossiped by the event loop
+    let (gossip_tx, gossip_rx) = tokio::sync::mpsc::channel(1000);
+    let api_state = api::AppState {
+        identity: p2p_node.identity(),
+        circuits: prover.circuits(),
+        admin: std::sync::Arc::new(api::admin::AdminAuth::new(
+            config.admin.operator_address,
+            std::time::Duration::from_secs(config.admin.challenge_ttl_secs),
+        )),
+        relays,
+        chains: std::sync::Arc::new(finality),
+        quotes,
+        prover: prover.clone(),
+        diagnostics: std::sync::Arc::new(diagnostics),
+        started,
+        roots,
+        resync: std::sync::Arc::new(light_client.resync_handles()),
+        validation: validation.clone(),
+        proofs: (config.prover.public_proofs_per_minute > 0).then(|| api::PublicProver {
+            prover: prover.clone(),
+            limit: std::sync::Arc::new(api::limit::RateLimiter::per_minute(
+                config.prover.public_proofs_per_minute,
+            )),
+        }),
+        peers: p2p_node.peers(),
+        served_proofs: p2p_node.proofs(),
+        signer,
+        withdrawals: Some(withdrawals.clone()),
+        gossip: Some(gossip_tx),
+    };
+    let api_handle = start_api_server(args.api_port, api_state).await?;
+
+    // Start metrics server if enabled
+    if args.metrics {
+        start_metrics_server(args.metrics_port).await?;
+    }
+
+    // Keep the store from growing without bound
+    let maintenance = store::maintenance::StoreMaintenance::new(
+        store.clone(),
+        light_client.finality_handles(),
+        config.maintenance.retention(),
+        std::time::Duration::from_secs(config.maintenance.compact_interval_secs),
+    );
+    tokio::spawn(maintenance.run(std::time::Duration::from_secs(
+        config.maintenance.interval_secs,
+    )));
+
+    // Watch pool contracts for events that trigger relays
+    let triggers = start_pool_watchers(&config, &light_client, &watch_from)?;
+
+    // Run main event loop
+    let shutdown = Shutdown {
+        grace: std::time::Duration::from_secs(config.shutdown.grace_period_secs),
+        timeout: std::time::Duration::from_secs(config.shutdown.timeout_secs),
+        withdrawals,
+    };
+    run_event_loop(
+        light_client,
+        p2p_node,
+        prover,
+        triggers,
+        gossip_rx,
+        validation,
+        shutdown,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Relayer configuration
+#[derive(Debug, serde::Deserialize)]
+struct RelayerConfig {
+    /// Chains the relayer serves, each with its own RPC endpoints
+    #[serde(default)]
+    chains: Vec<ChainEndpoints>,
+    /// Pre-`chains` Ethereum section, folded into `chains` on load
+    #[serde(default)]
+    ethereum: Option<ChainEndpoints>,
+    /// Pre-`chains` Arbitrum section, folded into `chains` on load
+    #[serde(default)]
+    arbitrum: Option<ChainEndpoints>,
+    /// Legacy plaintext transaction key, used when no `[signer]` is configured
+    #[serde(default)]
+    private_key: Option<zeroize::Zeroizing<String>>,
+    /// Source of the transaction signing key
+    #[serde(default)]
+    signer: Option<SignerConfig>,
+    /// Database URL (`sqlite://path` or `rocksdb://path`; a bare path is SQLite)
+    #[serde(alias = "database_path")]
+    database_url: String,
+    /// P2P configuration
+    p2p: P2PConfig,
+    /// Prover configuration
+    prover: ProverConfig,
+    /// Admin API authentication
+    #[serde(default)]
+    admin: AdminConfig,
+    /// Graceful shutdown limits
+    #[serde(default)]
+    shutdown: ShutdownConfig,
+    /// Binding fee quotes
+    #[serde(default)]
+    quote: QuoteConfig,
+    /// Store pruning and compaction
+    #[serde(default)]
+    maintenance: MaintenanceConfig,
+    /// How relayer transactions are priced
+    #[serde(default)]
+    transactions: TransactionsConfig,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TransactionsConfig {
+    /// `legacy` or `eip1559` (the default); EIP-1559 falls back to legacy on
+    /// chains without a base fee
+    #[serde(default)]
+    fee_strategy: submitter::FeeStrategy,
+    /// Highest priority tip offered, in gwei (unset follows recent blocks)
+    #[serde(default)]
+    max_priority_fee_gwei: Option<u64>,
+    /// How long `GET /tx/<id>` remembers a request after its last change
+    #[serde(default = "default_tx_status_ttl_secs")]
+    status_ttl_secs: u64,
+}
+
+fn default_tx_status_ttl_secs() -> u64 {
+    submitter::status::DEFAULT_TX_STATUS_TTL.as_secs()
+}
+
+impl Default for TransactionsConfig {
+    fn default() -> Self {
+        Self {
+            fee_strategy: Default::default(),
+            max_priority_fee_gwei: None,
+            status_ttl_secs: default_tx_status_ttl_secs(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MaintenanceConfig {
+    /// Time between maintenance passes
+    #[serde(default = "default_maintenance_interval_secs")]
+    interval_secs: u64,
+    /// Headers kept below each chain's finalized block
+    #[serde(default = "default_header_retention_blocks")]
+    header_retention_blocks: u64,
+    /// Age after which audit entries are deleted
+    #[serde(default = "default_audit_retention_days")]
+    audit_retention_days: u64,
+    /// Age after which reorg records are deleted
+    #[serde(default = "default_reorg_retention_days")]
+    reorg_retention_days: u64,
+    /// Age after which a peer's reputation score is deleted, counted from
+    /// its last change
+    #[serde(default = "default_reputation_retention_days")]
+    reputation_retention_days: u64,
+    /// Time between compactions, which rewrite the whole store
+    #[serde(default = "default_compact_interval_secs")]
+    compact_interval_secs: u64,
+}
+
+fn default_maintenance_interval_secs() -> u64 {
+    store::maintenance::DEFAULT_MAINTENANCE_INTERVAL.as_secs()
+}
+
+fn default_header_retention_blocks() -> u64 {
+    store::maintenance::DEFAULT_HEADER_RETENTION_BLOCKS
+}
+
+fn default_audit_retention_days() -> u64 {
+    store::maintenance::DEFAULT_AUDIT_RETENTION.as_secs() / (24 * 3600)
+}
+
+fn default_reorg_retention_days() -> u64 {
+    store::maintenance::DEFAULT_REORG_RETENTION.as_secs() / (24 * 3600)
+}
+
+fn default_reputation_retention_days() -> u64 {
+    store::maintenance::DEFAULT_REPUTATION_RETENTION.as_secs() / (24 * 3600)
+}
+
+fn default_compact_interval_secs() -> u64 {
+    store::maintenance::DEFAULT_COMPACT_INTERVAL.as_secs()
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_maintenance_interval_secs(),
+            header_retention_blocks: default_header_retention_blocks(),
+            audit_retention_days: default_audit_retention_days(),
+            reorg_retention_days: default_reorg_retention_days(),
+            reputation_retention_days: default_reputation_retention_days(),
+            compact_interval_secs: default_compact_interval_secs(),
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    fn retention(&self) -> store::maintenance::RetentionPolicy {
+        store::maintenance::RetentionPolicy {
+            header_blocks: self.header_retention_blocks,
+            audit_age: std::time::Duration::from_secs(self.audit_retention_days * 24 * 3600),
+            reorg_age: std::time::Duration::from_secs(self.reorg_retention_days * 24 * 3600),
+            reputation_age: std::time::Duration::from_secs(
+                self.reputation_retention_days * 24 * 3600,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct QuoteConfig {
+    /// How long an issued quote can be honored
+    #[serde(default = "default_quote_validity_secs")]
+    validity_secs: u64,
+    /// Gas cost overrun (basis points of the quoted fee) absorbed before deferring
+    #[serde(default = "default_absorb_buffer_bps")]
+    absorb_buffer_bps: u64,
+    /// Margin over the estimated gas cost added to quoted fees, in basis points
+    #[serde(default = "default_fee_margin_bps")]
+    fee_margin_bps: u64,
+}
+
+fn default_quote_validity_secs() -> u64 {
+    quote::DEFAULT_QUOTE_VALIDITY.as_secs()
+}
+
+fn default_absorb_buffer_bps() -> u64 {
+    quote::DEFAULT_ABSORB_BUFFER_BPS
+}
+
+fn default_fee_margin_bps() -> u64 {
+    quote::DEFAULT_FEE_MARGIN_BPS
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            validity_secs: default_quote_validity_secs(),
+            absorb_buffer_bps: default_absorb_buffer_bps(),
+            fee_margin_bps: default_fee_margin_bps(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AdminConfig {
+    /// Address whose signatures authorize admin mutations (unset disables them)
+    #[serde(default)]
+    operator_address: Option<ethers::types::Address>,
+    /// How long an issued admin challenge stays valid
+    #[serde(default = "default_challenge_ttl_secs")]
+    challenge_ttl_secs: u64,
+}
+
+fn default_challenge_ttl_secs() -> u64 {
+    api::admin::DEFAULT_CHALLENGE_TTL.as_secs()
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            operator_address: None,
+            challenge_ttl_secs: default_challenge_ttl_secs(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ShutdownConfig {
+    /// Time accepted proofs and withdrawals get to finish before components
+    /// are stopped
+    #[serde(default = "default_shutdown_grace_period_secs")]
+    grace_period_secs: u64,
+    /// Overall deadline for draining components before forcing exit
+    #[serde(default = "default_shutdown_timeout_secs")]
+    timeout_secs: u64,
+    /// Time the orchestrator allows between SIGTERM and SIGKILL; the grace
+    /// period and timeout together must end before it
+    #[serde(default = "default_shutdown_termination_period_secs")]
+    termination_period_secs: u64,
+}
+
+fn default_shutdown_grace_period_secs() -> u64 {
+    10
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    15
+}
+
+/// Kubernetes' default `terminationGracePeriodSeconds`
+fn default_shutdown_termination_period_secs() -> u64 {
+    30
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: default_shutdown_grace_period_secs(),
+            timeout_secs: default_shutdown_timeout_secs(),
+            termination_period_secs: default_shutdown_termination_period_secs(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ChainEndpoints {
+    /// HTTP endpoints in order of preference; older configs give a single
+    /// `http_url`
+    #[serde(alias = "http_url", deserialize_with = "one_or_more_urls")]
+    http_urls: Vec<String>,
+    ws_url: Option<String>,
+    chain_id: u64,
+    /// Blocks behind the head before a header is treated as final
+    #[serde(default = "default_finality_depth")]
+    finality_depth: u64,
+    /// Recent headers kept in memory; reorgs reaching further back resync
+    #[serde(default = "default_retained_headers")]
+    retained_headers: usize,
+    /// Accept blocks sharing their parent's timestamp; unset allows it only
+    /// on chains known to produce several blocks a second
+    #[serde(default)]
+    allow_equal_timestamps: Option<bool>,
+    /// How far the finalized height may move backwards before raising an alert
+    #[serde(default)]
+    finality_regression_tolerance: u64,
+    /// Opt in to raising the finality depth when trusted peers report deep
+    /// reorgs, never beyond this many blocks (unset keeps the depth fixed)
+    #[serde(default)]
+    adaptive_finality_max_depth: Option<u64>,
+    /// Highest gas price (in gwei) the relayer will submit at
+    #[serde(default)]
+    max_gas_price_gwei: Option<u64>,
+    /// Whether to defer or reject submissions while gas is above the ceiling
+    #[serde(default)]
+    gas_ceiling_action: submitter::GasCeilingAction,
+    /// Whether the verifier takes every public input or a hash of them
+    #[serde(default)]
+    public_input_commitment: submitter::InputCommitment,
+    /// Where signed transactions are broadcast (public mempool by default)
+    #[serde(default)]
+    submission_route: submitter::SubmissionRoute,
+    /// Broadcast transactions allowed to await confirmation at once;
+    /// further submissions on the chain wait for a slot
+    #[serde(default = "default_max_in_flight")]
+    max_in_flight: usize,
+    /// HTTP endpoint used while every `http_urls` entry is failing (sent
+    /// without `headers`)
+    #[serde(default)]
+    fallback_http_url: Option<String>,
+    /// Consecutive RPC failures before an endpoint is taken out of rotation
+    /// in favour of the next
+    #[serde(default = "default_breaker_failure_threshold")]
+    breaker_failure_threshold: u32,
+    /// Seconds before a failing endpoint is probed again (doubles while
+    /// probes keep failing)
+    #[serde(default = "default_breaker_cooldown_secs")]
+    breaker_cooldown_secs: u64,
+    /// Seconds an endpoint has to answer a request before the call counts
+    /// as failed
+    #[serde(default = "default_rpc_timeout_secs")]
+    rpc_timeout_secs: u64,
+    /// Pool contract whose events trigger relays (unset disables the watcher)
+    #[serde(default)]
+    pool_address: Option<ethers::types::Address>,
+    /// Block the pool was deployed in; its deposits are replayed from here
+    /// on startup to rebuild the tree
+    #[serde(default)]
+    pool_deployment_block: u64,
+    /// Pools relay requests may target besides `pool_address`; empty serves
+    /// every pool
+    #[serde(default)]
+    allowed_pools: Vec<AllowedPool>,
+    /// Confirmations a pool withdrawal's block needs before it is relayed
+    /// (unset waits for finality); deposits always wait for finality
+    #[serde(default)]
+    min_confirmations: Option<u64>,
+    /// Number of recent pool roots the relayer accepts proofs against
+    #[serde(default = "default_root_history_size")]
+    root_history_size: usize,
+    /// Blocks a withdrawal may stay unmined before it is replaced with a
+    /// higher fee (0 never replaces)
+    #[serde(default = "default_resubmit_after_blocks")]
+    resubmit_after_blocks: u64,
+    /// Gas limit withdrawals are sent with; quotes are priced for it too
+    #[serde(default = "default_withdraw_gas_limit")]
+    withdraw_gas_limit: u64,
+    /// Extra HTTP headers sent with every RPC request (e.g. provider API keys).
+    /// Each `${VAR}` in a value is read from the environment.
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+/// A list of URLs, or a single one
+fn one_or_more_urls<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Urls {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let urls = match <Urls as serde::Deserialize>::deserialize(deserializer)? {
+        Urls::One(url) => vec![url],
+        Urls::Many(urls) => urls,
+    };
+    if urls.is_empty() {
+        return Err(serde::de::Error::custom(
+            "at least one HTTP URL is required",
+        ));
+    }
+    Ok(urls)
+}
+
+fn default_finality_depth() -> u64 {
+    light_client::DEFAULT_FINALITY_DEPTH
+}
+
+fn default_retained_headers() -> usize {
+    light_client::DEFAULT_RETAINED_HEADERS
+}
+
+fn default_breaker_failure_threshold() -> u32 {
+    light_client::breaker::DEFAULT_FAILURE_THRESHOLD
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    light_client::breaker::DEFAULT_COOLDOWN.as_secs()
+}
+
+fn default_rpc_timeout_secs() -> u64 {
+    light_client::breaker::DEFAULT_REQUEST_TIMEOUT.as_secs()
+}
+
+fn default_max_in_flight() -> usize {
+    submitter::nonce::DEFAULT_MAX_IN_FLIGHT
+}
+
+fn default_root_history_size() -> usize {
+    merkle::roots::DEFAULT_ROOT_HISTORY
+}
+
+fn default_resubmit_after_blocks() -> u64 {
+    submitter::withdraw::DEFAULT_RESUBMIT_AFTER_BLOCKS
+}
+
+fn default_withdraw_gas_limit() -> u64 {
+    submitter::withdraw::DEFAULT_WITHDRAW_GAS_LIMIT
+}
+
+/// A pool served besides a chain's default one
+#[derive(Debug, Clone, serde::Deserialize)]
+struct AllowedPool {
+    address: ethers::types::Address,
+    /// Block the pool was deployed in; its deposits are replayed from here
+    #[serde(default)]
+    deployment_block: u64,
+}
+
+impl ChainEndpoints {
+    /// Pools watched on the chain, the default first, with the block each
+    /// was deployed in
+    ///
+    /// With no allowlist every pool is served, but only these have their
+    /// tree rebuilt and followed.
+    fn watched_pools(&self) -> Vec<(ethers::types::Address, u64)> {
+        let mut pools: Vec<_> = self
+            .pool_address
+            .map(|pool| (pool, self.pool_deployment_block))
+            .into_iter()
+            .collect();
+        for allowed in &self.allowed_pools {
+            if !pools.iter().any(|(pool, _)| *pool == allowed.address) {
+                pools.push((allowed.address, allowed.deployment_block));
+            }
+        }
+        pools
+    }
+}
+
+impl fmt::Debug for ChainEndpoints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Header values usually carry credentials, so only names are printed
+        let mut header_names: Vec<_> = self.headers.keys().collect();
+        header_names.sort();
+        f.debug_struct("ChainEndpoints")
+            .field("http_urls", &self.http_urls)
+            .field("ws_url", &self.ws_url)
+            .field("chain_id", &self.chain_id)
+            .field("finality_depth", &self.finality_depth)
+            .field("retained_headers", &self.retained_headers)
+            .field("allow_equal_timestamps", &self.allow_equal_timestamps)
+            .field(
+                "finality_regression_tolerance",
+                &self.finality_regression_tolerance,
+            )
+            .field(
+                "adaptive_finality_max_depth",
+                &self.adaptive_finality_max_depth,
+            )
+            .field("max_gas_price_gwei", &self.max_gas_price_gwei)
+            .field("gas_ceiling_action", &self.gas_ceiling_action)
+            .field("public_input_commitment", &self.public_input_commitment)
+            .field("submission_route", &self.submission_route)
+            .field("max_in_flight", &self.max_in_flight)
+            .field("fallback_http_url", &self.fallback_http_url)
+            .field("breaker_failure_threshold", &self.breaker_failure_threshold)
+            .field("breaker_cooldown_secs", &self.breaker_cooldown_secs)
+            .field("rpc_timeout_secs", &self.rpc_timeout_secs)
+            .field("pool_address", &self.pool_address)
+            .field("pool_deployment_block", &self.pool_deployment_block)
+            .field("allowed_pools", &self.allowed_pools)
+            .field("min_confirmations", &self.min_confirmations)
+            .field("root_history_size", &self.root_history_size)
+            .field("resubmit_after_blocks", &self.resubmit_after_blocks)
+            .field("withdraw_gas_limit", &self.withdraw_gas_limit)
+            .field("headers", &header_names)
+            .finish()
+    }
+}
+
+/// Where the transaction signing key is loaded from
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+enum SignerConfig {
+    /// Hex private key held in an environment variable
+    Env { var: String },
+    /// Encrypted JSON keystore, unlocked with a password from the environment
+    Keystore { path: PathBuf, password_env: String },
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct P2PConfig {
+    listen_addr: String,
+    bootstrap_peers: Vec<String>,
+    max_peers: usize,
+    /// Persisted libp2p identity key; generated on first start if missing
+    #[serde(default)]
+    identity_key_path: Option<PathBuf>,
+    /// Smoothed ping RTT above which a peer is counted slow
+    #[serde(default = "default_slow_peer_rtt_ms")]
+    slow_peer_rtt_ms: u64,
+    /// How long an accepted relay request suppresses equivalent ones
+    #[serde(default = "default_relay_dedup_window_secs")]
+    relay_dedup_window_secs: u64,
+    /// Accepted relay request keys remembered for deduplication
+    #[serde(default = "default_relay_dedup_capacity")]
+    relay_dedup_capacity: usize,
+    /// Compress gossip payloads for peers that support it
+    #[serde(default = "default_gossip_compression")]
+    compression: bool,
+    /// Payload size from which gossip is compressed
+    #[serde(default = "default_compression_threshold_bytes")]
+    compression_threshold_bytes: usize,
+    /// Aggregated reputation below which a peer is kept out of the mesh
+    #[serde(default = "default_reputation_threshold")]
+    reputation_threshold: i64,
+    /// How often this node gossips its peer scores
+    #[serde(default = "default_reputation_interval_secs")]
+    reputation_interval_secs: u64,
+    /// How often the Kademlia routing table is refreshed
+    #[serde(default = "default_dht_refresh_interval_secs")]
+    dht_refresh_interval_secs: u64,
+}
+
+fn default_slow_peer_rtt_ms() -> u64 {
+    p2p::peers::DEFAULT_SLOW_RTT.as_millis() as u64
+}
+
+fn default_relay_dedup_window_secs() -> u64 {
+    relay::dedup::DEFAULT_DEDUP_WINDOW.as_secs()
+}
+
+fn default_relay_dedup_capacity() -> usize {
+    relay::dedup::DEFAULT_DEDUP_CAPACITY
+}
+
+fn default_gossip_compression() -> bool {
+    true
+}
+
+fn default_compression_threshold_bytes() -> usize {
+    p2p::compression::DEFAULT_COMPRESSION_THRESHOLD
+}
+
+fn default_reputation_threshold() -> i64 {
+    p2p::reputation::DEFAULT_REPUTATION_THRESHOLD
+}
+
+fn default_reputation_interval_secs() -> u64 {
+    p2p::reputation::DEFAULT_SNAPSHOT_INTERVAL.as_secs()
+}
+
+fn default_dht_refresh_interval_secs() -> u64 {
+    p2p::DEFAULT_DHT_REFRESH_INTERVAL.as_secs()
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ProverConfig {
+    enabled: bool,
+    max_concurrent: usize,
+    timeout_secs: u64,
+    /// Maximum number of proof requests waiting for a prover slot
+    #[serde(default = "default_queue_capacity")]
+    queue_capacity: usize,
+    /// Behaviour when the request queue is full
+    #[serde(default)]
+    queue_full_policy: prover::QueueFullPolicy,
+    /// Priority points a queued request gains per second of waiting
+    #[serde(default = "default_priority_aging_per_sec")]
+    priority_aging_per_sec: f64,
+    /// Per-chain fee normalization for request priority
+    #[serde(default)]
+    fee_priority: Vec<FeePriorityConfig>,
+    /// Public-input layout for targets that don't use EVM addresses
+    #[serde(default)]
+    input_encoding: Vec<InputEncodingConfig>,
+    /// Merkle tree depth of pools that don't use the default of 20
+    #[serde(default)]
+    tree_depth: Vec<TreeDepthConfig>,
+    /// Generated proofs kept for identical requests (0 disables the cache)
+    #[serde(default = "default_proof_cache_capacity")]
+    cache_capacity: usize,
+    /// Circuit artifacts served to clients and used for proving
+    #[serde(default)]
+    circuits: Vec<CircuitConfig>,
+    /// Commitment parameters of the consistency circuit (unset disables
+    /// consistency proofs)
+    #[serde(default)]
+    consistency: Option<ConsistencyConfig>,
+    /// Bytes of batch members held in memory at once while aggregating
+    #[serde(default = "default_aggregation_memory_budget")]
+    aggregation_memory_budget: usize,
+    /// Real proving toolchain
+    #[serde(default)]
+    barretenberg: Option<BarretenbergConfig>,
+    /// Generate placeholder proofs when proving locally without `barretenberg`
+    /// (otherwise that fails startup), and accept them in relay requests
+    #[serde(default)]
+    allow_placeholder_proofs: bool,
+    /// Where proofs are generated
+    #[serde(default)]
+    backend: prover::ProverBackend,
+    /// Proofs `/prove` hands out per minute, across all callers (0 turns
+    /// the endpoint off)
+    #[serde(default = "default_public_proofs_per_minute")]
+    public_proofs_per_minute: u32,
+}
+
+/// Noir circuit and barretenberg binaries used for proving
+#[derive(Debug, Clone, serde::Deserialize)]
+struct BarretenbergConfig {
+    /// Noir package of the withdrawal circuit, for solving witnesses
+    program_dir: PathBuf,
+    /// Compiled withdrawal circuit (ACIR JSON)
+    bytecode_path: PathBuf,
+    /// Verification key of the compiled circuit
+    vk_path: PathBuf,
+    #[serde(default = "default_nargo_path")]
+    nargo_path: PathBuf,
+    #[serde(default = "default_bb_path")]
+    bb_path: PathBuf,
+}
+
+fn default_nargo_path() -> PathBuf {
+    PathBuf::from("nargo")
+}
+
+fn default_bb_path() -> PathBuf {
+    PathBuf::from("bb")
+}
+
+/// Pedersen generators and Paillier key the consistency circuit is built for
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ConsistencyConfig {
+    /// Value generator, hex-encoded 32-byte compressed point
+    pedersen_g: String,
+    /// Blinding generator, hex-encoded 32-byte compressed point
+    pedersen_h: String,
+    /// Paillier modulus `n`, hex-encoded big-endian (at least 2048 bits)
+    paillier_n: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct FeePriorityConfig {
+    chain_id: u64,
+    /// Fee (in the chain's fee units) worth one priority point
+    fee_per_point: u64,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TreeDepthConfig {
+    chain_id: u64,
+    /// Levels in the pool's commitment tree (Merkle path length)
+    depth: usize,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct InputEncodingConfig {
+    chain_id: u64,
+    #[serde(flatten)]
+    encoding: prover::encoding::InputEncoding,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct CircuitConfig {
+    /// Proof type this circuit proves (e.g. "withdrawal")
+    proof_type: String,
+    /// Circuit version tag
+    version: String,
+    /// Path to the verification key
+    vk_path: PathBuf,
+    /// Path to the compiled circuit bytecode (ACIR)
+    bytecode_path: Option<PathBuf>,
+    /// Proof system of the circuit's verifier
+    #[serde(default)]
+    proof_system: prover::ProofSystem,
+}
+
+fn default_queue_capacity() -> usize {
+    100
+}
+
+fn default_priority_aging_per_sec() -> f64 {
+    1.0
+}
+
+fn default_proof_cache_capacity() -> usize {
+    256
+}
+
+fn default_aggregation_memory_budget() -> usize {
+    prover::aggregate::DEFAULT_AGGREGATION_MEMORY_BUDGET
+}
+
+fn default_public_proofs_per_minute() -> u32 {
+    10
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_concurrent: 4,
+            timeout_secs: 120,
+            queue_capacity: default_queue_capacity(),
+            queue_full_policy: prover::QueueFullPolicy::default(),
+            priority_aging_per_sec: default_priority_aging_per_sec(),
+            fee_priority: Vec::new(),
+            input_encoding: Vec::new(),
+            tree_depth: Vec::new(),
+            cache_capacity: default_proof_cache_capacity(),
+            circuits: Vec::new(),
+            consistency: None,
+            aggregation_memory_budget: default_aggregation_memory_budget(),
+            barretenberg: None,
+            allow_placeholder_proofs: false,
+            backend: prover::ProverBackend::default(),
+            public_proofs_per_minute: default_public_proofs_per_minute(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProverConfig {
+    /// Defaults, proving with placeholders as tests have no toolchain
+    fn placeholder() -> Self {
+        Self {
+            allow_placeholder_proofs: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Replace every `${VAR}` placeholder in `value` with the variable's value
+///
+/// Placeholders can sit anywhere in the value (`Bearer ${TOKEN}`); text
+/// outside them is kept as written.
+fn expand_env(value: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unclosed `${{` in {:?}", value))?;
+        let var = &rest[start + 2..start + end];
+        expanded.push_str(
+            &std::env::var(var)
+                .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", var))?,
+        );
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// TCP port of a P2P listen multiaddr, if it names one
+fn p2p_tcp_port(listen_addr: &str) -> Result<Option<u16>> {
+    let addr: libp2p::Multiaddr = listen_addr.parse()?;
+    Ok(addr.iter().find_map(|protocol| match protocol {
+        libp2p::multiaddr::Protocol::Tcp(port) => Some(port),
+        _ => None,
+    }))
+}
+
+/// Named ports the node will bind, skipping disabled and ephemeral (0) ones
+fn bound_ports(
+    api_port: u16,
+    metrics_port: Option<u16>,
+    p2p_listen_addr: &str,
+) -> Result<Vec<(&'static str, u16)>> {
+    let mut ports = vec![("API", api_port)];
+    ports.extend(metrics_port.map(|port| ("metrics", port)));
+    ports.extend(p2p_tcp_port(p2p_listen_addr)?.map(|port| ("P2P", port)));
+    ports.retain(|(_, port)| *port != 0);
+    Ok(ports)
+}
+
+/// Ensure the API, metrics and P2P ports don't overlap
+fn validate_ports(api_port: u16, metrics_port: Option<u16>, p2p_listen_addr: &str) -> Result<()> {
+    let ports = bound_ports(api_port, metrics_port, p2p_listen_addr)?;
+    for (i, (name, port)) in ports.iter().enumerate() {
+        if let Some((other, _)) = ports[i + 1..].iter().find(|(_, p)| p == port) {
+            anyhow::bail!(
+                "{} and {} are both configured on port {}",
+                name,
+                other,
+                port
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Fail early with a clear message if a configured port is already taken
+fn check_ports_available(
+    api_port: u16,
+    metrics_port: Option<u16>,
+    p2p_listen_addr: &str,
+) -> Result<()> {
+    for (name, port) in bound_ports(api_port, metrics_port, p2p_listen_addr)? {
+        std::net::TcpListener::bind(("0.0.0.0", port))
+            .map_err(|e| anyhow::anyhow!("{} port {} is unavailable: {}", name, port, e))?;
+    }
+    Ok(())
+}
+
+fn load_config(path: &PathBuf) -> Result<RelayerConfig> {
+    let settings = config::Config::builder()
+        .add_source(config::File::from(path.as_ref()))
+        .add_source(config::Environment::with_prefix("RELAYER"))
+        .build()?;
+
+    parse_config(settings)
+}
+
+/// Deserialize the config, folding the legacy per-chain sections into `chains`
+fn parse_config(settings: config::Config) -> Result<RelayerConfig> {
+    let mut config: RelayerConfig = settings.try_deserialize()?;
+    let legacy = [config.ethereum.take(), config.arbitrum.take()];
+    config.chains.splice(0..0, legacy.into_iter().flatten());
+
+    if config.chains.is_empty() {
+        anyhow::bail!("No chains configured");
+    }
+    let mut seen = std::collections::HashSet::new();
+    for endpoints in &config.chains {
+        if !seen.insert(endpoints.chain_id) {
+            anyhow::bail!("Chain {} is configured twice", endpoints.chain_id);
+        }
+        // The finalized header has to stay in memory, however deep finality gets
+        let depth = endpoints
+            .adaptive_finality_max_depth
+            .map_or(endpoints.finality_depth, |max| {
+                max.max(endpoints.finality_depth)
+            });
+        if endpoints.retained_headers as u64 <= depth {
+            anyhow::bail!(
+                "Chain {} retains {} headers, not more than its finality depth {}",
+                endpoints.chain_id,
+                endpoints.retained_headers,
+                depth
+            );
+        }
+    }
+    // A zero-capacity queue turns every proof request away
+    if config.prover.queue_capacity == 0 {
+        anyhow::bail!("Prover queue_capacity must be at least 1");
+    }
+    let shutdown = &config.shutdown;
+    if shutdown
+        .grace_period_secs
+        .saturating_add(shutdown.timeout_secs)
+        >= shutdown.termination_period_secs
+    {
+        anyhow::bail!(
+            "Shutdown grace period ({}s) plus timeout ({}s) must be under the {}s termination period",
+            shutdown.grace_period_secs,
+            shutdown.timeout_secs,
+            shutdown.termination_period_secs
+        );
+    }
+    Ok(config)
+}
+
+async fn initialize_components(
+    config: &RelayerConfig,
+    store: std::sync::Arc<dyn store::Store>,
+) -> Result<(
+    light_client::LightClient,
+    p2p::P2PNode,
+    prover::ProverService,
+)> {
+    info!("Initializing light client...");
+    let light_client = light_client::LightClient::new(&config.chains, store).await?;
+
+    info!("Initializing P2P node...");
+    let p2p_node = p2p::P2PNode::new(&config.p2p).await?;
+
+    info!("Initializing prover service...");
+    let prover = prover::ProverService::new(&config.prover)?;
+
+    Ok((light_client, p2p_node, prover))
+}
+
+/// Probe every subsystem the node depends on, without starting it
+///
+/// Nothing binds, joins the P2P network or starts syncing; each probe's
+/// failure is recorded and the rest still run.
+async fn run_config_check(
+    config: &RelayerConfig,
+    api_port: u16,
+    metrics_port: Option<u16>,
+) -> diagnostics::CheckReport {
+    use ethers::providers::Middleware;
+
+    let mut report = diagnostics::CheckReport::default();
+
+    let listen_addr = &config.p2p.listen_addr;
+    report.record(
+        "ports",
+        validate_ports(api_port, metrics_port, listen_addr)
+            .and_then(|()| check_ports_available(api_port, metrics_port, listen_addr))
+            .map(|()| "all ports free".to_string()),
+    );
+
+    let signer = keys::load_tx_signer(
+        config.signer.as_ref(),
+        config.private_key.as_ref().map(|key| key.as_str()),
+    )
+    .map(|wallet| ethers::signers::Signer::address(&wallet));
+    report.record(
+        "signer",
+        match &signer {
+            Ok(address) => Ok(format!("{:?}", address)),
+            Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+        },
+    );
+
+    report.record(
+        "store",
+        store::open(&config.database_url)
+            .await
+            .map(|_| "opened".to_string()),
+    );
+
+    report.record(
+        "prover",
+        prover::ProverService::new(&config.prover)
+            .map(|prover| {
+                format!(
+                    "{} backend, {} circuits loaded",
+                    prover.backend(),
+                    prover.circuits().ids().len()
+                )
+            })
+            // The message already carries the cause; don't repeat it
+            .map_err(|e| anyhow::anyhow!("{}", e)),
+    );
+
+    for endpoints in &config.chains {
+        let chain_id = endpoints.chain_id;
+        let provider = match light_client::http_provider(endpoints) {
+            Ok(provider) => provider,
+            Err(e) => {
+                report.record(format!("chain {}", chain_id), Err(e));
+                continue;
+            }
+        };
+
+        let rpc = async {
+            let served = provider.get_chainid().await?;
+            if served != ethers::types::U256::from(chain_id) {
+                anyhow::bail!("RPC serves chain {}, configured {}", served, chain_id);
+            }
+            Ok(format!("head {}", provider.get_block_number().await?))
+        };
+        report.record(format!("chain {}", chain_id), rpc.await);
+
+        if let Some(pool) = endpoints.pool_address {
+            let code = async {
+                let code = provider.get_code(pool, None).await?;
+                if code.is_empty() {
+                    anyhow::bail!("No contract code at {:?}", pool);
+                }
+                Ok(format!("{} bytes of code at {:?}", code.len(), pool))
+            };
+            report.record(format!("pool {}", chain_id), code.await);
+        }
+
+        if let Ok(address) = signer {
+            let balance = async {
+                let balance = provider.get_balance(address, None).await?;
+                if balance.is_zero() {
+                    anyhow::bail!("Signer {:?} has no funds for gas", address);
+                }
+                Ok(format!("{} wei", balance))
+            };
+            report.record(format!("balance {}", chain_id), balance.await);
+        }
+    }
+
+    report
+}
+
+/// Probe each subsystem and build the startup readiness report
+async fn run_startup_diagnostics(
+    config: &RelayerConfig,
+    light_client: &light_client::LightClient,
+    p2p_node: &p2p::P2PNode,
+    prover: &prover::ProverService,
+    signer_address: ethers::types::Address,
+) -> diagnostics::StartupReport {
+    use ethers::providers::Middleware;
+
+    let mut chains = Vec::new();
+    let mut balances = Vec::new();
+    for endpoints in &config.chains {
+        let chain_id = endpoints.chain_id;
+        chains.push(diagnostics::ChainCheck::new(
+            chain_id,
+            light_client
+                .head_number(chain_id)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e)),
+        ));
+
+        let balance = match light_client::http_provider(endpoints) {
+            Ok(provider) => provider
+                .get_balance(signer_address, None)
+                .await
+                .map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        balances.push(diagnostics::BalanceCheck::new(chain_id, balance));
+    }
+
+    diagnostics::StartupReport::new(
+        chains,
+        prover,
+        p2p_node.peer_count(),
+        diagnostics::SignerCheck {
+            address: signer_address,
+            balances,
+        },
+    )
+}
+
+/// Transaction signer for submissions, rotatable through `/admin/rotate-key`
+fn active_signer(
+    config: &RelayerConfig,
+    wallet: ethers::signers::LocalWallet,
+) -> Result<keys::ActiveSigner> {
+    let mut nonce_sources = Vec::new();
+    let mut chains = Vec::new();
+    for endpoints in &config.chains {
+        let provider = light_client::http_provider(endpoints)?;
+        nonce_sources.push((
+            endpoints.chain_id,
+            std::sync::Arc::new(provider.clone()),
+            endpoints.max_in_flight,
+        ));
+        chains.push(keys::rotation::SubmitChain {
+            chain_id: endpoints.chain_id,
+            provider,
+            pool: endpoints.pool_address,
+        });
+    }
+
+    keys::ActiveSigner::new(
+        wallet,
+        Box::new(move |address| {
+            let mut submitters = submitter::ChainSubmitters::default();
+            for (chain_id, source, max_in_flight) in &nonce_sources {
+                submitters.insert(submitter::NonceManager::new(
+                    *chain_id,
+                    address,
+                    source.clone(),
+                    *max_in_flight,
+                ));
+            }
+            Ok(submitters)
+        }),
+        std::sync::Arc::new(keys::rotation::ChainSignerCheck { chains }),
+        keys::rotation::DEFAULT_DRAIN_TIMEOUT,
+    )
+}
+
+/// Root history of every served pool, keyed by chain ID and pool
+///
+/// Each pool's tree is rebuilt, at the prover's tree depth for its chain,
+/// from its deposits up to the chain's finalized block, and fails startup if
+/// it doesn't reach the pool's root there.
+/// Also returns the block each chain's watchers pick up from.
+async fn pool_roots(
+    config: &RelayerConfig,
+    light_client: &light_client::LightClient,
+    prover: &prover::ProverService,
+) -> Result<(
+    std::sync::Arc<HashMap<(u64, ethers::types::Address), SharedPoolRoots>>,
+    HashMap<u64, u64>,
+)> {
+    let mut roots = HashMap::new();
+    let mut watch_from = HashMap::new();
+    for endpoints in &config.chains {
+        let pools = endpoints.watched_pools();
+        if pools.is_empty() {
+            continue;
+        }
+        let synced_to = light_client
+            .get_finalized(endpoints.chain_id)
+            .ok_or_else(|| anyhow::anyhow!("Chain {} is not synced", endpoints.chain_id))?;
+        let source = light_client::http_provider(endpoints)?;
+        for (pool, deployment_block) in pools {
+            info!(
+                chain_id = endpoints.chain_id,
+                pool = ?pool,
+                from = deployment_block,
+                to = synced_to,
+                "Rebuilding pool tree from deposits"
+            );
+            let pool_roots = watcher::rebuild_roots(
+                &source,
+                pool,
+                prover.tree_depth(endpoints.chain_id),
+                deployment_block,
+                synced_to,
+                endpoints.root_history_size,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Chain {}: {:#}", endpoints.chain_id, e))?;
+            roots.insert(
+                (endpoints.chain_id, pool),
+                std::sync::Arc::new(std::sync::RwLock::new(pool_roots)),
+            );
+        }
+        watch_from.insert(endpoints.chain_id, synced_to + 1);
+    }
+    Ok((std::sync::Arc::new(roots), watch_from))
+}
+
+type SharedPoolRoots = std::sync::Arc<std::sync::RwLock<merkle::roots::PoolRoots>>;
+
+/// Spawn a watcher for every served pool, scanning from the block in
+/// `watch_from` where one is given for its chain
+///
+/// Returns their trigger channel, or `None` when no chain has a pool.
+fn start_pool_watchers(
+    config: &RelayerConfig,
+    light_client: &light_client::LightClient,
+    watch_from: &HashMap<u64, u64>,
+) -> Result<Option<tokio::sync::mpsc::Receiver<watcher::RelayTrigger>>> {
+    let (trigger_tx, trigger_rx) = tokio::sync::mpsc::channel(1000);
+    let mut watching = false;
+
+    for endpoints in &config.chains {
+        for (pool, _) in endpoints.watched_pools() {
+            let finality = light_client
+                .finality(endpoints.chain_id)
+                .ok_or_else(|| anyhow::anyhow!("Chain {} is not synced", endpoints.chain_id))?;
+            let source = std::sync::Arc::new(light_client::http_provider(endpoints)?);
+            let mut pool_watcher = watcher::PoolWatcher::new(
+                endpoints.chain_id,
+                pool,
+                source,
+                std::sync::Arc::new(finality),
+                trigger_tx.clone(),
+            )
+            .with_confirmation_policy(
+                watcher::ConfirmationPolicy::from_min_confirmations(endpoints.min_confirmations),
+            );
+            if let Some(block_number) = watch_from.get(&endpoints.chain_id) {
+                pool_watcher = pool_watcher.with_start_block(*block_number);
+            }
+            tokio::spawn(pool_watcher.run(watcher::DEFAULT_WATCH_INTERVAL));
+            watching = true;
+        }
+    }
+
+    Ok(watching.then_some(trigger_rx))
+}
+
+async fn start_api_server(port: u16, state: api::AppState) -> Result<tokio::task::JoinHandle<()>> {
+    let app = api::router(state);
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    info!("API server listening on port {}", port);
+
+    let handle = tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    Ok(handle)
+}
+
+async fn start_metrics_server(port: u16) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    info!("Metrics server listening on port {}", port);
+
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, metrics::router()).await {
+            warn!(error = %e, "Metrics server stopped");
+        }
+    });
+    Ok(())
+}
+
+/// How the node winds down once the event loop stops
+struct Shutdown {
+    /// Time accepted proofs and withdrawals get to finish
+    grace: std::time::Duration,
+    /// Deadline for components to stop after that
+    timeout: std::time::Duration,
+    withdrawals: std::sync::Arc<submitter::WithdrawalSubmitter>,
+}
+
+async fn run_event_loop(
+    mut light_client: light_client::LightClient,
+    mut p2p_node: p2p::P2PNode,
+    prover: std::sync::Arc<prover::ProverService>,
+    mut triggers: Option<tokio::sync::mpsc::Receiver<watcher::RelayTrigger>>,
+    mut accepted: tokio::sync::mpsc::Receiver<(String, api::types::RelayRequest)>,
+    validation: relay::RelayContext,
+    shutdown: Shutdown,
+) -> Result<()> {
+    info!("Starting main event loop...");
+    let adaptive_depths = light_client.adaptive_depths();
+    let reputation = p2p_node.reputation();
+    let ticker = |period| tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    let mut reputation_ticker = ticker(p2p_node.reputation_interval());
+    let mut dht_ticker = ticker(p2p_node.dht_refresh_interval());
+    // A component whose event channel closed; its task is gone, so the node
+    // stops rather than keep running without it
+    let mut closed = None;
+
+    loop {
+        tokio::select! {
+            // Handle light client events
+            event = light_client.next_event() => {
+                let Some(e) = event else {
+                    closed = Some(channel::closed("light_client_events"));
+                    break;
+                };
+                if let light_client::LightClientEvent::Reorg(reorg) = &e {
+                    let report = p2p::ReorgReport {
+                        chain_id: reorg.chain_id,
+                        depth: reorg.depth,
+                    };
+                    if let Err(e) = p2p_node.publish_reorg(&report) {
+                        debug!(chain_id = reorg.chain_id, error = %e, "Reorg report not published");
+                    }
+                }
+                handle_light_client_event(e, validation.store.as_ref()).await?;
+            }
+
+            // Handle P2P events
+            event = p2p_node.next_event() => {
+                let Some(e) = event else {
+                    closed = Some(channel::closed("p2p_events"));
+                    break;
+                };
+                handle_p2p_event(
+                    e,
+                    &mut p2p_node,
+                    &validation,
+                    &reputation,
+                    &adaptive_depths,
+                )
+                .await?;
+            }
+
+            // Share peer reputation
+            _ = reputation_ticker.tick() => {
+                if let Err(e) = p2p_node.publish_reputation() {
+                    debug!(error = %e, "Reputation snapshot not published");
+                }
+            }
+
+            // Keep the DHT routing table fresh
+            _ = dht_ticker.tick() => p2p_node.refresh_dht(),
+
+            // Handle confirmed pool events
+            trigger = next_trigger(&mut triggers) => {
+                let Some(trigger) = trigger else {
+                    closed = Some(channel::closed("relay_triggers"));
+                    break;
+                };
+                handle_relay_trigger(trigger, &validation.roots, validation.store.as_ref()).await?;
+            }
+
+            // Gossip relays accepted over HTTP
+            relay = accepted.recv() => {
+                let Some((relay_id, request)) = relay else {
+                    closed = Some(channel::closed("relay_gossip"));
+                    break;
+                };
+                if let Err(e) = p2p_node.publish_relay_request(&relay_id, request) {
+                    debug!(relay_id = %relay_id, error = %e, "Relay request not published");
+                }
+            }
+
+            // Handle shutdown signal
+            _ = tokio::signal::ctrl_c() => {
+                info!("Received shutdown signal, stopping...");
+                break;
+            }
+        }
+    }
+
+    if let Some(e) = closed {
+        tracing::error!(error = %e, "Internal channel closed, shutting down for restart");
+    }
+    info!(
+        grace_secs = shutdown.grace.as_secs(),
+        "Finishing accepted proofs and withdrawals..."
+    );
+    if !drain_in_flight(&prover, &shutdown.withdrawals, shutdown.grace).await {
+        warn!(
+            proofs_in_progress = prover.queue_depth(),
+            proofs_queued = prover.pending(),
+            grace_secs = shutdown.grace.as_secs(),
+            "Grace period over, abandoning in-flight work"
+        );
+    }
+
+    info!("Shutting down gracefully...");
+    let stuck = drain_with_deadline(
+        vec![
+            ("light_client", light_client.shutdown().err_into().boxed()),
+            ("p2p", p2p_node.shutdown().err_into().boxed()),
+        ],
+        shutdown.timeout,
+    )
+    .await;
+
+    if !stuck.is_empty() {
+        tracing::error!(
+            components = ?stuck,
+            timeout_secs = shutdown.timeout.as_secs(),
+            "Shutdown deadline exceeded, forcing exit"
+        );
+        std::process::exit(1);
+    }
+
+    match closed {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// Next trigger from the pool watchers; never resolves when none run
+async fn next_trigger(
+    triggers: &mut Option<tokio::sync::mpsc::Receiver<watcher::RelayTrigger>>,
+) -> Option<watcher::RelayTrigger> {
+    match triggers {
+        Some(triggers) => triggers.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Turn new proof and withdrawal requests away and wait up to `grace` for
+/// accepted ones to finish
+///
+/// Returns whether everything finished in time.
+async fn drain_in_flight(
+    prover: &prover::ProverService,
+    withdrawals: &submitter::WithdrawalSubmitter,
+    grace: std::time::Duration,
+) -> bool {
+    tokio::time::timeout(
+        grace,
+        futures::future::join(prover.drain(), withdrawals.drain()),
+    )
+    .await
+    .is_ok()
+}
+
+/// Shut components down concurrently under one overall deadline
+///
+/// Returns the names of components that had not finished when the deadline
+/// passed; their futures are dropped.
+async fn drain_with_deadline(
+    steps: Vec<(&'static str, BoxFuture<'_, Result<()>>)>,
+    timeout: std::time::Duration,
+) -> Vec<&'static str> {
+    let mut remaining: Vec<&'static str> = steps.iter().map(|(name, _)| *name).collect();
+    let mut running: FuturesUnordered<_> = steps
+        .into_iter()
+        .map(|(name, step)| async move { (name, step.await) })
+        .collect();
+
+    let _ = tokio::time::timeout(timeout, async {
+        while let Some((name, result)) = running.next().await {
+            remaining.retain(|pending| *pending != name);
+            if let Err(e) = result {
+                warn!(component = name, error = %e, "Shutdown failed");
+            }
+        }
+    })
+    .await;
+
+    remaining
+}
+
+async fn handle_light_client_event(
+    event: light_client::LightClientEvent,
+    store: &dyn store::Store,
+) -> Result<()> {
+    match event {
+        light_client::LightClientEvent::NewBlock {
+            chain_id,
+            block_number,
+            block_hash,
+            timestamp,
+        } => {
+            info!(
+                chain_id = chain_id,
+                block_number = block_number,
+                timestamp = timestamp,
+                "New block received"
+            );
+        }
+        light_client::LightClientEvent::Reorg(reorg) => {
+            info!(
+                chain_id = reorg.chain_id,
+                depth = reorg.depth,
+                fork_block = reorg.fork_block,
+                "Chain reorganization detected"
+            );
+            // Kept for analysis only, so a failed write doesn't stop the node
+            if let Err(e) = store.record_reorg(&reorg).await {
+                warn!(chain_id = reorg.chain_id, error = %e, "Reorg not recorded");
+            }
+        }
+        light_client::LightClientEvent::FinalityRegression {
+            chain_id,
+            previous,
+            current,
+        } => {
+            // Already logged at error level by the light client
+            warn!(
+                chain_id = chain_id,
+                previous = previous,
+                current = current,
+                "Finality regression reported"
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn handle_relay_trigger(
+    trigger: watcher::RelayTrigger,
+    roots: &HashMap<(u64, ethers::types::Address), SharedPoolRoots>,
+    store: &dyn store::Store,
+) -> Result<()> {
+    info!(
+        chain_id = trigger.chain_id,
+        block_number = trigger.block_number,
+        tx_hash = ?trigger.tx_hash,
+        event = ?trigger.event,
+        "Relay triggered by pool event"
+    );
+
+    match &trigger.event {
+        watcher::PoolEvent::Deposit {
+            commitment,
+            leaf_index,
+        } => {
+            let Some(pool) = roots.get(&(trigger.chain_id, trigger.pool)) else {
+                return Ok(());
+            };
+            let applied = u64::try_from(*leaf_index)
+                .map_err(|_| anyhow::anyhow!("Deposit leaf index {} out of range", leaf_index))
+                .and_then(|leaf_index| {
+                    pool.write().unwrap().apply_deposit(
+                        *commitment,
+                        leaf_index,
+                        trigger.block_number,
+                    )
+                });
+            if let Err(e) = applied {
+                warn!(chain_id = trigger.chain_id, error = %e, "Pool root history out of sync");
+            }
+        }
+        // Spent on-chain, whoever relayed it; no request may spend it again
+        watcher::PoolEvent::Withdrawal { nullifier, .. } => {
+            let spent = store
+                .insert_nullifier(trigger.chain_id, trigger.pool, *nullifier)
+                .await;
+            if let Err(e) = spent {
+                warn!(chain_id = trigger.chain_id, error = %e, "Failed to record spent nullifier");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_p2p_event(
+    event: p2p::P2PEvent,
+    p2p_node: &mut p2p::P2PNode,
+    validation: &relay::RelayContext,
+    reputation: &p2p::reputation::ReputationTracker,
+    adaptive_depths: &HashMap<u64, std::sync::Arc<light_client::adaptive::AdaptiveDepth>>,
+) -> Result<()> {
+    match event {
+        p2p::P2PEvent::RelayRequest {
+            request_id,
+            peer_id,
+            data,
+            validation: pending,
+        } => {
+            info!(request_id = %request_id, peer_id = %peer_id, "Received relay request");
+            let now = chrono::Utc::now().timestamp();
+            let accepted = relay::accept_gossiped(validation, &peer_id, &data, now).await;
+            // Only requests that check out are forwarded to the mesh
+            let acceptance = match &accepted {
+                Ok(_) => libp2p::gossipsub::MessageAcceptance::Accept,
+                Err(e) if e.is_sender_fault() => libp2p::gossipsub::MessageAcceptance::Reject,
+                Err(_) => libp2p::gossipsub::MessageAcceptance::Ignore,
+            };
+            p2p_node.report_validation(pending, acceptance);
+            match accepted {
+                Ok(request) => {
+                    metrics::RELAY_REQUESTS
+                        .with_label_values(&["gossip", "accepted"])
+                        .inc();
+                    reputation.record_valid_relay(&peer_id);
+                    // The node that accepted it over HTTP submits it; every
+                    // other node only forwards it, so it is sent once
+                    info!(request_id = %request_id, chain_id = request.chain_id, "Relay request valid, forwarded");
+                    p2p_node
+                        .proofs()
+                        .insert(request.proof, request.public_inputs);
+                }
+                Err(e @ relay::RelayRejection::Duplicate(_)) => {
+                    metrics::RELAY_REQUESTS
+                        .with_label_values(&["gossip", "duplicate"])
+                        .inc();
+                    debug!(request_id = %request_id, peer_id = %peer_id, error = %e, "Dropped duplicate relay request");
+                }
+                Err(e) => {
+                    metrics::RELAY_REQUESTS
+                        .with_label_values(&["gossip", "rejected"])
+                        .inc();
+                    if e.is_sender_fault() {
+                        reputation.record_invalid_relay(&peer_id);
+                    }
+                    warn!(request_id = %request_id, peer_id = %peer_id, error = %e, "Rejected relay request");
+                }
+            }
+        }
+        p2p::P2PEvent::ReorgReport { peer_id, report } => {
+            debug!(peer_id = %peer_id, chain_id = report.chain_id, depth = report.depth, "Peer reported reorg");
+            // Only peers this node has seen behave well count towards the
+            // finality depth; fresh identities score 0 and are ignored
+            if let Some(adaptive) = adaptive_depths.get(&report.chain_id) {
+                if reputation.local_score(&peer_id) > 0 {
+                    adaptive.record(&peer_id, report.depth);
+                }
+            }
+        }
+        p2p::P2PEvent::PeerConnected { peer_id } => {
+            info!(peer_id = %peer_id, "Peer connected");
+        }
+        p2p::P2PEvent::PeerDisconnected { peer_id } => {
+            info!(peer_id = %peer_id, "Peer disconnected");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_ports_rejected() {
+        assert!(validate_ports(8080, Some(9090), "/ip4/0.0.0.0/tcp/9000").is_ok());
+
+        let err = validate_ports(8080, Some(8080), "/ip4/0.0.0.0/tcp/9000").unwrap_err();
+        assert!(err.to_string().contains("API and metrics"));
+
+        let err = validate_ports(9000, None, "/ip4/0.0.0.0/tcp/9000").unwrap_err();
+        assert!(err.to_string().contains("API and P2P"));
+
+        let err = validate_ports(8080, Some(9000), "/ip4/0.0.0.0/tcp/9000").unwrap_err();
+        assert!(err.to_string().contains("metrics and P2P"));
+
+        // Ephemeral ports can't conflict
+        assert!(validate_ports(0, Some(0), "/ip4/0.0.0.0/tcp/0").is_ok());
+    }
+
+    #[test]
+    fn test_shutdown_must_end_before_termination() {
+        let parse = |shutdown: &str| {
+            let toml = format!(
+                r#"
+                database_url = "sqlite::memory:"
+
+                [[chains]]
+                http_urls = ["http://127.0.0.1:8545"]
+                chain_id = 1
+
+                [p2p]
+                listen_addr = "/ip4/127.0.0.1/tcp/0"
+                bootstrap_peers = []
+                max_peers = 10
+
+                [prover]
+                enabled = false
+                max_concurrent = 1
+                timeout_secs = 10
+
+                [shutdown]
+                {shutdown}
+                "#
+            );
+            parse_config(
+                config::Config::builder()
+                    .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+                    .build()
+                    .unwrap(),
+            )
+        };
+
+        // The defaults leave room before the orchestrator's SIGKILL
+        assert!(parse("").is_ok());
+        let err = parse("grace_period_secs = 20\ntimeout_secs = 30").unwrap_err();
+        assert!(err.to_string().contains("termination period"));
+        assert!(
+            parse("grace_period_secs = 20\ntimeout_secs = 30\ntermination_period_secs = 60")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_env_placeholders_expanded_anywhere() {
+        std::env::set_var("LAUNDRY_TEST_EXPAND_TOKEN", "abc");
+        std::env::set_var("LAUNDRY_TEST_EXPAND_USER", "relayer");
+        assert_eq!(expand_env("${LAUNDRY_TEST_EXPAND_TOKEN}").unwrap(), "abc");
+        assert_eq!(
+            expand_env("Bearer ${LAUNDRY_TEST_EXPAND_TOKEN}").unwrap(),
+            "Bearer abc"
+        );
+        assert_eq!(
+            expand_env("${LAUNDRY_TEST_EXPAND_USER}:${LAUNDRY_TEST_EXPAND_TOKEN}!").unwrap(),
+            "relayer:abc!"
+        );
+        assert_eq!(expand_env("no placeholders").unwrap(), "no placeholders");
+
+        assert!(expand_env("Bearer ${LAUNDRY_TEST_EXPAND_UNSET}").is_err());
+        assert!(expand_env("Bearer ${LAUNDRY_TEST_EXPAND_TOKEN").is_err());
+    }
+
+    #[test]
+    fn test_empty_prover_queue_rejected() {
+        let toml = r#"
+            database_url = "sqlite::memory:"
+
+            [[chains]]
+            http_urls = ["http://127.0.0.1:8545"]
+            chain_id = 1
+
+            [p2p]
+            listen_addr = "/ip4/127.0.0.1/tcp/0"
+            bootstrap_peers = []
+            max_peers = 10
+
+            [prover]
+            enabled = false
+            max_concurrent = 1
+            timeout_secs = 10
+            queue_capacity = 0
+            "#;
+        let err = parse_config(
+            config::Config::builder()
+                .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("queue_capacity"));
+    }
+
+    #[test]
+    fn test_port_in_use_reported() {
+        let taken = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let err = check_ports_available(port, None, "/ip4/0.0.0.0/tcp/0").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("API port {} is unavailable", port)));
+    }
+
+    /// JSON-RPC endpoint answering the calls `--check` makes
+    async fn mock_rpc(chain_id: u64, balance: u64) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break None;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break Some(body.to_string());
+                    }
+                };
+                let Some(body) = body else { continue };
+
+                let call: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let result = match call["method"].as_str().unwrap_or_default() {
+                    "eth_chainId" => format!("{:#x}", chain_id),
+                    "eth_blockNumber" => "0x64".to_string(),
+                    "eth_getCode" => "0x6080".to_string(),
+                    "eth_getBalance" => format!("{:#x}", balance),
+                    _ => "0x".to_string(),
+                };
+                let reply =
+                    serde_json::json!({"jsonrpc": "2.0", "id": call["id"], "result": result})
+                        .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_each_subsystem() {
+        let eth_url = mock_rpc(1, 0).await;
+        // Nothing listens here once the listener is dropped
+        let dead_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let dir = tempfile::tempdir().unwrap();
+
+        let toml = format!(
+            r#"
+            private_key = "0x{key}"
+            database_url = "sqlite://{db}"
+
+            [ethereum]
+            http_url = "{eth_url}"
+            chain_id = 1
+            pool_address = "0x00000000000000000000000000000000000000aa"
+
+            [[chains]]
+            http_urls = ["http://127.0.0.1:{dead_port}"]
+            chain_id = 42161
+
+            [p2p]
+            listen_addr = "/ip4/127.0.0.1/tcp/0"
+            bootstrap_peers = []
+            max_peers = 10
+
+            [prover]
+            enabled = false
+            max_concurrent = 1
+            timeout_secs = 10
+            "#,
+            key = "11".repeat(32),
+            db = dir.path().join("relayer.db").display(),
+        );
+        let config = parse_config(
+            config::Config::builder()
+                .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .chains
+                .iter()
+                .map(|endpoints| endpoints.chain_id)
+                .collect::<Vec<_>>(),
+            vec![1, 42161]
+        );
+
+        let report = run_config_check(&config, 0, None).await;
+        let passed = |subsystem: &str| report.result(subsystem).unwrap().passed;
+
+        for subsystem in ["ports", "signer", "store", "prover", "chain 1", "pool 1"] {
+            assert!(passed(subsystem), "{}", report.render());
+        }
+        assert!(report
+            .result("chain 1")
+            .unwrap()
+            .detail
+            .contains("head 100"));
+
+        // An unfunded signer and an unreachable chain fail, and fail the run
+        assert!(!passed("balance 1"));
+        assert!(report
+            .result("balance 1")
+            .unwrap()
+            .detail
+            .contains("no funds"));
+        assert!(!passed("chain 42161"));
+        assert!(report.result("pool 42161").is_none());
+        assert!(!report.passed());
+        assert!(report.render().contains("FAIL chain 42161"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stuck_component_does_not_block_shutdown() {
+        let timeout = std::time::Duration::from_secs(10);
+        let start = tokio::time::Instant::now();
+
+        let stuck = drain_with_deadline(
+            vec![
+                ("stops", async { anyhow::Ok(()) }.boxed()),
+                (
+                    "fails",
+                    async { Err::<(), _>(anyhow::anyhow!("already closed")) }.boxed(),
+                ),
+                ("hangs", std::future::pending::<Result<()>>().boxed()),
+            ],
+            timeout,
+        )
+        .await;
+
+        // Only the component that never finished is reported, at the deadline
+        assert_eq!(stuck, vec!["hangs"]);
+        assert_eq!(start.elapsed(), timeout);
+
+        // Everything stopping promptly returns without waiting out the deadline
+        let start = tokio::time::Instant::now();
+        let stuck =
+            drain_with_deadline(vec![("stops", async { anyhow::Ok(()) }.boxed())], timeout).await;
+        assert!(stuck.is_empty());
+        assert!(start.elapsed() < timeout);
+    }
+
+    /// Placeholder prover that takes a while
+    struct SlowGenerator;
+
+    #[async_trait::async_trait]
+    impl prover::ProofGenerator for SlowGenerator {
+        async fn generate(
+            &self,
+            request: prover::ProofRequest,
+            encoding: &prover::encoding::InputEncoding,
+            system: prover::ProofSystem,
+        ) -> Result<prover::GeneratedProof> {
+            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+            prover::PlaceholderGenerator
+                .generate(request, encoding, system)
+                .await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_waits_for_in_flight_proof() {
+        let prover = std::sync::Arc::new(
+            prover::ProverService::with_generator(
+                &ProverConfig::placeholder(),
+                std::sync::Arc::new(SlowGenerator),
+            )
+            .unwrap(),
+        );
+        let (withdrawals, _, _) = submitter::withdraw::test_withdrawals(
+            1,
+            ethers::types::Address::repeat_byte(0x50),
+            submitter::FeeSettings::default(),
+            light_client::FinalityHandle::with_headers(vec![], 0),
+            std::sync::Arc::default(),
+        )
+        .await;
+        let range = |value| prover::ProofRequest::Range {
+            commitment: [0u8; 32],
+            min_value: 0,
+            value,
+            randomness: [0u8; 32],
+        };
+
+        let proof = tokio::spawn({
+            let prover = prover.clone();
+            async move { prover.generate(range(1), 1).await }
+        });
+        while prover.queue_depth() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let started = tokio::time::Instant::now();
+        let grace = std::time::Duration::from_secs(30);
+        assert!(drain_in_flight(&prover, &withdrawals, grace).await);
+        // The drain lasted as long as the proof, which finished first
+        assert!(started.elapsed() < grace);
+        assert!(proof.is_finished());
+        assert!(proof.await.unwrap().is_ok());
+
+        // Work arriving after the drain started is refused
+        let err = prover.generate(range(2), 1).await.unwrap_err();
+        assert!(matches!(
+            err,
+            error::RelayerError::Prover(prover::ProverError::ShuttingDown)
+        ));
+        let (_, request) = relay::validate::valid_relay_fixture(1).await;
+        assert_eq!(
+            withdrawals.submit("r1", &request).await.unwrap_err().code(),
+            "shutting_down"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_withdrawal_event_spends_nullifier() {
+        let store = store::memory().await;
+        let nullifier = ethers::types::H256::repeat_byte(0x22);
+        let pool = ethers::types::Address::repeat_byte(0x50);
+        let trigger = watcher::RelayTrigger {
+            chain_id: 1,
+            pool,
+            block_number: 100,
+            block_hash: ethers::types::H256::repeat_byte(0x01),
+            tx_hash: ethers::types::H256::repeat_byte(0x02),
+            log_index: ethers::types::U256::zero(),
+            event: watcher::PoolEvent::Withdrawal {
+                nullifier,
+                recipient: ethers::types::Address::repeat_byte(0x33),
+                amount: ethers::types::U256::from(1_000u64),
+            },
+        };
+
+        handle_relay_trigger(trigger, &HashMap::new(), store.as_ref())
+            .await
+            .unwrap();
+        // Spent by someone else's relay, so no request may spend it again
+        assert!(store.has_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(!store.insert_nullifier(1, pool, nullifier).await.unwrap());
+    }
+}
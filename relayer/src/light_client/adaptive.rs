@@ -0,0 +1,169 @@
+//! Finality depth raised by peer-reported reorgs
+//!
+//! Relayers gossip the depth of every reorg they observe. A chain with
+//! adaptive finality enabled keeps each peer's deepest report from the last
+//! `REPORT_WINDOW`; once at least `MIN_REPORTING_PEERS` distinct peers have
+//! reported, the depth that many of them agree on (plus one) becomes the
+//! chain's finality depth if it is above the configured one. Requiring a
+//! quorum stops a single peer from stalling finality, and the result never
+//! exceeds the configured `max_depth`. Only reports from peers with a
+//! positive local reputation are recorded, so new identities can't make up
+//! the quorum.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long a peer's reorg report counts towards the depth
+pub const REPORT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Peers that must report a depth before it is adopted
+pub const MIN_REPORTING_PEERS: usize = 3;
+
+/// Peer-reported reorg depths for one chain
+#[derive(Debug)]
+pub struct AdaptiveDepth {
+    /// Upper bound on the effective finality depth
+    max_depth: u64,
+    /// Deepest recent report and when it arrived, by peer ID
+    reports: Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+impl AdaptiveDepth {
+    pub fn new(max_depth: u64) -> Self {
+        Self {
+            max_depth,
+            reports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a reorg of `depth` blocks reported by `peer_id`
+    pub fn record(&self, peer_id: &str, depth: u64) {
+        self.record_at(peer_id, depth, Instant::now());
+    }
+
+    /// Finality depth to use given the configured `min_depth`
+    pub fn effective_depth(&self, min_depth: u64) -> u64 {
+        self.effective_depth_at(min_depth, Instant::now())
+    }
+
+    fn record_at(&self, peer_id: &str, depth: u64, now: Instant) {
+        let mut reports = self.reports.lock().unwrap();
+        reports.retain(|_, (_, at)| now.duration_since(*at) < REPORT_WINDOW);
+        let report = reports.entry(peer_id.to_string()).or_insert((0, now));
+        *report = (report.0.max(depth), now);
+    }
+
+    fn effective_depth_at(&self, min_depth: u64, now: Instant) -> u64 {
+        let mut depths: Vec<u64> = self
+            .reports
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(_, at)| now.duration_since(*at) < REPORT_WINDOW)
+            .map(|(depth, _)| *depth)
+            .collect();
+        if depths.len() < MIN_REPORTING_PEERS {
+            return min_depth;
+        }
+
+        // Deepest reorg that at least MIN_REPORTING_PEERS peers have seen
+        depths.sort_unstable_by(|a, b| b.cmp(a));
+        let agreed = depths[MIN_REPORTING_PEERS - 1];
+        let depth = (agreed + 1).min(self.max_depth).max(min_depth);
+        if depth > min_depth {
+            debug!(
+                agreed_reorg_depth = agreed,
+                finality_depth = depth,
+                "Finality depth raised by peer reorg reports"
+            );
+        }
+        depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_client::sim::SimHarness;
+    use crate::light_client::{ChainSettings, LightClientEvent};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_peer_reorgs_raise_depth_within_bounds() {
+        let adaptive = AdaptiveDepth::new(40);
+        let now = Instant::now();
+
+        // Too few peers to act on
+        adaptive.record_at("a", 30, now);
+        adaptive.record_at("b", 25, now);
+        assert_eq!(adaptive.effective_depth_at(15, now), 15);
+
+        // A third peer: the depth all three agree on is 20
+        adaptive.record_at("c", 20, now);
+        assert_eq!(adaptive.effective_depth_at(15, now), 21);
+
+        // One peer claiming an extreme depth can't push past the others,
+        // and the result is capped
+        adaptive.record_at("d", 1_000, now);
+        assert_eq!(adaptive.effective_depth_at(15, now), 26);
+        for peer in ["e", "f"] {
+            adaptive.record_at(peer, 1_000, now);
+        }
+        assert_eq!(adaptive.effective_depth_at(15, now), 40);
+
+        // Shallow reorgs never lower the configured depth, and old reports expire
+        let later = now + REPORT_WINDOW;
+        adaptive.record_at("a", 2, later);
+        adaptive.record_at("b", 2, later);
+        adaptive.record_at("c", 2, later);
+        assert_eq!(adaptive.effective_depth_at(15, later), 15);
+    }
+
+    #[test]
+    fn test_quorum_needs_distinct_peers() {
+        let adaptive = AdaptiveDepth::new(64);
+        let now = Instant::now();
+
+        // Repeated reports from one peer only ever count once
+        for depth in [30, 31, 32] {
+            adaptive.record_at("a", depth, now);
+        }
+        adaptive.record_at("b", 30, now);
+        assert_eq!(adaptive.effective_depth_at(15, now), 15);
+
+        adaptive.record_at("c", 30, now);
+        assert_eq!(adaptive.effective_depth_at(15, now), 31);
+    }
+
+    #[tokio::test]
+    async fn test_raised_depth_holds_back_finality() {
+        let adaptive = Arc::new(AdaptiveDepth::new(64));
+        let settings = ChainSettings {
+            finality_depth: 5,
+            adaptive_depth: Some(adaptive.clone()),
+            ..ChainSettings::default()
+        };
+        let mut sim = SimHarness::new(1, 100, settings).await;
+        sim.chain.extend(1);
+        sim.advance().await.unwrap();
+        assert_eq!(sim.finalized(), 96);
+
+        // Peers saw 11-block reorgs: finality now trails the head by 12,
+        // holding at 96 rather than moving back
+        for peer in ["a", "b", "c"] {
+            adaptive.record(peer, 11);
+        }
+        sim.chain.extend(1);
+        sim.advance().await.unwrap();
+        assert_eq!(sim.finalized(), 96);
+        sim.chain.extend(8);
+        sim.advance().await.unwrap();
+        assert_eq!(sim.finalized(), 98);
+        assert!(!sim
+            .events()
+            .iter()
+            .any(|event| matches!(event, LightClientEvent::FinalityRegression { .. })));
+    }
+}
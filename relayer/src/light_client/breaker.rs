@@ -0,0 +1,607 @@
+//! Circuit breakers for RPC endpoints
+//!
+//! A chain's endpoints are tried in order of preference, a failed call
+//! moving on to the next. A call that gets no answer within the request
+//! timeout has failed like any other, so a hung endpoint trips its breaker
+//! instead of holding every caller up. After `failure_threshold`
+//! consecutive failures an endpoint is taken out of rotation: calls skip it
+//! (ending at the fallback endpoint, or failing fast) until the cooldown
+//! passes, then a single probe is let through. A successful probe closes the breaker and the endpoint
+//! takes its place back; a failed one reopens it with the cooldown doubled,
+//! up to `max_cooldown`. A probe dropped before it finishes reopens the
+//! breaker as it was, so a cancelled call can't leave it half-open.
+//!
+//! Every RPC client of a chain (light client, submitter, watcher, signer
+//! checks) shares the same breakers, through `BreakerClient`.
+
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::metrics;
+
+/// Default consecutive failures before the breaker opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default time an open breaker waits before probing the endpoint
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Default time an endpoint has to answer a request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Cap on the cooldown after repeated failed probes
+const MAX_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// Whether calls reach the endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Healthy: every call goes through
+    Closed,
+    /// Tripped: calls fail fast until the cooldown passes
+    Open,
+    /// Cooldown over: one probe call is in flight
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Value exported on the breaker state gauge
+    fn gauge_value(self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::Open => 1,
+            BreakerState::HalfOpen => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    cooldown: Duration,
+}
+
+/// Consecutive-failure breaker for a single endpoint
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Metric label, e.g. the chain ID
+    label: String,
+    failure_threshold: u32,
+    base_cooldown: Duration,
+    max_cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(label: impl Into<String>, failure_threshold: u32, cooldown: Duration) -> Self {
+        let breaker = Self {
+            label: label.into(),
+            failure_threshold: failure_threshold.max(1),
+            base_cooldown: cooldown,
+            max_cooldown: MAX_COOLDOWN.max(cooldown),
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                cooldown,
+            }),
+        };
+        breaker.publish(BreakerState::Closed);
+        breaker
+    }
+
+    /// Breaker shared by every client of the endpoint labelled `label`,
+    /// created with these settings by the first to ask
+    pub fn shared(label: String, failure_threshold: u32, cooldown: Duration) -> Arc<Self> {
+        static BREAKERS: Lazy<Mutex<HashMap<String, Arc<CircuitBreaker>>>> =
+            Lazy::new(Mutex::default);
+        BREAKERS
+            .lock()
+            .unwrap()
+            .entry(label.clone())
+            .or_insert_with(|| Arc::new(Self::new(label, failure_threshold, cooldown)))
+            .clone()
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Admit a call to the endpoint, if it may go there now
+    ///
+    /// Moves an open breaker whose cooldown has passed to half-open and
+    /// admits that caller as the probe. The call's outcome is recorded
+    /// through the returned permit.
+    pub fn admit(&self) -> Option<Permit<'_>> {
+        let mut inner = self.inner.lock().unwrap();
+        let probe = match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::HalfOpen => return None,
+            BreakerState::Open if inner.opened_at.elapsed() >= inner.cooldown => {
+                inner.state = BreakerState::HalfOpen;
+                self.publish(BreakerState::HalfOpen);
+                info!(endpoint = %self.label, "RPC breaker half-open, probing endpoint");
+                true
+            }
+            BreakerState::Open => return None,
+        };
+        Some(Permit {
+            breaker: self,
+            probe,
+        })
+    }
+
+    fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        if inner.state != BreakerState::Closed {
+            info!(endpoint = %self.label, "RPC endpoint recovered, breaker closed");
+            inner.state = BreakerState::Closed;
+            inner.cooldown = self.base_cooldown;
+            self.publish(BreakerState::Closed);
+        }
+    }
+
+    fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        let reopen = match inner.state {
+            BreakerState::HalfOpen => {
+                inner.cooldown = (inner.cooldown * 2).min(self.max_cooldown);
+                true
+            }
+            BreakerState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            BreakerState::Open => false,
+        };
+        if reopen {
+            warn!(
+                endpoint = %self.label,
+                failures = inner.consecutive_failures,
+                cooldown_secs = inner.cooldown.as_secs_f64(),
+                "RPC breaker open"
+            );
+            inner.state = BreakerState::Open;
+            inner.opened_at = Instant::now();
+            metrics::RPC_BREAKER_TRIPS
+                .with_label_values(&[&self.label])
+                .inc();
+            self.publish(BreakerState::Open);
+        }
+    }
+
+    /// Reopen a breaker whose probe never finished, cooldown unchanged
+    fn abandon_probe(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Instant::now();
+            self.publish(BreakerState::Open);
+        }
+    }
+
+    fn publish(&self, state: BreakerState) {
+        metrics::RPC_BREAKER_STATE
+            .with_label_values(&[&self.label])
+            .set(state.gauge_value());
+    }
+}
+
+/// A call admitted by a breaker, recording its outcome
+///
+/// Dropping the permit of a probe without recording an outcome (the call
+/// was cancelled or timed out) reopens the breaker.
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Permit<'_> {
+    pub fn success(mut self) {
+        self.probe = false;
+        self.breaker.record_success();
+    }
+
+    pub fn failure(mut self) {
+        self.probe = false;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.abandon_probe();
+        }
+    }
+}
+
+/// Why a call through `BreakerClient` failed
+#[derive(Debug, thiserror::Error)]
+pub enum BreakerClientError<E: RpcError> {
+    #[error(transparent)]
+    Rpc(E),
+    #[error("Every RPC endpoint of chain {0} has its breaker open")]
+    AllOpen(String),
+    #[error("RPC request got no answer within {0:?}")]
+    Timeout(Duration),
+    #[error("Failed to serialize RPC params: {0}")]
+    Serde(serde_json::Error),
+}
+
+impl<E: RpcError> RpcError for BreakerClientError<E> {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            BreakerClientError::Rpc(e) => e.as_error_response(),
+            BreakerClientError::AllOpen(_)
+            | BreakerClientError::Timeout(_)
+            | BreakerClientError::Serde(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            BreakerClientError::Rpc(e) => e.as_serde_error(),
+            BreakerClientError::Serde(e) => Some(e),
+            BreakerClientError::AllOpen(_) | BreakerClientError::Timeout(_) => None,
+        }
+    }
+}
+
+impl<E: RpcError + Into<ProviderError>> From<BreakerClientError<E>> for ProviderError {
+    fn from(e: BreakerClientError<E>) -> Self {
+        match e {
+            BreakerClientError::Rpc(e) => e.into(),
+            BreakerClientError::Serde(e) => ProviderError::SerdeJson(e),
+            e @ (BreakerClientError::AllOpen(_) | BreakerClientError::Timeout(_)) => {
+                ProviderError::CustomError(e.to_string())
+            }
+        }
+    }
+}
+
+/// JSON-RPC transport over a chain's endpoints in order of preference, each
+/// guarded by a breaker, with an optional unguarded fallback endpoint
+///
+/// Transport failures and timeouts count against an endpoint. An error
+/// response (a revert, a rejected transaction) means the endpoint is up and
+/// would be answered the same elsewhere, so it is returned without failing
+/// over.
+#[derive(Debug)]
+pub struct BreakerClient<C> {
+    chain: String,
+    endpoints: Vec<(C, Arc<CircuitBreaker>)>,
+    fallback: Option<C>,
+    /// Time each endpoint has to answer
+    timeout: Duration,
+}
+
+impl<C> BreakerClient<C> {
+    pub fn new(
+        chain: impl Into<String>,
+        endpoints: Vec<(C, Arc<CircuitBreaker>)>,
+        fallback: Option<C>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            chain: chain.into(),
+            endpoints,
+            fallback,
+            timeout,
+        }
+    }
+}
+
+impl<C: JsonRpcClient> BreakerClient<C> {
+    /// Send already serialized params, or none at all for a zero-sized
+    /// type, which the transport leaves out of the request
+    async fn send<R>(
+        &self,
+        client: &C,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Result<R, BreakerClientError<C::Error>>
+    where
+        R: DeserializeOwned + Send,
+    {
+        let request = async {
+            match params {
+                Some(params) => client.request(method, params).await,
+                None => client.request(method, ()).await,
+            }
+        };
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| BreakerClientError::Timeout(self.timeout))?
+            .map_err(BreakerClientError::Rpc)
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for BreakerClient<C> {
+    type Error = BreakerClientError<C::Error>;
+
+    /// Send the request to each endpoint whose breaker admits it until one
+    /// answers, then to the fallback
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = if std::mem::size_of::<T>() == 0 {
+            None
+        } else {
+            Some(serde_json::to_value(params).map_err(BreakerClientError::Serde)?)
+        };
+
+        let mut last_error = None;
+        for (client, breaker) in &self.endpoints {
+            let Some(permit) = breaker.admit() else {
+                continue;
+            };
+            match self.send(client, method, &params).await {
+                Ok(value) => {
+                    permit.success();
+                    return Ok(value);
+                }
+                Err(e) if e.as_error_response().is_some() => {
+                    permit.success();
+                    return Err(e);
+                }
+                Err(e) => {
+                    permit.failure();
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match &self.fallback {
+            Some(fallback) => self.send(fallback, method, &params).await,
+            None => {
+                Err(last_error.unwrap_or_else(|| BreakerClientError::AllOpen(self.chain.clone())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_client::BlockSource;
+    use ethers::providers::{Middleware, MockError, MockProvider, MockResponse, Provider};
+    use ethers::types::U64;
+
+    /// Provider over `endpoints`, each with a fresh breaker labelled by
+    /// `label` and its index
+    fn provider(
+        label: &str,
+        threshold: u32,
+        cooldown: Duration,
+        endpoints: &[&MockProvider],
+        fallback: Option<&MockProvider>,
+    ) -> (
+        Provider<BreakerClient<MockProvider>>,
+        Vec<Arc<CircuitBreaker>>,
+    ) {
+        let breakers: Vec<_> = (0..endpoints.len())
+            .map(|index| {
+                Arc::new(CircuitBreaker::new(
+                    format!("{}/{}", label, index),
+                    threshold,
+                    cooldown,
+                ))
+            })
+            .collect();
+        let client = BreakerClient::new(
+            label,
+            endpoints
+                .iter()
+                .map(|mock| (*mock).clone())
+                .zip(breakers.iter().cloned())
+                .collect(),
+            fallback.cloned(),
+            DEFAULT_REQUEST_TIMEOUT,
+        );
+        (Provider::new(client), breakers)
+    }
+
+    /// Transport answering through a mock after `delay`
+    #[derive(Debug, Clone)]
+    struct Slow(MockProvider, Duration);
+
+    #[async_trait]
+    impl JsonRpcClient for Slow {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            tokio::time::sleep(self.1).await;
+            self.0.request(method, params).await
+        }
+    }
+
+    /// Calls `mock` received for the head, draining its request log
+    fn head_calls(mock: &MockProvider) -> usize {
+        std::iter::from_fn(|| mock.assert_request("eth_blockNumber", ()).ok()).count()
+    }
+
+    fn answer(mock: &MockProvider, head: u64, times: usize) {
+        for _ in 0..times {
+            mock.push(U64::from(head)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failures_trip_breaker_and_success_closes_it() {
+        // An endpoint with no answers queued fails every call
+        let primary = MockProvider::new();
+        let fallback = MockProvider::new();
+        answer(&fallback, 99, 6);
+        let (source, breakers) = provider(
+            "test-trip",
+            3,
+            Duration::from_millis(50),
+            &[&primary],
+            Some(&fallback),
+        );
+        let gauge = metrics::RPC_BREAKER_STATE.with_label_values(&["test-trip/0"]);
+
+        // Failures below the threshold still try the primary first
+        for _ in 0..3 {
+            assert_eq!(source.fetch_block_number().await.unwrap(), 99);
+        }
+        assert_eq!(head_calls(&primary), 3);
+        assert_eq!(breakers[0].state(), BreakerState::Open);
+        assert_eq!(gauge.get(), 1);
+
+        // Open: the primary isn't touched
+        assert_eq!(source.fetch_block_number().await.unwrap(), 99);
+        assert_eq!(head_calls(&primary), 0);
+
+        // A failed probe reopens with a doubled cooldown
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        source.fetch_block_number().await.unwrap();
+        assert_eq!(head_calls(&primary), 1);
+        assert_eq!(breakers[0].state(), BreakerState::Open);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        source.fetch_block_number().await.unwrap();
+        assert_eq!(head_calls(&primary), 0);
+
+        // The endpoint recovers: the next probe closes the breaker
+        answer(&primary, 100, 1);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(source.fetch_block_number().await.unwrap(), 100);
+        assert_eq!(breakers[0].state(), BreakerState::Closed);
+        assert_eq!(gauge.get(), 0);
+        assert_eq!(
+            metrics::RPC_BREAKER_TRIPS
+                .with_label_values(&["test-trip/0"])
+                .get(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_without_fallback_fails_fast() {
+        let primary = MockProvider::new();
+        let (source, _) = provider(
+            "test-no-fallback",
+            1,
+            Duration::from_secs(60),
+            &[&primary],
+            None,
+        );
+
+        assert!(source.fetch_block_number().await.is_err());
+        let err = source.fetch_block_number().await.unwrap_err();
+        assert!(err.to_string().contains("breaker open"));
+        assert_eq!(head_calls(&primary), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_next_endpoint() {
+        let first = MockProvider::new();
+        let second = MockProvider::new();
+        answer(&second, 101, 5);
+        let (source, breakers) = provider(
+            "test-failover",
+            2,
+            Duration::from_secs(60),
+            &[&first, &second],
+            None,
+        );
+
+        for _ in 0..5 {
+            assert_eq!(source.fetch_block_number().await.unwrap(), 101);
+        }
+        // The failing endpoint is only retried until its breaker opens
+        assert_eq!(head_calls(&first), 2);
+        assert_eq!(head_calls(&second), 5);
+        assert_eq!(breakers[0].state(), BreakerState::Open);
+        assert_eq!(breakers[1].state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_neither_trips_nor_fails_over() {
+        let first = MockProvider::new();
+        let second = MockProvider::new();
+        answer(&second, 101, 1);
+        let (source, breakers) = provider(
+            "test-error-response",
+            1,
+            Duration::from_secs(60),
+            &[&first, &second],
+            None,
+        );
+
+        first.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "nonce too low".to_string(),
+            data: None,
+        }));
+        let err = source.get_block_number().await.unwrap_err();
+        assert!(err.as_error_response().is_some());
+        assert_eq!(breakers[0].state(), BreakerState::Closed);
+        assert_eq!(head_calls(&second), 0);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_trip_breaker() {
+        let hung = MockProvider::new();
+        answer(&hung, 100, 3);
+        let healthy = MockProvider::new();
+        answer(&healthy, 101, 3);
+        let breakers: Vec<_> = (0..2)
+            .map(|index| {
+                Arc::new(CircuitBreaker::new(
+                    format!("test-timeout/{}", index),
+                    2,
+                    Duration::from_secs(60),
+                ))
+            })
+            .collect();
+        let source = Provider::new(BreakerClient::new(
+            "test-timeout",
+            vec![
+                (Slow(hung, Duration::from_secs(5)), breakers[0].clone()),
+                (Slow(healthy, Duration::ZERO), breakers[1].clone()),
+            ],
+            None,
+            Duration::from_millis(20),
+        ));
+
+        // Each timeout fails over, and enough of them open the breaker
+        for _ in 0..3 {
+            assert_eq!(source.fetch_block_number().await.unwrap(), 101);
+        }
+        assert_eq!(breakers[0].state(), BreakerState::Open);
+        assert_eq!(breakers[1].state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_dropped_probe_reopens_breaker() {
+        let breaker = CircuitBreaker::new("test-dropped-probe", 1, Duration::ZERO);
+        breaker.admit().unwrap().failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // The probe is cancelled before it answers
+        let probe = breaker.admit().unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.admit().is_none());
+        drop(probe);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // so the next call probes again, and can close it
+        breaker.admit().unwrap().success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -1,14 +1,71 @@
 //! Light Client for cross-chain block header synchronization
 //!
-//! Maintains block headers for Ethereum and Arbitrum chains,
+//! Maintains block headers for each configured chain,
 //! enabling verification of cross-chain transactions.
 
+pub mod adaptive;
+pub mod breaker;
+pub mod mpt;
+#[cfg(test)]
+mod sim;
+
 use anyhow::Result;
+use async_trait::async_trait;
 use ethers::prelude::*;
-use std::collections::HashMap;
-use std::sync::Arc;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{BoxStream, StreamExt};
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{debug, info, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::channel;
+use crate::error::RelayerError;
+use crate::merkle::hash_pair;
+use crate::metrics;
+use crate::store::Store;
+use crate::ChainEndpoints;
+
+/// Default number of blocks before a header is considered final
+pub const DEFAULT_FINALITY_DEPTH: u64 = 15;
+
+/// Default number of recent headers kept in memory per chain
+pub const DEFAULT_RETAINED_HEADERS: usize = 1000;
+
+/// Chains that may produce several blocks within the same second (Arbitrum
+/// One, Nova and Sepolia), so a block may share its parent's timestamp
+const EQUAL_TIMESTAMP_CHAINS: [u64; 3] = [42161, 42170, 421614];
+
+/// Default interval between head polls on each chain
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fraction by which each poll interval is randomly stretched or shrunk
+const POLL_JITTER: f64 = 0.2;
+
+/// Cap on the poll interval while an RPC keeps failing
+const POLL_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Default silence on a WebSocket subscription before it's treated as stalled
+const DEFAULT_WS_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Initial delay before re-establishing a dropped WebSocket subscription
+const WS_RECONNECT_MIN: Duration = Duration::from_millis(500);
+
+/// Cap on the WebSocket reconnect delay
+const WS_RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// Transactions root of a block with no transactions
+///
+/// This is `keccak256(rlp(""))`, the root of an empty Merkle-Patricia trie.
+/// No transaction can be proven included under it.
+pub const EMPTY_TRIE_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
 
 /// Events emitted by the light client
 #[derive(Debug, Clone)]
@@ -18,13 +75,125 @@ pub enum LightClientEvent {
         chain_id: u64,
         block_number: u64,
         block_hash: H256,
+        /// Block timestamp (Unix seconds)
+        timestamp: u64,
     },
     /// Chain reorganization detected
-    Reorg { chain_id: u64, depth: u64 },
+    Reorg(ReorgRecord),
+    /// Finalized height moved backwards beyond the configured tolerance
+    FinalityRegression {
+        chain_id: u64,
+        previous: u64,
+        current: u64,
+    },
+}
+
+/// A new head whose chain disagrees with the retained headers further back
+/// than they can be walked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("New head on chain {chain_id} doesn't link onto the headers retained from {oldest}")]
+pub struct DeepReorg {
+    pub chain_id: u64,
+    /// Lowest stored block number the new chain was seen to replace
+    pub replaced_from: u64,
+    /// Oldest retained block number
+    pub oldest: u64,
+}
+
+/// A new head further past the stored tip than the retained window, so the
+/// gap can't be backfilled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("New head {head} on chain {chain_id} is too far past the stored tip {tip} to backfill")]
+pub struct HeaderGap {
+    pub chain_id: u64,
+    pub tip: u64,
+    pub head: u64,
+}
+
+/// A header from the RPC whose fields can't belong to the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidHeader {
+    #[error("Asked for block {requested}, got block {returned}")]
+    WrongNumber { requested: u64, returned: u64 },
+    #[error("Block {0} has a zero block or parent hash")]
+    ZeroHash(u64),
+    #[error(
+        "Block {block_number} timestamp {timestamp} does not follow its parent's {parent_timestamp}"
+    )]
+    Timestamp {
+        block_number: u64,
+        timestamp: u64,
+        parent_timestamp: u64,
+    },
+}
+
+impl InvalidHeader {
+    /// Label for the invalid headers metric
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::WrongNumber { .. } => "wrong_number",
+            Self::ZeroHash(_) => "zero_hash",
+            Self::Timestamp { .. } => "timestamp",
+        }
+    }
+}
+
+/// Check a header fetched as `requested` is numbered as asked and hashed
+pub fn check_header(header: &StoredHeader, requested: u64) -> Result<(), InvalidHeader> {
+    if header.block_number != requested {
+        return Err(InvalidHeader::WrongNumber {
+            requested,
+            returned: header.block_number,
+        });
+    }
+    // Only genesis has no parent
+    if header.block_hash.is_zero() || (header.parent_hash.is_zero() && requested > 0) {
+        return Err(InvalidHeader::ZeroHash(requested));
+    }
+    Ok(())
+}
+
+/// Check `header` was produced after `parent`, or in the same second when
+/// `allow_equal` is set
+pub fn check_timestamp(
+    header: &StoredHeader,
+    parent: &StoredHeader,
+    allow_equal: bool,
+) -> Result<(), InvalidHeader> {
+    let follows = if allow_equal {
+        header.timestamp >= parent.timestamp
+    } else {
+        header.timestamp > parent.timestamp
+    };
+    if !follows {
+        return Err(InvalidHeader::Timestamp {
+            block_number: header.block_number,
+            timestamp: header.timestamp,
+            parent_timestamp: parent.timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// A reorg as the node observed it, kept for post-incident analysis
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReorgRecord {
+    pub chain_id: u64,
+    /// Unix time (seconds) the reorg was handled
+    pub detected_at: i64,
+    /// Height of the first block replaced
+    pub fork_block: u64,
+    /// Number of stored blocks replaced
+    pub depth: u64,
+    /// Hashes of the replaced blocks, from `fork_block` up
+    pub orphaned: Vec<H256>,
+    /// Hashes of the new branch, from `fork_block` up to the new head; after
+    /// a deep reorg, only from where the resync started
+    pub replacement: Vec<H256>,
 }
 
 /// Stored block header
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct StoredHeader {
     pub block_number: u64,
     pub block_hash: H256,
@@ -35,288 +204,2344 @@ pub struct StoredHeader {
     pub timestamp: u64,
 }
 
-/// Light client for multiple chains
-pub struct LightClient {
-    /// Ethereum provider
-    eth_provider: Arc<Provider<Http>>,
-    /// Arbitrum provider
-    arb_provider: Arc<Provider<Http>>,
-    /// Stored headers by chain ID
-    headers: HashMap<u64, Vec<StoredHeader>>,
-    /// Latest finalized block by chain
-    finalized: HashMap<u64, u64>,
-    /// Event sender
-    event_tx: mpsc::Sender<LightClientEvent>,
-    /// Event receiver
-    event_rx: mpsc::Receiver<LightClientEvent>,
-    /// Finality depth
-    finality_depth: u64,
-}
+impl StoredHeader {
+    /// Build a header from an RPC block, skipping pending blocks
+    ///
+    /// Some RPCs report a zero transactions root for empty blocks; that is
+    /// normalized to `EMPTY_TRIE_ROOT`.
+    fn from_block<T>(block: &Block<T>) -> Option<Self> {
+        let transactions_root =
+            if block.transactions_root.is_zero() && block.transactions.is_empty() {
+                EMPTY_TRIE_ROOT
+            } else {
+                block.transactions_root
+            };
 
-impl LightClient {
-    /// Create a new light client
-    pub async fn new(eth_rpc: &str, arb_rpc: &str) -> Result<Self> {
-        let eth_provider = Arc::new(Provider::<Http>::try_from(eth_rpc)?);
-        let arb_provider = Arc::new(Provider::<Http>::try_from(arb_rpc)?);
+        Some(Self {
+            block_number: block.number?.as_u64(),
+            block_hash: block.hash?,
+            parent_hash: block.parent_hash,
+            state_root: block.state_root,
+            transactions_root,
+            receipts_root: block.receipts_root,
+            timestamp: block.timestamp.as_u64(),
+        })
+    }
+}
 
-        let (event_tx, event_rx) = mpsc::channel(1000);
+/// Source of block data for a single chain
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Chain ID served by this source
+    async fn fetch_chain_id(&self) -> Result<u64>;
+    /// Current head block number
+    async fn fetch_block_number(&self) -> Result<u64>;
+    /// Header at the given height, if the block exists
+    async fn fetch_header(&self, block_number: u64) -> Result<Option<StoredHeader>>;
+}
 
-        let mut client = Self {
-            eth_provider,
-            arb_provider,
-            headers: HashMap::new(),
-            finalized: HashMap::new(),
-            event_tx,
-            event_rx,
-            finality_depth: 15,
-        };
+#[async_trait]
+impl<P: JsonRpcClient + 'static> BlockSource for Provider<P> {
+    async fn fetch_chain_id(&self) -> Result<u64> {
+        Ok(self.get_chainid().await?.as_u64())
+    }
 
-        // Initialize with current block
-        client.sync_initial().await?;
+    async fn fetch_block_number(&self) -> Result<u64> {
+        Ok(self.get_block_number().await?.as_u64())
+    }
 
-        Ok(client)
+    async fn fetch_header(&self, block_number: u64) -> Result<Option<StoredHeader>> {
+        Ok(self
+            .get_block(BlockId::Number(block_number.into()))
+            .await?
+            .as_ref()
+            .and_then(StoredHeader::from_block))
     }
+}
 
-    /// Perform initial synchronization
-    async fn sync_initial(&mut self) -> Result<()> {
-        // Get current Ethereum block
-        let eth_block = self.eth_provider.get_block_number().await?;
-        info!(block = eth_block.as_u64(), "Ethereum sync starting");
+/// Push-based source of new head block numbers
+#[async_trait]
+pub trait HeadSubscriber: Send + Sync {
+    /// Open a subscription; the stream ending means the connection dropped
+    async fn subscribe(&self) -> Result<BoxStream<'static, u64>>;
+}
 
-        // Get current Arbitrum block
-        let arb_block = self.arb_provider.get_block_number().await?;
-        info!(block = arb_block.as_u64(), "Arbitrum sync starting");
+/// `newHeads` subscription over a WebSocket endpoint
+pub struct WsSubscriber {
+    url: String,
+}
 
-        // Fetch recent headers
-        let eth_chain_id = self.eth_provider.get_chainid().await?.as_u64();
-        let arb_chain_id = self.arb_provider.get_chainid().await?.as_u64();
+impl WsSubscriber {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+}
 
-        self.sync_headers(&self.eth_provider.clone(), eth_chain_id, eth_block.as_u64())
-            .await?;
-        self.sync_headers(&self.arb_provider.clone(), arb_chain_id, arb_block.as_u64())
-            .await?;
+#[async_trait]
+impl HeadSubscriber for WsSubscriber {
+    async fn subscribe(&self) -> Result<BoxStream<'static, u64>> {
+        let provider = Provider::<Ws>::connect(&self.url).await?;
+        let (tx, rx) = mpsc::channel(64);
 
-        Ok(())
+        // The subscription stream borrows the provider, so both live in a
+        // forwarding task that ends when either side goes away
+        tokio::spawn(async move {
+            let mut blocks = match provider.subscribe_blocks().await {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    warn!(error = %e, "Failed to subscribe to new heads");
+                    return;
+                }
+            };
+            while let Some(block) = blocks.next().await {
+                let Some(number) = block.number else {
+                    continue;
+                };
+                if tx.send(number.as_u64()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|number| (number, rx))
+        })
+        .boxed())
     }
+}
 
-    /// Sync headers from a starting block
-    async fn sync_headers(
-        &mut self,
-        provider: &Provider<Http>,
-        chain_id: u64,
-        current_block: u64,
-    ) -> Result<()> {
-        let start_block = current_block.saturating_sub(self.finality_depth * 2);
-        let mut headers = Vec::new();
+/// Keep a head subscription alive, forwarding heads to the chain task
+///
+/// Reconnects with exponential backoff when the stream ends or goes silent
+/// for longer than `stall_timeout`. `live` is cleared while disconnected so
+/// the chain task falls back to HTTP polling.
+async fn ws_head_feed(
+    chain_id: u64,
+    subscriber: Arc<dyn HeadSubscriber>,
+    heads: mpsc::Sender<u64>,
+    live: Arc<AtomicBool>,
+    stall_timeout: Duration,
+) {
+    let chain_label = chain_id.to_string();
+    let mut backoff = WS_RECONNECT_MIN;
 
-        for block_num in start_block..=current_block {
-            if let Some(block) = provider
-                .get_block(BlockId::Number(block_num.into()))
-                .await?
-            {
-                headers.push(StoredHeader {
-                    block_number: block.number.unwrap().as_u64(),
-                    block_hash: block.hash.unwrap(),
-                    parent_hash: block.parent_hash,
-                    state_root: block.state_root,
-                    transactions_root: block.transactions_root,
-                    receipts_root: block.receipts_root,
-                    timestamp: block.timestamp.as_u64(),
-                });
+    loop {
+        match subscriber.subscribe().await {
+            Ok(mut stream) => {
+                info!(chain_id = chain_id, "Head subscription established");
+                live.store(true, Ordering::SeqCst);
+                backoff = WS_RECONNECT_MIN;
+
+                loop {
+                    match tokio::time::timeout(stall_timeout, stream.next()).await {
+                        Ok(Some(block_number)) => {
+                            if heads.send(block_number).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => {
+                            warn!(chain_id = chain_id, "Head subscription dropped");
+                            break;
+                        }
+                        Err(_) => {
+                            warn!(
+                                chain_id = chain_id,
+                                stall_secs = stall_timeout.as_secs(),
+                                "Head subscription stalled"
+                            );
+                            break;
+                        }
+                    }
+                }
+
+                live.store(false, Ordering::SeqCst);
+            }
+            Err(e) => {
+                warn!(chain_id = chain_id, error = %e, "Head subscription failed");
             }
         }
 
-        self.headers.insert(chain_id, headers);
-        self.finalized
-            .insert(chain_id, current_block.saturating_sub(self.finality_depth));
-
         info!(
             chain_id = chain_id,
-            headers = self.headers.get(&chain_id).map_or(0, |h| h.len()),
-            "Headers synchronized"
+            backoff_ms = backoff.as_millis() as u64,
+            "Polling over HTTP until head subscription reconnects"
         );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(WS_RECONNECT_MAX);
+        metrics::WS_RECONNECTS
+            .with_label_values(&[&chain_label])
+            .inc();
+    }
+}
 
-        Ok(())
+/// Per-chain light client settings
+#[derive(Debug, Clone)]
+pub struct ChainSettings {
+    /// Number of blocks before a header is considered final
+    pub finality_depth: u64,
+    /// How far the finalized height may move backwards before alerting
+    pub finality_regression_tolerance: u64,
+    /// Silence after which a head subscription is considered stalled
+    pub ws_stall_timeout: Duration,
+    /// Peer reorg reports that may raise `finality_depth` (unset: fixed depth)
+    pub adaptive_depth: Option<Arc<adaptive::AdaptiveDepth>>,
+    /// Recent headers kept in memory; reorgs reaching further back resync
+    pub retained_headers: usize,
+    /// Accept blocks sharing their parent's timestamp, rejecting only
+    /// decreasing ones
+    pub allow_equal_timestamps: bool,
+}
+
+impl Default for ChainSettings {
+    fn default() -> Self {
+        Self {
+            finality_depth: DEFAULT_FINALITY_DEPTH,
+            finality_regression_tolerance: 0,
+            ws_stall_timeout: DEFAULT_WS_STALL_TIMEOUT,
+            adaptive_depth: None,
+            retained_headers: DEFAULT_RETAINED_HEADERS,
+            allow_equal_timestamps: false,
+        }
     }
+}
 
-    /// Get the next event from the light client
-    pub async fn next_event(&mut self) -> Option<LightClientEvent> {
-        // Poll for new blocks
-        self.poll_new_blocks().await.ok();
-        self.event_rx.recv().await
+impl ChainSettings {
+    /// Finality depth in force, after any raise from peer reports
+    pub fn effective_finality_depth(&self) -> u64 {
+        self.adaptive_depth
+            .as_ref()
+            .map_or(self.finality_depth, |adaptive| {
+                adaptive.effective_depth(self.finality_depth)
+            })
     }
+}
 
-    /// Poll for new blocks on all chains
-    async fn poll_new_blocks(&mut self) -> Result<()> {
-        // Check Ethereum
-        let eth_chain_id = self.eth_provider.get_chainid().await?.as_u64();
-        let eth_current = self.eth_provider.get_block_number().await?.as_u64();
+/// Everything needed to track one chain
+pub struct ChainSpec {
+    /// Request/response access to blocks (always required)
+    pub source: Arc<dyn BlockSource>,
+    /// Optional push feed of new heads
+    pub subscriber: Option<Arc<dyn HeadSubscriber>>,
+    pub settings: ChainSettings,
+}
 
-        if let Some(headers) = self.headers.get(&eth_chain_id) {
-            if let Some(latest) = headers.last() {
-                if eth_current > latest.block_number {
-                    self.process_new_block(&self.eth_provider.clone(), eth_chain_id, eth_current)
-                        .await?;
-                }
-            }
+impl ChainSpec {
+    /// Build the spec for a configured chain, reading blocks through the
+    /// chain's breaker-guarded endpoints (see `http_provider`)
+    fn from_endpoints(endpoints: &ChainEndpoints) -> Result<Self> {
+        Ok(Self {
+            source: Arc::new(http_provider(endpoints)?),
+            subscriber: endpoints
+                .ws_url
+                .as_ref()
+                .map(|url| Arc::new(WsSubscriber::new(url.clone())) as Arc<dyn HeadSubscriber>),
+            settings: ChainSettings::from(endpoints),
+        })
+    }
+}
+
+impl From<&ChainEndpoints> for ChainSettings {
+    fn from(endpoints: &ChainEndpoints) -> Self {
+        Self {
+            finality_depth: endpoints.finality_depth,
+            finality_regression_tolerance: endpoints.finality_regression_tolerance,
+            adaptive_depth: endpoints
+                .adaptive_finality_max_depth
+                .map(|max_depth| Arc::new(adaptive::AdaptiveDepth::new(max_depth))),
+            retained_headers: endpoints.retained_headers,
+            allow_equal_timestamps: endpoints
+                .allow_equal_timestamps
+                .unwrap_or_else(|| EQUAL_TIMESTAMP_CHAINS.contains(&endpoints.chain_id)),
+            ..Self::default()
         }
+    }
+}
 
-        // Check Arbitrum
-        let arb_chain_id = self.arb_provider.get_chainid().await?.as_u64();
-        let arb_current = self.arb_provider.get_block_number().await?.as_u64();
+/// In-flight head request shared by every caller that arrives while it runs
+type HeadFuture = Shared<BoxFuture<'static, std::result::Result<u64, String>>>;
+
+/// Coalesces concurrent head lookups into a single RPC call
+///
+/// A caller arriving while a `fetch_block_number` is already pending awaits
+/// that request instead of issuing another one.
+pub struct CoalescedHead {
+    source: Arc<dyn BlockSource>,
+    in_flight: Mutex<Option<HeadFuture>>,
+}
+
+impl CoalescedHead {
+    pub fn new(source: Arc<dyn BlockSource>) -> Self {
+        Self {
+            source,
+            in_flight: Mutex::new(None),
+        }
+    }
 
-        if let Some(headers) = self.headers.get(&arb_chain_id) {
-            if let Some(latest) = headers.last() {
-                if arb_current > latest.block_number {
-                    self.process_new_block(&self.arb_provider.clone(), arb_chain_id, arb_current)
-                        .await?;
+    /// Current head block number
+    pub async fn fetch(&self) -> Result<u64> {
+        let request = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.as_ref() {
+                Some(request) if request.peek().is_none() => request.clone(),
+                _ => {
+                    let source = self.source.clone();
+                    let request =
+                        async move { source.fetch_block_number().await.map_err(|e| e.to_string()) }
+                            .boxed()
+                            .shared();
+                    *in_flight = Some(request.clone());
+                    request
                 }
             }
-        }
+        };
 
-        Ok(())
+        request.await.map_err(|e| anyhow::anyhow!(e))
     }
+}
 
-    /// Process a new block
-    async fn process_new_block(
-        &mut self,
-        provider: &Provider<Http>,
-        chain_id: u64,
-        block_number: u64,
-    ) -> Result<()> {
-        if let Some(block) = provider
-            .get_block(BlockId::Number(block_number.into()))
-            .await?
-        {
-            let header = StoredHeader {
-                block_number: block.number.unwrap().as_u64(),
-                block_hash: block.hash.unwrap(),
-                parent_hash: block.parent_hash,
-                state_root: block.state_root,
-                transactions_root: block.transactions_root,
-                receipts_root: block.receipts_root,
-                timestamp: block.timestamp.as_u64(),
-            };
+/// Stretch or shrink an interval by up to `POLL_JITTER`
+///
+/// Relayers sharing a public RPC would otherwise poll in lockstep.
+fn jittered(interval: Duration) -> Duration {
+    let factor = 1.0 + rand::thread_rng().gen_range(-POLL_JITTER..=POLL_JITTER);
+    interval.mul_f64(factor)
+}
 
-            // Check for reorg
-            if let Some(headers) = self.headers.get_mut(&chain_id) {
-                if let Some(last) = headers.last() {
-                    if header.parent_hash != last.block_hash {
-                        // Reorg detected
-                        let depth = self.handle_reorg(chain_id, &header).await?;
-                        let _ = self.event_tx.send(LightClientEvent::Reorg { chain_id, depth }).await;
-                    }
-                }
+/// Consecutive failed head polls on a chain
+///
+/// Each failure doubles the poll interval, up to `POLL_BACKOFF_MAX`, so a
+/// struggling RPC isn't hammered; a successful poll resets it.
+#[derive(Debug, Default)]
+struct PollBackoff {
+    failures: u32,
+}
 
-                headers.push(header.clone());
+impl PollBackoff {
+    /// Jittered delay before the next poll
+    fn interval(&self, base: Duration) -> Duration {
+        let stretched = base.saturating_mul(1 << self.failures.min(16));
+        jittered(stretched.min(POLL_BACKOFF_MAX.max(base)))
+    }
 
-                // Prune old headers
-                while headers.len() > 1000 {
-                    headers.remove(0);
-                }
-            }
+    fn is_backing_off(&self) -> bool {
+        self.failures > 0
+    }
+}
 
-            // Update finalized
-            if block_number > self.finality_depth {
-                self.finalized
-                    .insert(chain_id, block_number - self.finality_depth);
-            }
+/// Headers and finality tracked for a single chain
+#[derive(Debug, Default)]
+struct ChainState {
+    /// Stored headers, oldest first
+    headers: VecDeque<StoredHeader>,
+    /// Latest finalized block number
+    finalized: u64,
+    /// Highest head the RPC has reported, possibly not yet stored
+    network_head: u64,
+}
 
-            // Emit event
-            let _ = self
-                .event_tx
-                .send(LightClientEvent::NewBlock {
-                    chain_id,
-                    block_number: header.block_number,
-                    block_hash: header.block_hash,
-                })
-                .await;
-        }
+/// Read-only view of one chain's headers and finality, shareable with other components
+#[derive(Clone)]
+pub struct FinalityHandle {
+    state: Arc<RwLock<ChainState>>,
+}
 
-        Ok(())
+impl FinalityHandle {
+    /// Handle over a fixed set of headers, for tests outside this module
+    #[cfg(test)]
+    pub(crate) fn with_headers(headers: Vec<StoredHeader>, finalized: u64) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(ChainState {
+                network_head: headers.last().map_or(0, |h| h.block_number),
+                headers: headers.into(),
+                finalized,
+            })),
+        }
     }
 
-    /// Handle chain reorganization
-    async fn handle_reorg(&mut self, chain_id: u64, new_header: &StoredHeader) -> Result<u64> {
-        let headers = self.headers.get_mut(&chain_id).unwrap();
+    /// Pretend the RPC reported `head`, for tests outside this module
+    #[cfg(test)]
+    pub(crate) fn set_network_head(&self, head: u64) {
+        self.state.write().unwrap().network_head = head;
+    }
 
-        // Find common ancestor
-        let mut depth = 0u64;
-        while let Some(header) = headers.pop() {
-            depth += 1;
-            if header.block_hash == new_header.parent_hash {
-                break;
-            }
-        }
+    /// Store `header` as the new tip, for tests outside this module
+    #[cfg(test)]
+    pub(crate) fn push_header(&self, header: StoredHeader) {
+        let mut state = self.state.write().unwrap();
+        state.network_head = state.network_head.max(header.block_number);
+        state.headers.push_back(header);
+    }
 
-        warn!(chain_id = chain_id, depth = depth, "Reorg handled");
-        Ok(depth)
+    /// Latest finalized block number
+    pub fn finalized(&self) -> u64 {
+        self.state.read().unwrap().finalized
     }
 
-    /// Get block header by hash
-    pub fn get_header(&self, chain_id: u64, block_hash: H256) -> Option<&StoredHeader> {
-        self.headers
-            .get(&chain_id)?
+    /// Hash of the stored header at `block_number`, if still retained
+    pub fn canonical_hash(&self, block_number: u64) -> Option<H256> {
+        self.state
+            .read()
+            .unwrap()
+            .headers
             .iter()
-            .find(|h| h.block_hash == block_hash)
+            .rev()
+            .find(|h| h.block_number == block_number)
+            .map(|h| h.block_hash)
     }
 
-    /// Get latest finalized block number
-    pub fn get_finalized(&self, chain_id: u64) -> Option<u64> {
-        self.finalized.get(&chain_id).copied()
+    /// Blocks between the stored tip and the chain head, or `None` before
+    /// any header is stored
+    pub fn sync_lag(&self) -> Option<u64> {
+        let state = self.state.read().unwrap();
+        let tip = state.headers.back()?.block_number;
+        Some(state.network_head.saturating_sub(tip))
     }
 
-    /// Verify a transaction inclusion proof
+    /// Whether the stored tip is within `max_lag` blocks of the chain head
+    pub fn is_synced(&self, max_lag: u64) -> bool {
+        self.sync_lag().is_some_and(|lag| lag <= max_lag)
+    }
+
+    /// Most recent stored header
+    pub fn head(&self) -> Option<StoredHeader> {
+        self.state.read().unwrap().headers.back().cloned()
+    }
+
+    /// Stored header at `block_number`, if still retained
+    pub fn header(&self, block_number: u64) -> Option<StoredHeader> {
+        self.state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .rev()
+            .find(|h| h.block_number == block_number)
+            .cloned()
+    }
+
+    /// Verify a transaction inclusion proof against a stored header
     pub fn verify_inclusion(
         &self,
-        chain_id: u64,
         block_hash: H256,
         tx_hash: H256,
         proof: &[H256],
+        indices: &[u8],
     ) -> bool {
-        if let Some(header) = self.get_header(chain_id, block_hash) {
-            // Verify Merkle proof against transactions_root
-            verify_merkle_proof(tx_hash, proof, header.transactions_root)
-        } else {
-            false
-        }
+        self.state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .find(|h| h.block_hash == block_hash)
+            .is_some_and(|header| {
+                verify_merkle_proof(tx_hash, proof, indices, header.transactions_root)
+            })
     }
 
-    /// Shutdown the light client
-    pub async fn shutdown(&mut self) -> Result<()> {
-        info!("Shutting down light client");
-        Ok(())
+    /// Verify a receipt's trie proof against a stored header's receipts root
+    pub fn verify_receipt_inclusion(
+        &self,
+        block_hash: H256,
+        receipt_rlp: &[u8],
+        proof: &[Bytes],
+    ) -> bool {
+        self.state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .find(|h| h.block_hash == block_hash)
+            .is_some_and(|header| mpt::verify_proof(header.receipts_root, receipt_rlp, proof))
     }
 }
 
-/// Verify a Merkle proof
-fn verify_merkle_proof(leaf: H256, proof: &[H256], root: H256) -> bool {
-    let mut current = leaf;
-    for sibling in proof {
-        // Combine hashes (simplified - actual implementation depends on tree structure)
-        current = H256::from_slice(
-            &ethers::utils::keccak256([current.as_bytes(), sibling.as_bytes()].concat()),
+/// Polls one chain and applies new blocks to its state
+///
+/// Each chain runs its own `ChainSync` task, so a slow RPC on one chain
+/// never delays head detection on another.
+#[derive(Clone)]
+struct ChainSync {
+    chain_id: u64,
+    source: Arc<dyn BlockSource>,
+    subscriber: Option<Arc<dyn HeadSubscriber>>,
+    head: Arc<CoalescedHead>,
+    state: Arc<RwLock<ChainState>>,
+    settings: ChainSettings,
+    event_tx: mpsc::Sender<LightClientEvent>,
+    /// Where headers and the finalized height are written through, if anywhere
+    store: Option<Arc<dyn Store>>,
+}
+
+impl ChainSync {
+    /// Sync headers from a starting block, returning how many were loaded
+    ///
+    /// The stored headers are replaced wholesale once the fetch succeeds.
+    async fn sync_headers(&self, current_block: u64) -> Result<usize> {
+        let depth = self.settings.effective_finality_depth();
+        let start_block = current_block.saturating_sub(depth * 2);
+        let mut headers = Vec::new();
+
+        for block_num in start_block..=current_block {
+            if let Some(header) = self.source.fetch_header(block_num).await? {
+                headers.push(header);
+            }
+        }
+
+        let count = headers.len();
+        let finalized = current_block.saturating_sub(depth);
+        self.persist(&headers, finalized).await;
+        {
+            let mut state = self.state.write().unwrap();
+            state.headers = headers.into();
+            state.finalized = finalized;
+            state.network_head = state.network_head.max(current_block);
+        }
+
+        info!(
+            chain_id = self.chain_id,
+            headers = count,
+            "Headers synchronized"
         );
+
+        Ok(count)
     }
-    current == root
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Restore the finalized headers persisted by a previous run
+    ///
+    /// Returns `false`, leaving the state untouched, when nothing usable is
+    /// stored, `current_block` is too far ahead to link the gap back onto
+    /// the stored headers, or the RPC no longer has the stored finalized
+    /// header at its height; the caller then syncs from scratch. Otherwise
+    /// the gap is backfilled by the first head the chain task processes.
+    async fn restore(&self, current_block: u64) -> bool {
+        let Some(store) = &self.store else {
+            return false;
+        };
+        let stored = async {
+            let Some(finalized) = store.get_finalized(self.chain_id).await? else {
+                return Ok(None);
+            };
+            let headers = store
+                .latest_headers(self.chain_id, finalized, self.settings.retained_headers)
+                .await?;
+            anyhow::Ok(Some((finalized, headers)))
+        }
+        .await;
 
-    #[tokio::test]
-    async fn test_header_storage() {
-        let header = StoredHeader {
-            block_number: 1,
-            block_hash: H256::zero(),
-            parent_hash: H256::zero(),
-            state_root: H256::zero(),
-            transactions_root: H256::zero(),
-            receipts_root: H256::zero(),
-            timestamp: 0,
+        let (finalized, headers) = match stored {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!(chain_id = self.chain_id, error = %e, "Stored headers unreadable");
+                return false;
+            }
+        };
+        let Some(tip) = headers.last().filter(|h| h.block_number == finalized) else {
+            return false;
         };
+        if current_block < finalized
+            || current_block - finalized >= self.settings.retained_headers as u64
+        {
+            return false;
+        }
 
-        assert_eq!(header.block_number, 1);
+        // The chain may have reorged below the stored finalized height while
+        // the node was down
+        match self.fetch_valid_header(finalized).await {
+            Ok(Some(canonical)) if canonical.block_hash == tip.block_hash => {}
+            Ok(_) => {
+                warn!(
+                    chain_id = self.chain_id,
+                    finalized = finalized,
+                    "Stored finalized header is no longer canonical, resyncing"
+                );
+                return false;
+            }
+            Err(e) => {
+                warn!(chain_id = self.chain_id, error = %e, "Stored finalized header unverifiable, resyncing");
+                return false;
+            }
+        }
+
+        info!(
+            chain_id = self.chain_id,
+            headers = headers.len(),
+            finalized = finalized,
+            "Headers restored from store"
+        );
+        let mut state = self.state.write().unwrap();
+        state.headers = headers.into();
+        state.finalized = finalized;
+        state.network_head = state.network_head.max(current_block);
+        true
+    }
+
+    /// Write headers and the finalized height through to the store, if any
+    ///
+    /// The in-memory state stays authoritative, so a failed write only costs
+    /// a full sync on the next start.
+    async fn persist(&self, headers: &[StoredHeader], finalized: u64) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let written = async {
+            for header in headers {
+                store.put_header(self.chain_id, header).await?;
+            }
+            store.set_finalized(self.chain_id, finalized).await
+        }
+        .await;
+        if let Err(e) = written {
+            warn!(chain_id = self.chain_id, error = %e, "Headers not persisted");
+        }
+    }
+
+    /// Track the chain until the task is aborted
+    ///
+    /// Heads arrive from the subscription when one is live; otherwise (or
+    /// while it reconnects) the chain is polled over HTTP.
+    async fn run(self, poll_interval: Duration) {
+        let chain_label = self.chain_id.to_string();
+        let ws_live = Arc::new(AtomicBool::new(false));
+        let (head_tx, mut head_rx) = mpsc::channel(64);
+
+        // Driven inside this task so aborting the chain also stops the feed
+        let mut feed = match self.subscriber.clone() {
+            Some(subscriber) => ws_head_feed(
+                self.chain_id,
+                subscriber,
+                head_tx,
+                ws_live.clone(),
+                self.settings.ws_stall_timeout,
+            )
+            .boxed(),
+            None => futures::future::pending().boxed(),
+        };
+
+        let mut backoff = PollBackoff::default();
+        loop {
+            let interval = backoff.interval(poll_interval);
+            metrics::POLL_INTERVAL_SECONDS
+                .with_label_values(&[&chain_label])
+                .set(interval.as_secs_f64());
+
+            tokio::select! {
+                _ = &mut feed => {}
+                Some(block_number) = head_rx.recv() => {
+                    if let Err(e) = self.apply_head(block_number).await {
+                        if self.consumer_gone(&e) {
+                            return;
+                        }
+                        debug!(chain_id = self.chain_id, error = %e, "Subscribed head failed");
+                    }
+                }
+                _ = tokio::time::sleep(interval) => {
+                    if !ws_live.load(Ordering::SeqCst) {
+                        let polled = self.poll_new_blocks().await;
+                        if matches!(&polled, Err(e) if self.consumer_gone(e)) {
+                            return;
+                        }
+                        self.record_poll(&mut backoff, &polled);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether `error` means nothing receives this chain's events any more,
+    /// in which case the chain task stops rather than sync unobserved
+    fn consumer_gone(&self, error: &anyhow::Error) -> bool {
+        let gone = error.is::<channel::ChannelClosed>();
+        if gone {
+            warn!(
+                chain_id = self.chain_id,
+                "Light client events unconsumed, stopping chain task"
+            );
+        }
+        gone
+    }
+
+    /// Track a poll's outcome, logging when polling starts or stops backing
+    /// off
+    fn record_poll(&self, backoff: &mut PollBackoff, polled: &Result<()>) {
+        match polled {
+            Ok(()) => {
+                if backoff.is_backing_off() {
+                    info!(
+                        chain_id = self.chain_id,
+                        failures = backoff.failures,
+                        "Block polling recovered"
+                    );
+                }
+                backoff.failures = 0;
+            }
+            Err(e) => {
+                backoff.failures = backoff.failures.saturating_add(1);
+                if backoff.failures == 1 {
+                    warn!(chain_id = self.chain_id, error = %e, "Block poll failed, backing off");
+                } else {
+                    debug!(
+                        chain_id = self.chain_id,
+                        failures = backoff.failures,
+                        error = %e,
+                        "Block poll failed"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Poll for a new head on this chain
+    async fn poll_new_blocks(&self) -> Result<()> {
+        let current = self.head.fetch().await?;
+        self.apply_head(current).await
+    }
+
+    /// Process `current` if it is ahead of the stored tip
+    async fn apply_head(&self, current: u64) -> Result<()> {
+        let latest = {
+            let mut state = self.state.write().unwrap();
+            state.network_head = state.network_head.max(current);
+            state.headers.back().map(|h| h.block_number)
+        };
+
+        if let Some(latest) = latest {
+            if current > latest {
+                match self.process_new_block(current).await {
+                    Ok(events) => self.emit(events).await?,
+                    Err(RelayerError::Reorg(deep)) => {
+                        error!(
+                            chain_id = self.chain_id,
+                            oldest = deep.oldest,
+                            "Reorg deeper than the retained headers, resyncing"
+                        );
+                        metrics::DEEP_REORGS
+                            .with_label_values(&[&self.chain_id.to_string()])
+                            .inc();
+                        self.resync_after_deep_reorg(&deep, current).await?;
+                    }
+                    Err(RelayerError::Rpc(e)) => {
+                        let gap = e.downcast::<HeaderGap>()?;
+                        warn!(
+                            chain_id = self.chain_id,
+                            tip = gap.tip,
+                            head = gap.head,
+                            "Head too far past the stored headers to backfill, resyncing"
+                        );
+                        metrics::HEADER_GAP_RESYNCS
+                            .with_label_values(&[&self.chain_id.to_string()])
+                            .inc();
+                        self.sync_headers(current).await?;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hand events from processing a head to the light client's consumer
+    async fn emit(&self, events: Vec<LightClientEvent>) -> Result<(), channel::ChannelClosed> {
+        for event in events {
+            channel::send(&self.event_tx, "light_client_events", event).await?;
+        }
+        Ok(())
+    }
+
+    /// Resync from `current` after `deep`, reporting the stored headers it
+    /// replaced as a reorg
+    async fn resync_after_deep_reorg(&self, deep: &DeepReorg, current: u64) -> Result<()> {
+        let orphaned: Vec<H256> = self
+            .state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .filter(|h| h.block_number >= deep.replaced_from)
+            .map(|h| h.block_hash)
+            .collect();
+        self.sync_headers(current).await?;
+        if orphaned.is_empty() {
+            return Ok(());
+        }
+        let replacement = self
+            .state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .filter(|h| h.block_number >= deep.replaced_from)
+            .map(|h| h.block_hash)
+            .collect();
+        let record = ReorgRecord {
+            chain_id: self.chain_id,
+            detected_at: chrono::Utc::now().timestamp(),
+            fork_block: deep.replaced_from,
+            depth: orphaned.len() as u64,
+            orphaned,
+            replacement,
+        };
+        channel::send(
+            &self.event_tx,
+            "light_client_events",
+            LightClientEvent::Reorg(record),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Process a new block, returning the events it produced
+    ///
+    /// Missing headers between the stored tip and `block_number` are
+    /// backfilled, and stored headers the new chain no longer agrees with
+    /// are replaced (a reorg). The walk back is bounded by the retained
+    /// headers: a new chain that disagrees with them further back fails with
+    /// `RelayerError::Reorg`, and one too far past the stored tip to reach it
+    /// fails with a `HeaderGap` RPC error, leaving the stored headers as they
+    /// were. Headers that fail validation are skipped with a warning, nothing
+    /// is stored, and the block is retried from the next head.
+    async fn process_new_block(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<LightClientEvent>, RelayerError> {
+        let Some(header) = self.fetch_valid_header(block_number).await? else {
+            return Ok(Vec::new());
+        };
+
+        // Walk back from the new header until it links onto a stored one
+        let mut branch = vec![header];
+        let linked_parent = loop {
+            let child = branch.last().expect("branch is never empty");
+            let Some(parent_number) = child.block_number.checked_sub(1) else {
+                break None;
+            };
+            let (linked, retained, tip) = {
+                let state = self.state.read().unwrap();
+                let linked = state
+                    .headers
+                    .iter()
+                    .rev()
+                    .find(|h| h.block_number == parent_number)
+                    .filter(|h| h.block_hash == child.parent_hash)
+                    .cloned();
+                let retained = state
+                    .headers
+                    .front()
+                    .is_some_and(|oldest| oldest.block_number <= parent_number);
+                let tip = state.headers.back().map_or(0, |h| h.block_number);
+                (linked, retained, tip)
+            };
+            if linked.is_some() {
+                break linked;
+            }
+            if !retained || branch.len() >= self.settings.retained_headers {
+                // Never reaching a stored height is a gap, not a reorg
+                if parent_number > tip {
+                    return Err(RelayerError::Rpc(
+                        HeaderGap {
+                            chain_id: self.chain_id,
+                            tip,
+                            head: block_number,
+                        }
+                        .into(),
+                    ));
+                }
+                let oldest = self
+                    .state
+                    .read()
+                    .unwrap()
+                    .headers
+                    .front()
+                    .map_or(0, |h| h.block_number);
+                return Err(DeepReorg {
+                    chain_id: self.chain_id,
+                    replaced_from: parent_number.max(oldest),
+                    oldest,
+                }
+                .into());
+            }
+            match self.fetch_valid_header(parent_number).await? {
+                Some(parent) => branch.push(parent),
+                // Retried from the next head
+                None => return Ok(Vec::new()),
+            }
+        };
+        branch.reverse();
+
+        // Timestamps must rise from the stored parent through the branch
+        let mut parent = linked_parent.as_ref();
+        for header in &branch {
+            if let Some(Err(e)) = parent
+                .map(|parent| check_timestamp(header, parent, self.settings.allow_equal_timestamps))
+            {
+                self.reject_header(&e);
+                return Ok(Vec::new());
+            }
+            parent = Some(header);
+        }
+
+        let mut events = Vec::new();
+        let finalized = {
+            let mut state = self.state.write().unwrap();
+            let fork_point = branch[0].block_number;
+
+            // Stored headers at or above the fork point are superseded
+            let kept = state
+                .headers
+                .iter()
+                .position(|h| h.block_number >= fork_point)
+                .unwrap_or(state.headers.len());
+            let depth = (state.headers.len() - kept) as u64;
+
+            // Finalized headers are never rewritten: the new chain is
+            // refused, and keeps being refused on every head built on it
+            if depth > 0 && fork_point <= state.finalized {
+                error!(
+                    chain_id = self.chain_id,
+                    fork_block = fork_point,
+                    finalized = state.finalized,
+                    "New chain would replace finalized headers, ignoring it"
+                );
+                metrics::FINALIZED_HEADER_CONFLICTS
+                    .with_label_values(&[&self.chain_id.to_string()])
+                    .inc();
+                return Ok(Vec::new());
+            }
+
+            if depth > 0 {
+                warn!(chain_id = self.chain_id, depth = depth, "Reorg handled");
+                metrics::REORGS_DETECTED
+                    .with_label_values(&[&self.chain_id.to_string()])
+                    .inc();
+                let orphaned = state.headers.range(kept..).map(|h| h.block_hash).collect();
+                state.headers.truncate(kept);
+                events.push(LightClientEvent::Reorg(ReorgRecord {
+                    chain_id: self.chain_id,
+                    detected_at: chrono::Utc::now().timestamp(),
+                    fork_block: fork_point,
+                    depth,
+                    orphaned,
+                    replacement: branch.iter().map(|h| h.block_hash).collect(),
+                }));
+            }
+
+            state.headers.extend(branch.iter().cloned());
+            metrics::BLOCKS_PROCESSED
+                .with_label_values(&[&self.chain_id.to_string()])
+                .inc_by(branch.len() as u64);
+
+            // Prune old headers from the front
+            while state.headers.len() > self.settings.retained_headers {
+                state.headers.pop_front();
+            }
+
+            // Update finalized; a raised depth holds the pointer rather
+            // than moving it back
+            let depth = self.settings.effective_finality_depth();
+            if block_number > depth {
+                let held = state
+                    .finalized
+                    .min(block_number.saturating_sub(self.settings.finality_depth));
+                let finalized = (block_number - depth).max(held);
+                events.extend(self.update_finalized(&mut state, finalized));
+            }
+            state.finalized
+        };
+        self.persist(&branch, finalized).await;
+
+        events.extend(branch.into_iter().map(|header| LightClientEvent::NewBlock {
+            chain_id: self.chain_id,
+            block_number: header.block_number,
+            block_hash: header.block_hash,
+            timestamp: header.timestamp,
+        }));
+        Ok(events)
+    }
+
+    /// Fetch the header of `block_number`, or `None` if the RPC has none or
+    /// returned one that fails validation
+    async fn fetch_valid_header(
+        &self,
+        block_number: u64,
+    ) -> Result<Option<StoredHeader>, RelayerError> {
+        let Some(header) = self
+            .source
+            .fetch_header(block_number)
+            .await
+            .map_err(RelayerError::Rpc)?
+        else {
+            return Ok(None);
+        };
+        if let Err(e) = check_header(&header, block_number) {
+            self.reject_header(&e);
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
+    fn reject_header(&self, invalid: &InvalidHeader) {
+        warn!(chain_id = self.chain_id, error = %invalid, "Invalid header skipped");
+        metrics::INVALID_HEADERS
+            .with_label_values(&[&self.chain_id.to_string(), invalid.reason()])
+            .inc();
+    }
+
+    /// Move the finalized pointer, flagging regressions beyond tolerance
+    fn update_finalized(&self, state: &mut ChainState, finalized: u64) -> Option<LightClientEvent> {
+        let previous = state.finalized;
+        state.finalized = finalized;
+
+        if previous.saturating_sub(finalized) <= self.settings.finality_regression_tolerance {
+            return None;
+        }
+
+        error!(
+            chain_id = self.chain_id,
+            previous = previous,
+            current = finalized,
+            tolerance = self.settings.finality_regression_tolerance,
+            "Finalized height regressed"
+        );
+        metrics::FINALITY_REGRESSIONS
+            .with_label_values(&[&self.chain_id.to_string()])
+            .inc();
+
+        Some(LightClientEvent::FinalityRegression {
+            chain_id: self.chain_id,
+            previous,
+            current: finalized,
+        })
+    }
+}
+
+/// Outcome of a forced resync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResyncReport {
+    pub chain_id: u64,
+    pub headers_loaded: usize,
+    /// Head the headers were reloaded up to
+    pub head: u64,
+}
+
+/// Rebuilds one chain's header store from the RPC on demand
+#[derive(Clone)]
+pub struct ResyncHandle {
+    sync: ChainSync,
+}
+
+impl ResyncHandle {
+    /// Discard the chain's stored headers and reload them from the current head
+    ///
+    /// Other chains and the chain's polling task keep running; the old
+    /// headers stay in place if the reload fails.
+    pub async fn resync(&self) -> Result<ResyncReport> {
+        let head = self.sync.head.fetch().await?;
+        warn!(
+            chain_id = self.sync.chain_id,
+            head = head,
+            "Forced header resync"
+        );
+        let headers_loaded = self.sync.sync_headers(head).await?;
+        Ok(ResyncReport {
+            chain_id: self.sync.chain_id,
+            headers_loaded,
+            head,
+        })
+    }
+}
+
+/// Light client for multiple chains
+pub struct LightClient {
+    /// Per-chain header state, keyed by chain ID
+    chains: HashMap<u64, Arc<RwLock<ChainState>>>,
+    /// Per-chain head lookups, shared with the polling tasks
+    heads: HashMap<u64, Arc<CoalescedHead>>,
+    /// Per-chain resync access, sharing state with the polling tasks
+    resyncs: HashMap<u64, ResyncHandle>,
+    /// Per-chain polling tasks
+    tasks: Vec<JoinHandle<()>>,
+    /// Event receiver shared by all chain tasks
+    event_rx: mpsc::Receiver<LightClientEvent>,
+}
+
+impl LightClient {
+    /// Create a light client for the configured chains, persisting
+    /// headers to `store`
+    pub async fn new(
+        chains: &[ChainEndpoints],
+        store: Arc<dyn Store>,
+    ) -> Result<Self, RelayerError> {
+        let specs = chains
+            .iter()
+            .map(ChainSpec::from_endpoints)
+            .collect::<Result<Vec<_>>>()
+            .map_err(RelayerError::Config)?;
+        Self::with_sources(specs, Some(store), DEFAULT_POLL_INTERVAL).await
+    }
+
+    /// Create a light client over arbitrary block sources
+    ///
+    /// Every source is synced before this returns, resuming from the
+    /// headers in `store` where they are recent enough; afterwards each
+    /// chain is polled by its own task feeding the shared event channel.
+    pub async fn with_sources(
+        specs: Vec<ChainSpec>,
+        store: Option<Arc<dyn Store>>,
+        poll_interval: Duration,
+    ) -> Result<Self, RelayerError> {
+        let (event_tx, event_rx) = mpsc::channel(1000);
+
+        let mut chains = HashMap::new();
+        let mut heads = HashMap::new();
+        let mut resyncs = HashMap::new();
+        let mut syncs = Vec::new();
+
+        // Initialize with current block
+        for ChainSpec {
+            source,
+            subscriber,
+            settings,
+        } in specs
+        {
+            let chain_id = source.fetch_chain_id().await.map_err(RelayerError::Rpc)?;
+            let current_block = source
+                .fetch_block_number()
+                .await
+                .map_err(RelayerError::Rpc)?;
+            info!(
+                chain_id = chain_id,
+                block = current_block,
+                "Chain sync starting"
+            );
+
+            let state = Arc::new(RwLock::new(ChainState::default()));
+            let head = Arc::new(CoalescedHead::new(source.clone()));
+            let sync = ChainSync {
+                chain_id,
+                source,
+                subscriber,
+                head: head.clone(),
+                state: state.clone(),
+                settings,
+                event_tx: event_tx.clone(),
+                store: store.clone(),
+            };
+            if !sync.restore(current_block).await {
+                sync.sync_headers(current_block)
+                    .await
+                    .map_err(RelayerError::Rpc)?;
+            }
+
+            chains.insert(chain_id, state);
+            heads.insert(chain_id, head);
+            resyncs.insert(chain_id, ResyncHandle { sync: sync.clone() });
+            syncs.push(sync);
+        }
+
+        let tasks = syncs
+            .into_iter()
+            .map(|sync| tokio::spawn(sync.run(poll_interval)))
+            .collect();
+
+        Ok(Self {
+            chains,
+            heads,
+            resyncs,
+            tasks,
+            event_rx,
+        })
+    }
+
+    /// Get the next event from the light client
+    pub async fn next_event(&mut self) -> Option<LightClientEvent> {
+        self.event_rx.recv().await
+    }
+
+    /// Current head of a chain, sharing any lookup already in flight
+    pub async fn head_number(&self, chain_id: u64) -> Result<u64, RelayerError> {
+        let head = self.heads.get(&chain_id).ok_or_else(|| {
+            RelayerError::Validation(anyhow::anyhow!("Unknown chain {}", chain_id))
+        })?;
+        head.fetch().await.map_err(RelayerError::Rpc)
+    }
+
+    /// Get block header by hash
+    pub fn get_header(&self, chain_id: u64, block_hash: H256) -> Option<StoredHeader> {
+        self.chains
+            .get(&chain_id)?
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .find(|h| h.block_hash == block_hash)
+            .cloned()
+    }
+
+    /// Get latest finalized block number
+    pub fn get_finalized(&self, chain_id: u64) -> Option<u64> {
+        self.chains
+            .get(&chain_id)
+            .map(|state| state.read().unwrap().finalized)
+    }
+
+    /// Finality view of a chain for components that gate on confirmations
+    pub fn finality(&self, chain_id: u64) -> Option<FinalityHandle> {
+        self.chains.get(&chain_id).map(|state| FinalityHandle {
+            state: state.clone(),
+        })
+    }
+
+    /// Finality views of every tracked chain, keyed by chain ID
+    pub fn finality_handles(&self) -> HashMap<u64, FinalityHandle> {
+        self.chains
+            .iter()
+            .map(|(chain_id, state)| {
+                (
+                    *chain_id,
+                    FinalityHandle {
+                        state: state.clone(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Peer reorg trackers of chains with adaptive finality, keyed by chain ID
+    pub fn adaptive_depths(&self) -> HashMap<u64, Arc<adaptive::AdaptiveDepth>> {
+        self.resyncs
+            .iter()
+            .filter_map(|(chain_id, handle)| {
+                let adaptive = handle.sync.settings.adaptive_depth.clone()?;
+                Some((*chain_id, adaptive))
+            })
+            .collect()
+    }
+
+    /// Resync access to every tracked chain, keyed by chain ID
+    pub fn resync_handles(&self) -> HashMap<u64, ResyncHandle> {
+        self.resyncs.clone()
+    }
+
+    /// Verify a transaction inclusion proof
+    pub fn verify_inclusion(
+        &self,
+        chain_id: u64,
+        block_hash: H256,
+        tx_hash: H256,
+        proof: &[H256],
+        indices: &[u8],
+    ) -> bool {
+        self.finality(chain_id)
+            .is_some_and(|chain| chain.verify_inclusion(block_hash, tx_hash, proof, indices))
+    }
+
+    /// Verify a receipt inclusion proof
+    ///
+    /// `receipt_rlp` is the receipt as stored in the trie (prefixed with its
+    /// type byte for typed receipts) and `proof` the trie nodes from the
+    /// root down to it.
+    pub fn verify_receipt_inclusion(
+        &self,
+        chain_id: u64,
+        block_hash: H256,
+        receipt_rlp: &[u8],
+        proof: &[Bytes],
+    ) -> bool {
+        self.finality(chain_id)
+            .is_some_and(|chain| chain.verify_receipt_inclusion(block_hash, receipt_rlp, proof))
+    }
+
+    /// Shutdown the light client
+    pub async fn shutdown(&mut self) -> Result<(), RelayerError> {
+        info!("Shutting down light client");
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        Ok(())
+    }
+}
+
+/// Provider over a chain's HTTP endpoints, failing over between them
+pub(crate) type RpcProvider = Provider<breaker::BreakerClient<Http>>;
+
+/// Build the provider for a configured chain
+///
+/// Each HTTP endpoint sits behind its own circuit breaker, shared by every
+/// provider built for the chain; calls go to the first whose breaker is
+/// closed, failing over down the list and then to the fallback endpoint
+/// (if configured).
+pub(crate) fn http_provider(endpoints: &ChainEndpoints) -> Result<RpcProvider> {
+    let fallback = match &endpoints.fallback_http_url {
+        Some(url) => Some(http_at(url, &HashMap::new())?),
+        None => None,
+    };
+    let clients = endpoints
+        .http_urls
+        .iter()
+        .enumerate()
+        .map(|(index, url)| {
+            // The first keeps the bare chain ID as its metric label
+            let label = match index {
+                0 => endpoints.chain_id.to_string(),
+                _ => format!("{}/{}", endpoints.chain_id, index),
+            };
+            let breaker = breaker::CircuitBreaker::shared(
+                label,
+                endpoints.breaker_failure_threshold,
+                Duration::from_secs(endpoints.breaker_cooldown_secs),
+            );
+            Ok((http_at(url, &endpoints.headers)?, breaker))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Provider::new(breaker::BreakerClient::new(
+        endpoints.chain_id.to_string(),
+        clients,
+        fallback,
+        Duration::from_secs(endpoints.rpc_timeout_secs),
+    )))
+}
+
+/// HTTP transport for `url`, sending `extra_headers` with every request
+fn http_at(url: &str, extra_headers: &HashMap<String, String>) -> Result<Http> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in extra_headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())?;
+        let mut value = reqwest::header::HeaderValue::from_str(&crate::expand_env(value)?)?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()?;
+    let url: reqwest::Url = url.parse()?;
+
+    Ok(Http::new_with_client(url, client))
+}
+
+/// Verify a Merkle proof
+///
+/// `indices` give the position of the node at each level, in the prover's
+/// convention: 0 for a left child, 1 for a right child. Empty-block roots
+/// (and the zero root of a header missing one) never contain a leaf. A
+/// single-transaction block has the leaf as its root, so the proof is empty.
+fn verify_merkle_proof(leaf: H256, proof: &[H256], indices: &[u8], root: H256) -> bool {
+    if root == EMPTY_TRIE_ROOT || root.is_zero() || indices.len() != proof.len() {
+        return false;
+    }
+
+    let mut current = leaf;
+    for (sibling, index) in proof.iter().zip(indices) {
+        current = match index {
+            0 => hash_pair(current, *sibling),
+            1 => hash_pair(*sibling, current),
+            _ => return false,
+        };
+    }
+    current == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    #[tokio::test]
+    async fn test_header_storage() {
+        let header = StoredHeader {
+            block_number: 1,
+            block_hash: H256::zero(),
+            parent_hash: H256::zero(),
+            state_root: H256::zero(),
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+            timestamp: 0,
+        };
+
+        assert_eq!(header.block_number, 1);
+    }
+
+    #[test]
+    fn test_empty_trie_root_constant() {
+        // rlp("") is the single byte 0x80
+        assert_eq!(EMPTY_TRIE_ROOT, H256(ethers::utils::keccak256([0x80u8])));
+    }
+
+    #[test]
+    fn test_empty_block_includes_nothing() {
+        // RPC reporting a zero root for an empty block
+        let block: Block<H256> = Block {
+            number: Some(10.into()),
+            hash: Some(H256::repeat_byte(0x10)),
+            ..Default::default()
+        };
+        let header = StoredHeader::from_block(&block).unwrap();
+        assert_eq!(header.transactions_root, EMPTY_TRIE_ROOT);
+
+        let chain = FinalityHandle::with_headers(vec![header.clone()], 10);
+        // Not even a "proof" that the root itself is the only leaf
+        assert!(!chain.verify_inclusion(header.block_hash, EMPTY_TRIE_ROOT, &[], &[]));
+        assert!(!chain.verify_inclusion(header.block_hash, H256::zero(), &[], &[]));
+        assert!(!chain.verify_inclusion(header.block_hash, H256::repeat_byte(1), &[], &[]));
+
+        // Zero root left as-is when the block does have transactions
+        let block = Block {
+            transactions: vec![H256::repeat_byte(1)],
+            ..block
+        };
+        let header = StoredHeader::from_block(&block).unwrap();
+        assert!(header.transactions_root.is_zero());
+        let chain = FinalityHandle::with_headers(vec![header.clone()], 10);
+        assert!(!chain.verify_inclusion(header.block_hash, H256::zero(), &[], &[]));
+    }
+
+    #[test]
+    fn test_single_transaction_block_inclusion() {
+        let tx_hash = H256::repeat_byte(0xaa);
+        let block: Block<H256> = Block {
+            number: Some(11.into()),
+            hash: Some(H256::repeat_byte(0x11)),
+            transactions_root: tx_hash,
+            transactions: vec![tx_hash],
+            ..Default::default()
+        };
+        let header = StoredHeader::from_block(&block).unwrap();
+        let chain = FinalityHandle::with_headers(vec![header.clone()], 11);
+
+        assert!(chain.verify_inclusion(header.block_hash, tx_hash, &[], &[]));
+        assert!(!chain.verify_inclusion(header.block_hash, H256::repeat_byte(0xbb), &[], &[]));
+        // Extra proof elements can't reach the same root
+        assert!(!chain.verify_inclusion(header.block_hash, tx_hash, &[H256::zero()], &[0]));
+    }
+
+    #[test]
+    fn test_receipt_inclusion_checks_stored_root() {
+        // Single-receipt block: the root is a leaf keyed by rlp(0) = 0x80
+        let receipt_rlp = vec![0xab; 40];
+        let mut leaf = ethers::utils::rlp::RlpStream::new_list(2);
+        leaf.append(&[0x20u8, 0x80].as_slice())
+            .append(&receipt_rlp.as_slice());
+        let leaf = leaf.out().to_vec();
+
+        let block: Block<H256> = Block {
+            number: Some(12.into()),
+            hash: Some(H256::repeat_byte(0x12)),
+            receipts_root: H256(ethers::utils::keccak256(&leaf)),
+            ..Default::default()
+        };
+        let header = StoredHeader::from_block(&block).unwrap();
+        let chain = FinalityHandle::with_headers(vec![header.clone()], 12);
+        let proof = [Bytes::from(leaf)];
+
+        assert!(chain.verify_receipt_inclusion(header.block_hash, &receipt_rlp, &proof));
+        assert!(!chain.verify_receipt_inclusion(header.block_hash, &[0xab; 41], &proof));
+        assert!(!chain.verify_receipt_inclusion(H256::zero(), &receipt_rlp, &proof));
+    }
+
+    /// Keccak tree over `leaves` (a power of two), returning the root and
+    /// each leaf's proof with its indices
+    fn keccak_tree(leaves: &[H256]) -> (H256, Vec<(Vec<H256>, Vec<u8>)>) {
+        let mut levels = vec![leaves.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            levels.push(level.chunks(2).map(|p| hash_pair(p[0], p[1])).collect());
+        }
+        let proofs = (0..leaves.len())
+            .map(|leaf| {
+                levels[..levels.len() - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(depth, level)| {
+                        let position = leaf >> depth;
+                        (level[position ^ 1], (position % 2) as u8)
+                    })
+                    .unzip()
+            })
+            .collect();
+        (levels.last().unwrap()[0], proofs)
+    }
+
+    #[test]
+    fn test_merkle_proof_honours_leaf_positions() {
+        for size in [4u64, 8] {
+            let leaves: Vec<H256> = (1..=size).map(H256::from_low_u64_be).collect();
+            let (root, proofs) = keccak_tree(&leaves);
+            for (leaf, (proof, indices)) in leaves.iter().zip(&proofs) {
+                assert!(verify_merkle_proof(*leaf, proof, indices, root));
+
+                // Flipping any one position lands on a different root
+                for level in 0..indices.len() {
+                    let mut flipped = indices.clone();
+                    flipped[level] ^= 1;
+                    assert!(!verify_merkle_proof(*leaf, proof, &flipped, root));
+                }
+                assert!(!verify_merkle_proof(*leaf, proof, &indices[1..], root));
+            }
+        }
+    }
+
+    /// In-memory chain whose head can be advanced or made to hang
+    struct StubSource {
+        chain_id: u64,
+        head: AtomicU64,
+        hang: AtomicBool,
+        /// Head requests fail while set
+        failing: AtomicBool,
+        chain_id_calls: AtomicU64,
+        /// Headers served in place of the stub chain's
+        forged: Mutex<HashMap<u64, StoredHeader>>,
+    }
+
+    impl StubSource {
+        fn new(chain_id: u64, head: u64) -> Arc<Self> {
+            Arc::new(Self {
+                chain_id,
+                head: AtomicU64::new(head),
+                hang: AtomicBool::new(false),
+                failing: AtomicBool::new(false),
+                chain_id_calls: AtomicU64::new(0),
+                forged: Mutex::default(),
+            })
+        }
+    }
+
+    fn stub_hash(chain_id: u64, block_number: u64) -> H256 {
+        H256::from_low_u64_be((chain_id << 32) | block_number)
+    }
+
+    #[async_trait]
+    impl BlockSource for StubSource {
+        async fn fetch_chain_id(&self) -> Result<u64> {
+            self.chain_id_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.chain_id)
+        }
+
+        async fn fetch_block_number(&self) -> Result<u64> {
+            if self.hang.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            if self.failing.load(Ordering::SeqCst) {
+                anyhow::bail!("upstream unavailable");
+            }
+            Ok(self.head.load(Ordering::SeqCst))
+        }
+
+        async fn fetch_header(&self, block_number: u64) -> Result<Option<StoredHeader>> {
+            if let Some(forged) = self.forged.lock().unwrap().get(&block_number) {
+                return Ok(Some(forged.clone()));
+            }
+            Ok(Some(StoredHeader {
+                block_number,
+                block_hash: stub_hash(self.chain_id, block_number),
+                parent_hash: stub_hash(self.chain_id, block_number.saturating_sub(1)),
+                state_root: H256::zero(),
+                transactions_root: H256::zero(),
+                receipts_root: H256::zero(),
+                timestamp: block_number * 12,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hung_chain_does_not_block_others() {
+        let slow = StubSource::new(1, 100);
+        let fast = StubSource::new(42161, 100);
+
+        let mut client = LightClient::with_sources(
+            vec![
+                ChainSpec {
+                    source: slow.clone(),
+                    subscriber: None,
+                    settings: ChainSettings::default(),
+                },
+                ChainSpec {
+                    source: fast.clone(),
+                    subscriber: None,
+                    settings: ChainSettings::default(),
+                },
+            ],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        slow.hang.store(true, Ordering::SeqCst);
+        fast.head.store(101, Ordering::SeqCst);
+
+        let event = tokio::time::timeout(Duration::from_secs(5), client.next_event())
+            .await
+            .expect("fast chain stalled behind hung chain")
+            .unwrap();
+
+        match event {
+            LightClientEvent::NewBlock {
+                chain_id,
+                block_number,
+                ..
+            } => {
+                assert_eq!(chain_id, 42161);
+                assert_eq!(block_number, 101);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(
+            client.get_finalized(42161),
+            Some(101 - DEFAULT_FINALITY_DEPTH)
+        );
+        assert_eq!(client.get_finalized(1), Some(100 - DEFAULT_FINALITY_DEPTH));
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_chains_sync_independently() {
+        let chains = [
+            StubSource::new(1, 100),
+            StubSource::new(10, 200),
+            StubSource::new(8453, 300),
+        ];
+        let mut client = LightClient::with_sources(
+            chains
+                .iter()
+                .map(|source| ChainSpec {
+                    source: source.clone(),
+                    subscriber: None,
+                    settings: ChainSettings::default(),
+                })
+                .collect(),
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        for source in &chains {
+            let head = source.head.load(Ordering::SeqCst);
+            assert_eq!(
+                client.get_finalized(source.chain_id),
+                Some(head - DEFAULT_FINALITY_DEPTH)
+            );
+            let hash = stub_hash(source.chain_id, head);
+            assert_eq!(
+                client
+                    .get_header(source.chain_id, hash)
+                    .map(|h| h.block_number),
+                Some(head)
+            );
+        }
+
+        // Only the chain that moved reports a block
+        chains[1].head.store(201, Ordering::SeqCst);
+        let event = tokio::time::timeout(Duration::from_secs(5), client.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            LightClientEvent::NewBlock {
+                chain_id: 10,
+                block_number: 201,
+                ..
+            }
+        ));
+        assert_eq!(client.get_finalized(10), Some(201 - DEFAULT_FINALITY_DEPTH));
+        assert_eq!(client.get_finalized(1), Some(100 - DEFAULT_FINALITY_DEPTH));
+        assert_eq!(
+            client.get_finalized(8453),
+            Some(300 - DEFAULT_FINALITY_DEPTH)
+        );
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_finality_depth_per_chain() {
+        let mainnet = StubSource::new(1, 100);
+        let arbitrum = StubSource::new(42161, 100);
+        let spec = |source: &StubSource, finality_depth| ChainSpec {
+            source: source.clone(),
+            subscriber: None,
+            settings: ChainSettings {
+                finality_depth,
+                ..ChainSettings::default()
+            },
+        };
+        let mut client = LightClient::with_sources(
+            vec![spec(&mainnet, 15), spec(&arbitrum, 5)],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(client.get_finalized(1), Some(85));
+        assert_eq!(client.get_finalized(42161), Some(95));
+
+        // New blocks keep each chain's own depth
+        mainnet.head.store(110, Ordering::SeqCst);
+        arbitrum.head.store(110, Ordering::SeqCst);
+        while client.get_finalized(1) != Some(95) || client.get_finalized(42161) != Some(105) {
+            tokio::time::timeout(Duration::from_secs(5), client.next_event())
+                .await
+                .expect("finalized pointers never caught up")
+                .unwrap();
+        }
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_chain_id_fetched_only_at_startup() {
+        let source = StubSource::new(1, 100);
+        let mut client = LightClient::with_sources(
+            vec![ChainSpec {
+                source: source.clone(),
+                subscriber: None,
+                settings: ChainSettings::default(),
+            }],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        for head in 101..=103 {
+            source.head.store(head, Ordering::SeqCst);
+            expect_new_block(&mut client, head).await;
+        }
+        assert_eq!(source.chain_id_calls.load(Ordering::SeqCst), 1);
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_errors_name_their_cause() {
+        let source = StubSource::new(1, 100);
+        let spec = || ChainSpec {
+            source: source.clone(),
+            subscriber: None,
+            settings: ChainSettings::default(),
+        };
+
+        source.failing.store(true, Ordering::SeqCst);
+        let err = LightClient::with_sources(vec![spec()], None, Duration::from_millis(10))
+            .await
+            .err()
+            .expect("startup needs the RPC");
+        assert!(matches!(err, RelayerError::Rpc(_)));
+
+        source.failing.store(false, Ordering::SeqCst);
+        let mut client = LightClient::with_sources(vec![spec()], None, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(client.head_number(1).await.unwrap(), 100);
+        assert!(matches!(
+            client.head_number(5).await,
+            Err(RelayerError::Validation(_))
+        ));
+        source.failing.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            client.head_number(1).await,
+            Err(RelayerError::Rpc(_))
+        ));
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_skipped_blocks_backfilled_in_order() {
+        let source = StubSource::new(77, 100);
+        let mut client = LightClient::with_sources(
+            vec![ChainSpec {
+                source: source.clone(),
+                subscriber: None,
+                settings: ChainSettings::default(),
+            }],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+        let reorgs = metrics::REORGS_DETECTED.with_label_values(&["77"]).get();
+
+        // The relayer wakes up several blocks behind the chain
+        source.head.store(108, Ordering::SeqCst);
+        for block_number in 101..=108 {
+            expect_new_block(&mut client, block_number).await;
+        }
+
+        for block_number in 100..=108 {
+            let header = client
+                .get_header(77, stub_hash(77, block_number))
+                .expect("intermediate header stored");
+            assert_eq!(header.parent_hash, stub_hash(77, block_number - 1));
+        }
+        assert_eq!(
+            metrics::REORGS_DETECTED.with_label_values(&["77"]).get(),
+            reorgs
+        );
+        assert_eq!(client.get_finalized(77), Some(108 - DEFAULT_FINALITY_DEPTH));
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resync_reloads_only_one_chain() {
+        let eth = StubSource::new(1, 100);
+        let arb = StubSource::new(42161, 100);
+        let client = LightClient::with_sources(
+            vec![
+                ChainSpec {
+                    source: eth.clone(),
+                    subscriber: None,
+                    settings: ChainSettings::default(),
+                },
+                ChainSpec {
+                    source: arb,
+                    subscriber: None,
+                    settings: ChainSettings::default(),
+                },
+            ],
+            None,
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+        let headers = |chain_id: u64| client.chains[&chain_id].read().unwrap().headers.clone();
+
+        // Simulate a corrupted store on chain 1 while its RPC moves on
+        client.chains[&1].write().unwrap().headers.clear();
+        eth.head.store(110, Ordering::SeqCst);
+        let untouched = headers(42161);
+
+        let report = client.resync_handles()[&1].resync().await.unwrap();
+        assert_eq!(
+            report,
+            ResyncReport {
+                chain_id: 1,
+                headers_loaded: 31,
+                head: 110,
+            }
+        );
+        assert_eq!(headers(1).len(), 31);
+        assert_eq!(headers(1).back().unwrap().block_number, 110);
+        assert_eq!(client.get_finalized(1), Some(110 - DEFAULT_FINALITY_DEPTH));
+        assert_eq!(headers(42161), untouched);
+
+        client.shutdown().await.unwrap();
+    }
+
+    /// Subscriber handing out pre-arranged streams, one per (re)connect
+    struct StubSubscriber {
+        streams: Mutex<std::collections::VecDeque<mpsc::Receiver<u64>>>,
+    }
+
+    #[async_trait]
+    impl HeadSubscriber for StubSubscriber {
+        async fn subscribe(&self) -> Result<BoxStream<'static, u64>> {
+            let rx = self
+                .streams
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| anyhow::anyhow!("connection refused"))?;
+            Ok(futures::stream::unfold(
+                rx,
+                |mut rx| async move { rx.recv().await.map(|n| (n, rx)) },
+            )
+            .boxed())
+        }
+    }
+
+    async fn expect_new_block(client: &mut LightClient, expected: u64) {
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), client.next_event())
+                .await
+                .expect("timed out waiting for block")
+                .unwrap();
+            if let LightClientEvent::NewBlock { block_number, .. } = event {
+                assert_eq!(block_number, expected);
+                return;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_headers_skipped() {
+        let source = StubSource::new(78, 100);
+        let mut client = LightClient::with_sources(
+            vec![ChainSpec {
+                source: source.clone(),
+                subscriber: None,
+                settings: ChainSettings::default(),
+            }],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+        let genuine = source.fetch_header(101).await.unwrap().unwrap();
+        let rejected = |reason| {
+            metrics::INVALID_HEADERS
+                .with_label_values(&["78", reason])
+                .get()
+        };
+
+        let forgeries = [
+            (
+                "timestamp",
+                StoredHeader {
+                    timestamp: 99 * 12,
+                    ..genuine.clone()
+                },
+            ),
+            (
+                "zero_hash",
+                StoredHeader {
+                    block_hash: H256::zero(),
+                    ..genuine.clone()
+                },
+            ),
+        ];
+        source.head.store(101, Ordering::SeqCst);
+        for (reason, forged) in forgeries {
+            let before = rejected(reason);
+            source.forged.lock().unwrap().insert(101, forged);
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while rejected(reason) == before {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("forged header never rejected");
+            assert_eq!(client.get_header(78, genuine.block_hash), None);
+            assert_eq!(client.get_header(78, H256::zero()), None);
+            assert_eq!(client.get_finalized(78), Some(100 - DEFAULT_FINALITY_DEPTH));
+        }
+
+        // The genuine header is taken once the RPC serves it
+        source.forged.lock().unwrap().clear();
+        expect_new_block(&mut client, 101).await;
+        assert_eq!(client.get_header(78, genuine.block_hash), Some(genuine));
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[test]
+    fn test_header_checks() {
+        let parent = StoredHeader {
+            block_number: 9,
+            block_hash: H256::repeat_byte(9),
+            parent_hash: H256::repeat_byte(8),
+            state_root: H256::zero(),
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+            timestamp: 108,
+        };
+        let header = StoredHeader {
+            block_number: 10,
+            block_hash: H256::repeat_byte(10),
+            parent_hash: parent.block_hash,
+            timestamp: 120,
+            ..parent.clone()
+        };
+        assert_eq!(check_header(&header, 10), Ok(()));
+        assert_eq!(check_timestamp(&header, &parent, false), Ok(()));
+
+        assert_eq!(
+            check_header(&header, 11),
+            Err(InvalidHeader::WrongNumber {
+                requested: 11,
+                returned: 10,
+            })
+        );
+        let orphan = StoredHeader {
+            parent_hash: H256::zero(),
+            ..header.clone()
+        };
+        assert_eq!(check_header(&orphan, 10), Err(InvalidHeader::ZeroHash(10)));
+        let genesis = StoredHeader {
+            block_number: 0,
+            ..orphan
+        };
+        assert_eq!(check_header(&genesis, 0), Ok(()));
+
+        let same_second = StoredHeader {
+            timestamp: parent.timestamp,
+            ..header
+        };
+        assert!(matches!(
+            check_timestamp(&same_second, &parent, false),
+            Err(InvalidHeader::Timestamp { .. })
+        ));
+        assert_eq!(check_timestamp(&same_second, &parent, true), Ok(()));
+        let earlier = StoredHeader {
+            timestamp: parent.timestamp - 1,
+            ..same_second
+        };
+        assert!(matches!(
+            check_timestamp(&earlier, &parent, true),
+            Err(InvalidHeader::Timestamp { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_polling_covers_dropped_subscription() {
+        let source = StubSource::new(5, 100);
+        let (first_tx, first_rx) = mpsc::channel(8);
+        let (second_tx, second_rx) = mpsc::channel(8);
+        let subscriber = Arc::new(StubSubscriber {
+            streams: Mutex::new([first_rx, second_rx].into()),
+        });
+        let reconnects = metrics::WS_RECONNECTS.with_label_values(&["5"]).get();
+
+        let mut client = LightClient::with_sources(
+            vec![ChainSpec {
+                source: source.clone(),
+                subscriber: Some(subscriber),
+                settings: ChainSettings::default(),
+            }],
+            None,
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        // The HTTP head stays at 100, so 101 can only arrive over the subscription
+        first_tx.send(101).await.unwrap();
+        expect_new_block(&mut client, 101).await;
+
+        // Drop the subscription; polling has to pick up the next head
+        drop(first_tx);
+        source.head.store(102, Ordering::SeqCst);
+        expect_new_block(&mut client, 102).await;
+
+        // After the backoff the subscription resumes
+        tokio::time::sleep(WS_RECONNECT_MIN * 2).await;
+        second_tx.send(103).await.unwrap();
+        expect_new_block(&mut client, 103).await;
+        assert!(metrics::WS_RECONNECTS.with_label_values(&["5"]).get() > reconnects);
+
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_headers_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("relayer.db").display());
+        let source = StubSource::new(1, 100);
+        let spec = || ChainSpec {
+            source: source.clone(),
+            subscriber: None,
+            settings: ChainSettings::default(),
+        };
+
+        let store = crate::store::open(&url).await.unwrap();
+        let mut client =
+            LightClient::with_sources(vec![spec()], Some(store), Duration::from_millis(10))
+                .await
+                .unwrap();
+        source.head.store(101, Ordering::SeqCst);
+        expect_new_block(&mut client, 101).await;
+        client.shutdown().await.unwrap();
+        drop(client);
+
+        // Headers and the finalized height outlive the process
+        let store = crate::store::open(&url).await.unwrap();
+        let finalized = 101 - DEFAULT_FINALITY_DEPTH;
+        assert_eq!(store.get_finalized(1).await.unwrap(), Some(finalized));
+        assert_eq!(
+            store.get_header(1, 101).await.unwrap(),
+            source.fetch_header(101).await.unwrap()
+        );
+        assert_eq!(
+            store
+                .get_header_by_hash(1, stub_hash(1, 70))
+                .await
+                .unwrap()
+                .map(|h| h.block_number),
+            Some(70)
+        );
+
+        // A restarted client resumes from them and backfills what it missed
+        source.head.store(105, Ordering::SeqCst);
+        let mut client =
+            LightClient::with_sources(vec![spec()], Some(store), Duration::from_millis(10))
+                .await
+                .unwrap();
+        assert_eq!(client.get_finalized(1), Some(finalized));
+        assert_eq!(
+            client.chains[&1].read().unwrap().headers[0].block_number,
+            70
+        );
+        expect_new_block(&mut client, finalized + 1).await;
+        client.shutdown().await.unwrap();
+        drop(client);
+
+        // Stored headers the chain has since reorged away are resynced
+        let store = crate::store::open(&url).await.unwrap();
+        let stored_finalized = store.get_finalized(1).await.unwrap().unwrap();
+        let mut replaced = source
+            .fetch_header(stored_finalized)
+            .await
+            .unwrap()
+            .unwrap();
+        replaced.block_hash = H256::repeat_byte(0xee);
+        source
+            .forged
+            .lock()
+            .unwrap()
+            .insert(stored_finalized, replaced);
+        source.head.store(106, Ordering::SeqCst);
+        let mut client =
+            LightClient::with_sources(vec![spec()], Some(store), Duration::from_millis(10))
+                .await
+                .unwrap();
+        assert_eq!(client.get_finalized(1), Some(106 - DEFAULT_FINALITY_DEPTH));
+        assert_eq!(
+            client.chains[&1].read().unwrap().headers[0].block_number,
+            106 - 2 * DEFAULT_FINALITY_DEPTH
+        );
+
+        client.shutdown().await.unwrap();
+    }
+
+    /// Source whose head lookups are slow and counted
+    struct CountingSource {
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl BlockSource for CountingSource {
+        async fn fetch_chain_id(&self) -> Result<u64> {
+            Ok(1)
+        }
+
+        async fn fetch_block_number(&self) -> Result<u64> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(100)
+        }
+
+        async fn fetch_header(&self, _block_number: u64) -> Result<Option<StoredHeader>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_head_polls_coalesce() {
+        let source = Arc::new(CountingSource {
+            calls: AtomicU64::new(0),
+        });
+        let head = CoalescedHead::new(source.clone());
+
+        let (a, b) = tokio::join!(head.fetch(), head.fetch());
+        assert_eq!(a.unwrap(), 100);
+        assert_eq!(b.unwrap(), 100);
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+
+        // Once the shared request resolves, the next poll goes to the RPC again
+        head.fetch().await.unwrap();
+        assert_eq!(source.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_poll_interval_jitter_bounded() {
+        let base = Duration::from_secs(10);
+        for _ in 0..100 {
+            let interval = jittered(base);
+            assert!(interval >= base.mul_f64(1.0 - POLL_JITTER));
+            assert!(interval <= base.mul_f64(1.0 + POLL_JITTER));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_backs_off_while_rpc_fails() {
+        let (event_tx, _event_rx) = mpsc::channel(10);
+        let source = StubSource::new(7, 100);
+        let sync = ChainSync {
+            chain_id: 7,
+            source: source.clone(),
+            subscriber: None,
+            head: Arc::new(CoalescedHead::new(source.clone())),
+            state: Arc::new(RwLock::new(ChainState::default())),
+            settings: ChainSettings::default(),
+            event_tx,
+            store: None,
+        };
+        let base = Duration::from_secs(1);
+        let within_jitter = |interval: Duration, expected: Duration| {
+            interval >= expected.mul_f64(1.0 - POLL_JITTER)
+                && interval <= expected.mul_f64(1.0 + POLL_JITTER)
+        };
+        let mut backoff = PollBackoff::default();
+        assert!(within_jitter(backoff.interval(base), base));
+
+        // Each consecutive failure doubles the interval
+        source.failing.store(true, Ordering::SeqCst);
+        for failures in 1..=3 {
+            let polled = sync.poll_new_blocks().await;
+            assert!(polled.is_err());
+            sync.record_poll(&mut backoff, &polled);
+            assert!(within_jitter(
+                backoff.interval(base),
+                base * (1 << failures)
+            ));
+        }
+
+        // Up to the cap
+        for _ in 0..20 {
+            sync.record_poll(&mut backoff, &sync.poll_new_blocks().await);
+        }
+        assert!(within_jitter(backoff.interval(base), POLL_BACKOFF_MAX));
+
+        // One good poll restores the base interval
+        source.failing.store(false, Ordering::SeqCst);
+        let polled = sync.poll_new_blocks().await;
+        assert!(polled.is_ok());
+        sync.record_poll(&mut backoff, &polled);
+        assert!(!backoff.is_backing_off());
+        assert!(within_jitter(backoff.interval(base), base));
+    }
+
+    #[test]
+    fn test_finality_regression_detected() {
+        let (event_tx, _event_rx) = mpsc::channel(10);
+        let source = StubSource::new(7, 0);
+        let sync = ChainSync {
+            chain_id: 7,
+            source: source.clone(),
+            subscriber: None,
+            head: Arc::new(CoalescedHead::new(source)),
+            state: Arc::new(RwLock::new(ChainState::default())),
+            settings: ChainSettings {
+                finality_regression_tolerance: 2,
+                ..ChainSettings::default()
+            },
+            event_tx,
+            store: None,
+        };
+        let mut state = ChainState {
+            finalized: 100,
+            ..ChainState::default()
+        };
+        let before = metrics::FINALITY_REGRESSIONS
+            .with_label_values(&["7"])
+            .get();
+
+        // Advancing and regressing within tolerance are both fine
+        assert!(sync.update_finalized(&mut state, 101).is_none());
+        assert!(sync.update_finalized(&mut state, 99).is_none());
+
+        match sync.update_finalized(&mut state, 95) {
+            Some(LightClientEvent::FinalityRegression {
+                chain_id,
+                previous,
+                current,
+            }) => {
+                assert_eq!(chain_id, 7);
+                assert_eq!(previous, 99);
+                assert_eq!(current, 95);
+            }
+            other => panic!("expected regression, got {:?}", other),
+        }
+        assert_eq!(
+            metrics::FINALITY_REGRESSIONS
+                .with_label_values(&["7"])
+                .get(),
+            before + 1
+        );
+    }
+
+    fn endpoints(http_url: String) -> ChainEndpoints {
+        ChainEndpoints {
+            http_urls: vec![http_url],
+            ws_url: None,
+            chain_id: 1,
+            finality_depth: DEFAULT_FINALITY_DEPTH,
+            retained_headers: DEFAULT_RETAINED_HEADERS,
+            allow_equal_timestamps: None,
+            finality_regression_tolerance: 0,
+            adaptive_finality_max_depth: None,
+            max_gas_price_gwei: None,
+            gas_ceiling_action: Default::default(),
+            public_input_commitment: Default::default(),
+            submission_route: Default::default(),
+            max_in_flight: 4,
+            fallback_http_url: None,
+            breaker_failure_threshold: 5,
+            breaker_cooldown_secs: 30,
+            rpc_timeout_secs: 10,
+            pool_address: None,
+            pool_deployment_block: 0,
+            allowed_pools: Vec::new(),
+            min_confirmations: None,
+            root_history_size: 30,
+            resubmit_after_blocks: 5,
+            withdraw_gas_limit: 600_000,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_ws_url_selects_head_subscription() {
+        let polled = endpoints("http://127.0.0.1:8545".to_string());
+        assert!(ChainSpec::from_endpoints(&polled)
+            .unwrap()
+            .subscriber
+            .is_none());
+
+        let subscribed = ChainEndpoints {
+            ws_url: Some("ws://127.0.0.1:8546".to_string()),
+            ..polled
+        };
+        assert!(ChainSpec::from_endpoints(&subscribed)
+            .unwrap()
+            .subscriber
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_configured_headers_sent_to_rpc() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+
+        let endpoints = ChainEndpoints {
+            headers: HashMap::from([("X-Api-Key".to_string(), "secret-key".to_string())]),
+            ..endpoints(format!("http://{}", addr))
+        };
+
+        let provider = http_provider(&endpoints).unwrap();
+        assert_eq!(provider.fetch_chain_id().await.unwrap(), 1);
+
+        let request = server.await.unwrap();
+        assert!(request.contains("x-api-key: secret-key"));
+        assert!(!format!("{:?}", endpoints).contains("secret-key"));
     }
 }
@@ -0,0 +1,198 @@
+//! Merkle-Patricia trie proof verification
+//!
+//! Receipts (and transactions) are committed to by a hexary Merkle-Patricia
+//! trie keyed by `rlp(index)`, not a binary Merkle tree. A proof is the list
+//! of RLP-encoded nodes on the path from the root to the leaf, as returned by
+//! `eth_getProof`-style tooling. Nodes shorter than 32 bytes are embedded in
+//! their parent instead of being referenced by hash.
+
+use ethers::types::{Bytes, H256};
+use ethers::utils::keccak256;
+use ethers::utils::rlp::Rlp;
+
+/// How a parent node points at its child
+enum NodeRef<'a> {
+    Hash(H256),
+    Inline(&'a [u8]),
+}
+
+impl NodeRef<'_> {
+    fn matches(&self, node: &[u8]) -> bool {
+        match self {
+            NodeRef::Hash(hash) => H256(keccak256(node)) == *hash,
+            NodeRef::Inline(raw) => *raw == node,
+        }
+    }
+}
+
+/// Verify that `value` is stored in the trie with root `root`
+///
+/// The key is not needed: every node in `proof` must be referenced by the
+/// one before it, so the path the proof spells out is the key the value is
+/// stored under.
+pub fn verify_proof(root: H256, value: &[u8], proof: &[Bytes]) -> bool {
+    let mut expected = NodeRef::Hash(root);
+    for (i, node) in proof.iter().enumerate() {
+        if !expected.matches(node) {
+            return false;
+        }
+        let next = proof.get(i + 1).map(|n| &n[..]);
+        match step(node, next) {
+            Some(Step::Child(child)) => expected = child,
+            Some(Step::Value(found)) => return next.is_none() && found == value,
+            None => return false,
+        }
+    }
+    false
+}
+
+enum Step<'a> {
+    Child(NodeRef<'a>),
+    Value(&'a [u8]),
+}
+
+/// Follow `node` towards `next`, or read its value if the proof ends here
+fn step<'a>(node: &'a [u8], next: Option<&[u8]>) -> Option<Step<'a>> {
+    let rlp = Rlp::new(node);
+    match rlp.item_count().ok()? {
+        17 => match next {
+            None => Some(Step::Value(rlp.at(16).ok()?.data().ok()?)),
+            Some(next) => (0..16)
+                .filter_map(|slot| child_ref(&rlp.at(slot).ok()?))
+                .find(|child| child.matches(next))
+                .map(Step::Child),
+        },
+        2 => {
+            let path = rlp.at(0).ok()?.data().ok()?;
+            match path.first()? >> 4 {
+                // Extension
+                0 | 1 => child_ref(&rlp.at(1).ok()?).map(Step::Child),
+                // Leaf
+                2 | 3 => Some(Step::Value(rlp.at(1).ok()?.data().ok()?)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Reference held in a branch slot or extension, if the slot isn't empty
+fn child_ref<'a>(item: &Rlp<'a>) -> Option<NodeRef<'a>> {
+    if item.is_list() {
+        Some(NodeRef::Inline(item.as_raw()))
+    } else {
+        let data = item.data().ok()?;
+        (data.len() == 32).then(|| NodeRef::Hash(H256::from_slice(data)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Bloom, TransactionReceipt, U64};
+    use ethers::utils::rlp::{self, RlpStream};
+
+    fn receipt(cumulative_gas_used: u64) -> Vec<u8> {
+        let receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            cumulative_gas_used: cumulative_gas_used.into(),
+            logs_bloom: Bloom::repeat_byte(0x01),
+            ..Default::default()
+        };
+        rlp::encode(&receipt).to_vec()
+    }
+
+    fn leaf(path: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&path).append(&value);
+        stream.out().to_vec()
+    }
+
+    fn branch(children: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(17);
+        for slot in 0..17 {
+            match children.iter().find(|(s, _)| *s == slot) {
+                Some((_, node)) => stream.append(&keccak256(node).as_slice()),
+                None => stream.append_empty_data(),
+            };
+        }
+        stream.out().to_vec()
+    }
+
+    #[test]
+    fn test_receipt_proof() {
+        // Receipts trie of a three-transaction block. Keys rlp(0) = 0x80,
+        // rlp(1) = 0x01 and rlp(2) = 0x02 share nothing at the root, and
+        // 0x01/0x02 then split on their second nibble.
+        let receipts = [receipt(21_000), receipt(63_000), receipt(105_000)];
+        let leaf0 = leaf(&[0x30], &receipts[0]);
+        let leaf1 = leaf(&[0x20], &receipts[1]);
+        let leaf2 = leaf(&[0x20], &receipts[2]);
+        let inner = branch(&[(1, &leaf1), (2, &leaf2)]);
+        let root_node = branch(&[(0, &inner), (8, &leaf0)]);
+        let root = H256(keccak256(&root_node));
+
+        let proof0: Vec<Bytes> = vec![root_node.clone().into(), leaf0.into()];
+        let proof2: Vec<Bytes> = vec![root_node.into(), inner.into(), leaf2.clone().into()];
+        assert!(verify_proof(root, &receipts[0], &proof0));
+        assert!(verify_proof(root, &receipts[2], &proof2));
+
+        // Another receipt's leaf doesn't prove this one
+        assert!(!verify_proof(root, &receipts[1], &proof2));
+
+        // Tampered leaf
+        let mut tampered = leaf2.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut bad = proof2.clone();
+        bad[2] = tampered.into();
+        assert!(!verify_proof(root, &receipts[2], &bad));
+
+        // Truncated and extended proofs
+        assert!(!verify_proof(root, &receipts[2], &proof2[..2]));
+        let mut extended = proof2.clone();
+        extended.push(leaf2.into());
+        assert!(!verify_proof(root, &receipts[2], &extended));
+        assert!(!verify_proof(root, &receipts[2], &[]));
+    }
+
+    #[test]
+    fn test_published_trie_vector() {
+        // The do/dog/doge/horse trie from the ethereum/tests trie suite, whose
+        // root is published there. It has the extension and inline-node shapes
+        // the receipt test doesn't: `doge` is embedded three levels deep.
+        let node = |hex: &str| Bytes::from(ethers::utils::hex::decode(hex).unwrap());
+        let root: H256 = "0x5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+            .parse()
+            .unwrap();
+        let root_ext =
+            node("e216a0bd3ee507e6c67cfefca98f84be47c1bbc009315fabc4405db4ba32190374572a");
+        let root_branch = node(
+            "f84080808080a094a9f95bd89698e4da1812e0518053813b4d5b87caaf6b3c6fa57e9e50c0ff68808080\
+             cf85206f727365887374616c6c696f6e8080808080808080",
+        );
+        let do_ext =
+            node("e482006fa0d43b87fdcd4217013ccc92d04662e12d36e4cc25dc690077cd821a1956fc3e36");
+        let do_branch = node(
+            "f3808080808080de17dc808080808080c63584636f696e808080808080808080857075707079808080\
+             8080808080808476657262",
+        );
+        let dog_ext = node("de17dc808080808080c63584636f696e808080808080808080857075707079");
+        let dog_branch = node("dc808080808080c63584636f696e808080808080808080857075707079");
+        let doge_leaf = node("c63584636f696e");
+        let horse_leaf = node("cf85206f727365887374616c6c696f6e");
+
+        let do_path = [root_ext.clone(), root_branch.clone(), do_ext, do_branch];
+        let doge_path = [&do_path[..], &[dog_ext, dog_branch, doge_leaf][..]].concat();
+        assert!(verify_proof(root, b"verb", &do_path));
+        assert!(verify_proof(root, b"puppy", &doge_path[..6]));
+        assert!(verify_proof(root, b"coin", &doge_path));
+        assert!(verify_proof(
+            root,
+            b"stallion",
+            &[root_ext, root_branch, horse_leaf]
+        ));
+
+        assert!(!verify_proof(root, b"coin", &doge_path[..6]));
+        assert!(!verify_proof(root, b"stallion", &do_path));
+    }
+}
@@ -0,0 +1,542 @@
+//! Reorg simulation for tests
+//!
+//! `SimulatedChain` is a block source whose canonical chain can be extended
+//! or reorganized to any depth; `SimHarness` feeds its heads straight into
+//! `ChainSync::process_new_block` and exposes the emitted events and the
+//! resulting header store.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::mpsc;
+
+use crate::error::RelayerError;
+
+use super::{
+    BlockSource, ChainSettings, ChainState, ChainSync, CoalescedHead, DeepReorg, HeaderGap,
+    LightClientEvent, StoredHeader,
+};
+
+/// In-memory chain that can be extended and reorganized
+pub(super) struct SimulatedChain {
+    chain_id: u64,
+    state: Mutex<SimState>,
+}
+
+struct SimState {
+    /// Canonical headers by block number
+    blocks: BTreeMap<u64, StoredHeader>,
+    /// Bumped on every reorg so replacement blocks get fresh hashes
+    fork: u64,
+}
+
+impl SimulatedChain {
+    /// Chain with blocks `0..=head` on the original fork
+    pub(super) fn new(chain_id: u64, head: u64) -> Arc<Self> {
+        let chain = Arc::new(Self {
+            chain_id,
+            state: Mutex::new(SimState {
+                blocks: BTreeMap::new(),
+                fork: 0,
+            }),
+        });
+        chain.mine(0, head + 1);
+        chain
+    }
+
+    /// Current head block number
+    pub(super) fn head(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        *state
+            .blocks
+            .keys()
+            .next_back()
+            .expect("chain has a genesis block")
+    }
+
+    /// Canonical hash at `block_number`
+    pub(super) fn hash(&self, block_number: u64) -> Option<H256> {
+        let state = self.state.lock().unwrap();
+        state.blocks.get(&block_number).map(|h| h.block_hash)
+    }
+
+    /// Append `count` blocks to the current fork, returning the new head
+    pub(super) fn extend(&self, count: u64) -> u64 {
+        let next = self.head() + 1;
+        self.mine(next, count);
+        self.head()
+    }
+
+    /// Append `count` blocks sharing the current head's timestamp, as chains
+    /// producing several blocks a second do, returning the new head
+    pub(super) fn extend_same_second(&self, count: u64) -> u64 {
+        let parent = self.head();
+        let head = self.extend(count);
+        let mut state = self.state.lock().unwrap();
+        let timestamp = state.blocks[&parent].timestamp;
+        for (_, header) in state.blocks.range_mut(parent + 1..) {
+            header.timestamp = timestamp;
+        }
+        head
+    }
+
+    /// Replace the top `depth` blocks with `length` blocks on a new fork,
+    /// returning the new head
+    pub(super) fn reorg(&self, depth: u64, length: u64) -> u64 {
+        let fork_point = self.head() + 1 - depth;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.blocks.retain(|number, _| *number < fork_point);
+            state.fork += 1;
+        }
+        self.mine(fork_point, length);
+        self.head()
+    }
+
+    /// Add `count` blocks starting at `from` on the current fork
+    fn mine(&self, from: u64, count: u64) {
+        let mut state = self.state.lock().unwrap();
+        for block_number in from..from + count {
+            let parent_hash = block_number
+                .checked_sub(1)
+                .and_then(|parent| state.blocks.get(&parent))
+                .map_or(H256::zero(), |parent| parent.block_hash);
+            let block_hash = H256(keccak256(
+                [
+                    self.chain_id.to_be_bytes(),
+                    state.fork.to_be_bytes(),
+                    block_number.to_be_bytes(),
+                ]
+                .concat(),
+            ));
+            state.blocks.insert(
+                block_number,
+                StoredHeader {
+                    block_number,
+                    block_hash,
+                    parent_hash,
+                    state_root: H256::zero(),
+                    transactions_root: H256::zero(),
+                    receipts_root: H256::zero(),
+                    timestamp: 1_700_000_000 + block_number * 12,
+                },
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for SimulatedChain {
+    async fn fetch_chain_id(&self) -> Result<u64> {
+        Ok(self.chain_id)
+    }
+
+    async fn fetch_block_number(&self) -> Result<u64> {
+        Ok(self.head())
+    }
+
+    async fn fetch_header(&self, block_number: u64) -> Result<Option<StoredHeader>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .blocks
+            .get(&block_number)
+            .cloned())
+    }
+}
+
+/// A `ChainSync` driven by hand over a `SimulatedChain`
+pub(super) struct SimHarness {
+    pub(super) chain: Arc<SimulatedChain>,
+    sync: ChainSync,
+    events: mpsc::Receiver<LightClientEvent>,
+}
+
+impl SimHarness {
+    /// Simulated chain at `head`, with headers synced as on startup
+    pub(super) async fn new(chain_id: u64, head: u64, settings: ChainSettings) -> Self {
+        let chain = SimulatedChain::new(chain_id, head);
+        let (event_tx, events) = mpsc::channel(10_000);
+        let sync = ChainSync {
+            chain_id,
+            source: chain.clone(),
+            subscriber: None,
+            head: Arc::new(CoalescedHead::new(chain.clone())),
+            state: Arc::new(RwLock::new(ChainState::default())),
+            settings,
+            event_tx,
+            store: None,
+        };
+        sync.sync_headers(head).await.unwrap();
+        Self {
+            chain,
+            sync,
+            events,
+        }
+    }
+
+    /// Process the simulated chain's current head
+    pub(super) async fn advance(&self) -> Result<(), RelayerError> {
+        let events = self.sync.process_new_block(self.chain.head()).await?;
+        self.sync
+            .emit(events)
+            .await
+            .expect("the harness keeps the event receiver");
+        Ok(())
+    }
+
+    /// Events emitted since the last call
+    pub(super) fn events(&mut self) -> Vec<LightClientEvent> {
+        std::iter::from_fn(|| self.events.try_recv().ok()).collect()
+    }
+
+    /// Stored headers, oldest first
+    pub(super) fn stored(&self) -> Vec<StoredHeader> {
+        self.sync
+            .state
+            .read()
+            .unwrap()
+            .headers
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub(super) fn finalized(&self) -> u64 {
+        self.sync.state.read().unwrap().finalized
+    }
+
+    /// Assert the store is a contiguous chain agreeing with the simulation
+    pub(super) fn assert_store_canonical(&self) {
+        let stored = self.stored();
+        for pair in stored.windows(2) {
+            assert_eq!(pair[1].block_number, pair[0].block_number + 1);
+            assert_eq!(pair[1].parent_hash, pair[0].block_hash);
+        }
+        for header in &stored {
+            assert_eq!(
+                Some(header.block_hash),
+                self.chain.hash(header.block_number),
+                "stale header at {}",
+                header.block_number
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short finality so the synced window (2 × depth + 1) is small
+    fn settings() -> ChainSettings {
+        ChainSettings {
+            finality_depth: 5,
+            ..ChainSettings::default()
+        }
+    }
+
+    fn reorg_depths(events: &[LightClientEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                LightClientEvent::Reorg(reorg) => Some(reorg.depth),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn new_blocks(events: &[LightClientEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                LightClientEvent::NewBlock { block_number, .. } => Some(*block_number),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_shallow_reorg() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+        assert_eq!(sim.stored().len(), 11);
+
+        // Blocks 99 and 100 are replaced by 99..=101 on a new fork
+        sim.chain.reorg(2, 3);
+        sim.advance().await.unwrap();
+
+        let events = sim.events();
+        assert_eq!(reorg_depths(&events), vec![2]);
+        assert_eq!(new_blocks(&events), vec![99, 100, 101]);
+        assert_eq!(sim.stored().len(), 12);
+        assert_eq!(sim.finalized(), 96);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_reorg_record_kept_queryable() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+        let orphaned: Vec<H256> = sim.stored()[9..].iter().map(|h| h.block_hash).collect();
+
+        sim.chain.reorg(2, 3);
+        sim.advance().await.unwrap();
+
+        let reorg = sim
+            .events()
+            .into_iter()
+            .find_map(|event| match event {
+                LightClientEvent::Reorg(reorg) => Some(reorg),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(reorg.depth, 2);
+        assert_eq!(reorg.fork_block, 99);
+        assert_eq!(reorg.orphaned, orphaned);
+        let replacement: Vec<H256> = (99..=101).filter_map(|n| sim.chain.hash(n)).collect();
+        assert_eq!(reorg.replacement, replacement);
+
+        let store = crate::store::memory().await;
+        store.record_reorg(&reorg).await.unwrap();
+        assert_eq!(store.reorgs(1, 10).await.unwrap(), vec![reorg]);
+        assert!(store.reorgs(2, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reorg_rolls_back_finalized() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+        assert_eq!(sim.finalized(), 95);
+
+        // Blocks 98..=100 are replaced by a shorter fork ending at 99
+        sim.chain.reorg(3, 2);
+        sim.advance().await.unwrap();
+
+        let events = sim.events();
+        assert_eq!(reorg_depths(&events), vec![3]);
+        assert_eq!(new_blocks(&events), vec![98, 99]);
+        assert_eq!(sim.finalized(), 99 - 5);
+        assert_eq!(sim.stored().last().unwrap().block_number, 99);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_reorg_of_finalized_headers_refused() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+        let stored = sim.stored();
+        assert_eq!(sim.finalized(), 95);
+        let conflicts = || {
+            crate::metrics::FINALIZED_HEADER_CONFLICTS
+                .with_label_values(&["1"])
+                .get()
+        };
+        let before = conflicts();
+
+        // The fork point (94) is below the finalized block
+        sim.chain.reorg(7, 8);
+        sim.advance().await.unwrap();
+        assert!(sim.events().is_empty());
+        assert_eq!(sim.stored(), stored);
+        assert_eq!(sim.finalized(), 95);
+
+        // Every head built on that chain is refused too
+        sim.chain.extend(1);
+        sim.advance().await.unwrap();
+        assert!(sim.events().is_empty());
+        assert_eq!(sim.stored(), stored);
+        assert_eq!(conflicts(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_deep_reorg_beyond_retention() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+        let stored = sim.stored();
+        assert_eq!(stored[0].block_number, 90);
+
+        // The fork point (81) is older than anything retained
+        sim.chain.reorg(20, 21);
+        let err = sim.advance().await.unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Reorg(DeepReorg {
+                chain_id: 1,
+                replaced_from: 90,
+                oldest: 90,
+            })
+        ));
+        assert!(sim.events().is_empty());
+        assert_eq!(sim.stored(), stored);
+        assert_eq!(sim.finalized(), 95);
+
+        // The chain task recovers by resyncing from the new head, reporting
+        // every stored header as replaced
+        let before = crate::metrics::DEEP_REORGS.with_label_values(&["1"]).get();
+        sim.sync.apply_head(sim.chain.head()).await.unwrap();
+        assert_eq!(
+            crate::metrics::DEEP_REORGS.with_label_values(&["1"]).get(),
+            before + 1
+        );
+        let reorgs: Vec<_> = sim
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                LightClientEvent::Reorg(reorg) => Some(reorg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!(reorgs[0].fork_block, 90);
+        assert_eq!(reorgs[0].depth, 11);
+        assert_eq!(
+            reorgs[0].orphaned,
+            stored.iter().map(|h| h.block_hash).collect::<Vec<_>>()
+        );
+        assert_eq!(reorgs[0].replacement.last(), sim.chain.hash(101).as_ref());
+        assert_eq!(sim.finalized(), 101 - 5);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_long_gap_resyncs_without_reorg() {
+        let settings = ChainSettings {
+            retained_headers: 16,
+            ..settings()
+        };
+        let mut sim = SimHarness::new(1, 100, settings).await;
+
+        sim.chain.extend(40);
+        let RelayerError::Rpc(err) = sim.advance().await.unwrap_err() else {
+            panic!("a header gap is reported as an RPC error");
+        };
+        assert_eq!(
+            err.downcast_ref::<HeaderGap>(),
+            Some(&HeaderGap {
+                chain_id: 1,
+                tip: 100,
+                head: 140,
+            })
+        );
+
+        let before = crate::metrics::HEADER_GAP_RESYNCS
+            .with_label_values(&["1"])
+            .get();
+        sim.sync.apply_head(sim.chain.head()).await.unwrap();
+        assert_eq!(
+            crate::metrics::HEADER_GAP_RESYNCS
+                .with_label_values(&["1"])
+                .get(),
+            before + 1
+        );
+        assert!(reorg_depths(&sim.events()).is_empty());
+        assert_eq!(sim.finalized(), 140 - 5);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_retention_window_bounds_store() {
+        let settings = ChainSettings {
+            retained_headers: 16,
+            ..settings()
+        };
+        let mut sim = SimHarness::new(1, 100, settings).await;
+
+        for _ in 0..50 {
+            sim.chain.extend(1);
+            sim.advance().await.unwrap();
+            sim.events();
+        }
+        let stored = sim.stored();
+        assert_eq!(stored.len(), 16);
+        assert_eq!(stored[0].block_number, 135);
+        sim.assert_store_canonical();
+
+        // Pruning reuses the buffer rather than growing it
+        let filled = sim.sync.state.read().unwrap().headers.capacity();
+        for _ in 0..50 {
+            sim.chain.extend(1);
+            sim.advance().await.unwrap();
+            sim.events();
+        }
+        assert_eq!(sim.sync.state.read().unwrap().headers.capacity(), filled);
+
+        // Reorgs inside the window are handled in place
+        sim.chain.reorg(3, 4);
+        sim.advance().await.unwrap();
+        assert_eq!(reorg_depths(&sim.events()), vec![3]);
+        assert_eq!(sim.stored().len(), 16);
+        sim.assert_store_canonical();
+
+        // ...while those reaching past it need a resync
+        sim.chain.reorg(20, 21);
+        let err = sim.advance().await.unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Reorg(DeepReorg {
+                chain_id: 1,
+                replaced_from: 186,
+                oldest: 186,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_same_second_blocks_follow_chain_setting() {
+        let mut strict = SimHarness::new(1, 100, settings()).await;
+        strict.chain.extend_same_second(2);
+        strict.advance().await.unwrap();
+        assert!(new_blocks(&strict.events()).is_empty());
+        assert_eq!(strict.stored().last().unwrap().block_number, 100);
+
+        let equal_allowed = ChainSettings {
+            allow_equal_timestamps: true,
+            ..settings()
+        };
+        let mut sim = SimHarness::new(42161, 100, equal_allowed).await;
+        sim.chain.extend_same_second(2);
+        sim.advance().await.unwrap();
+        assert_eq!(new_blocks(&sim.events()), vec![101, 102]);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_gap_is_backfilled() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+
+        sim.chain.extend(4);
+        sim.advance().await.unwrap();
+
+        let events = sim.events();
+        assert!(reorg_depths(&events).is_empty());
+        assert_eq!(new_blocks(&events), vec![101, 102, 103, 104]);
+        assert_eq!(sim.finalized(), 99);
+        sim.assert_store_canonical();
+    }
+
+    #[tokio::test]
+    async fn test_reorg_during_gap() {
+        let mut sim = SimHarness::new(1, 100, settings()).await;
+
+        // Unseen blocks 101..=103 arrive, then 99..=103 are reorged out
+        sim.chain.extend(3);
+        sim.chain.reorg(5, 6);
+        assert_eq!(sim.chain.head(), 104);
+        sim.advance().await.unwrap();
+
+        // Only the two stored blocks count towards the reorg depth
+        let events = sim.events();
+        assert_eq!(reorg_depths(&events), vec![2]);
+        assert_eq!(new_blocks(&events), (99..=104).collect::<Vec<_>>());
+        sim.assert_store_canonical();
+
+        // A reorg confined to blocks never seen is just a backfill
+        sim.chain.extend(3);
+        sim.chain.reorg(2, 3);
+        sim.advance().await.unwrap();
+        let events = sim.events();
+        assert!(reorg_depths(&events).is_empty());
+        assert_eq!(new_blocks(&events), (105..=108).collect::<Vec<_>>());
+        sim.assert_store_canonical();
+    }
+}
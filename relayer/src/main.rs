@@ -1,299 +1,6 @@
 //! Laundry Cash Relayer Node
-//!
-//! The relayer node performs the following functions:
-//! - Submits withdrawal transactions on behalf of users (gas-less withdrawals)
-//! - Synchronizes block headers across chains (light client)
-//! - Participates in the P2P relayer network
-//! - Generates ZK proofs (optional, with proper hardware)
-
-mod light_client;
-mod p2p;
-mod prover;
-
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
-
-/// Laundry Cash Relayer Node
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config/relayer.toml")]
-    config: PathBuf,
-
-    /// Log level (trace, debug, info, warn, error)
-    #[arg(short, long, default_value = "info")]
-    log_level: String,
-
-    /// Enable metrics endpoint
-    #[arg(long, default_value = "false")]
-    metrics: bool,
-
-    /// Metrics port
-    #[arg(long, default_value = "9090")]
-    metrics_port: u16,
-
-    /// HTTP API port
-    #[arg(long, default_value = "8080")]
-    api_port: u16,
-}
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-
-    // Initialize logging
-    let log_level = match args.log_level.as_str() {
-        "trace" => Level::TRACE,
-        "debug" => Level::DEBUG,
-        "info" => Level::INFO,
-        "warn" => Level::WARN,
-        "error" => Level::ERROR,
-        _ => Level::INFO,
-    };
-
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(log_level)
-        .with_target(true)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .json()
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber)?;
-
-    info!("Starting Laundry Cash Relayer v{}", env!("CARGO_PKG_VERSION"));
-    info!("Loading configuration from {:?}", args.config);
-
-    // Load configuration
-    let config = load_config(&args.config)?;
-
-    // Initialize components
-    let (light_client, p2p_node, prover) = initialize_components(&config).await?;
-
-    // Start HTTP API server
-    let api_handle = start_api_server(args.api_port, &config).await?;
-
-    // Start metrics server if enabled
-    if args.metrics {
-        start_metrics_server(args.metrics_port).await?;
-    }
-
-    // Run main event loop
-    run_event_loop(light_client, p2p_node, prover).await?;
-
-    Ok(())
-}
-
-/// Relayer configuration
-#[derive(Debug, serde::Deserialize)]
-struct RelayerConfig {
-    /// Ethereum RPC endpoints
-    ethereum: ChainEndpoints,
-    /// Arbitrum RPC endpoints
-    arbitrum: ChainEndpoints,
-    /// Relayer private key (for signing transactions)
-    private_key: String,
-    /// Database path
-    database_path: String,
-    /// P2P configuration
-    p2p: P2PConfig,
-    /// Prover configuration
-    prover: ProverConfig,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ChainEndpoints {
-    http_url: String,
-    ws_url: Option<String>,
-    chain_id: u64,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct P2PConfig {
-    listen_addr: String,
-    bootstrap_peers: Vec<String>,
-    max_peers: usize,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ProverConfig {
-    enabled: bool,
-    max_concurrent: usize,
-    timeout_secs: u64,
-}
-
-fn load_config(path: &PathBuf) -> Result<RelayerConfig> {
-    let settings = config::Config::builder()
-        .add_source(config::File::from(path.as_ref()))
-        .add_source(config::Environment::with_prefix("RELAYER"))
-        .build()?;
-
-    Ok(settings.try_deserialize()?)
-}
-
-async fn initialize_components(
-    config: &RelayerConfig,
-) -> Result<(
-    light_client::LightClient,
-    p2p::P2PNode,
-    prover::ProverService,
-)> {
-    info!("Initializing light client...");
-    let light_client = light_client::LightClient::new(
-        &config.ethereum.http_url,
-        &config.arbitrum.http_url,
-    )
-    .await?;
-
-    info!("Initializing P2P node...");
-    let p2p_node = p2p::P2PNode::new(&config.p2p).await?;
-
-    info!("Initializing prover service...");
-    let prover = prover::ProverService::new(&config.prover)?;
-
-    Ok((light_client, p2p_node, prover))
-}
-
-async fn start_api_server(port: u16, config: &RelayerConfig) -> Result<tokio::task::JoinHandle<()>> {
-    use axum::{
-        routing::{get, post},
-        Router,
-    };
-
-    let app = Router::new()
-        .route("/health", get(health_handler))
-        .route("/status", get(status_handler))
-        .route("/relay", post(relay_handler))
-        .route("/quote", post(quote_handler));
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    info!("API server listening on port {}", port);
-
-    let handle = tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
-
-    Ok(handle)
-}
-
-async fn start_metrics_server(port: u16) -> Result<()> {
-    info!("Metrics server listening on port {}", port);
-    // Prometheus metrics endpoint would go here
-    Ok(())
-}
-
-async fn run_event_loop(
-    mut light_client: light_client::LightClient,
-    mut p2p_node: p2p::P2PNode,
-    prover: prover::ProverService,
-) -> Result<()> {
-    info!("Starting main event loop...");
-
-    loop {
-        tokio::select! {
-            // Handle light client events
-            event = light_client.next_event() => {
-                if let Some(e) = event {
-                    handle_light_client_event(e).await?;
-                }
-            }
-
-            // Handle P2P events
-            event = p2p_node.next_event() => {
-                if let Some(e) = event {
-                    handle_p2p_event(e, &prover).await?;
-                }
-            }
-
-            // Handle shutdown signal
-            _ = tokio::signal::ctrl_c() => {
-                info!("Received shutdown signal, stopping...");
-                break;
-            }
-        }
-    }
-
-    info!("Shutting down gracefully...");
-    light_client.shutdown().await?;
-    p2p_node.shutdown().await?;
-
-    Ok(())
-}
-
-async fn handle_light_client_event(event: light_client::LightClientEvent) -> Result<()> {
-    match event {
-        light_client::LightClientEvent::NewBlock { chain_id, block_number, block_hash } => {
-            info!(
-                chain_id = chain_id,
-                block_number = block_number,
-                "New block received"
-            );
-        }
-        light_client::LightClientEvent::Reorg { chain_id, depth } => {
-            info!(
-                chain_id = chain_id,
-                depth = depth,
-                "Chain reorganization detected"
-            );
-        }
-    }
-    Ok(())
-}
-
-async fn handle_p2p_event(
-    event: p2p::P2PEvent,
-    prover: &prover::ProverService,
-) -> Result<()> {
-    match event {
-        p2p::P2PEvent::RelayRequest { request_id, data } => {
-            info!(request_id = %request_id, "Received relay request");
-            // Process relay request
-        }
-        p2p::P2PEvent::PeerConnected { peer_id } => {
-            info!(peer_id = %peer_id, "Peer connected");
-        }
-        p2p::P2PEvent::PeerDisconnected { peer_id } => {
-            info!(peer_id = %peer_id, "Peer disconnected");
-        }
-    }
-    Ok(())
-}
-
-// HTTP handlers
-
-async fn health_handler() -> &'static str {
-    "OK"
-}
-
-async fn status_handler() -> axum::Json<serde_json::Value> {
-    axum::Json(serde_json::json!({
-        "version": env!("CARGO_PKG_VERSION"),
-        "status": "running",
-        "uptime": 0,
-    }))
-}
-
-async fn relay_handler(
-    axum::Json(request): axum::Json<serde_json::Value>,
-) -> axum::Json<serde_json::Value> {
-    // Process relay request
-    axum::Json(serde_json::json!({
-        "status": "submitted",
-        "tx_hash": "0x...",
-    }))
-}
-
-async fn quote_handler(
-    axum::Json(request): axum::Json<serde_json::Value>,
-) -> axum::Json<serde_json::Value> {
-    // Return fee quote
-    axum::Json(serde_json::json!({
-        "fee": "0.01",
-        "valid_until": chrono::Utc::now().timestamp() + 300,
-    }))
+async fn main() -> anyhow::Result<()> {
+    laundry_relayer::run().await
 }
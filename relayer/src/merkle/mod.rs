@@ -0,0 +1,267 @@
+//! Incremental Merkle tree mirroring the pool contract
+//!
+//! Matches `contracts/crypto/MerkleTree.sol`: depth 20, nodes hashed with
+//! `keccak256(abi.encode(left, right))` and empty leaves set to
+//! `keccak256("laundry_zero")`. Inserting a leaf touches one node per level,
+//! and the node cache can be snapshotted to disk so a restart reloads the
+//! tree instead of replaying every deposit event.
+
+pub mod roots;
+
+use anyhow::Result;
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use std::path::Path;
+
+/// Tree depth used by the pool contract
+pub const TREE_DEPTH: usize = 20;
+
+/// Preimage of the empty-leaf value
+const ZERO_SEED: &[u8] = b"laundry_zero";
+
+/// Hash two children, equivalent to `keccak256(abi.encode(left, right))`
+pub fn hash_pair(left: H256, right: H256) -> H256 {
+    H256(keccak256([left.as_bytes(), right.as_bytes()].concat()))
+}
+
+/// Zero hashes for each level, `zeros[0]` being the empty leaf
+pub fn zero_values(depth: usize) -> Vec<H256> {
+    let mut zeros = Vec::with_capacity(depth + 1);
+    zeros.push(H256(keccak256(ZERO_SEED)));
+    for level in 0..depth {
+        zeros.push(hash_pair(zeros[level], zeros[level]));
+    }
+    zeros
+}
+
+/// Append-only Merkle tree with cached internal nodes
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IncrementalMerkleTree {
+    depth: usize,
+    /// Rightmost filled left child at each level, as stored by the contract
+    filled_subtrees: Vec<H256>,
+    /// Populated nodes per level; level 0 holds the leaves
+    nodes: Vec<Vec<H256>>,
+    root: H256,
+    #[serde(skip)]
+    zeros: Vec<H256>,
+}
+
+impl IncrementalMerkleTree {
+    /// Create an empty tree
+    pub fn new(depth: usize) -> Self {
+        let zeros = zero_values(depth);
+        Self {
+            depth,
+            filled_subtrees: zeros[..depth].to_vec(),
+            nodes: vec![Vec::new(); depth],
+            root: zeros[depth],
+            zeros,
+        }
+    }
+
+    /// Number of leaves inserted
+    pub fn len(&self) -> u64 {
+        self.nodes[0].len() as u64
+    }
+
+    /// Whether no leaves have been inserted
+    pub fn is_empty(&self) -> bool {
+        self.nodes[0].is_empty()
+    }
+
+    /// Current root
+    pub fn root(&self) -> H256 {
+        self.root
+    }
+
+    /// Tree depth
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Insert a leaf, returning its index
+    pub fn insert(&mut self, leaf: H256) -> Result<u64> {
+        let index = self.len();
+        if index >= 1u64 << self.depth {
+            return Err(anyhow::anyhow!("Merkle tree is full"));
+        }
+
+        let mut current = leaf;
+        let mut position = index as usize;
+
+        for level in 0..self.depth {
+            set_node(&mut self.nodes[level], position, current);
+
+            current = if position % 2 == 0 {
+                self.filled_subtrees[level] = current;
+                hash_pair(current, self.zeros[level])
+            } else {
+                hash_pair(self.filled_subtrees[level], current)
+            };
+            position /= 2;
+        }
+
+        self.root = current;
+        Ok(index)
+    }
+
+    /// Sibling path and direction bits for a leaf, in the prover's layout
+    ///
+    /// Indices use the circuit convention: 0 when the node is a left child,
+    /// 1 when it is a right child.
+    pub fn proof(&self, index: u64) -> Option<(Vec<H256>, Vec<u8>)> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path = Vec::with_capacity(self.depth);
+        let mut indices = Vec::with_capacity(self.depth);
+        let mut position = index as usize;
+
+        for level in 0..self.depth {
+            let sibling = position ^ 1;
+            path.push(
+                self.nodes[level]
+                    .get(sibling)
+                    .copied()
+                    .unwrap_or(self.zeros[level]),
+            );
+            indices.push((position % 2) as u8);
+            position /= 2;
+        }
+
+        Some((path, indices))
+    }
+
+    /// Persist the tree so it can be reloaded without replaying deposits
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Load a previously saved tree
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut tree: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        if tree.nodes.len() != tree.depth || tree.filled_subtrees.len() != tree.depth {
+            return Err(anyhow::anyhow!("Corrupt Merkle tree snapshot"));
+        }
+        tree.zeros = zero_values(tree.depth);
+        Ok(tree)
+    }
+}
+
+/// Write a node at `position`, extending the level if needed
+fn set_node(level: &mut Vec<H256>, position: usize, node: H256) {
+    if position < level.len() {
+        level[position] = node;
+    } else {
+        level.push(node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Recompute the root from scratch over the full leaf set
+    fn full_root(leaves: &[H256], depth: usize) -> H256 {
+        let zeros = zero_values(depth);
+        let mut level: Vec<H256> = leaves.to_vec();
+        for zero in zeros.iter().take(depth) {
+            level = level
+                .chunks(2)
+                .map(|pair| hash_pair(pair[0], pair.get(1).copied().unwrap_or(*zero)))
+                .collect();
+        }
+        level.first().copied().unwrap_or(zeros[depth])
+    }
+
+    fn leaf(i: u64) -> H256 {
+        H256::from_low_u64_be(i + 1)
+    }
+
+    #[test]
+    fn test_zero_values_match_contract() {
+        let zeros = zero_values(TREE_DEPTH);
+        assert_eq!(
+            hex::encode(zeros[0]),
+            "1af8af579191064f2206cca9793817aefb56ab970bdb90c4b66cd0323c54fa17"
+        );
+        assert_eq!(
+            hex::encode(zeros[1]),
+            "57b941e9b3e46d30c35d893403c51cf638bd4d519ca6a69a684c6cd60774e296"
+        );
+        assert_eq!(
+            hex::encode(zeros[19]),
+            "ada83948c9ed6fb3c417c9afbe8d3f3d7155c123bdda632875c1adc6f3600dba"
+        );
+    }
+
+    #[test]
+    fn test_incremental_matches_full_recomputation() {
+        let mut tree = IncrementalMerkleTree::new(TREE_DEPTH);
+        let mut leaves = Vec::new();
+
+        for i in 0..37 {
+            leaves.push(leaf(i));
+            assert_eq!(tree.insert(leaf(i)).unwrap(), i);
+            assert_eq!(tree.root(), full_root(&leaves, TREE_DEPTH));
+        }
+    }
+
+    #[test]
+    fn test_proof_reconstructs_root() {
+        let mut tree = IncrementalMerkleTree::new(4);
+        for i in 0..11 {
+            tree.insert(leaf(i)).unwrap();
+        }
+
+        for i in 0..11 {
+            let (path, indices) = tree.proof(i).unwrap();
+            let mut current = leaf(i);
+            for (sibling, index) in path.iter().zip(&indices) {
+                current = if *index == 0 {
+                    hash_pair(current, *sibling)
+                } else {
+                    hash_pair(*sibling, current)
+                };
+            }
+            assert_eq!(current, tree.root());
+        }
+        assert!(tree.proof(11).is_none());
+    }
+
+    #[test]
+    fn test_full_tree_rejects_insert() {
+        let mut tree = IncrementalMerkleTree::new(2);
+        for i in 0..4 {
+            tree.insert(leaf(i)).unwrap();
+        }
+        assert!(tree.insert(leaf(4)).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_reload_continues_incrementally() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.json");
+
+        let mut tree = IncrementalMerkleTree::new(TREE_DEPTH);
+        let mut leaves = Vec::new();
+        for i in 0..5 {
+            leaves.push(leaf(i));
+            tree.insert(leaf(i)).unwrap();
+        }
+        tree.save(&path).unwrap();
+
+        let mut reloaded = IncrementalMerkleTree::load(&path).unwrap();
+        assert_eq!(reloaded.root(), tree.root());
+        assert_eq!(reloaded.len(), 5);
+
+        leaves.push(leaf(5));
+        reloaded.insert(leaf(5)).unwrap();
+        assert_eq!(reloaded.root(), full_root(&leaves, TREE_DEPTH));
+    }
+}
@@ -0,0 +1,135 @@
+//! Recent pool roots the relayer accepts proofs against
+//!
+//! Deposits are replayed into a local copy of the pool tree and every root
+//! it passes through is recorded with the block that produced it. Only the
+//! newest `window` roots are kept; older ones are no longer accepted.
+
+use anyhow::Result;
+use ethers::types::H256;
+use std::collections::VecDeque;
+use tracing::warn;
+
+use super::IncrementalMerkleTree;
+
+/// Default number of recent roots accepted per pool
+pub const DEFAULT_ROOT_HISTORY: usize = 30;
+
+/// A root the pool tree passed through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownRoot {
+    pub root: H256,
+    /// Block of the deposit that produced the root
+    pub block_number: u64,
+    /// Leaves in the tree at this root
+    pub leaf_count: u64,
+}
+
+/// Local pool tree plus its recent root history
+#[derive(Debug)]
+pub struct PoolRoots {
+    tree: IncrementalMerkleTree,
+    window: usize,
+    /// Oldest first
+    history: VecDeque<KnownRoot>,
+}
+
+impl PoolRoots {
+    /// Track roots of `tree`, keeping the newest `window`
+    pub fn new(tree: IncrementalMerkleTree, window: usize) -> Self {
+        Self {
+            tree,
+            window: window.max(1),
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Insert a deposit's commitment and record the resulting root
+    ///
+    /// Deposits must arrive in leaf order; a leaf already inserted is
+    /// ignored, and a gap means the local tree is out of sync with the pool.
+    pub fn apply_deposit(
+        &mut self,
+        commitment: H256,
+        leaf_index: u64,
+        block_number: u64,
+    ) -> Result<Option<H256>> {
+        let expected = self.tree.len();
+        if leaf_index < expected {
+            return Ok(None);
+        }
+        if leaf_index > expected {
+            warn!(
+                leaf_index = leaf_index,
+                expected = expected,
+                "Deposit skips ahead of the local tree"
+            );
+            return Err(anyhow::anyhow!(
+                "Deposit at leaf {} but the local tree has {} leaves",
+                leaf_index,
+                expected
+            ));
+        }
+
+        self.tree.insert(commitment)?;
+        let root = self.tree.root();
+        self.history.push_back(KnownRoot {
+            root,
+            block_number,
+            leaf_count: self.tree.len(),
+        });
+        while self.history.len() > self.window {
+            self.history.pop_front();
+        }
+        Ok(Some(root))
+    }
+
+    /// Root of the local tree
+    pub fn root(&self) -> H256 {
+        self.tree.root()
+    }
+
+    /// Accepted roots, newest first
+    pub fn roots(&self) -> Vec<KnownRoot> {
+        self.history.iter().rev().copied().collect()
+    }
+
+    /// Whether a proof against `root` would be accepted
+    pub fn is_known(&self, root: H256) -> bool {
+        self.history.iter().any(|known| known.root == root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+
+    #[test]
+    fn test_history_keeps_newest_roots() {
+        let mut roots = PoolRoots::new(IncrementalMerkleTree::new(TREE_DEPTH), 2);
+        let first = roots
+            .apply_deposit(H256::repeat_byte(1), 0, 10)
+            .unwrap()
+            .unwrap();
+        roots.apply_deposit(H256::repeat_byte(2), 1, 11).unwrap();
+        let third = roots
+            .apply_deposit(H256::repeat_byte(3), 2, 12)
+            .unwrap()
+            .unwrap();
+
+        let known = roots.roots();
+        assert_eq!(known.len(), 2);
+        assert_eq!(known[0].root, third);
+        assert_eq!(known[0].block_number, 12);
+        assert_eq!(known[0].leaf_count, 3);
+        assert!(!roots.is_known(first));
+
+        // Replays are ignored, gaps rejected
+        assert_eq!(
+            roots.apply_deposit(H256::repeat_byte(2), 1, 11).unwrap(),
+            None
+        );
+        assert!(roots.apply_deposit(H256::repeat_byte(5), 4, 13).is_err());
+        assert_eq!(roots.roots().len(), 2);
+    }
+}
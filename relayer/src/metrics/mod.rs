@@ -0,0 +1,645 @@
+//! Prometheus metrics for the relayer node
+//!
+//! All metrics live in a single registry so they can be scraped together,
+//! served in the text exposition format at `/metrics` when `--metrics` is
+//! set.
+
+use axum::{http::header, routing::get, Router};
+use once_cell::sync::Lazy;
+use prometheus::{
+    core::Collector, Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// Registry holding every relayer metric
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// Headers stored by the light client, by chain
+pub static BLOCKS_PROCESSED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_blocks_processed_total",
+                "Block headers processed by the light client",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Reorgs the light client handled within its retained headers, by chain
+pub static REORGS_DETECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_reorgs_detected_total",
+                "Reorgs replacing stored headers",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Reorgs reaching below the retained headers, recovered by a resync, by
+/// chain
+pub static DEEP_REORGS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_deep_reorgs_total",
+                "Reorgs deeper than the retained headers, forcing a resync",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// New chains refused for replacing finalized headers, by chain
+pub static FINALIZED_HEADER_CONFLICTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_finalized_header_conflicts_total",
+                "New chains refused because they replace finalized headers",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Heads too far past the stored tip to backfill, recovered by a resync, by
+/// chain
+pub static HEADER_GAP_RESYNCS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_header_gap_resyncs_total",
+                "Gaps longer than the retained headers, forcing a resync",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Headers from the RPC skipped for failing validation, by chain and reason
+pub static INVALID_HEADERS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_invalid_headers_total",
+                "Headers rejected for inconsistent fields",
+            ),
+            &["chain_id", "reason"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Finalized height moved backwards by more than the configured tolerance
+pub static FINALITY_REGRESSIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_finality_regressions_total",
+                "Finalized height regressions beyond tolerance",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof requests rejected because the prover queue was full, by policy
+pub static PROOF_REQUESTS_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_proof_requests_rejected_total",
+                "Proof requests rejected by the queue overflow policy",
+            ),
+            &["policy"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Prover queue worker deaths detected by the watchdog
+pub static PROVER_WORKER_RESTARTS: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_prover_worker_restarts_total",
+            "Prover worker tasks that died and were restarted",
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof requests answered from the proof cache
+pub static PROOF_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_proof_cache_hits_total",
+            "Proof requests served from the proof cache",
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof requests that had to be proved
+pub static PROOF_CACHE_MISSES: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_proof_cache_misses_total",
+            "Proof requests not found in the proof cache",
+        )
+        .unwrap(),
+    )
+});
+
+/// Share of cache lookups that hit, since startup
+pub static PROOF_CACHE_HIT_RATE: Lazy<Gauge> = Lazy::new(|| {
+    register(
+        Gauge::new(
+            "laundry_proof_cache_hit_rate",
+            "Fraction of proof cache lookups that hit",
+        )
+        .unwrap(),
+    )
+});
+
+/// Most batch-member bytes held at once by the last aggregation
+pub static PROVER_AGGREGATION_PEAK_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register(
+        IntGauge::new(
+            "laundry_prover_aggregation_peak_bytes",
+            "Peak bytes of batch members held in memory by the last aggregation",
+        )
+        .unwrap(),
+    )
+});
+
+/// Proofs currently held in the cache
+pub static PROOF_CACHE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    register(
+        IntGauge::new(
+            "laundry_proof_cache_size",
+            "Proofs currently held in the proof cache",
+        )
+        .unwrap(),
+    )
+});
+
+/// Proofs dropped from the cache to make room
+pub static PROOF_CACHE_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_proof_cache_evictions_total",
+            "Proofs evicted from the proof cache at capacity",
+        )
+        .unwrap(),
+    )
+});
+
+/// Effective (backed-off, jittered) head poll interval, by chain
+pub static POLL_INTERVAL_SECONDS: Lazy<GaugeVec> = Lazy::new(|| {
+    register(
+        GaugeVec::new(
+            Opts::new(
+                "laundry_poll_interval_seconds",
+                "Most recent jittered head poll interval",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Head subscription reconnect attempts, by chain
+pub static WS_RECONNECTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_ws_reconnects_total",
+                "WebSocket head subscription reconnect attempts",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proofs generated successfully, by proof type
+pub static PROOFS_GENERATED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("laundry_proofs_generated_total", "Proofs generated"),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Time taken by successful proof generations, by proof type
+pub static PROOF_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new("laundry_proof_duration_seconds", "Proof generation latency")
+                .buckets(vec![
+                    0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+                ]),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Relay requests handled, by where they came from and how they ended
+pub static RELAY_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_relay_requests_total",
+                "Relay requests handled from the API and gossip",
+            ),
+            &["source", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+/// HTTP API requests, by matched route template and status code
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("laundry_http_requests_total", "HTTP API requests handled"),
+            &["route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+/// HTTP API request latency, by matched route template
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "laundry_http_request_duration_seconds",
+                "HTTP API request latency",
+            ),
+            &["route"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proofs being generated right now
+pub static PROOFS_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    register(
+        IntGauge::new(
+            "laundry_proofs_in_flight",
+            "Proofs currently being generated",
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof requests waiting for a prover slot, by proof type
+pub static PROOF_QUEUE_DEPTH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "laundry_proof_queue_depth",
+                "Proof requests waiting for a prover slot",
+            ),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof requests cancelled by their caller, by proof type
+pub static PROOFS_CANCELLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_proofs_cancelled_total",
+                "Proof requests cancelled before their proof was ready",
+            ),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof generations that hit the configured timeout, by proof type
+pub static PROOFS_TIMEOUT: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_proofs_timeout_total",
+                "Proof generations abandoned after the configured timeout",
+            ),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Proof generations that returned an error, by proof type
+pub static PROOFS_FAILED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_proofs_failed_total",
+                "Proof generations that failed before the timeout",
+            ),
+            &["proof_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Broadcast transactions not yet mined or abandoned, by chain
+pub static SUBMISSIONS_IN_FLIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "laundry_submissions_in_flight",
+                "Relayer transactions broadcast and awaiting confirmation",
+            ),
+            &["chain_id"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Gossiped relay requests dropped as equivalent to one already accepted
+pub static RELAY_DUPLICATES_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_relay_duplicates_dropped_total",
+            "Gossiped relay requests spending the same nullifier as an accepted one",
+        )
+        .unwrap(),
+    )
+});
+
+/// Relay transactions that reverted on-chain, by decoded reason
+pub static RELAY_REVERTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_relay_reverted_total",
+                "Relay transactions mined but reverted",
+            ),
+            &["reason"],
+        )
+        .unwrap(),
+    )
+});
+
+/// RPC circuit breaker state by endpoint (0 closed, 1 open, 2 half-open)
+pub static RPC_BREAKER_STATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "laundry_rpc_breaker_state",
+                "RPC circuit breaker state: 0 closed, 1 open, 2 half-open",
+            ),
+            &["endpoint"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Times an RPC circuit breaker opened, by endpoint
+pub static RPC_BREAKER_TRIPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_rpc_breaker_trips_total",
+                "RPC circuit breaker trips",
+            ),
+            &["endpoint"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Gossip publishes attempted, by topic
+pub static GOSSIP_PUBLISH_ATTEMPTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_gossip_publish_attempts_total",
+                "Gossip publishes attempted",
+            ),
+            &["topic"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Gossip publishes accepted by gossipsub, by topic
+pub static GOSSIP_PUBLISH_SUCCESSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_gossip_publish_successes_total",
+                "Gossip publishes accepted for propagation",
+            ),
+            &["topic"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Gossip publishes that failed, by topic and reason
+pub static GOSSIP_PUBLISH_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_gossip_publish_failures_total",
+                "Gossip publishes that failed",
+            ),
+            &["topic", "reason"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Ping round-trip times to connected peers
+pub static P2P_PEER_RTT: Lazy<Histogram> = Lazy::new(|| {
+    register(
+        Histogram::with_opts(HistogramOpts::new(
+            "laundry_p2p_peer_rtt_seconds",
+            "Ping round-trip time to connected peers",
+        ))
+        .unwrap(),
+    )
+});
+
+/// Peers with an open connection
+pub static P2P_CONNECTED_PEERS: Lazy<IntGauge> = Lazy::new(|| {
+    register(IntGauge::new("laundry_p2p_connected_peers", "Connected P2P peers").unwrap())
+});
+
+/// Peers evicted for a negative local score, to stay within `max_peers`
+pub static P2P_PEERS_PRUNED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_p2p_peers_pruned_total",
+            "Peers evicted for a negative local score",
+        )
+        .unwrap(),
+    )
+});
+
+/// Inbound connections turned away at `max_peers`
+pub static P2P_INBOUND_REJECTED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_p2p_inbound_rejected_total",
+            "Inbound peers turned away at max_peers",
+        )
+        .unwrap(),
+    )
+});
+
+pub static P2P_COMPRESSION_BYTES_SAVED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "laundry_p2p_compression_bytes_saved_total",
+            "Gossip payload bytes saved by compression",
+        )
+        .unwrap(),
+    )
+});
+
+/// Approximate on-disk size of the store, as of the last maintenance pass
+pub static STORE_SIZE_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register(
+        IntGauge::new(
+            "laundry_store_size_bytes",
+            "Approximate store size in bytes",
+        )
+        .unwrap(),
+    )
+});
+
+/// Records removed by store maintenance, by data type
+pub static STORE_RECORDS_PRUNED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_store_records_pruned_total",
+                "Records removed by store maintenance",
+            ),
+            &["data"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Sends that found an internal channel full and waited, by channel
+pub static CHANNEL_SENDS_BLOCKED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_channel_sends_blocked_total",
+                "Sends that waited on a full internal channel",
+            ),
+            &["channel"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Internal channels found closed, by channel
+pub static CHANNEL_CLOSED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "laundry_channel_closed_total",
+                "Sends or receives that found an internal channel closed",
+            ),
+            &["channel"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Every registered metric in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        tracing::warn!(error = %e, "Failed to encode metrics");
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Routes of the metrics server
+pub fn router() -> Router {
+    Router::new().route(
+        "/metrics",
+        get(|| async { ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], render()) }),
+    )
+}
+
+/// Register a collector with the relayer registry
+fn register<C: Collector + Clone + 'static>(collector: C) -> C {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("metric registered twice");
+    collector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_scrape_lists_node_metrics() {
+        // A block, a reorg, a proof, a peer and a relay
+        BLOCKS_PROCESSED.with_label_values(&["1"]).inc_by(3);
+        REORGS_DETECTED.with_label_values(&["1"]).inc();
+        PROOFS_GENERATED.with_label_values(&["withdrawal"]).inc();
+        PROOF_DURATION
+            .with_label_values(&["withdrawal"])
+            .observe(1.5);
+        P2P_CONNECTED_PEERS.set(2);
+        RELAY_REQUESTS.with_label_values(&["api", "accepted"]).inc();
+
+        let response = router()
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            prometheus::TEXT_FORMAT
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        for name in [
+            "laundry_blocks_processed_total",
+            "laundry_reorgs_detected_total",
+            "laundry_proofs_generated_total",
+            "laundry_proof_duration_seconds_bucket",
+            "laundry_p2p_connected_peers",
+            "laundry_relay_requests_total",
+        ] {
+            assert!(body.contains(name), "{} missing from scrape", name);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Optional zstd compression of gossip payloads
+//!
+//! Compression is a wire format version of its own: every topic has a
+//! `1.1.0` counterpart whose messages carry a one-byte envelope saying
+//! whether the rest is zstd-compressed. Payloads under the threshold are sent
+//! as-is inside the envelope, as compressing them costs more than it saves.
+//! Nodes with compression enabled subscribe to both versions and only publish
+//! a bare `1.0.0` copy while some peer is subscribed to the old topic alone,
+//! so peers that predate compression keep receiving everything.
+
+use anyhow::Result;
+
+use crate::metrics;
+
+/// Default payload size from which gossip is compressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// zstd level; proofs are mostly incompressible field elements, so the
+/// cheap levels save nearly as much as the expensive ones
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Largest payload a compressed message may expand to
+pub const MAX_DECOMPRESSED_BYTES: usize = 1024 * 1024;
+
+/// Envelope tag of a payload sent as-is
+const TAG_RAW: u8 = 0;
+/// Envelope tag of a zstd-compressed payload
+const TAG_ZSTD: u8 = 1;
+
+/// Wrap `payload` in a compression envelope, compressing it if it is at
+/// least `threshold` bytes and compression actually shrinks it
+pub fn encode(payload: &[u8], threshold: usize) -> Vec<u8> {
+    if payload.len() >= threshold {
+        match zstd::bulk::compress(payload, COMPRESSION_LEVEL) {
+            Ok(compressed) if compressed.len() < payload.len() => {
+                metrics::P2P_COMPRESSION_BYTES_SAVED
+                    .inc_by((payload.len() - compressed.len()) as u64);
+                return envelope(TAG_ZSTD, &compressed);
+            }
+            _ => {}
+        }
+    }
+    envelope(TAG_RAW, payload)
+}
+
+/// Unwrap a compression envelope
+pub fn decode(data: &[u8]) -> Result<Vec<u8>> {
+    match data.split_first() {
+        Some((&TAG_RAW, payload)) => Ok(payload.to_vec()),
+        Some((&TAG_ZSTD, compressed)) => {
+            Ok(zstd::bulk::decompress(compressed, MAX_DECOMPRESSED_BYTES)?)
+        }
+        Some((tag, _)) => anyhow::bail!("Unknown payload encoding {}", tag),
+        None => anyhow::bail!("Empty message"),
+    }
+}
+
+fn envelope(tag: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(payload.len() + 1);
+    data.push(tag);
+    data.extend_from_slice(payload);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_respects_threshold() {
+        // A proof-sized payload with plenty of repetition
+        let large: Vec<u8> = (0..4096u32).map(|i| (i % 7) as u8).collect();
+        let encoded = encode(&large, DEFAULT_COMPRESSION_THRESHOLD);
+        assert_eq!(encoded[0], TAG_ZSTD);
+        assert!(encoded.len() < large.len());
+        assert_eq!(decode(&encoded).unwrap(), large);
+
+        // Below the threshold the payload is sent unchanged
+        let small = vec![0u8; DEFAULT_COMPRESSION_THRESHOLD - 1];
+        let encoded = encode(&small, DEFAULT_COMPRESSION_THRESHOLD);
+        assert_eq!(encoded[0], TAG_RAW);
+        assert_eq!(&encoded[1..], &small[..]);
+        assert_eq!(decode(&encoded).unwrap(), small);
+
+        assert!(decode(&[]).is_err());
+        assert!(decode(&[9, 1, 2]).is_err());
+    }
+}
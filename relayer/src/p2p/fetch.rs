@@ -0,0 +1,156 @@
+//! Direct proof fetching between relayers
+//!
+//! Gossip broadcasts relay requests; this request/response protocol lets a
+//! relayer ask one peer for the proof of a specific request instead. Each
+//! node answers only with proofs of relays it validated itself, keyed by the
+//! hash of their public inputs, so an answer can be checked against what
+//! was asked for. Raw gossip never reaches the index.
+
+use ethers::types::{Bytes, H256};
+use ethers::utils::keccak256;
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::StreamProtocol;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Protocol name negotiated for proof requests
+pub const PROTOCOL: &str = "/laundry/proof/1.1.0";
+
+/// Proofs kept for serving to peers
+pub const DEFAULT_PROOF_INDEX_CAPACITY: usize = 1024;
+
+/// Time a peer has to answer a proof request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Ask a peer for the proof of the public inputs hashing to `inputs_hash`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProofRequest {
+    pub inputs_hash: H256,
+}
+
+/// A peer's answer to a `ProofRequest`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProofResponse {
+    Found {
+        proof: Bytes,
+        public_inputs: Vec<H256>,
+    },
+    NotFound,
+}
+
+/// Request/response behaviour with a JSON codec
+pub type Behaviour = request_response::json::Behaviour<ProofRequest, ProofResponse>;
+
+pub fn behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(StreamProtocol::new(PROTOCOL), ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(REQUEST_TIMEOUT),
+    )
+}
+
+/// `keccak256` of the packed public inputs, the key proofs are served by
+pub fn inputs_hash(public_inputs: &[H256]) -> H256 {
+    H256(keccak256(
+        public_inputs
+            .iter()
+            .flat_map(|input| input.0)
+            .collect::<Vec<u8>>(),
+    ))
+}
+
+/// Recently validated proofs by public-inputs hash, oldest evicted first
+pub struct ProofIndex {
+    capacity: usize,
+    inner: Mutex<IndexInner>,
+}
+
+#[derive(Default)]
+struct IndexInner {
+    proofs: HashMap<H256, (Bytes, Vec<H256>)>,
+    order: VecDeque<H256>,
+}
+
+impl ProofIndex {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Index a proof this node generated or validated
+    ///
+    /// The first proof of a set of public inputs is kept; returns whether
+    /// this one was added.
+    pub fn insert(&self, proof: Bytes, public_inputs: Vec<H256>) -> bool {
+        let key = inputs_hash(&public_inputs);
+        let mut inner = self.inner.lock().unwrap();
+        if inner.proofs.contains_key(&key) {
+            return false;
+        }
+        inner.proofs.insert(key, (proof, public_inputs));
+        inner.order.push_back(key);
+        while inner.proofs.len() > self.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.proofs.remove(&oldest);
+        }
+        true
+    }
+
+    /// Answer to a peer asking for `request`
+    pub fn respond(&self, request: &ProofRequest) -> ProofResponse {
+        match self.inner.lock().unwrap().proofs.get(&request.inputs_hash) {
+            Some((proof, public_inputs)) => ProofResponse::Found {
+                proof: proof.clone(),
+                public_inputs: public_inputs.clone(),
+            },
+            None => ProofResponse::NotFound,
+        }
+    }
+}
+
+impl Default for ProofIndex {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROOF_INDEX_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_evicts_oldest() {
+        let index = ProofIndex::new(2);
+        let inputs = |n: u8| vec![H256::repeat_byte(n)];
+        for n in 1..=3 {
+            assert!(index.insert(Bytes::from(vec![n]), inputs(n)));
+        }
+
+        let request = |n: u8| ProofRequest {
+            inputs_hash: inputs_hash(&inputs(n)),
+        };
+        assert_eq!(index.respond(&request(1)), ProofResponse::NotFound);
+        assert_eq!(
+            index.respond(&request(3)),
+            ProofResponse::Found {
+                proof: Bytes::from(vec![3]),
+                public_inputs: inputs(3),
+            }
+        );
+
+        // A second proof of the same inputs doesn't replace the first
+        assert!(!index.insert(Bytes::from(vec![0xff]), inputs(3)));
+        assert_eq!(
+            index.respond(&request(3)),
+            ProofResponse::Found {
+                proof: Bytes::from(vec![3]),
+                public_inputs: inputs(3),
+            }
+        );
+    }
+}
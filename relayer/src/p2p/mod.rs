@@ -3,45 +3,155 @@
 //! Implements a gossip-based network for relayer communication:
 //! - Relay request distribution
 //! - Block header propagation
-//! - Reputation sharing
+//! - Reputation sharing, see `reputation`
+//! - Direct proof fetching between two relayers, see `fetch`
+
+pub mod compression;
+pub mod fetch;
+pub mod peers;
+pub mod relay_message;
+pub mod reputation;
 
 use anyhow::Result;
+use ethers::types::H256;
 use libp2p::{
-    gossipsub::{self, IdentTopic, MessageAuthenticity},
+    gossipsub::{self, IdentTopic, MessageAcceptance, MessageAuthenticity},
     identify,
     kad::{self, store::MemoryStore},
-    noise,
+    multiaddr::Protocol,
+    noise, ping,
+    request_response::{self, OutboundRequestId},
     swarm::{NetworkBehaviour, SwarmEvent},
     tcp, yamux, Multiaddr, PeerId, Swarm,
 };
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, info, warn};
 
+use crate::api::types::RelayRequest;
+use crate::channel;
+use crate::error::RelayerError;
+use crate::metrics;
 use crate::P2PConfig;
+use fetch::{inputs_hash, ProofIndex, ProofRequest, ProofResponse};
+use peers::PeerTable;
+use relay_message::RelayGossipMessage;
+use reputation::{ReputationTracker, SignedReputationSnapshot};
 
 /// Events from the P2P network
 #[derive(Debug, Clone)]
 pub enum P2PEvent {
-    /// Relay request received from network
-    RelayRequest { request_id: String, data: Vec<u8> },
+    /// Relay request published, and signed, by `peer_id`; `data` is the
+    /// request's JSON
+    ///
+    /// The message is only forwarded once `validation` is reported.
+    RelayRequest {
+        request_id: String,
+        peer_id: String,
+        data: Vec<u8>,
+        validation: PendingValidation,
+    },
+    /// Reorg observed by `peer_id`, from the headers topic
+    ReorgReport {
+        peer_id: String,
+        report: ReorgReport,
+    },
     /// Peer connected
     PeerConnected { peer_id: String },
     /// Peer disconnected
     PeerDisconnected { peer_id: String },
 }
 
+/// Gossiped message held back from the mesh until it is validated
+#[derive(Debug, Clone)]
+pub struct PendingValidation {
+    message_id: gossipsub::MessageId,
+    propagation_source: PeerId,
+}
+
+/// Reorg a relayer observed, gossiped on the headers topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReorgReport {
+    pub chain_id: u64,
+    /// Number of stored blocks the reorg replaced
+    pub depth: u64,
+}
+
+/// Identity of the local node, as reported to operators
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NodeIdentity {
+    /// Local libp2p peer ID
+    pub peer_id: String,
+    /// Hex-encoded blake3 hash of the protobuf-encoded identity public key
+    pub public_key_fingerprint: String,
+    /// Addresses the swarm is listening on
+    pub listen_addrs: Vec<String>,
+    /// External addresses confirmed by peers
+    pub external_addrs: Vec<String>,
+    /// Protocols spoken by this node
+    pub protocols: Vec<String>,
+    /// Relayer software version
+    pub version: String,
+}
+
+/// Protocol version advertised via identify
+const PROTOCOL_VERSION: &str = "/laundry/1.0.0";
+
+/// Wire protocols supported by the relayer behaviour
+const SUPPORTED_PROTOCOLS: &[&str] = &[
+    PROTOCOL_VERSION,
+    "/meshsub/1.1.0",
+    "/ipfs/kad/1.0.0",
+    "/ipfs/id/1.0.0",
+    "/ipfs/ping/1.0.0",
+    fetch::PROTOCOL,
+];
+
+/// Default time between Kademlia bootstrap refreshes
+pub const DEFAULT_DHT_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
 /// Topics for gossip protocol
-const TOPIC_RELAY_REQUESTS: &str = "laundry/relay/1.0.0";
+const TOPIC_RELAY_REQUESTS: &str = "laundry/relay/2.0.0";
 const TOPIC_BLOCK_HEADERS: &str = "laundry/headers/1.0.0";
 const TOPIC_REPUTATION: &str = "laundry/reputation/1.0.0";
 
+/// Compressed counterparts of the topics above, see `compression`
+const TOPIC_RELAY_REQUESTS_COMPRESSED: &str = "laundry/relay/2.1.0";
+const TOPIC_BLOCK_HEADERS_COMPRESSED: &str = "laundry/headers/1.1.0";
+const TOPIC_REPUTATION_COMPRESSED: &str = "laundry/reputation/1.1.0";
+
+/// Compressed counterpart of an uncompressed topic, or `None` if `topic`
+/// has none
+fn compressed_topic(topic: &str) -> Option<&'static str> {
+    match topic {
+        TOPIC_RELAY_REQUESTS => Some(TOPIC_RELAY_REQUESTS_COMPRESSED),
+        TOPIC_BLOCK_HEADERS => Some(TOPIC_BLOCK_HEADERS_COMPRESSED),
+        TOPIC_REPUTATION => Some(TOPIC_REPUTATION_COMPRESSED),
+        _ => None,
+    }
+}
+
+/// Uncompressed topic a compressed one mirrors, or `None` if `topic` isn't
+/// compressed
+fn uncompressed_topic(topic: &str) -> Option<&'static str> {
+    match topic {
+        TOPIC_RELAY_REQUESTS_COMPRESSED => Some(TOPIC_RELAY_REQUESTS),
+        TOPIC_BLOCK_HEADERS_COMPRESSED => Some(TOPIC_BLOCK_HEADERS),
+        TOPIC_REPUTATION_COMPRESSED => Some(TOPIC_REPUTATION),
+        _ => None,
+    }
+}
+
 /// Combined network behaviour
 #[derive(NetworkBehaviour)]
 struct RelayerBehaviour {
     gossipsub: gossipsub::Behaviour,
     kademlia: kad::Behaviour<MemoryStore>,
     identify: identify::Behaviour,
+    ping: ping::Behaviour,
+    fetch: fetch::Behaviour,
 }
 
 /// P2P Network Node
@@ -50,22 +160,78 @@ pub struct P2PNode {
     event_tx: mpsc::Sender<P2PEvent>,
     event_rx: mpsc::Receiver<P2PEvent>,
     topics: Vec<IdentTopic>,
+    identity: Arc<RwLock<NodeIdentity>>,
+    /// Latency of connected peers
+    peers: Arc<PeerTable>,
+    /// Behaviour scores of peers, local and gossiped
+    reputation: Arc<ReputationTracker>,
+    /// Signs published relay requests and reputation snapshots
+    local_key: libp2p::identity::Keypair,
+    reputation_interval: Duration,
+    dht_refresh_interval: Duration,
+    /// Proofs this node serves to peers
+    proofs: Arc<ProofIndex>,
+    /// Callers waiting on a peer's answer to `request_proof`, with the
+    /// inputs hash they asked for
+    proof_requests:
+        HashMap<OutboundRequestId, (H256, oneshot::Sender<Result<ProofResponse, RelayerError>>)>,
+    max_peers: usize,
+    /// Size from which published payloads are compressed, if compression is
+    /// enabled
+    compression_threshold: Option<usize>,
 }
 
 impl P2PNode {
     /// Create a new P2P node
-    pub async fn new(config: &P2PConfig) -> Result<Self> {
-        let (event_tx, event_rx) = mpsc::channel(1000);
+    ///
+    /// An unusable identity key or listen address is a `Config` error;
+    /// anything failing once the swarm is being built is a `P2p` one.
+    pub async fn new(config: &P2PConfig) -> Result<Self, RelayerError> {
+        // Load (or create) the persisted identity
+        let local_key = crate::keys::load_or_create_identity(config.identity_key_path.as_deref())
+            .map_err(RelayerError::Config)?;
+        let listen_addr: Multiaddr = config.listen_addr.parse().map_err(|e| {
+            RelayerError::Config(anyhow::anyhow!(
+                "Invalid listen address {}: {}",
+                config.listen_addr,
+                e
+            ))
+        })?;
+        Self::start(config, local_key, listen_addr)
+            .await
+            .map_err(RelayerError::P2p)
+    }
 
-        // Generate identity
-        let local_key = libp2p::identity::Keypair::generate_ed25519();
+    /// Build the swarm, then start listening and dialing bootstrap peers
+    async fn start(
+        config: &P2PConfig,
+        local_key: libp2p::identity::Keypair,
+        listen_addr: Multiaddr,
+    ) -> Result<Self> {
+        let (event_tx, event_rx) = mpsc::channel(1000);
         let local_peer_id = PeerId::from(local_key.public());
         info!(peer_id = %local_peer_id, "Local peer ID");
 
+        let identity = Arc::new(RwLock::new(NodeIdentity {
+            peer_id: local_peer_id.to_string(),
+            public_key_fingerprint: blake3::hash(&local_key.public().encode_protobuf())
+                .to_hex()
+                .to_string(),
+            listen_addrs: Vec::new(),
+            external_addrs: Vec::new(),
+            protocols: SUPPORTED_PROTOCOLS.iter().map(|p| p.to_string()).collect(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }));
+
         // Configure gossipsub
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(Duration::from_secs(10))
             .validation_mode(gossipsub::ValidationMode::Strict)
+            // Messages are forwarded only once the node has checked them
+            .validate_messages()
+            // Content hash: byte-identical messages are dropped here, while
+            // differently encoded copies of a relay request are deduplicated
+            // after decoding, by `relay::dedup`
             .message_id_fn(|msg: &gossipsub::Message| {
                 let mut hasher = blake3::Hasher::new();
                 hasher.update(&msg.data);
@@ -74,18 +240,36 @@ impl P2PNode {
             .build()
             .map_err(|e| anyhow::anyhow!("Gossipsub config error: {}", e))?;
 
-        let gossipsub = gossipsub::Behaviour::new(
+        let mut gossipsub = gossipsub::Behaviour::new(
             MessageAuthenticity::Signed(local_key.clone()),
             gossipsub_config,
         )
         .map_err(|e| anyhow::anyhow!("Gossipsub error: {}", e))?;
 
+        // Reputation enters gossipsub as the application-specific score;
+        // peers below the threshold get no gossip and leave the mesh
+        let threshold = config.reputation_threshold.min(0) as f64;
+        gossipsub
+            .with_peer_score(
+                gossipsub::PeerScoreParams {
+                    app_specific_weight: 1.0,
+                    ..Default::default()
+                },
+                gossipsub::PeerScoreThresholds {
+                    gossip_threshold: threshold,
+                    publish_threshold: 5.0 * threshold,
+                    graylist_threshold: 8.0 * threshold,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| anyhow::anyhow!("Gossipsub peer score error: {}", e))?;
+
         // Configure Kademlia
         let kademlia = kad::Behaviour::new(local_peer_id, MemoryStore::new(local_peer_id));
 
         // Configure Identify
         let identify = identify::Behaviour::new(identify::Config::new(
-            "/laundry/1.0.0".to_string(),
+            PROTOCOL_VERSION.to_string(),
             local_key.public(),
         ));
 
@@ -94,10 +278,12 @@ impl P2PNode {
             gossipsub,
             kademlia,
             identify,
+            ping: ping::Behaviour::new(ping::Config::new()),
+            fetch: fetch::behaviour(),
         };
 
         // Build swarm
-        let swarm = libp2p::SwarmBuilder::with_existing_identity(local_key)
+        let swarm = libp2p::SwarmBuilder::with_existing_identity(local_key.clone())
             .with_tokio()
             .with_tcp(
                 tcp::Config::default(),
@@ -109,24 +295,45 @@ impl P2PNode {
             .build();
 
         // Create topics
-        let topics = vec![
+        let mut topics = vec![
             IdentTopic::new(TOPIC_RELAY_REQUESTS),
             IdentTopic::new(TOPIC_BLOCK_HEADERS),
             IdentTopic::new(TOPIC_REPUTATION),
         ];
+        if config.compression {
+            topics.extend([
+                IdentTopic::new(TOPIC_RELAY_REQUESTS_COMPRESSED),
+                IdentTopic::new(TOPIC_BLOCK_HEADERS_COMPRESSED),
+                IdentTopic::new(TOPIC_REPUTATION_COMPRESSED),
+            ]);
+        }
 
         let mut node = Self {
             swarm,
             event_tx,
             event_rx,
             topics,
+            identity,
+            peers: Arc::new(PeerTable::new(Duration::from_millis(
+                config.slow_peer_rtt_ms,
+            ))),
+            reputation: Arc::new(ReputationTracker::new(config.reputation_threshold)),
+            local_key,
+            reputation_interval: Duration::from_secs(config.reputation_interval_secs),
+            dht_refresh_interval: Duration::from_secs(config.dht_refresh_interval_secs),
+            proofs: Arc::default(),
+            proof_requests: HashMap::new(),
+            max_peers: config.max_peers,
+            compression_threshold: config
+                .compression
+                .then_some(config.compression_threshold_bytes),
         };
 
         // Subscribe to topics
         node.subscribe_topics()?;
 
         // Start listening
-        node.start_listening(&config.listen_addr)?;
+        node.start_listening(listen_addr)?;
 
         // Connect to bootstrap peers
         node.connect_bootstrap(&config.bootstrap_peers).await?;
@@ -148,26 +355,50 @@ impl P2PNode {
     }
 
     /// Start listening on an address
-    fn start_listening(&mut self, addr: &str) -> Result<()> {
-        let listen_addr: Multiaddr = addr.parse()?;
-        self.swarm.listen_on(listen_addr)?;
-        info!(addr = addr, "Listening");
+    fn start_listening(&mut self, addr: Multiaddr) -> Result<()> {
+        self.swarm.listen_on(addr.clone())?;
+        info!(addr = %addr, "Listening");
         Ok(())
     }
 
     /// Connect to bootstrap peers
+    ///
+    /// Peers whose address ends in `/p2p/<peer id>` also seed the Kademlia
+    /// routing table, which is then bootstrapped.
     async fn connect_bootstrap(&mut self, peers: &[String]) -> Result<()> {
+        let mut dht_seeded = false;
         for peer in peers {
             if let Ok(addr) = peer.parse::<Multiaddr>() {
                 match self.swarm.dial(addr.clone()) {
                     Ok(_) => info!(peer = %addr, "Dialing bootstrap peer"),
                     Err(e) => warn!(peer = %addr, error = %e, "Failed to dial bootstrap peer"),
                 }
+                let mut dht_addr = addr;
+                if let Some(Protocol::P2p(peer_id)) = dht_addr.pop() {
+                    self.swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .add_address(&peer_id, dht_addr);
+                    dht_seeded = true;
+                } else {
+                    debug!(peer = %peer, "Bootstrap peer has no peer ID, not added to the DHT");
+                }
             }
         }
+        if dht_seeded {
+            self.refresh_dht();
+        }
         Ok(())
     }
 
+    /// Start a Kademlia bootstrap to refresh the routing table
+    pub fn refresh_dht(&mut self) {
+        match self.swarm.behaviour_mut().kademlia.bootstrap() {
+            Ok(query) => debug!(query = ?query, "Started DHT bootstrap"),
+            Err(e) => debug!(error = %e, "DHT bootstrap skipped"),
+        }
+    }
+
     /// Get the next event from the P2P network
     pub async fn next_event(&mut self) -> Option<P2PEvent> {
         // Process swarm events
@@ -177,6 +408,17 @@ impl P2PNode {
         self.event_rx.recv().await
     }
 
+    /// Handle swarm events for `duration`, without waiting on `next_event`
+    #[cfg(test)]
+    async fn run_for(&mut self, duration: Duration) {
+        let deadline = tokio::time::Instant::now() + duration;
+        while tokio::time::Instant::now() < deadline {
+            if let Some(event) = self.poll_swarm().await {
+                self.handle_swarm_event(event).await;
+            }
+        }
+    }
+
     /// Poll the swarm for events
     async fn poll_swarm(&mut self) -> Option<SwarmEvent<RelayerBehaviourEvent>> {
         tokio::select! {
@@ -189,63 +431,573 @@ impl P2PNode {
     async fn handle_swarm_event(&mut self, event: SwarmEvent<RelayerBehaviourEvent>) {
         match event {
             SwarmEvent::Behaviour(RelayerBehaviourEvent::Gossipsub(
-                gossipsub::Event::Message { message, .. },
+                gossipsub::Event::Message {
+                    propagation_source,
+                    message_id,
+                    mut message,
+                },
             )) => {
-                let topic = message.topic.to_string();
+                // Signed messages always name their author, who answers for
+                // the content; the forwarding peer only relayed it
+                let author = message.source.unwrap_or(propagation_source);
+                let validation = PendingValidation {
+                    message_id,
+                    propagation_source,
+                };
+                let mut topic = message.topic.to_string();
                 debug!(topic = %topic, "Received gossip message");
+                if let Some(uncompressed) = uncompressed_topic(&topic) {
+                    match compression::decode(&message.data) {
+                        Ok(data) => message.data = data,
+                        Err(e) => {
+                            debug!(peer_id = %author, error = %e, "Undecodable compressed message");
+                            self.report_validation(validation, MessageAcceptance::Reject);
+                            return;
+                        }
+                    }
+                    topic = uncompressed.to_string();
+                }
 
-                if topic.contains("relay") {
-                    let _ = self
-                        .event_tx
-                        .send(P2PEvent::RelayRequest {
-                            request_id: hex::encode(&message.data[..32.min(message.data.len())]),
-                            data: message.data,
-                        })
-                        .await;
+                if topic == TOPIC_BLOCK_HEADERS {
+                    match serde_json::from_slice::<ReorgReport>(&message.data) {
+                        Ok(report) => {
+                            self.report_validation(validation, MessageAcceptance::Accept);
+                            self.emit(P2PEvent::ReorgReport {
+                                peer_id: author.to_string(),
+                                report,
+                            })
+                            .await;
+                        }
+                        Err(e) => {
+                            debug!(peer_id = %author, error = %e, "Undecodable headers message");
+                            self.report_validation(validation, MessageAcceptance::Reject);
+                        }
+                    }
+                } else if topic == TOPIC_RELAY_REQUESTS {
+                    match RelayGossipMessage::decode(&message.data, &author.to_string()) {
+                        Ok(message) => {
+                            let data = serde_json::to_vec(&message.request)
+                                .expect("relay request serializes");
+                            self.emit(P2PEvent::RelayRequest {
+                                request_id: message.request_id,
+                                peer_id: author.to_string(),
+                                data,
+                                validation,
+                            })
+                            .await;
+                        }
+                        Err(e) => {
+                            metrics::RELAY_REQUESTS
+                                .with_label_values(&["gossip", "rejected"])
+                                .inc();
+                            debug!(peer_id = %author, error = %e, "Dropped relay gossip message");
+                            self.report_validation(validation, MessageAcceptance::Reject);
+                            self.reputation.record_invalid_relay(&author.to_string());
+                            self.apply_score(&author);
+                        }
+                    }
+                } else if topic == TOPIC_REPUTATION {
+                    match SignedReputationSnapshot::decode(&message.data) {
+                        Ok(signed) => {
+                            self.report_validation(validation, MessageAcceptance::Accept);
+                            debug!(reporter = %signed.snapshot.reporter, peers = signed.snapshot.scores.len(), "Received reputation snapshot");
+                            if self.reputation.merge(signed.snapshot) {
+                                self.apply_scores();
+                            }
+                        }
+                        Err(e) => {
+                            debug!(peer_id = %author, error = %e, "Dropped reputation snapshot");
+                            self.report_validation(validation, MessageAcceptance::Reject);
+                        }
+                    }
+                } else {
+                    self.report_validation(validation, MessageAcceptance::Ignore);
                 }
             }
-            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                info!(addr = %address, "Listening on new address");
+                self.identity
+                    .write()
+                    .unwrap()
+                    .listen_addrs
+                    .push(address.to_string());
+            }
+            SwarmEvent::ExternalAddrConfirmed { address } => {
+                info!(addr = %address, "External address confirmed");
+                self.identity
+                    .write()
+                    .unwrap()
+                    .external_addrs
+                    .push(address.to_string());
+            }
+            SwarmEvent::ExternalAddrExpired { address } => {
+                let address = address.to_string();
+                self.identity
+                    .write()
+                    .unwrap()
+                    .external_addrs
+                    .retain(|a| *a != address);
+            }
+            SwarmEvent::Behaviour(RelayerBehaviourEvent::Kademlia(
+                kad::Event::OutboundQueryProgressed {
+                    result: kad::QueryResult::Bootstrap(result),
+                    ..
+                },
+            )) => match result {
+                Ok(kad::BootstrapOk {
+                    peer,
+                    num_remaining,
+                }) => {
+                    debug!(peer_id = %peer, remaining = num_remaining, "DHT bootstrap progressed");
+                    if num_remaining == 0 {
+                        info!(
+                            routing_peers = self.routing_table_len(),
+                            "DHT bootstrap complete"
+                        );
+                    }
+                }
+                Err(e) => warn!(error = %e, "DHT bootstrap failed"),
+            },
+            SwarmEvent::Behaviour(RelayerBehaviourEvent::Kademlia(
+                kad::Event::RoutingUpdated { peer, .. },
+            )) => {
+                debug!(peer_id = %peer, "DHT routing table updated");
+            }
+            SwarmEvent::Behaviour(RelayerBehaviourEvent::Fetch(event)) => {
+                self.handle_fetch_event(event);
+            }
+            SwarmEvent::Behaviour(RelayerBehaviourEvent::Ping(ping::Event {
+                peer,
+                result: Ok(rtt),
+                ..
+            })) => {
+                metrics::P2P_PEER_RTT.observe(rtt.as_secs_f64());
+                let peer_id = peer.to_string();
+                if self.peers.record_rtt(&peer_id, rtt) {
+                    self.slow_peer(peer_id);
+                }
+            }
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                endpoint,
+                num_established,
+                ..
+            } => {
                 info!(peer_id = %peer_id, "Connection established");
-                let _ = self
-                    .event_tx
-                    .send(P2PEvent::PeerConnected {
-                        peer_id: peer_id.to_string(),
-                    })
-                    .await;
+                metrics::P2P_CONNECTED_PEERS.set(self.peer_count() as i64);
+                if endpoint.is_listener()
+                    && num_established.get() == 1
+                    && self.peer_count() > self.max_peers
+                    && !self.admit_inbound(peer_id)
+                {
+                    return;
+                }
+                self.peers.connected(&peer_id.to_string());
+                self.evict_over_max_peers();
+                self.emit(P2PEvent::PeerConnected {
+                    peer_id: peer_id.to_string(),
+                })
+                .await;
             }
-            SwarmEvent::ConnectionClosed { peer_id, .. } => {
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established,
+                ..
+            } => {
                 info!(peer_id = %peer_id, "Connection closed");
-                let _ = self
-                    .event_tx
-                    .send(P2PEvent::PeerDisconnected {
-                        peer_id: peer_id.to_string(),
-                    })
-                    .await;
+                metrics::P2P_CONNECTED_PEERS.set(self.peer_count() as i64);
+                // Only count drops of peers this node didn't disconnect itself
+                if num_established == 0 && self.peers.disconnected(&peer_id.to_string()) {
+                    self.reputation.record_disconnect(&peer_id.to_string());
+                    self.apply_score(&peer_id);
+                }
+                self.emit(P2PEvent::PeerDisconnected {
+                    peer_id: peer_id.to_string(),
+                })
+                .await;
             }
             _ => {}
         }
     }
 
-    /// Publish a relay request to the network
-    pub fn publish_relay_request(&mut self, data: Vec<u8>) -> Result<()> {
-        let topic = IdentTopic::new(TOPIC_RELAY_REQUESTS);
-        self.swarm
+    /// Answer proof requests from peers and route answers to our own
+    fn handle_fetch_event(&mut self, event: request_response::Event<ProofRequest, ProofResponse>) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let response = self.proofs.respond(&request);
+                debug!(peer_id = %peer, inputs_hash = ?request.inputs_hash, found = matches!(response, ProofResponse::Found { .. }), "Proof requested by peer");
+                if self
+                    .swarm
+                    .behaviour_mut()
+                    .fetch
+                    .send_response(channel, response)
+                    .is_err()
+                {
+                    debug!(peer_id = %peer, "Proof response not sent, connection closed");
+                }
+            }
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => {
+                if let Some((asked, waiter)) = self.proof_requests.remove(&request_id) {
+                    let answer = match &response {
+                        ProofResponse::Found { public_inputs, .. }
+                            if inputs_hash(public_inputs) != asked =>
+                        {
+                            Err(RelayerError::P2p(anyhow::anyhow!(
+                                "Peer answered with a proof of other public inputs"
+                            )))
+                        }
+                        _ => Ok(response),
+                    };
+                    let _ = waiter.send(answer);
+                }
+            }
+            request_response::Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                debug!(peer_id = %peer, error = %error, "Proof request failed");
+                if let Some((_, waiter)) = self.proof_requests.remove(&request_id) {
+                    let _ = waiter.send(Err(RelayerError::P2p(anyhow::anyhow!(
+                        "Proof request failed: {}",
+                        error
+                    ))));
+                }
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                debug!(peer_id = %peer, error = %error, "Failed to answer proof request");
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+
+    /// Down-score a peer that just turned consistently slow, evicting it
+    /// if the node is over `max_peers`
+    fn slow_peer(&mut self, peer_id: String) {
+        warn!(peer_id = %peer_id, "Peer is consistently slow");
+        self.reputation.record_slow(&peer_id);
+        if let Ok(peer) = peer_id.parse::<PeerId>() {
+            self.apply_score(&peer);
+        }
+        self.evict_over_max_peers();
+    }
+
+    /// Queue an event for `next_event`
+    async fn emit(&self, event: P2PEvent) {
+        if let Err(e) = channel::send(&self.event_tx, "p2p_events", event).await {
+            warn!(error = %e, "P2P event dropped");
+        }
+    }
+
+    /// Connected peers other than `except` that may be evicted, least
+    /// wanted first
+    ///
+    /// This is the node's only eviction policy: a peer goes only for a
+    /// negative score from this node's own observations (slowness included),
+    /// lowest first and, among equals, unmeasured peers first, then the
+    /// slowest. Reports from other nodes never get a peer evicted.
+    fn eviction_order(&self, except: Option<PeerId>) -> Vec<(i64, PeerId)> {
+        let latency_rank: HashMap<String, usize> = self
+            .peers
+            .snapshot()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, peer)| (peer.peer_id, rank))
+            .collect();
+        let mut evictable: Vec<_> = self
+            .swarm
+            .connected_peers()
+            .filter(|p| Some(**p) != except)
+            .map(|p| (self.reputation.local_score(&p.to_string()), *p))
+            .filter(|(score, _)| *score < 0)
+            .collect();
+        evictable.sort_by_key(|(score, p)| {
+            let rank = latency_rank
+                .get(&p.to_string())
+                .copied()
+                .unwrap_or(usize::MAX);
+            (*score, std::cmp::Reverse(rank))
+        });
+        evictable
+    }
+
+    fn evict(&mut self, peer: PeerId, score: i64, reason: &str) {
+        if self.swarm.disconnect_peer_id(peer).is_ok() {
+            info!(peer_id = %peer, score = score, reason = reason, "Evicted peer");
+            metrics::P2P_PEERS_PRUNED.inc();
+        }
+        self.peers.disconnected(&peer.to_string());
+    }
+
+    /// Make room for an inbound `peer` beyond `max_peers`
+    ///
+    /// The newcomer is ranked by this node's own score of it, so one never
+    /// seen before counts as zero and can only displace a peer measured as
+    /// misbehaving. Returns whether the newcomer stays.
+    fn admit_inbound(&mut self, peer: PeerId) -> bool {
+        let newcomer = self.reputation.local_score(&peer.to_string());
+        match self.eviction_order(Some(peer)).first().copied() {
+            Some((score, weakest)) if score < newcomer => {
+                self.evict(weakest, score, "inbound peer");
+                true
+            }
+            _ => {
+                let _ = self.swarm.disconnect_peer_id(peer);
+                info!(peer_id = %peer, max_peers = self.max_peers, "Rejected inbound peer at max_peers");
+                metrics::P2P_INBOUND_REJECTED.inc();
+                false
+            }
+        }
+    }
+
+    /// Evict peers beyond `max_peers`, as far as `eviction_order` allows
+    fn evict_over_max_peers(&mut self) {
+        let excess = self.peers.connected_count().saturating_sub(self.max_peers);
+        for (score, peer) in self.eviction_order(None).into_iter().take(excess) {
+            self.evict(peer, score, "over max_peers");
+        }
+    }
+
+    /// Gossip a relay request this node accepted, signed with its identity
+    pub fn publish_relay_request(
+        &mut self,
+        request_id: &str,
+        request: RelayRequest,
+    ) -> Result<(), RelayerError> {
+        let message = RelayGossipMessage::sign(request_id, request, &self.local_key)
+            .map_err(RelayerError::P2p)?;
+        self.publish(TOPIC_RELAY_REQUESTS, encode(&message)?)
+    }
+
+    /// Publish block headers
+    pub fn publish_headers(&mut self, data: Vec<u8>) -> Result<(), RelayerError> {
+        self.publish(TOPIC_BLOCK_HEADERS, data)
+    }
+
+    /// Tell peers about a reorg this node observed
+    pub fn publish_reorg(&mut self, report: &ReorgReport) -> Result<(), RelayerError> {
+        self.publish_headers(encode(report)?)
+    }
+
+    /// Gossip a signed snapshot of this node's peer scores
+    ///
+    /// Local scores decay first, then scores recorded outside the node since
+    /// the last call (such as relay validation results) are handed to
+    /// gossipsub.
+    pub fn publish_reputation(&mut self) -> Result<(), RelayerError> {
+        // Reports live for a few intervals, so one missed snapshot is survived
+        self.reputation.decay(self.reputation_interval * 3);
+        self.apply_scores();
+        let reporter = self.swarm.local_peer_id().to_string();
+        let snapshot = self
+            .reputation
+            .snapshot(&reporter, chrono::Utc::now().timestamp_millis() as u64);
+        let signed =
+            SignedReputationSnapshot::sign(snapshot, &self.local_key).map_err(RelayerError::P2p)?;
+        self.publish(TOPIC_REPUTATION, encode(&signed)?)
+    }
+
+    /// Tell gossipsub whether a held-back message may be forwarded
+    ///
+    /// Accepted messages go out to the mesh; rejected ones count against the
+    /// peer that forwarded them in gossipsub's own scoring, and ignored ones
+    /// are dropped without penalty.
+    pub fn report_validation(
+        &mut self,
+        validation: PendingValidation,
+        acceptance: MessageAcceptance,
+    ) {
+        let reported = self
+            .swarm
             .behaviour_mut()
             .gossipsub
-            .publish(topic, data)
-            .map_err(|e| anyhow::anyhow!("Publish error: {:?}", e))?;
-        Ok(())
+            .report_message_validation_result(
+                &validation.message_id,
+                &validation.propagation_source,
+                acceptance,
+            );
+        match reported {
+            Ok(true) => {}
+            // Held too long; gossipsub has already let the message go
+            Ok(false) => debug!(message_id = %validation.message_id, "Validated message expired"),
+            Err(e) => {
+                debug!(message_id = %validation.message_id, error = ?e, "Validated message not forwarded")
+            }
+        }
     }
 
-    /// Publish block headers
-    pub fn publish_headers(&mut self, data: Vec<u8>) -> Result<()> {
-        let topic = IdentTopic::new(TOPIC_BLOCK_HEADERS);
+    /// Hand a peer's aggregated reputation to gossipsub
+    fn apply_score(&mut self, peer: &PeerId) {
+        let score = self.reputation.application_score(&peer.to_string());
         self.swarm
             .behaviour_mut()
             .gossipsub
-            .publish(topic, data)
-            .map_err(|e| anyhow::anyhow!("Publish error: {:?}", e))?;
-        Ok(())
+            .set_application_score(peer, score);
+    }
+
+    /// `apply_score` for every connected peer
+    fn apply_scores(&mut self) {
+        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+        for peer in &peers {
+            self.apply_score(peer);
+        }
+    }
+
+    /// Publish on `topic`, or its compressed counterpart if compression is
+    /// enabled
+    ///
+    /// A bare copy still goes out on `topic` while any peer only subscribes
+    /// to that; receivers on both topics deduplicate after decoding. A copy
+    /// no peer subscribes to is left out rather than counted as failed.
+    fn publish(&mut self, topic: &'static str, data: Vec<u8>) -> Result<(), RelayerError> {
+        let (Some(threshold), Some(compressed)) =
+            (self.compression_threshold, compressed_topic(topic))
+        else {
+            return self.publish_on(topic, data);
+        };
+        let (compressed_peers, legacy_peers) = self.subscribers(topic, compressed);
+        if !legacy_peers {
+            return self.publish_on(compressed, compression::encode(&data, threshold));
+        }
+        if !compressed_peers {
+            return self.publish_on(topic, data);
+        }
+        let compressed_copy = self.publish_on(compressed, compression::encode(&data, threshold));
+        let bare = self.publish_on(topic, data);
+        compressed_copy.or(bare)
+    }
+
+    /// Whether some peer subscribes to `compressed`, and whether some peer
+    /// subscribes to `uncompressed` but not `compressed`
+    fn subscribers(&self, uncompressed: &str, compressed: &str) -> (bool, bool) {
+        let uncompressed = IdentTopic::new(uncompressed).hash();
+        let compressed = IdentTopic::new(compressed).hash();
+        self.swarm.behaviour().gossipsub.all_peers().fold(
+            (false, false),
+            |(compressed_peers, legacy_peers), (_, topics)| {
+                let on_compressed = topics.contains(&&compressed);
+                (
+                    compressed_peers || on_compressed,
+                    legacy_peers || (!on_compressed && topics.contains(&&uncompressed)),
+                )
+            },
+        )
+    }
+
+    /// Publish on exactly `topic`, recording the outcome
+    ///
+    /// Failures are counted and logged here, so a publish that never
+    /// propagates is visible even if the caller drops the error.
+    fn publish_on(&mut self, topic: &'static str, data: Vec<u8>) -> Result<(), RelayerError> {
+        metrics::GOSSIP_PUBLISH_ATTEMPTS
+            .with_label_values(&[topic])
+            .inc();
+        let bytes = data.len();
+        match self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(IdentTopic::new(topic), data)
+        {
+            Ok(message_id) => {
+                metrics::GOSSIP_PUBLISH_SUCCESSES
+                    .with_label_values(&[topic])
+                    .inc();
+                debug!(topic, %message_id, bytes, "Published gossip message");
+                Ok(())
+            }
+            Err(e) => {
+                let reason = publish_error_reason(&e);
+                metrics::GOSSIP_PUBLISH_FAILURES
+                    .with_label_values(&[topic, reason])
+                    .inc();
+                warn!(topic, reason, bytes, error = ?e, "Gossip publish failed");
+                Err(RelayerError::P2p(anyhow::anyhow!(
+                    "Publish to {} failed ({}): {:?}",
+                    topic,
+                    reason,
+                    e
+                )))
+            }
+        }
+    }
+
+    /// Local peer ID
+    pub fn local_peer_id(&self) -> PeerId {
+        *self.swarm.local_peer_id()
+    }
+
+    /// Shared handle to the node's identity, kept current as addresses change
+    pub fn identity(&self) -> Arc<RwLock<NodeIdentity>> {
+        self.identity.clone()
+    }
+
+    /// Latency of connected peers, for `/admin/peers`
+    pub fn peers(&self) -> Arc<PeerTable> {
+        self.peers.clone()
+    }
+
+    /// Ask `peer_id` for the proof of the public inputs hashing to
+    /// `inputs_hash`
+    ///
+    /// The answer arrives on the returned channel once the swarm is polled
+    /// (through `next_event`); a proof of any other inputs is an error.
+    pub fn request_proof(
+        &mut self,
+        peer_id: PeerId,
+        inputs_hash: H256,
+    ) -> oneshot::Receiver<Result<ProofResponse, RelayerError>> {
+        let (waiter, answer) = oneshot::channel();
+        let id = self
+            .swarm
+            .behaviour_mut()
+            .fetch
+            .send_request(&peer_id, ProofRequest { inputs_hash });
+        self.proof_requests.insert(id, (inputs_hash, waiter));
+        answer
+    }
+
+    /// Proofs served to peers, for adding ones this node validated
+    pub fn proofs(&self) -> Arc<ProofIndex> {
+        self.proofs.clone()
+    }
+
+    /// Peers in the Kademlia routing table
+    fn routing_table_len(&mut self) -> usize {
+        self.swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .map(|bucket| bucket.num_entries())
+            .sum()
+    }
+
+    /// Time between reputation snapshots
+    pub fn reputation_interval(&self) -> Duration {
+        self.reputation_interval
+    }
+
+    /// Time between DHT refreshes
+    pub fn dht_refresh_interval(&self) -> Duration {
+        self.dht_refresh_interval
+    }
+
+    /// Peer reputation, for recording relay validation results
+    pub fn reputation(&self) -> Arc<ReputationTracker> {
+        self.reputation.clone()
     }
 
     /// Get connected peer count
@@ -254,12 +1006,28 @@ impl P2PNode {
     }
 
     /// Shutdown the P2P node
-    pub async fn shutdown(&mut self) -> Result<()> {
+    pub async fn shutdown(&mut self) -> Result<(), RelayerError> {
         info!("Shutting down P2P node");
         Ok(())
     }
 }
 
+/// JSON payload of a gossip message
+fn encode<T: serde::Serialize>(message: &T) -> Result<Vec<u8>, RelayerError> {
+    serde_json::to_vec(message).map_err(|e| RelayerError::P2p(e.into()))
+}
+
+/// Metric label for a publish failure
+fn publish_error_reason(error: &gossipsub::PublishError) -> &'static str {
+    match error {
+        gossipsub::PublishError::Duplicate => "duplicate",
+        gossipsub::PublishError::SigningError(_) => "signing_error",
+        gossipsub::PublishError::InsufficientPeers => "insufficient_peers",
+        gossipsub::PublishError::MessageTooLarge => "message_too_large",
+        gossipsub::PublishError::TransformFailed(_) => "transform_failed",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,5 +1036,246 @@ mod tests {
     fn test_topics() {
         assert!(TOPIC_RELAY_REQUESTS.contains("relay"));
         assert!(TOPIC_BLOCK_HEADERS.contains("headers"));
+        for topic in [TOPIC_RELAY_REQUESTS, TOPIC_BLOCK_HEADERS, TOPIC_REPUTATION] {
+            assert_eq!(
+                compressed_topic(topic).and_then(uncompressed_topic),
+                Some(topic)
+            );
+        }
+        assert_eq!(uncompressed_topic(TOPIC_RELAY_REQUESTS), None);
+        assert_eq!(compressed_topic(TOPIC_RELAY_REQUESTS_COMPRESSED), None);
+        assert_eq!(compressed_topic("laundry/unknown/1.0.0"), None);
+    }
+
+    fn test_config(identity_key_path: Option<std::path::PathBuf>) -> P2PConfig {
+        P2PConfig {
+            listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(),
+            bootstrap_peers: Vec::new(),
+            max_peers: 10,
+            identity_key_path,
+            slow_peer_rtt_ms: 500,
+            relay_dedup_window_secs: 600,
+            relay_dedup_capacity: 100_000,
+            compression: false,
+            compression_threshold_bytes: 1024,
+            reputation_threshold: -10,
+            reputation_interval_secs: 300,
+            dht_refresh_interval_secs: 300,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peer_id_stable_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(Some(dir.path().join("identity.key")));
+
+        let first = P2PNode::new(&config).await.unwrap();
+        let peer_id = first.identity().read().unwrap().peer_id.clone();
+        drop(first);
+
+        let second = P2PNode::new(&config).await.unwrap();
+        assert_eq!(second.identity().read().unwrap().peer_id, peer_id);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("identity.key"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inbound_peers_capped_at_max_peers() {
+        let mut hub = P2PNode::new(&P2PConfig {
+            max_peers: 2,
+            ..test_config(None)
+        })
+        .await
+        .unwrap();
+        hub.run_for(Duration::from_millis(300)).await;
+        let addr = hub.identity().read().unwrap().listen_addrs[0].clone();
+        let hub_id = hub.identity().read().unwrap().peer_id.clone();
+
+        let dialer = P2PConfig {
+            bootstrap_peers: vec![format!("{}/p2p/{}", addr, hub_id)],
+            ..test_config(None)
+        };
+        let mut dialers = Vec::new();
+        for _ in 0..3 {
+            dialers.push(P2PNode::new(&dialer).await.unwrap());
+        }
+
+        let run = Duration::from_secs(2);
+        let [a, b, c] = &mut dialers[..] else {
+            unreachable!()
+        };
+        tokio::join!(
+            hub.run_for(run),
+            a.run_for(run),
+            b.run_for(run),
+            c.run_for(run)
+        );
+
+        assert_eq!(hub.peer_count(), 2);
+        assert_eq!(hub.peers().connected_count(), 2);
+
+        // A peer the hub measured misbehaving makes way for a newcomer
+        let before: Vec<PeerId> = hub.swarm.connected_peers().copied().collect();
+        let (misbehaving, kept) = (before[0], before[1]);
+        hub.reputation
+            .record_invalid_relay(&misbehaving.to_string());
+        let mut newcomer = P2PNode::new(&dialer).await.unwrap();
+        let newcomer_id = newcomer.local_peer_id();
+        tokio::join!(hub.run_for(run), newcomer.run_for(run));
+
+        let connected: Vec<PeerId> = hub.swarm.connected_peers().copied().collect();
+        assert!(connected.contains(&newcomer_id));
+        assert!(!connected.contains(&misbehaving));
+        assert!(connected.contains(&kept));
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_peers_seed_routing_table() {
+        let seed = PeerId::from(libp2p::identity::Keypair::generate_ed25519().public());
+        let mut node = P2PNode::new(&P2PConfig {
+            bootstrap_peers: vec![
+                format!("/ip4/127.0.0.1/tcp/1/p2p/{}", seed),
+                // Without a peer ID it can only be dialed
+                "/ip4/127.0.0.1/tcp/2".to_string(),
+            ],
+            ..test_config(None)
+        })
+        .await
+        .unwrap();
+
+        let routed: Vec<PeerId> = node
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|entry| *entry.node.key.preimage())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(routed, vec![seed]);
+        assert_eq!(node.routing_table_len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proof_fetched_from_peer() {
+        let mut server = P2PNode::new(&test_config(None)).await.unwrap();
+        server.run_for(Duration::from_millis(300)).await;
+        let addr = server.identity().read().unwrap().listen_addrs[0].clone();
+        let server_id: PeerId = server.identity().read().unwrap().peer_id.parse().unwrap();
+        let proof = ethers::types::Bytes::from(vec![3u8; 128]);
+        let inputs = vec![H256::repeat_byte(4)];
+        server.proofs().insert(proof.clone(), inputs.clone());
+
+        let mut client = P2PNode::new(&P2PConfig {
+            bootstrap_peers: vec![format!("{}/p2p/{}", addr, server_id)],
+            ..test_config(None)
+        })
+        .await
+        .unwrap();
+        let found = client.request_proof(server_id, fetch::inputs_hash(&inputs));
+        let missing = client.request_proof(server_id, H256::repeat_byte(5));
+
+        let run = Duration::from_secs(2);
+        tokio::join!(server.run_for(run), client.run_for(run));
+
+        assert_eq!(
+            found.await.unwrap().unwrap(),
+            ProofResponse::Found {
+                proof,
+                public_inputs: inputs,
+            }
+        );
+        assert_eq!(missing.await.unwrap().unwrap(), ProofResponse::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_published_relay_request_received_from_publisher() {
+        let mut publisher = P2PNode::new(&test_config(None)).await.unwrap();
+        publisher.run_for(Duration::from_millis(300)).await;
+        let addr = publisher.identity().read().unwrap().listen_addrs[0].clone();
+        let publisher_id = publisher.local_peer_id();
+
+        let mut receiver = P2PNode::new(&P2PConfig {
+            bootstrap_peers: vec![format!("{}/p2p/{}", addr, publisher_id)],
+            ..test_config(None)
+        })
+        .await
+        .unwrap();
+        // Long enough to connect and learn each other's subscriptions
+        let run = Duration::from_secs(2);
+        tokio::join!(publisher.run_for(run), receiver.run_for(run));
+
+        let (_, request) = crate::relay::validate::valid_relay_fixture(1).await;
+        publisher
+            .publish_relay_request("r1", request.clone())
+            .unwrap();
+        tokio::join!(publisher.run_for(run), receiver.run_for(run));
+
+        // Only emitted once `decode` checked it against its author
+        let mut received = None;
+        while let Ok(event) = receiver.event_rx.try_recv() {
+            if let P2PEvent::RelayRequest {
+                request_id,
+                peer_id,
+                data,
+                ..
+            } = event
+            {
+                received = Some((request_id, peer_id, data));
+            }
+        }
+        let (request_id, peer_id, data) = received.expect("relay request received");
+        assert_eq!(request_id, "r1");
+        assert_eq!(peer_id, publisher_id.to_string());
+        assert_eq!(
+            serde_json::from_slice::<RelayRequest>(&data).unwrap(),
+            request
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bad_listen_address_is_config_error() {
+        let config = P2PConfig {
+            listen_addr: "0.0.0.0:9000".to_string(),
+            ..test_config(None)
+        };
+        let err = P2PNode::new(&config)
+            .await
+            .err()
+            .expect("listen address is not a multiaddr");
+        assert!(matches!(err, RelayerError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_publish_counted_by_reason() {
+        let mut node = P2PNode::new(&test_config(None)).await.unwrap();
+        let failures = metrics::GOSSIP_PUBLISH_FAILURES
+            .with_label_values(&[TOPIC_REPUTATION, "insufficient_peers"]);
+        let attempts = metrics::GOSSIP_PUBLISH_ATTEMPTS.with_label_values(&[TOPIC_REPUTATION]);
+        let (failures_before, attempts_before) = (failures.get(), attempts.get());
+
+        // No peers are connected, so gossipsub has nobody to send to
+        let err = node.publish_reputation().unwrap_err();
+        assert!(matches!(err, RelayerError::P2p(_)));
+        assert!(err.to_string().contains("insufficient_peers"));
+        assert_eq!(failures.get(), failures_before + 1);
+        assert_eq!(attempts.get(), attempts_before + 1);
+        assert_eq!(
+            metrics::GOSSIP_PUBLISH_SUCCESSES
+                .with_label_values(&[TOPIC_REPUTATION])
+                .get(),
+            0
+        );
     }
 }
@@ -0,0 +1,176 @@
+//! Per-peer latency tracking and slow-peer detection
+//!
+//! Each connected peer keeps a smoothed round-trip time of this node's own
+//! pings, which the peer can't misreport. A sample above the configured
+//! threshold is a strike; `SLOW_STRIKES` in a row mark the peer
+//! consistently slow, at which point it is down-scored. Eviction goes by
+//! score, with the slowest of equally scored peers going first and peers not
+//! yet measured ahead of all measured ones.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default smoothed RTT above which a ping counts against a peer
+pub const DEFAULT_SLOW_RTT: Duration = Duration::from_millis(500);
+
+/// Consecutive slow samples before a peer is treated as consistently slow
+pub const SLOW_STRIKES: u32 = 5;
+
+/// Reputation a peer loses each time it turns consistently slow
+pub const SLOW_PEER_PENALTY: i64 = 5;
+
+/// Weight of the newest sample in the smoothed latencies
+const SMOOTHING: f64 = 0.3;
+
+/// Latency view of one peer, as served at `/admin/peers`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerLatency {
+    pub peer_id: String,
+    /// Smoothed ping round-trip time, unset until the first ping
+    pub rtt_ms: Option<f64>,
+    /// Slow samples in a row
+    pub slow_strikes: u32,
+    pub slow: bool,
+}
+
+#[derive(Debug, Default)]
+struct PeerStats {
+    rtt_ms: Option<f64>,
+    slow_strikes: u32,
+}
+
+impl PeerStats {
+    /// RTT used to rank peers; unmeasured peers rank as slowest
+    fn cost(&self) -> f64 {
+        self.rtt_ms.unwrap_or(f64::INFINITY)
+    }
+
+    fn is_slow(&self) -> bool {
+        self.slow_strikes >= SLOW_STRIKES
+    }
+}
+
+/// Latency statistics of every connected peer
+#[derive(Debug)]
+pub struct PeerTable {
+    /// Smoothed RTT above which a ping counts as slow
+    slow_rtt: Duration,
+    peers: Mutex<HashMap<String, PeerStats>>,
+}
+
+impl Default for PeerTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_RTT)
+    }
+}
+
+impl PeerTable {
+    pub fn new(slow_rtt: Duration) -> Self {
+        Self {
+            slow_rtt,
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn connected(&self, peer_id: &str) {
+        self.peers
+            .lock()
+            .unwrap()
+            .entry(peer_id.to_string())
+            .or_default();
+    }
+
+    /// Forget a peer; false if it wasn't tracked (already evicted or
+    /// never admitted)
+    pub fn disconnected(&self, peer_id: &str) -> bool {
+        self.peers.lock().unwrap().remove(peer_id).is_some()
+    }
+
+    /// Number of connected peers
+    pub fn connected_count(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+
+    /// Record a ping round trip; true if it made the peer consistently slow
+    pub fn record_rtt(&self, peer_id: &str, rtt: Duration) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        let stats = peers.entry(peer_id.to_string()).or_default();
+        let was_slow = stats.is_slow();
+
+        let sample_ms = rtt.as_secs_f64() * 1000.0;
+        let value = stats
+            .rtt_ms
+            .map_or(sample_ms, |prev| prev + SMOOTHING * (sample_ms - prev));
+        stats.rtt_ms = Some(value);
+
+        if value > self.slow_rtt.as_secs_f64() * 1000.0 {
+            stats.slow_strikes += 1;
+        } else {
+            stats.slow_strikes = 0;
+        }
+        !was_slow && stats.is_slow()
+    }
+
+    /// Every connected peer, fastest first and unmeasured ones last
+    pub fn snapshot(&self) -> Vec<PeerLatency> {
+        let peers = self.peers.lock().unwrap();
+        let mut ranked: Vec<_> = peers.iter().collect();
+        ranked.sort_by(|(a_id, a), (b_id, b)| a.cost().total_cmp(&b.cost()).then(a_id.cmp(b_id)));
+        ranked
+            .into_iter()
+            .map(|(peer_id, stats)| PeerLatency {
+                peer_id: peer_id.clone(),
+                rtt_ms: stats.rtt_ms,
+                slow_strikes: stats.slow_strikes,
+                slow: stats.is_slow(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_latency_peer_deprioritized() {
+        let table = PeerTable::default();
+        for peer in ["fast", "steady", "laggy", "silent"] {
+            table.connected(peer);
+        }
+
+        // "laggy" answers pings in 1.5s; it turns slow only after enough strikes
+        let mut became_slow = Vec::new();
+        for _ in 0..SLOW_STRIKES {
+            table.record_rtt("fast", Duration::from_millis(20));
+            table.record_rtt("steady", Duration::from_millis(80));
+            became_slow.push(table.record_rtt("laggy", Duration::from_millis(1_500)));
+        }
+        assert_eq!(became_slow.iter().filter(|slow| **slow).count(), 1);
+        assert_eq!(became_slow.last(), Some(&true));
+
+        // A peer never pinged ranks behind every measured one
+        let ranked: Vec<_> = table
+            .snapshot()
+            .into_iter()
+            .map(|peer| (peer.peer_id, peer.slow))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("fast".to_string(), false),
+                ("steady".to_string(), false),
+                ("laggy".to_string(), true),
+                ("silent".to_string(), false),
+            ]
+        );
+
+        // Recovering clears the strikes
+        for _ in 0..10 {
+            table.record_rtt("laggy", Duration::from_millis(10));
+        }
+        assert!(table.snapshot().iter().all(|peer| !peer.slow));
+    }
+}
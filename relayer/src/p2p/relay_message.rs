@@ -0,0 +1,261 @@
+//! Signed envelope for relay requests gossiped between relayers
+//!
+//! The publishing relayer signs, with its libp2p identity key, a hash of the
+//! ABI-encoded request together with the request ID and the peer ID it
+//! publishes from. Receivers drop any message that does not decode, that
+//! arrives from a different peer than the one signed for, or whose signature
+//! does not verify against that peer's key, before it reaches relay
+//! validation. Relays this node accepts over HTTP are published this way.
+
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, H256, I256, U256};
+use ethers::utils::keccak256;
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+
+use crate::api::types::RelayRequest;
+
+/// Domain separating relay signatures from anything else the key signs
+const SIGNING_DOMAIN: &str = "laundry-relay-v2";
+
+/// Relay request as published on the relay topics
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RelayGossipMessage {
+    pub request_id: String,
+    /// The request, carrying the proof and its public inputs
+    pub request: RelayRequest,
+    /// libp2p peer the message is published from
+    pub peer_id: String,
+    /// Hex protobuf encoding of the publishing peer's public key
+    pub public_key: String,
+    /// Hex signature over `signing_hash`
+    pub signature: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GossipMessageError {
+    #[error("Malformed relay gossip message: {0}")]
+    Malformed(String),
+    #[error("Relay gossip message signed for peer {signed_for}, published by {author}")]
+    WrongPeer { signed_for: String, author: String },
+    #[error("Relay gossip message not signed by its publisher")]
+    InvalidSignature,
+}
+
+impl RelayGossipMessage {
+    /// Sign `request` with the identity `key` of the peer publishing it
+    pub fn sign(
+        request_id: impl Into<String>,
+        request: RelayRequest,
+        key: &Keypair,
+    ) -> anyhow::Result<Self> {
+        let request_id = request_id.into();
+        let peer_id = PeerId::from(key.public()).to_string();
+        let digest = signing_hash(&request_id, &peer_id, &request);
+        let signature = key.sign(digest.as_bytes())?;
+        Ok(Self {
+            request_id,
+            request,
+            peer_id,
+            public_key: hex::encode(key.public().encode_protobuf()),
+            signature: hex::encode(signature),
+        })
+    }
+
+    /// Decode a payload gossiped by `author`, checking it was signed with
+    /// that peer's identity key
+    pub fn decode(data: &[u8], author: &str) -> Result<Self, GossipMessageError> {
+        let message = serde_json::from_slice::<Self>(data)
+            .map_err(|e| GossipMessageError::Malformed(e.to_string()))?;
+        message.verify(author)?;
+        Ok(message)
+    }
+
+    /// Check the message was signed for `author`, with the key `peer_id`
+    /// is derived from
+    pub fn verify(&self, author: &str) -> Result<(), GossipMessageError> {
+        if self.peer_id != author {
+            return Err(GossipMessageError::WrongPeer {
+                signed_for: self.peer_id.clone(),
+                author: author.to_string(),
+            });
+        }
+        let public_key = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+            .ok_or(GossipMessageError::InvalidSignature)?;
+        if PeerId::from(&public_key).to_string() != self.peer_id {
+            return Err(GossipMessageError::InvalidSignature);
+        }
+        let signature =
+            hex::decode(&self.signature).map_err(|_| GossipMessageError::InvalidSignature)?;
+        let digest = signing_hash(&self.request_id, &self.peer_id, &self.request);
+        if !public_key.verify(digest.as_bytes(), &signature) {
+            return Err(GossipMessageError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+/// `(present, value)`, with `value` zeroed when absent
+fn optional(value: Option<Token>, absent: Token) -> Token {
+    Token::Tuple(vec![Token::Bool(value.is_some()), value.unwrap_or(absent)])
+}
+
+/// What the publisher signs: `keccak256` of the ABI-encoded domain, request
+/// ID, peer ID and every field of the request, variable-length ones hashed
+fn signing_hash(request_id: &str, peer_id: &str, request: &RelayRequest) -> H256 {
+    let hashed = |bytes: &[u8]| Token::FixedBytes(keccak256(bytes).to_vec());
+    let word = |hash: H256| Token::FixedBytes(hash.as_bytes().to_vec());
+    let inputs: Vec<u8> = request
+        .public_inputs
+        .iter()
+        .flat_map(|input| input.0)
+        .collect();
+    H256(keccak256(encode(&[
+        hashed(SIGNING_DOMAIN.as_bytes()),
+        hashed(request_id.as_bytes()),
+        hashed(peer_id.as_bytes()),
+        Token::Uint(U256::from(request.chain_id)),
+        hashed(&request.proof),
+        hashed(&inputs),
+        Token::Uint(request.fee),
+        optional(
+            request
+                .quote
+                .as_ref()
+                .map(|quote| hashed(quote.quote.signing_message().as_bytes())),
+            word(H256::zero()),
+        ),
+        optional(
+            request
+                .deadline
+                .map(|deadline| Token::Int(I256::from(deadline).into_raw())),
+            Token::Int(U256::zero()),
+        ),
+        optional(
+            request
+                .circuit_version
+                .as_ref()
+                .map(|version| hashed(version.as_bytes())),
+            word(H256::zero()),
+        ),
+        optional(
+            request
+                .recipient
+                .map(|recipient| Token::Address(recipient.0)),
+            Token::Address(Address::zero()),
+        ),
+        optional(request.public_inputs_hash.map(word), word(H256::zero())),
+        optional(
+            request.pool.map(Token::Address),
+            Token::Address(Address::zero()),
+        ),
+    ])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Bytes;
+
+    fn request() -> RelayRequest {
+        RelayRequest {
+            chain_id: 1,
+            proof: Bytes::from(vec![7u8; 64]),
+            public_inputs: vec![H256::repeat_byte(1), H256::repeat_byte(2)],
+            quote: None,
+            fee: U256::from(1_000u64),
+            deadline: None,
+            circuit_version: None,
+            recipient: None,
+            public_inputs_hash: None,
+            pool: None,
+        }
+    }
+
+    /// A publisher's identity key and the peer ID it publishes as
+    fn publisher() -> (Keypair, String) {
+        let key = Keypair::generate_ed25519();
+        let peer = PeerId::from(key.public()).to_string();
+        (key, peer)
+    }
+
+    #[test]
+    fn test_signed_message_decodes() {
+        let (key, peer) = publisher();
+        let message = RelayGossipMessage::sign("r1", request(), &key).unwrap();
+        assert_eq!(message.peer_id, peer);
+
+        let data = serde_json::to_vec(&message).unwrap();
+        let decoded = RelayGossipMessage::decode(&data, &peer).unwrap();
+        assert_eq!(decoded, message);
+
+        // Replayed by a peer other than the one it was signed for
+        let (_, replayer) = publisher();
+        assert!(matches!(
+            RelayGossipMessage::decode(&data, &replayer),
+            Err(GossipMessageError::WrongPeer { .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_payload_rejected() {
+        let (_, peer) = publisher();
+        assert!(matches!(
+            RelayGossipMessage::decode(b"not json", &peer),
+            Err(GossipMessageError::Malformed(_))
+        ));
+        // A bare request without the signed envelope
+        let bare = serde_json::to_vec(&request()).unwrap();
+        assert!(matches!(
+            RelayGossipMessage::decode(&bare, &peer),
+            Err(GossipMessageError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_bad_signature_rejected() {
+        let (key, peer) = publisher();
+        let message = RelayGossipMessage::sign("r1", request(), &key).unwrap();
+
+        // Altered in transit, including optional terms added afterwards
+        let mut tampered = message.clone();
+        tampered.request.fee = U256::from(1u64);
+        assert!(matches!(
+            tampered.verify(&peer),
+            Err(GossipMessageError::InvalidSignature)
+        ));
+        let mut extended = message.clone();
+        extended.request.deadline = Some(0);
+        assert!(matches!(
+            extended.verify(&peer),
+            Err(GossipMessageError::InvalidSignature)
+        ));
+
+        // Re-addressed to another peer, keeping the original key
+        let (other_key, other) = publisher();
+        let mut redirected = message.clone();
+        redirected.peer_id = other.clone();
+        assert!(matches!(
+            redirected.verify(&other),
+            Err(GossipMessageError::InvalidSignature)
+        ));
+
+        // Signed by another key while claiming this peer
+        let mut forged = RelayGossipMessage::sign("r1", request(), &other_key).unwrap();
+        forged.peer_id = peer.clone();
+        assert!(matches!(
+            forged.verify(&peer),
+            Err(GossipMessageError::InvalidSignature)
+        ));
+
+        let mut garbage = message;
+        garbage.signature = "0xdead".to_string();
+        let data = serde_json::to_vec(&garbage).unwrap();
+        assert!(matches!(
+            RelayGossipMessage::decode(&data, &peer),
+            Err(GossipMessageError::InvalidSignature)
+        ));
+    }
+}
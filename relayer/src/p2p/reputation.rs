@@ -0,0 +1,442 @@
+//! Peer reputation from observed behaviour, shared over gossip
+//!
+//! Each node scores the peers it talks to: relay messages that validate earn
+//! a point, invalid ones, slowness and dropped connections cost some. Local
+//! scores are periodically gossiped as a snapshot signed with the node's
+//! libp2p identity. A peer's aggregated score is the local one plus half the
+//! mean of what reporters this node itself scores positively say about it;
+//! peers below the threshold are kept out of the gossip mesh. Local scores
+//! decay towards zero every snapshot interval, and both score tables are
+//! bounded in size.
+
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default aggregated score below which a peer is deprioritized
+pub const DEFAULT_REPUTATION_THRESHOLD: i64 = -10;
+
+/// Default time between published snapshots
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Reputation a peer gains for each valid relay message it forwards
+pub const VALID_RELAY_REWARD: i64 = 1;
+
+/// Reputation a peer loses for each invalid relay message it forwards
+pub const INVALID_RELAY_PENALTY: i64 = 5;
+
+/// Reputation a peer loses each time its last connection closes
+pub const DISCONNECT_PENALTY: i64 = 1;
+
+/// Bound on local scores, so good behaviour can't bank unlimited credit
+pub const MAX_SCORE: i64 = 100;
+
+/// Peers scored locally, and peers taken from a single snapshot
+pub const MAX_TRACKED_PEERS: usize = 4096;
+
+/// Reporters whose latest snapshot is kept
+pub const MAX_REPORTERS: usize = 256;
+
+/// Points every local score moves towards zero each snapshot interval
+pub const SCORE_DECAY: i64 = 1;
+
+/// Reputation a node assigns its peers, as gossiped
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReputationSnapshot {
+    /// Peer ID of the reporting node
+    pub reporter: String,
+    pub scores: BTreeMap<String, i64>,
+    /// Reporter's unix time in milliseconds
+    pub sent_at_ms: u64,
+}
+
+/// Snapshot signed with the reporter's libp2p identity
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignedReputationSnapshot {
+    pub snapshot: ReputationSnapshot,
+    /// Hex protobuf encoding of the reporter's public key
+    pub public_key: String,
+    /// Hex signature over the JSON-encoded snapshot
+    pub signature: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReputationError {
+    #[error("Malformed reputation snapshot: {0}")]
+    Malformed(String),
+    #[error("Reputation snapshot not signed by its reporter")]
+    InvalidSignature,
+}
+
+impl SignedReputationSnapshot {
+    pub fn sign(snapshot: ReputationSnapshot, key: &Keypair) -> anyhow::Result<Self> {
+        let signature = key.sign(&serde_json::to_vec(&snapshot)?)?;
+        Ok(Self {
+            snapshot,
+            public_key: hex::encode(key.public().encode_protobuf()),
+            signature: hex::encode(signature),
+        })
+    }
+
+    /// Decode a gossiped payload, checking it was signed by its reporter
+    pub fn decode(data: &[u8]) -> Result<Self, ReputationError> {
+        let signed = serde_json::from_slice::<Self>(data)
+            .map_err(|e| ReputationError::Malformed(e.to_string()))?;
+        signed.verify()?;
+        Ok(signed)
+    }
+
+    pub fn verify(&self) -> Result<(), ReputationError> {
+        let public_key = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+            .ok_or(ReputationError::InvalidSignature)?;
+        if PeerId::from(&public_key).to_string() != self.snapshot.reporter {
+            return Err(ReputationError::InvalidSignature);
+        }
+        let signature =
+            hex::decode(&self.signature).map_err(|_| ReputationError::InvalidSignature)?;
+        let message = serde_json::to_vec(&self.snapshot)
+            .map_err(|e| ReputationError::Malformed(e.to_string()))?;
+        if !public_key.verify(&message, &signature) {
+            return Err(ReputationError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+/// Latest snapshot taken in from a reporter
+#[derive(Debug)]
+struct Report {
+    sent_at_ms: u64,
+    received_at: Instant,
+    scores: BTreeMap<String, i64>,
+}
+
+/// Local and reported reputation of every peer seen
+#[derive(Debug)]
+pub struct ReputationTracker {
+    threshold: i64,
+    local: Mutex<HashMap<String, i64>>,
+    /// Latest snapshot from each reporter
+    reported: Mutex<HashMap<String, Report>>,
+}
+
+impl Default for ReputationTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPUTATION_THRESHOLD)
+    }
+}
+
+impl ReputationTracker {
+    pub fn new(threshold: i64) -> Self {
+        Self {
+            threshold,
+            local: Mutex::default(),
+            reported: Mutex::default(),
+        }
+    }
+
+    pub fn record_valid_relay(&self, peer_id: &str) {
+        self.adjust(peer_id, VALID_RELAY_REWARD);
+    }
+
+    pub fn record_invalid_relay(&self, peer_id: &str) {
+        self.adjust(peer_id, -INVALID_RELAY_PENALTY);
+    }
+
+    /// A peer that turned consistently slow
+    pub fn record_slow(&self, peer_id: &str) {
+        self.adjust(peer_id, -super::peers::SLOW_PEER_PENALTY);
+    }
+
+    pub fn record_disconnect(&self, peer_id: &str) {
+        self.adjust(peer_id, -DISCONNECT_PENALTY);
+    }
+
+    fn adjust(&self, peer_id: &str, delta: i64) {
+        let mut local = self.local.lock().unwrap();
+        if !local.contains_key(peer_id) && local.len() >= MAX_TRACKED_PEERS {
+            // Forget the peer the least is known about
+            let weakest = local
+                .iter()
+                .min_by_key(|(_, score)| score.abs())
+                .map(|(peer, _)| peer.clone());
+            if let Some(weakest) = weakest {
+                local.remove(&weakest);
+            }
+        }
+        let score = local.entry(peer_id.to_string()).or_default();
+        *score = (*score + delta).clamp(-MAX_SCORE, MAX_SCORE);
+    }
+
+    /// Move every local score `SCORE_DECAY` towards zero and forget reports
+    /// received more than `report_ttl` ago
+    pub fn decay(&self, report_ttl: Duration) {
+        self.local.lock().unwrap().retain(|_, score| {
+            *score -= score.signum() * SCORE_DECAY.min(score.abs());
+            *score != 0
+        });
+        self.reported
+            .lock()
+            .unwrap()
+            .retain(|_, report| report.received_at.elapsed() < report_ttl);
+    }
+
+    /// Score from this node's own observations
+    pub fn local_score(&self, peer_id: &str) -> i64 {
+        self.local
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Local score plus half the mean of reports from peers this node
+    /// scores positively
+    pub fn score(&self, peer_id: &str) -> i64 {
+        let reported = self.reported.lock().unwrap();
+        let reports: Vec<i64> = reported
+            .iter()
+            .filter(|(reporter, _)| self.local_score(reporter) > 0)
+            .filter_map(|(_, report)| report.scores.get(peer_id))
+            .map(|score| (*score).clamp(-MAX_SCORE, MAX_SCORE))
+            .collect();
+        let remote = match reports.len() {
+            0 => 0,
+            n => reports.iter().sum::<i64>() / n as i64 / 2,
+        };
+        self.local_score(peer_id) + remote
+    }
+
+    /// Whether the peer's aggregated score is below the threshold
+    pub fn is_deprioritized(&self, peer_id: &str) -> bool {
+        self.score(peer_id) < self.threshold
+    }
+
+    /// Score to hand gossipsub for `peer_id`: nothing while above the
+    /// threshold, so only deprioritized peers are pushed out of the mesh
+    pub fn application_score(&self, peer_id: &str) -> f64 {
+        let score = self.score(peer_id);
+        if score < self.threshold {
+            score as f64
+        } else {
+            score.max(0) as f64
+        }
+    }
+
+    pub fn threshold(&self) -> i64 {
+        self.threshold
+    }
+
+    /// This node's local scores, for gossiping as `reporter`
+    pub fn snapshot(&self, reporter: &str, sent_at_ms: u64) -> ReputationSnapshot {
+        let local = self.local.lock().unwrap();
+        ReputationSnapshot {
+            reporter: reporter.to_string(),
+            scores: local
+                .iter()
+                .map(|(peer, score)| (peer.clone(), *score))
+                .collect(),
+            sent_at_ms,
+        }
+    }
+
+    /// Take in a verified snapshot, replacing the reporter's previous one
+    ///
+    /// Returns whether it was kept: snapshots no newer than the reporter's
+    /// last, oversized ones, and new reporters once the table is full of
+    /// trusted ones are dropped.
+    pub fn merge(&self, snapshot: ReputationSnapshot) -> bool {
+        if snapshot.scores.len() > MAX_TRACKED_PEERS {
+            return false;
+        }
+        let mut reported = self.reported.lock().unwrap();
+        let last_sent_at_ms = reported.get(&snapshot.reporter).map(|last| last.sent_at_ms);
+        match last_sent_at_ms {
+            Some(last) if snapshot.sent_at_ms <= last => return false,
+            None if reported.len() >= MAX_REPORTERS => {
+                // Make room by dropping the least trusted reporter, if any
+                // is untrusted
+                let untrusted = reported
+                    .keys()
+                    .map(|reporter| (self.local_score(reporter), reporter))
+                    .filter(|(score, _)| *score <= 0)
+                    .min()
+                    .map(|(_, reporter)| reporter.clone());
+                let Some(untrusted) = untrusted else {
+                    return false;
+                };
+                reported.remove(&untrusted);
+            }
+            _ => {}
+        }
+        reported.insert(
+            snapshot.reporter,
+            Report {
+                sent_at_ms: snapshot.sent_at_ms,
+                received_at: Instant::now(),
+                scores: snapshot.scores,
+            },
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_follow_behaviour() {
+        let tracker = ReputationTracker::new(-10);
+        for _ in 0..3 {
+            tracker.record_valid_relay("good");
+        }
+        tracker.record_invalid_relay("bad");
+        tracker.record_slow("bad");
+        tracker.record_disconnect("flaky");
+
+        assert_eq!(tracker.score("good"), 3 * VALID_RELAY_REWARD);
+        assert_eq!(
+            tracker.score("bad"),
+            -INVALID_RELAY_PENALTY - super::super::peers::SLOW_PEER_PENALTY
+        );
+        assert_eq!(tracker.score("flaky"), -DISCONNECT_PENALTY);
+        assert_eq!(tracker.score("unknown"), 0);
+        assert!(!tracker.is_deprioritized("good"));
+        assert!(!tracker.is_deprioritized("flaky"));
+        assert_eq!(tracker.application_score("flaky"), 0.0);
+
+        // Another invalid message takes "bad" below the threshold
+        tracker.record_invalid_relay("bad");
+        assert!(tracker.is_deprioritized("bad"));
+        assert_eq!(tracker.application_score("bad"), -15.0);
+
+        // Scores are bounded either way
+        for _ in 0..1_000 {
+            tracker.record_valid_relay("good");
+            tracker.record_invalid_relay("bad");
+        }
+        assert_eq!(tracker.score("good"), MAX_SCORE);
+        assert_eq!(tracker.score("bad"), -MAX_SCORE);
+    }
+
+    #[test]
+    fn test_reported_scores_aggregate() {
+        let tracker = ReputationTracker::new(-10);
+        let report = |reporter: &str, score, sent_at_ms| ReputationSnapshot {
+            reporter: reporter.to_string(),
+            scores: BTreeMap::from([("target".to_string(), score)]),
+            sent_at_ms,
+        };
+        // Reporters this node knows nothing good about are not listened to
+        assert!(tracker.merge(report("a", -20, 1)));
+        assert_eq!(tracker.score("target"), 0);
+
+        tracker.record_valid_relay("a");
+        tracker.record_valid_relay("b");
+        assert!(tracker.merge(report("b", -40, 1)));
+        assert_eq!(tracker.score("target"), -15);
+        assert!(tracker.is_deprioritized("target"));
+
+        // A newer snapshot replaces the reporter's last one; a stale or
+        // replayed one doesn't
+        assert!(tracker.merge(report("b", 0, 2)));
+        assert_eq!(tracker.score("target"), -5);
+        assert!(!tracker.merge(report("b", -40, 2)));
+        assert!(!tracker.merge(report("b", -40, 1)));
+        assert_eq!(tracker.score("target"), -5);
+
+        // Reporters in bad standing are not listened to
+        tracker.record_invalid_relay("a");
+        assert_eq!(tracker.score("target"), 0);
+    }
+
+    #[test]
+    fn test_scores_decay_and_stay_bounded() {
+        let tracker = ReputationTracker::new(-10);
+        tracker.record_valid_relay("good");
+        tracker.record_valid_relay("good");
+        tracker.record_invalid_relay("bad");
+        tracker.merge(ReputationSnapshot {
+            reporter: "good".to_string(),
+            scores: BTreeMap::from([("target".to_string(), -40)]),
+            sent_at_ms: 1,
+        });
+
+        tracker.decay(Duration::from_secs(60));
+        assert_eq!(tracker.local_score("good"), 1);
+        assert_eq!(tracker.local_score("bad"), -INVALID_RELAY_PENALTY + 1);
+        assert_eq!(tracker.score("target"), -20);
+        tracker.decay(Duration::from_secs(60));
+        assert_eq!(tracker.local_score("good"), 0);
+        assert!(!tracker.local.lock().unwrap().contains_key("good"));
+
+        // Expired reports are forgotten
+        tracker.decay(Duration::ZERO);
+        assert!(tracker.reported.lock().unwrap().is_empty());
+
+        for peer in 0..MAX_TRACKED_PEERS + 10 {
+            tracker.record_disconnect(&peer.to_string());
+        }
+        assert_eq!(tracker.local.lock().unwrap().len(), MAX_TRACKED_PEERS);
+        // The strongest signal survives the churn
+        assert!(tracker.local_score("bad") < 0);
+
+        let oversized = ReputationSnapshot {
+            reporter: "flood".to_string(),
+            scores: (0..=MAX_TRACKED_PEERS)
+                .map(|peer| (peer.to_string(), -1))
+                .collect(),
+            sent_at_ms: 1,
+        };
+        assert!(!tracker.merge(oversized));
+    }
+
+    #[test]
+    fn test_snapshot_signature_round_trip() {
+        let key = Keypair::generate_ed25519();
+        let reporter = PeerId::from(key.public()).to_string();
+        let tracker = ReputationTracker::default();
+        tracker.record_valid_relay("peer-a");
+        tracker.record_invalid_relay("peer-b");
+
+        let signed =
+            SignedReputationSnapshot::sign(tracker.snapshot(&reporter, 1_000), &key).unwrap();
+        let data = serde_json::to_vec(&signed).unwrap();
+        let decoded = SignedReputationSnapshot::decode(&data).unwrap();
+        assert_eq!(decoded, signed);
+        assert_eq!(
+            decoded.snapshot.scores,
+            BTreeMap::from([
+                ("peer-a".to_string(), VALID_RELAY_REWARD),
+                ("peer-b".to_string(), -INVALID_RELAY_PENALTY),
+            ])
+        );
+
+        let mut tampered = signed.clone();
+        tampered.snapshot.scores.insert("peer-b".to_string(), 50);
+        assert!(matches!(
+            tampered.verify(),
+            Err(ReputationError::InvalidSignature)
+        ));
+
+        // Signed, but not by the claimed reporter
+        let mut impersonated = signed;
+        impersonated.snapshot.reporter =
+            PeerId::from(Keypair::generate_ed25519().public()).to_string();
+        assert!(matches!(
+            impersonated.verify(),
+            Err(ReputationError::InvalidSignature)
+        ));
+
+        assert!(matches!(
+            SignedReputationSnapshot::decode(b"{}"),
+            Err(ReputationError::Malformed(_))
+        ));
+    }
+}
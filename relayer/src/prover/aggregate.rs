@@ -0,0 +1,240 @@
+//! Streaming batch aggregation
+//!
+//! A batch is folded into one aggregate proof member by member. Members are
+//! pulled from a stream only while the ones already pulled fit the memory
+//! budget, and each is dropped as soon as its proof has been folded in, so
+//! the working set stays bounded however large the batch is. Proofs are
+//! folded in stream order, so a batch always aggregates to the same proof.
+//! `POST /prove/aggregate/:chain_id` feeds the stream from the request body
+//! as it arrives.
+
+use ethers::utils::keccak256;
+use futures::stream::{FuturesOrdered, Stream, StreamExt};
+use std::mem::size_of;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use super::{
+    generate_dummy_proof, GeneratedProof, ProofRequest, ProverError, ProverService,
+    WithdrawalOutput,
+};
+use crate::error::RelayerError;
+use crate::metrics;
+use crate::relay::validate::CURRENT_CIRCUIT_VERSION;
+
+/// Default bytes of batch members held in memory at once
+pub const DEFAULT_AGGREGATION_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+impl ProofRequest {
+    /// Approximate bytes the request occupies, heap data included
+    pub fn approx_size(&self) -> usize {
+        let heap = match self {
+            ProofRequest::Withdrawal {
+                outputs,
+                merkle_path,
+                merkle_indices,
+                ..
+            } => {
+                outputs
+                    .iter()
+                    .map(|output| size_of::<WithdrawalOutput>() + output.recipient.len())
+                    .sum::<usize>()
+                    + merkle_path.len() * 32
+                    + merkle_indices.len()
+            }
+            ProofRequest::Transfer {
+                merkle_path,
+                merkle_indices,
+                ..
+            } => merkle_path.len() * 32 + merkle_indices.len(),
+            ProofRequest::Consistency {
+                paillier_ciphertext,
+                paillier_randomness,
+                ..
+            } => paillier_ciphertext.len() + paillier_randomness.len(),
+            ProofRequest::Range { .. } => 0,
+        };
+        size_of::<Self>() + heap
+    }
+}
+
+/// An aggregate proof and the memory it took to build
+#[derive(Debug, Clone)]
+pub struct AggregatedProof {
+    /// Public inputs are the member count and a digest of every member proof
+    pub proof: GeneratedProof,
+    pub members: usize,
+    /// Most member bytes held at once
+    pub peak_working_set: usize,
+}
+
+/// Running digest of the member proofs folded so far
+#[derive(Default)]
+struct Accumulator {
+    digest: [u8; 32],
+    members: usize,
+}
+
+impl Accumulator {
+    fn fold(&mut self, member: &GeneratedProof) {
+        let mut data =
+            Vec::with_capacity(32 + member.proof_data.len() + 32 * member.public_inputs.len());
+        data.extend_from_slice(&self.digest);
+        data.extend_from_slice(&member.proof_data);
+        for input in &member.public_inputs {
+            data.extend_from_slice(input);
+        }
+        self.digest = keccak256(data);
+        self.members += 1;
+    }
+}
+
+impl ProverService {
+    /// Prove each member of a batch for `chain_id` and fold the proofs into
+    /// one aggregate proof
+    ///
+    /// Members in flight never total more than the configured
+    /// `aggregation_memory_budget`; the stream isn't polled again until the
+    /// oldest member's proof is folded and its share freed. A member larger
+    /// than the whole budget fails the batch, as does any member failing or
+    /// the stream yielding an error.
+    pub async fn aggregate<S>(
+        &self,
+        members: S,
+        chain_id: u64,
+    ) -> Result<AggregatedProof, RelayerError>
+    where
+        S: Stream<Item = Result<ProofRequest, RelayerError>>,
+    {
+        let budget = self.aggregation_budget;
+        let start = std::time::Instant::now();
+        let mut members = std::pin::pin!(members);
+        let mut in_flight = FuturesOrdered::new();
+        let mut accumulator = Accumulator::default();
+        let mut in_use = 0;
+        let mut peak = 0;
+
+        while let Some(request) = members.next().await {
+            let request = request?;
+            let size = request.approx_size();
+            if size > budget {
+                return Err(ProverError::AggregationBudget { size, budget }.into());
+            }
+            while in_use + size > budget {
+                let Some((proof, freed)) = in_flight.next().await else {
+                    break;
+                };
+                accumulator.fold(&proof?);
+                in_use -= freed;
+            }
+
+            in_use += size;
+            peak = peak.max(in_use);
+            in_flight.push_back(async move {
+                (
+                    self.prove(request, chain_id, false, CancellationToken::new())
+                        .await,
+                    size,
+                )
+            });
+        }
+        while let Some((proof, _)) = in_flight.next().await {
+            accumulator.fold(&proof?);
+        }
+
+        if accumulator.members == 0 {
+            return Err(RelayerError::Validation(anyhow::anyhow!(
+                "Aggregation batch is empty"
+            )));
+        }
+        metrics::PROVER_AGGREGATION_PEAK_BYTES.set(peak as i64);
+        info!(
+            members = accumulator.members,
+            peak_bytes = peak,
+            "Batch aggregated"
+        );
+
+        let inputs = vec![
+            self.encoding(chain_id)
+                .encode_u64(accumulator.members as u64),
+            accumulator.digest,
+        ];
+        let system = self
+            .circuits
+            .proof_system("aggregate", CURRENT_CIRCUIT_VERSION);
+        Ok(AggregatedProof {
+            proof: GeneratedProof {
+                proof_type: "aggregate".to_string(),
+                proof_system: system,
+                proof_data: generate_dummy_proof(&[0u8; 32], &[0u8; 32], &[], &inputs, system),
+                public_inputs: inputs,
+                generation_time_ms: start.elapsed().as_millis() as u64,
+            },
+            members: accumulator.members,
+            peak_working_set: peak,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+    use crate::ProverConfig;
+
+    const BUDGET: usize = 16 * 1024;
+
+    fn member(index: u64) -> ProofRequest {
+        let mut nullifier = [0u8; 32];
+        nullifier[..8].copy_from_slice(&index.to_be_bytes());
+        ProofRequest::Transfer {
+            merkle_root: [1u8; 32],
+            nullifier,
+            new_commitment_a: [3u8; 32],
+            new_commitment_b: [4u8; 32],
+            secret: [5u8; 32],
+            randomness: [6u8; 32],
+            merkle_path: vec![[7u8; 32]; TREE_DEPTH],
+            merkle_indices: vec![0u8; TREE_DEPTH],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_batch_stays_within_budget() {
+        let config = ProverConfig {
+            aggregation_memory_budget: BUDGET,
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+
+        // Members are built lazily, as the aggregator pulls them
+        let members = 500;
+        let batch = || futures::stream::iter(0..members).map(|index| Ok(member(index)));
+        let batch_bytes = member(0).approx_size() * members as usize;
+        assert!(batch_bytes > 20 * BUDGET);
+
+        let aggregated = prover.aggregate(batch(), 1).await.unwrap();
+        assert_eq!(aggregated.members, members as usize);
+        assert!(aggregated.peak_working_set <= BUDGET);
+        assert!(aggregated.peak_working_set > BUDGET / 2);
+        assert!(prover.verify(&aggregated.proof).await.unwrap());
+
+        // Folding is in stream order, so the same batch aggregates identically
+        let again = prover.aggregate(batch(), 1).await.unwrap();
+        assert_eq!(again.proof.public_inputs, aggregated.proof.public_inputs);
+
+        // A member that can't fit at all fails the batch
+        let mut oversized = member(0);
+        if let ProofRequest::Transfer { merkle_path, .. } = &mut oversized {
+            *merkle_path = vec![[7u8; 32]; BUDGET / 32];
+        }
+        let err = prover
+            .aggregate(futures::stream::iter([Ok(member(1)), Ok(oversized)]), 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Prover(ProverError::AggregationBudget { budget: BUDGET, .. })
+        ));
+    }
+}
@@ -0,0 +1,458 @@
+//! Proving with the compiled Noir circuits and barretenberg
+//!
+//! `nargo execute` solves the witness from a `Prover.toml` built out of the
+//! request, then `bb` proves it against the compiled circuit bytecode. Each
+//! job runs in a scratch copy of the circuit package so concurrent jobs never
+//! share files. Only single-output full withdrawals map onto the withdrawal
+//! circuit, and barretenberg only proves UltraHonk; anything else is refused
+//! rather than answered with a placeholder.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::{debug, info};
+
+use super::encoding::InputEncoding;
+use super::scheme::ProofSystem;
+use super::{withdrawal_public_inputs, GeneratedProof, ProofGenerator, ProofRequest};
+use crate::BarretenbergConfig;
+
+/// Fields ahead of the public inputs in a bb Honk proof (circuit size,
+/// public input count and offset)
+const HONK_HEADER_FIELDS: usize = 3;
+
+/// Generator running the real prover toolchain
+pub struct BarretenbergGenerator {
+    /// Noir package of the withdrawal circuit
+    program_dir: PathBuf,
+    /// Compiled withdrawal circuit (ACIR)
+    bytecode_path: PathBuf,
+    /// Verification key matching `bytecode_path`
+    vk_path: PathBuf,
+    nargo: PathBuf,
+    bb: PathBuf,
+}
+
+impl BarretenbergGenerator {
+    pub fn new(config: &BarretenbergConfig) -> Result<Self> {
+        for path in [&config.bytecode_path, &config.vk_path] {
+            if !path.is_file() {
+                bail!("Circuit artifact {:?} not found", path);
+            }
+        }
+        if !config.program_dir.join("Nargo.toml").is_file() {
+            bail!("No Noir package at {:?}", config.program_dir);
+        }
+        Ok(Self {
+            program_dir: config.program_dir.clone(),
+            bytecode_path: config.bytecode_path.clone(),
+            vk_path: config.vk_path.clone(),
+            nargo: config.nargo_path.clone(),
+            bb: config.bb_path.clone(),
+        })
+    }
+
+    /// Check a proof with `bb` against the configured verification key
+    pub async fn verify(&self, proof: &GeneratedProof) -> Result<bool> {
+        self.verify_at(proof, &self.vk_path).await
+    }
+
+    /// Check a proof with `bb` against verification key bytes
+    pub async fn verify_with_key(&self, proof: &GeneratedProof, vk: &[u8]) -> Result<bool> {
+        let scratch = tempfile::tempdir()?;
+        let vk_path = scratch.path().join("vk");
+        tokio::fs::write(&vk_path, vk).await?;
+        self.verify_at(proof, &vk_path).await
+    }
+
+    async fn verify_at(&self, proof: &GeneratedProof, vk_path: &Path) -> Result<bool> {
+        let scratch = tempfile::tempdir()?;
+        let proof_path = scratch.path().join("proof");
+        tokio::fs::write(
+            &proof_path,
+            join_public_inputs(&proof.proof_data, &proof.public_inputs)?,
+        )
+        .await?;
+        let status = Command::new(&self.bb)
+            .arg("verify_ultra_keccak_honk")
+            .arg("-k")
+            .arg(vk_path)
+            .arg("-p")
+            .arg(&proof_path)
+            .output()
+            .await
+            .context("Failed to run bb")?
+            .status;
+        Ok(status.success())
+    }
+
+    /// Run a toolchain command, failing with its stderr
+    ///
+    /// The process is killed if the job is dropped on timeout or
+    /// cancellation.
+    async fn run(&self, command: &mut Command) -> Result<()> {
+        debug!(command = ?command, "Running prover toolchain");
+        let output = command
+            .kill_on_drop(true)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {:?}", command.as_std().get_program()))?;
+        if !output.status.success() {
+            bail!(
+                "{:?} failed: {}",
+                command.as_std().get_program(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ProofGenerator for BarretenbergGenerator {
+    fn name(&self) -> &'static str {
+        "barretenberg"
+    }
+
+    async fn generate(
+        &self,
+        request: ProofRequest,
+        encoding: &InputEncoding,
+        system: ProofSystem,
+    ) -> Result<GeneratedProof> {
+        if system != ProofSystem::UltraHonk {
+            bail!(
+                "Barretenberg proves ultra_honk, but the circuit is deployed as {}",
+                system.name()
+            );
+        }
+        let start = std::time::Instant::now();
+        let (prover_toml, public_inputs) = withdrawal_witness(&request, encoding)?;
+        info!("Generating withdrawal proof with barretenberg");
+
+        let scratch = tempfile::tempdir()?;
+        let (program_dir, package_dir) = (self.program_dir.clone(), scratch.path().to_owned());
+        tokio::task::spawn_blocking(move || copy_package(&program_dir, &package_dir)).await??;
+        tokio::fs::write(scratch.path().join("Prover.toml"), prover_toml).await?;
+
+        self.run(
+            Command::new(&self.nargo)
+                .args(["execute", "witness", "--program-dir"])
+                .arg(scratch.path()),
+        )
+        .await?;
+        let proof_path = scratch.path().join("proof");
+        self.run(
+            Command::new(&self.bb)
+                .arg("prove_ultra_keccak_honk")
+                .arg("-b")
+                .arg(&self.bytecode_path)
+                .arg("-w")
+                .arg(scratch.path().join("target").join("witness.gz"))
+                .arg("-o")
+                .arg(&proof_path),
+        )
+        .await?;
+
+        let proof_data = split_public_inputs(&tokio::fs::read(&proof_path).await?, &public_inputs)?;
+        if proof_data.len() != system.proof_len() {
+            bail!(
+                "bb produced a {}-byte proof, expected {}",
+                proof_data.len(),
+                system.proof_len()
+            );
+        }
+
+        Ok(GeneratedProof {
+            proof_type: "withdrawal".to_string(),
+            proof_system: system,
+            proof_data,
+            public_inputs,
+            generation_time_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+/// `Prover.toml` for the withdrawal circuit, and its public inputs in
+/// circuit order
+fn withdrawal_witness(
+    request: &ProofRequest,
+    encoding: &InputEncoding,
+) -> Result<(String, Vec<[u8; 32]>)> {
+    let ProofRequest::Withdrawal {
+        merkle_root,
+        nullifier,
+        outputs,
+        amount,
+        fee,
+        change_commitment,
+        secret,
+        randomness,
+        merkle_path,
+        merkle_indices,
+        ..
+    } = request
+    else {
+        bail!(
+            "No barretenberg circuit for {} proofs",
+            request.proof_type()
+        );
+    };
+    if outputs.len() != 1 || change_commitment.is_some() {
+        bail!("The withdrawal circuit proves single-output full withdrawals only");
+    }
+
+    let inputs = withdrawal_public_inputs(
+        *merkle_root,
+        *nullifier,
+        outputs,
+        *amount,
+        *fee,
+        *change_commitment,
+        encoding,
+    )?;
+    let [root, nullifier, recipient, amount] = &inputs[..] else {
+        bail!("Recipient does not fit one field element");
+    };
+
+    let field = |value: &[u8; 32]| format!("\"0x{}\"", hex::encode(value));
+    let list = |items: Vec<String>| format!("[{}]", items.join(", "));
+    let toml = [
+        ("merkle_root", field(root)),
+        ("nullifier", field(nullifier)),
+        ("recipient", field(recipient)),
+        ("amount", field(amount)),
+        ("secret", field(secret)),
+        ("commitment_randomness", field(randomness)),
+        ("merkle_path", list(merkle_path.iter().map(field).collect())),
+        (
+            "merkle_indices",
+            list(merkle_indices.iter().map(u8::to_string).collect()),
+        ),
+    ]
+    .iter()
+    .map(|(name, value)| format!("{} = {}\n", name, value))
+    .collect();
+
+    Ok((toml, inputs))
+}
+
+/// Copy a Noir package's manifest and sources into `dest`
+fn copy_package(src: &Path, dest: &Path) -> Result<()> {
+    std::fs::copy(src.join("Nargo.toml"), dest.join("Nargo.toml"))?;
+    copy_dir(&src.join("src"), &dest.join("src"))
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Take the public inputs out of a bb proof file, checking they are the ones
+/// we expect
+///
+/// bb writes the proof as a big-endian field count followed by the fields,
+/// with the public inputs after the header; on-chain they're passed apart.
+fn split_public_inputs(file: &[u8], expected: &[[u8; 32]]) -> Result<Vec<u8>> {
+    let fields = file.get(4..).unwrap_or_default();
+    let start = HONK_HEADER_FIELDS * 32;
+    let end = start + expected.len() * 32;
+    if fields.len() < end || fields.len() % 32 != 0 {
+        bail!("Malformed bb proof of {} bytes", file.len());
+    }
+    if fields[start..end] != expected.concat()[..] {
+        bail!("bb proof commits to different public inputs");
+    }
+    Ok([&fields[..start], &fields[end..]].concat())
+}
+
+/// Inverse of `split_public_inputs`
+fn join_public_inputs(proof: &[u8], public_inputs: &[[u8; 32]]) -> Result<Vec<u8>> {
+    let start = HONK_HEADER_FIELDS * 32;
+    if proof.len() < start {
+        bail!("Proof too short for a Honk header");
+    }
+    let fields = (proof.len() / 32 + public_inputs.len()) as u32;
+    Ok([
+        &fields.to_be_bytes()[..],
+        &proof[..start],
+        &public_inputs.concat(),
+        &proof[start..],
+    ]
+    .concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::WithdrawalOutput;
+
+    fn withdrawal(recipient: [u8; 20], outputs: usize) -> ProofRequest {
+        ProofRequest::Withdrawal {
+            merkle_root: [1; 32],
+            nullifier: [2; 32],
+            outputs: vec![
+                WithdrawalOutput {
+                    recipient: recipient.to_vec(),
+                    amount: 900,
+                };
+                outputs
+            ],
+            amount: 1_000,
+            fee: 100,
+            note_value: 1_000,
+            change_commitment: None,
+            change_value: 0,
+            secret: [3; 32],
+            randomness: [4; 32],
+            merkle_path: vec![[5; 32]; 20],
+            merkle_indices: vec![0; 20],
+        }
+    }
+
+    #[test]
+    fn test_witness_matches_circuit_abi() {
+        let request = withdrawal([0xaa; 20], 1);
+        let (toml, inputs) = withdrawal_witness(&request, &InputEncoding::default()).unwrap();
+
+        assert_eq!(inputs.len(), 4);
+        assert!(toml.contains(&format!("merkle_root = \"0x{}\"", "01".repeat(32))));
+        assert!(toml.contains(&format!(
+            "recipient = \"0x{}{}\"",
+            "00".repeat(12),
+            "aa".repeat(20)
+        )));
+        assert!(toml.contains(&format!("amount = \"0x{:064x}\"", 1_000)));
+        assert!(toml.contains("merkle_indices = [0, 0, 0"));
+
+        // Multi-output withdrawals have no circuit yet
+        assert!(withdrawal_witness(&withdrawal([0xaa; 20], 2), &InputEncoding::default()).is_err());
+    }
+
+    #[test]
+    fn test_public_inputs_split_and_rejoined() {
+        let inputs = vec![[7u8; 32], [8u8; 32]];
+        let proof: Vec<u8> = (0..10 * 32).map(|i| i as u8).collect();
+        let file = join_public_inputs(&proof, &inputs).unwrap();
+        assert_eq!(&file[..4], &12u32.to_be_bytes());
+        assert_eq!(split_public_inputs(&file, &inputs).unwrap(), proof);
+        assert!(split_public_inputs(&file, &[[9u8; 32], [8u8; 32]]).is_err());
+    }
+
+    /// Proves and verifies a withdrawal with the real toolchain; run with
+    /// `cargo test -- --ignored` where `nargo` and `bb` are installed
+    #[tokio::test]
+    #[ignore = "needs nargo and bb"]
+    async fn test_withdrawal_proof_end_to_end() {
+        // The artifacts `config/relayer.toml` points at
+        let circuits = Path::new(env!("CARGO_MANIFEST_DIR")).join("../circuits");
+        let generator = BarretenbergGenerator::new(&BarretenbergConfig {
+            program_dir: circuits.join("withdrawal"),
+            bytecode_path: circuits.join("target/withdrawal.json"),
+            vk_path: circuits.join("target/vk"),
+            nargo_path: "nargo".into(),
+            bb_path: "bb".into(),
+        })
+        .unwrap();
+
+        // Derive a consistent root and nullifier with the circuit's own hashes
+        let (root, nullifier) = note_fixture(&generator, [3; 32], [4; 32], 1_000).await;
+        let mut request = withdrawal([0xaa; 20], 1);
+        if let ProofRequest::Withdrawal {
+            merkle_root,
+            nullifier: request_nullifier,
+            merkle_path,
+            ..
+        } = &mut request
+        {
+            *merkle_root = root;
+            *request_nullifier = nullifier;
+            *merkle_path = vec![[0; 32]; 20];
+        }
+
+        let proof = generator
+            .generate(request, &InputEncoding::default(), ProofSystem::UltraHonk)
+            .await
+            .unwrap();
+        assert_eq!(proof.public_inputs[0], root);
+        assert_eq!(proof.public_inputs[1], nullifier);
+        assert!(generator.verify(&proof).await.unwrap());
+
+        let mut forged = proof;
+        forged.public_inputs[3] = [0xff; 32];
+        assert!(!generator.verify(&forged).await.unwrap());
+    }
+
+    /// Root (over an all-zero path at leaf 0) and nullifier of a note,
+    /// computed by a throwaway Noir program
+    async fn note_fixture(
+        generator: &BarretenbergGenerator,
+        secret: [u8; 32],
+        randomness: [u8; 32],
+        amount: u64,
+    ) -> ([u8; 32], [u8; 32]) {
+        let scratch = tempfile::tempdir().unwrap();
+        tokio::fs::write(
+            scratch.path().join("Nargo.toml"),
+            "[package]\nname = \"note\"\ntype = \"bin\"\n\n[dependencies]\n",
+        )
+        .await
+        .unwrap();
+        tokio::fs::create_dir(scratch.path().join("src"))
+            .await
+            .unwrap();
+        tokio::fs::write(
+            scratch.path().join("src/main.nr"),
+            "use std::hash::pedersen_hash;\n\
+             fn main(secret: Field, randomness: Field, amount: Field) -> pub [Field; 2] {\n\
+                 let leaf = pedersen_hash([pedersen_hash([amount, randomness]), secret]);\n\
+                 let mut root = leaf;\n\
+                 for _ in 0..20 { root = pedersen_hash([root, 0]); }\n\
+                 [root, pedersen_hash([secret, 0])]\n\
+             }\n",
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(
+            scratch.path().join("Prover.toml"),
+            format!(
+                "secret = \"0x{}\"\nrandomness = \"0x{}\"\namount = \"{}\"\n",
+                hex::encode(secret),
+                hex::encode(randomness),
+                amount
+            ),
+        )
+        .await
+        .unwrap();
+
+        let output = Command::new(&generator.nargo)
+            .args(["execute", "--program-dir"])
+            .arg(scratch.path())
+            .output()
+            .await
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let fields: Vec<[u8; 32]> = stdout
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter_map(|token| token.strip_prefix("0x"))
+            .map(|digits| {
+                let mut field = [0u8; 32];
+                let bytes = hex::decode(format!("{:0>64}", digits)).unwrap();
+                field.copy_from_slice(&bytes);
+                field
+            })
+            .collect();
+        assert_eq!(fields.len(), 2, "unexpected nargo output: {}", stdout);
+        (fields[0], fields[1])
+    }
+}
@@ -0,0 +1,178 @@
+//! Cache of generated proofs
+//!
+//! Identical requests (client retries, the same withdrawal gossiped by
+//! several peers) reuse an earlier proof instead of proving again. Entries
+//! are keyed by a hash of the canonical request and encoding, so a hit is
+//! exact without the cache holding on to request secrets.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use super::encoding::InputEncoding;
+use super::{GeneratedProof, ProofRequest};
+use crate::metrics;
+
+/// blake3 of a request and its input encoding
+pub type CacheKey = [u8; 32];
+
+/// Content address of a request proved with `encoding`
+pub fn cache_key(request: &ProofRequest, encoding: &InputEncoding) -> CacheKey {
+    let canonical = serde_json::to_vec(&(request, encoding)).expect("proof requests serialize");
+    *blake3::hash(&canonical).as_bytes()
+}
+
+/// Bounded least-recently-used proof cache
+pub struct ProofCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<CacheKey, GeneratedProof>,
+    /// Keys from least to most recently used
+    order: VecDeque<CacheKey>,
+}
+
+impl ProofCache {
+    /// Cache holding up to `capacity` proofs (zero disables caching)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// Cached proof for a request, recording the hit or miss
+    ///
+    /// A hit reports a `generation_time_ms` of 0, as nothing was proved.
+    pub fn get(&self, key: &CacheKey) -> Option<GeneratedProof> {
+        if self.capacity == 0 {
+            return None;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let proof = inner.entries.get(key).map(|proof| GeneratedProof {
+            generation_time_ms: 0,
+            ..proof.clone()
+        });
+        if proof.is_some() {
+            inner.touch(key);
+            metrics::PROOF_CACHE_HITS.inc();
+        } else {
+            metrics::PROOF_CACHE_MISSES.inc();
+        }
+        update_hit_rate();
+        proof
+    }
+
+    /// Store a freshly generated proof, evicting the least recently used
+    pub fn insert(&self, key: CacheKey, proof: GeneratedProof) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.entries.insert(key, proof).is_some() {
+            inner.touch(&key);
+        } else {
+            inner.order.push_back(key);
+        }
+
+        while inner.entries.len() > self.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+            metrics::PROOF_CACHE_EVICTIONS.inc();
+        }
+        metrics::PROOF_CACHE_SIZE.set(inner.entries.len() as i64);
+    }
+
+    /// Number of cached proofs
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheInner {
+    /// Mark a key as most recently used
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(position).expect("position is in range");
+            self.order.push_back(key);
+        }
+    }
+}
+
+fn update_hit_rate() {
+    let hits = metrics::PROOF_CACHE_HITS.get() as f64;
+    let lookups = hits + metrics::PROOF_CACHE_MISSES.get() as f64;
+    if lookups > 0.0 {
+        metrics::PROOF_CACHE_HIT_RATE.set(hits / lookups);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::ProofSystem;
+
+    fn request(value: u64) -> CacheKey {
+        let request = ProofRequest::Range {
+            commitment: [0u8; 32],
+            min_value: 0,
+            value,
+            randomness: [0u8; 32],
+        };
+        cache_key(&request, &InputEncoding::default())
+    }
+
+    fn proof(tag: u8) -> GeneratedProof {
+        GeneratedProof {
+            proof_type: "range".to_string(),
+            proof_system: ProofSystem::Groth16,
+            proof_data: vec![tag],
+            public_inputs: vec![],
+            generation_time_ms: 40,
+        }
+    }
+
+    #[test]
+    fn test_hit_and_miss_counted() {
+        let cache = ProofCache::new(4);
+
+        let misses = metrics::PROOF_CACHE_MISSES.get();
+        assert!(cache.get(&request(1)).is_none());
+        assert!(metrics::PROOF_CACHE_MISSES.get() > misses);
+
+        cache.insert(request(1), proof(1));
+        let hits = metrics::PROOF_CACHE_HITS.get();
+        let hit = cache.get(&request(1)).unwrap();
+        assert_eq!(hit.proof_data, vec![1]);
+        assert_eq!(hit.generation_time_ms, 0);
+        assert!(metrics::PROOF_CACHE_HITS.get() > hits);
+        assert!(metrics::PROOF_CACHE_HIT_RATE.get() > 0.0);
+    }
+
+    #[test]
+    fn test_least_recently_used_evicted() {
+        let cache = ProofCache::new(2);
+        let evictions = metrics::PROOF_CACHE_EVICTIONS.get();
+
+        cache.insert(request(1), proof(1));
+        cache.insert(request(2), proof(2));
+        // Using 1 makes 2 the eviction candidate
+        cache.get(&request(1)).unwrap();
+        cache.insert(request(3), proof(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&request(1)).is_some());
+        assert!(cache.get(&request(2)).is_none());
+        assert!(metrics::PROOF_CACHE_EVICTIONS.get() > evictions);
+    }
+}
@@ -0,0 +1,108 @@
+//! Circuit artifact registry
+//!
+//! Holds the verification keys (and optionally bytecode) of the circuits the
+//! relayer proves against, so clients proving locally can fetch byte-identical
+//! artifacts and check them against published hashes. Each circuit also names
+//! the proof system its verifier was deployed with.
+
+use anyhow::{Context, Result};
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::scheme::ProofSystem;
+use crate::CircuitConfig;
+
+/// Largest bytecode artifact kept in memory and served over HTTP
+pub const MAX_SERVED_ARTIFACT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// A loaded circuit and its integrity hashes
+#[derive(Debug)]
+pub struct CircuitArtifact {
+    pub proof_type: String,
+    pub version: String,
+    /// Proof system the circuit is proved and verified in
+    pub proof_system: ProofSystem,
+    /// Verification key bytes
+    pub vk: Vec<u8>,
+    /// keccak256 of `vk`
+    pub vk_hash: H256,
+    /// keccak256 of the circuit bytecode, if configured
+    pub bytecode_hash: Option<H256>,
+    /// Size of the circuit bytecode in bytes
+    pub bytecode_size: Option<u64>,
+    /// Bytecode, retained only when within `MAX_SERVED_ARTIFACT_BYTES`
+    pub bytecode: Option<Vec<u8>>,
+}
+
+/// Circuits loaded at startup, keyed by proof type and version
+#[derive(Debug, Default)]
+pub struct CircuitRegistry {
+    artifacts: HashMap<(String, String), Arc<CircuitArtifact>>,
+}
+
+impl CircuitRegistry {
+    /// Load every configured circuit from disk
+    pub fn load(configs: &[CircuitConfig]) -> Result<Self> {
+        let mut artifacts = HashMap::new();
+
+        for config in configs {
+            let vk = std::fs::read(&config.vk_path)
+                .with_context(|| format!("Reading verification key {:?}", config.vk_path))?;
+
+            let (bytecode_hash, bytecode_size, bytecode) = match &config.bytecode_path {
+                Some(path) => {
+                    let bytes = std::fs::read(path)
+                        .with_context(|| format!("Reading circuit bytecode {:?}", path))?;
+                    let size = bytes.len() as u64;
+                    let hash = H256(keccak256(&bytes));
+                    let retained = (size <= MAX_SERVED_ARTIFACT_BYTES).then_some(bytes);
+                    (Some(hash), Some(size), retained)
+                }
+                None => (None, None, None),
+            };
+
+            let artifact = CircuitArtifact {
+                proof_type: config.proof_type.clone(),
+                version: config.version.clone(),
+                proof_system: config.proof_system,
+                vk_hash: H256(keccak256(&vk)),
+                vk,
+                bytecode_hash,
+                bytecode_size,
+                bytecode,
+            };
+            artifacts.insert(
+                (config.proof_type.clone(), config.version.clone()),
+                Arc::new(artifact),
+            );
+        }
+
+        Ok(Self { artifacts })
+    }
+
+    /// Loaded circuits as sorted `proof_type/version` identifiers
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .artifacts
+            .keys()
+            .map(|(proof_type, version)| format!("{}/{}", proof_type, version))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Proof system of a circuit; Groth16 for circuits not loaded
+    pub fn proof_system(&self, proof_type: &str, version: &str) -> ProofSystem {
+        self.get(proof_type, version)
+            .map_or(ProofSystem::default(), |circuit| circuit.proof_system)
+    }
+
+    /// Look up a circuit by proof type and version
+    pub fn get(&self, proof_type: &str, version: &str) -> Option<Arc<CircuitArtifact>> {
+        self.artifacts
+            .get(&(proof_type.to_string(), version.to_string()))
+            .cloned()
+    }
+}
@@ -0,0 +1,186 @@
+//! Commitment scheme parameters for consistency proofs
+//!
+//! A consistency proof shows that a Pedersen commitment `g^v · h^r` and a
+//! Paillier ciphertext under public key `n` hide the same value. The proof is
+//! only meaningful against the exact generators and key the circuit was
+//! built for, so they are configured once, validated at startup, and every
+//! consistency request is checked against them before proving.
+
+use anyhow::{Context, Result};
+
+use super::{ProofRequest, ProverError};
+use crate::ConsistencyConfig;
+
+/// Smallest Paillier modulus accepted
+pub const MIN_PAILLIER_MODULUS_BITS: usize = 2048;
+
+/// Pedersen generator points, as 32-byte compressed encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PedersenGenerators {
+    /// Value generator
+    pub g: [u8; 32],
+    /// Blinding generator
+    pub h: [u8; 32],
+}
+
+/// Paillier public key (generator fixed at `n + 1`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaillierPublicKey {
+    /// Modulus, big-endian without leading zero bytes
+    pub n: Vec<u8>,
+}
+
+impl PaillierPublicKey {
+    /// Size of a ciphertext, which lives modulo `n²`
+    pub fn ciphertext_len(&self) -> usize {
+        2 * self.n.len()
+    }
+
+    fn modulus_bits(&self) -> usize {
+        self.n
+            .first()
+            .map_or(0, |top| self.n.len() * 8 - top.leading_zeros() as usize)
+    }
+}
+
+/// Parameters the consistency circuit is built for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentParams {
+    pub generators: PedersenGenerators,
+    pub paillier: PaillierPublicKey,
+}
+
+impl CommitmentParams {
+    /// Parse and validate configured parameters
+    pub fn from_config(config: &ConsistencyConfig) -> Result<Self> {
+        let params = Self {
+            generators: PedersenGenerators {
+                g: parse_point(&config.pedersen_g).context("Invalid pedersen_g")?,
+                h: parse_point(&config.pedersen_h).context("Invalid pedersen_h")?,
+            },
+            paillier: PaillierPublicKey {
+                n: parse_hex(&config.paillier_n)
+                    .context("Invalid paillier_n")?
+                    .into_iter()
+                    .skip_while(|byte| *byte == 0)
+                    .collect(),
+            },
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Reject parameters no sound circuit could use
+    pub fn validate(&self) -> Result<()> {
+        let PedersenGenerators { g, h } = &self.generators;
+        if *g == [0u8; 32] || *h == [0u8; 32] {
+            return Err(anyhow::anyhow!("Pedersen generators must be non-zero"));
+        }
+        if g == h {
+            return Err(anyhow::anyhow!("Pedersen generators g and h must differ"));
+        }
+
+        let bits = self.paillier.modulus_bits();
+        if bits < MIN_PAILLIER_MODULUS_BITS {
+            return Err(anyhow::anyhow!(
+                "Paillier modulus is {} bits, at least {} required",
+                bits,
+                MIN_PAILLIER_MODULUS_BITS
+            ));
+        }
+        if self.paillier.n.last().is_some_and(|low| low % 2 == 0) {
+            return Err(anyhow::anyhow!("Paillier modulus must be odd"));
+        }
+        Ok(())
+    }
+
+    /// Check a consistency request was built for these parameters
+    pub fn check_request(&self, request: &ProofRequest) -> Result<(), ProverError> {
+        let ProofRequest::Consistency {
+            generators,
+            paillier_ciphertext,
+            paillier_randomness,
+            ..
+        } = request
+        else {
+            return Ok(());
+        };
+
+        if *generators != self.generators {
+            return Err(ProverError::GeneratorMismatch);
+        }
+        let expected = self.paillier.ciphertext_len();
+        if paillier_ciphertext.len() != expected {
+            return Err(ProverError::CiphertextLength {
+                expected,
+                got: paillier_ciphertext.len(),
+            });
+        }
+        if paillier_randomness.len() > self.paillier.n.len() {
+            return Err(ProverError::CiphertextLength {
+                expected: self.paillier.n.len(),
+                got: paillier_randomness.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(value.trim_start_matches("0x"))?)
+}
+
+fn parse_point(value: &str) -> Result<[u8; 32]> {
+    let bytes = parse_hex(value)?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("Expected 32 bytes, got {}", bytes.len()))
+}
+
+/// Well-formed parameters with a 2048-bit modulus, for tests across the crate
+#[cfg(test)]
+pub(crate) fn test_config() -> ConsistencyConfig {
+    ConsistencyConfig {
+        pedersen_g: format!("0x{}", hex::encode([0x11u8; 32])),
+        pedersen_h: format!("0x{}", hex::encode([0x22u8; 32])),
+        paillier_n: hex::encode([0xc5u8; 256]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_parameters_rejected() {
+        assert!(CommitmentParams::from_config(&test_config()).is_ok());
+
+        let same_generators = ConsistencyConfig {
+            pedersen_h: test_config().pedersen_g,
+            ..test_config()
+        };
+        assert!(CommitmentParams::from_config(&same_generators).is_err());
+
+        let short_point = ConsistencyConfig {
+            pedersen_g: "0x1234".to_string(),
+            ..test_config()
+        };
+        assert!(CommitmentParams::from_config(&short_point).is_err());
+
+        // Leading zero bytes don't count towards the modulus size
+        let mut padded = vec![0u8; 4];
+        padded.extend([0x45u8; 255]);
+        let small_modulus = ConsistencyConfig {
+            paillier_n: hex::encode(padded),
+            ..test_config()
+        };
+        let err = CommitmentParams::from_config(&small_modulus).unwrap_err();
+        assert!(err.to_string().contains("2039 bits"));
+
+        let even_modulus = ConsistencyConfig {
+            paillier_n: hex::encode([0xc4u8; 256]),
+            ..test_config()
+        };
+        assert!(CommitmentParams::from_config(&even_modulus).is_err());
+    }
+}
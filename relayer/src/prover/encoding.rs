@@ -0,0 +1,172 @@
+//! Public-input encoding
+//!
+//! How recipients and amounts are laid out as 32-byte field elements. The
+//! default is the EVM layout (20-byte addresses, big-endian, one element per
+//! value); pools on other chains can use different address widths,
+//! little-endian elements, or split wide addresses across two elements.
+
+use anyhow::Result;
+
+/// Byte order of integers within a field element
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    /// Value right-aligned, most significant byte first
+    #[default]
+    Big,
+    /// Value left-aligned, least significant byte first
+    Little,
+}
+
+/// How a recipient address is packed into field elements
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressPacking {
+    /// Whole address in one element (at most 31 bytes, so it fits the field)
+    #[default]
+    Single,
+    /// Address split into high and low 16-byte limbs, one element each
+    Split,
+}
+
+/// Layout of recipients and amounts in the public inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InputEncoding {
+    /// Recipient address length in bytes
+    pub address_bytes: usize,
+    pub endianness: Endianness,
+    pub address_packing: AddressPacking,
+}
+
+impl Default for InputEncoding {
+    /// EVM layout: 20-byte addresses and big-endian amounts, one element each
+    fn default() -> Self {
+        Self {
+            address_bytes: 20,
+            endianness: Endianness::Big,
+            address_packing: AddressPacking::Single,
+        }
+    }
+}
+
+impl InputEncoding {
+    /// Reject layouts that can't represent an address
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=32).contains(&self.address_bytes) {
+            return Err(anyhow::anyhow!(
+                "Address width {} must be between 1 and 32 bytes",
+                self.address_bytes
+            ));
+        }
+        if self.address_packing == AddressPacking::Single && self.address_bytes > 31 {
+            return Err(anyhow::anyhow!(
+                "{}-byte addresses don't fit one field element; use split packing",
+                self.address_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check a recipient has the configured width
+    pub fn check_address(&self, address: &[u8]) -> Result<()> {
+        if address.len() != self.address_bytes {
+            return Err(anyhow::anyhow!(
+                "Recipient 0x{} is {} bytes, expected {}",
+                hex::encode(address),
+                address.len(),
+                self.address_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode a recipient as one or two field elements
+    pub fn encode_address(&self, address: &[u8]) -> Result<Vec<[u8; 32]>> {
+        self.check_address(address)?;
+
+        Ok(match self.address_packing {
+            AddressPacking::Single => vec![self.place(address)],
+            AddressPacking::Split => {
+                let mut padded = [0u8; 32];
+                padded[32 - address.len()..].copy_from_slice(address);
+                vec![self.place(&padded[..16]), self.place(&padded[16..])]
+            }
+        })
+    }
+
+    /// Encode an amount as a field element
+    pub fn encode_u64(&self, value: u64) -> [u8; 32] {
+        self.place(&value.to_be_bytes())
+    }
+
+    /// Place big-endian `bytes` into an element in the configured byte order
+    fn place(&self, bytes: &[u8]) -> [u8; 32] {
+        let mut element = [0u8; 32];
+        match self.endianness {
+            Endianness::Big => element[32 - bytes.len()..].copy_from_slice(bytes),
+            Endianness::Little => {
+                for (slot, byte) in element.iter_mut().zip(bytes.iter().rev()) {
+                    *slot = *byte;
+                }
+            }
+        }
+        element
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evm_encoding() {
+        let evm = InputEncoding::default();
+        assert!(evm.validate().is_ok());
+
+        let encoded = evm.encode_address(&[3u8; 20]).unwrap();
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0][..12], [0u8; 12]);
+        assert_eq!(encoded[0][12..], [3u8; 20]);
+        assert_eq!(evm.encode_u64(500)[24..], 500u64.to_be_bytes());
+        assert_eq!(evm.encode_u64(500)[..24], [0u8; 24]);
+
+        // A 32-byte recipient is not an EVM address
+        assert!(evm.encode_address(&[3u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_wide_little_endian_encoding() {
+        let wide = InputEncoding {
+            address_bytes: 32,
+            endianness: Endianness::Little,
+            address_packing: AddressPacking::Split,
+        };
+        assert!(wide.validate().is_ok());
+
+        let mut address = [0u8; 32];
+        address[0] = 0xaa; // most significant byte of the high limb
+        address[31] = 0x01; // least significant byte of the low limb
+        let encoded = wide.encode_address(&address).unwrap();
+        assert_eq!(encoded.len(), 2);
+        assert_eq!(encoded[0][15], 0xaa);
+        assert_eq!(encoded[0][16..], [0u8; 16]);
+        assert_eq!(encoded[1][0], 0x01);
+        assert_eq!(encoded[1][16..], [0u8; 16]);
+
+        assert_eq!(wide.encode_u64(500)[..8], 500u64.to_le_bytes());
+        assert_eq!(wide.encode_u64(500)[8..], [0u8; 24]);
+        assert!(wide.encode_address(&[3u8; 20]).is_err());
+
+        // 32 bytes can't share a single field element
+        let single = InputEncoding {
+            address_packing: AddressPacking::Single,
+            ..wide
+        };
+        assert!(single.validate().is_err());
+    }
+}
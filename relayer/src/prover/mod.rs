@@ -1,24 +1,177 @@
 //! ZK Prover Service
 //!
-//! Generates ZK proofs for withdrawal and transfer operations.
-//! Can offload proving to specialized hardware or external services.
+//! Generates ZK proofs for withdrawal and transfer operations, locally or
+//! by offloading them to an external HTTP prover.
+
+pub mod aggregate;
+pub mod barretenberg;
+pub mod cache;
+pub mod circuits;
+pub mod consistency;
+pub mod encoding;
+pub mod remote;
+pub mod scheme;
+pub mod stats;
+pub mod verifier;
+pub mod wire;
 
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::{mpsc, Semaphore};
-use tracing::{debug, info};
+use async_trait::async_trait;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
 
+use crate::error::RelayerError;
+use crate::metrics;
+use crate::relay::validate::CURRENT_CIRCUIT_VERSION;
 use crate::ProverConfig;
 
+use cache::ProofCache;
+use circuits::CircuitRegistry;
+use consistency::{CommitmentParams, PedersenGenerators};
+use encoding::InputEncoding;
+use stats::{LatencyTracker, ProverStats};
+use verifier::{PlaceholderVerifier, ProofVerifier};
+
+pub use scheme::ProofSystem;
+pub use wire::{
+    compress_proof, decompress_proof, deserialize_public_inputs, serialize_public_inputs, WireError,
+};
+
+/// Errors specific to the prover service
+#[derive(Debug, thiserror::Error)]
+pub enum ProverError {
+    /// The request queue was full and the configured policy rejected a request
+    #[error("Proof request queue is full")]
+    QueueFull,
+    /// Proving didn't finish within `timeout_secs`
+    #[error("{proof_type} proof generation timed out after {timeout_secs}s")]
+    Timeout {
+        proof_type: &'static str,
+        timeout_secs: u64,
+    },
+    /// The proving backend returned an error
+    #[error("{proof_type} proof generation failed: {reason}")]
+    Failed {
+        proof_type: &'static str,
+        reason: String,
+    },
+    /// The service is draining for shutdown and takes no new requests
+    #[error("Prover is shutting down")]
+    ShuttingDown,
+    /// The caller cancelled the request before its proof was ready
+    #[error("Proof request was cancelled")]
+    Cancelled,
+    /// The worker went away without answering, e.g. it died mid-proof
+    #[error("Prover stopped before answering the request")]
+    Dropped,
+    /// The verification backend couldn't check a proof either way
+    #[error("Could not verify {proof_type} proof: {reason}")]
+    Verification { proof_type: String, reason: String },
+    /// The Merkle path doesn't match the pool's tree depth
+    #[error(
+        "Merkle path has {path_len} siblings and {indices_len} indices, but the pool tree depth is {tree_depth}"
+    )]
+    MerklePathLength {
+        tree_depth: usize,
+        path_len: usize,
+        indices_len: usize,
+    },
+    /// A Merkle path index that isn't a left/right bit
+    #[error("Merkle path index {index} at level {level} is not 0 or 1")]
+    MerkleIndex { level: usize, index: u8 },
+    /// A consistency request built for other Pedersen generators
+    #[error("Consistency proof generators don't match the configured Pedersen parameters")]
+    GeneratorMismatch,
+    /// A Paillier value doesn't fit the configured public key
+    #[error("Paillier value is {got} bytes, expected {expected} for the configured key")]
+    CiphertextLength { expected: usize, got: usize },
+    /// A batch member too large to ever fit the aggregation memory budget
+    #[error("Batch member needs {size} bytes, over the {budget}-byte aggregation budget")]
+    AggregationBudget { size: usize, budget: usize },
+}
+
+/// What to do with a new request when the queue is at capacity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueFullPolicy {
+    /// Wait for space (callers are backpressured)
+    #[default]
+    Block,
+    /// Reject the incoming request
+    RejectNewest,
+    /// Evict the oldest queued request to make room
+    RejectOldest,
+}
+
+impl QueueFullPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            QueueFullPolicy::Block => "block",
+            QueueFullPolicy::RejectNewest => "reject_newest",
+            QueueFullPolicy::RejectOldest => "reject_oldest",
+        }
+    }
+}
+
+/// Where proofs are generated
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProverBackend {
+    /// In-process: barretenberg if configured, placeholder proofs if allowed
+    #[default]
+    Local,
+    /// POST requests to an external prover (see `remote`)
+    RemoteHttp { url: String },
+}
+
+/// Base priority of withdrawal proofs, in fee priority points
+///
+/// Queued jobs age at `priority_aging_per_sec`, so a range proof waits at
+/// most this many points' worth of aging behind fresh zero-fee withdrawals.
+pub const WITHDRAWAL_PRIORITY: f64 = 100.0;
+
+/// Base priority of transfer proofs, in fee priority points
+pub const TRANSFER_PRIORITY: f64 = 50.0;
+
+/// A single payout of a withdrawal
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct WithdrawalOutput {
+    /// Recipient address, in the target chain's width (20 bytes on EVM chains)
+    pub recipient: Vec<u8>,
+    pub amount: u64,
+}
+
 /// Proof request types
-#[derive(Debug, Clone)]
+///
+/// `Debug` prints only public inputs, so requests can be logged without
+/// exposing secrets, randomness or committed values.
+#[derive(Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ProofRequest {
     /// Withdrawal proof
     Withdrawal {
         merkle_root: [u8; 32],
         nullifier: [u8; 32],
-        recipient: [u8; 20],
+        /// Ordered payouts; must sum to `amount - fee`
+        outputs: Vec<WithdrawalOutput>,
+        /// Gross amount taken from the note
         amount: u64,
+        /// Relayer fee deducted from `amount` before the outputs are paid
+        fee: u64,
+        /// Total value of the note being spent (`amount` plus any change)
+        note_value: u64,
+        /// Commitment to the remainder re-inserted into the pool on partial withdrawals
+        change_commitment: Option<[u8; 32]>,
+        /// Value held by `change_commitment` (zero for a full withdrawal)
+        change_value: u64,
         secret: [u8; 32],
         randomness: [u8; 32],
         merkle_path: Vec<[u8; 32]>,
@@ -38,6 +191,8 @@ pub enum ProofRequest {
     /// Consistency proof (Pedersen ↔ Paillier)
     Consistency {
         pedersen_commitment: [u8; 32],
+        /// Generators the commitment was made with
+        generators: PedersenGenerators,
         paillier_ciphertext: Vec<u8>,
         value: u64,
         pedersen_randomness: [u8; 32],
@@ -52,187 +207,1143 @@ pub enum ProofRequest {
     },
 }
 
+impl std::fmt::Debug for ProofRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = f.debug_struct(self.proof_type());
+        match self {
+            ProofRequest::Withdrawal {
+                merkle_root,
+                nullifier,
+                outputs,
+                amount,
+                fee,
+                change_commitment,
+                ..
+            } => out
+                .field("merkle_root", &hex::encode(merkle_root))
+                .field("nullifier", &hex::encode(nullifier))
+                .field("outputs", outputs)
+                .field("amount", amount)
+                .field("fee", fee)
+                .field("change_commitment", &change_commitment.map(hex::encode)),
+            ProofRequest::Transfer {
+                merkle_root,
+                nullifier,
+                new_commitment_a,
+                new_commitment_b,
+                ..
+            } => out
+                .field("merkle_root", &hex::encode(merkle_root))
+                .field("nullifier", &hex::encode(nullifier))
+                .field("new_commitment_a", &hex::encode(new_commitment_a))
+                .field("new_commitment_b", &hex::encode(new_commitment_b)),
+            ProofRequest::Consistency {
+                pedersen_commitment,
+                ..
+            } => out.field("pedersen_commitment", &hex::encode(pedersen_commitment)),
+            ProofRequest::Range {
+                commitment,
+                min_value,
+                ..
+            } => out
+                .field("commitment", &hex::encode(commitment))
+                .field("min_value", min_value),
+        };
+        out.finish_non_exhaustive()
+    }
+}
+
+impl ProofRequest {
+    /// Circuit name for this request, as used in metrics and `GeneratedProof`
+    pub fn proof_type(&self) -> &'static str {
+        match self {
+            ProofRequest::Withdrawal { .. } => "withdrawal",
+            ProofRequest::Transfer { .. } => "transfer",
+            ProofRequest::Consistency { .. } => "consistency",
+            ProofRequest::Range { .. } => "range",
+        }
+    }
+
+    /// Scheduling priority from the request kind, in fee priority points
+    ///
+    /// Withdrawals are waited on by users and outrank everything else;
+    /// transfers come next, and range and consistency proofs only serve
+    /// background checks.
+    pub fn base_priority(&self) -> f64 {
+        match self {
+            ProofRequest::Withdrawal { .. } => WITHDRAWAL_PRIORITY,
+            ProofRequest::Transfer { .. } => TRANSFER_PRIORITY,
+            ProofRequest::Consistency { .. } | ProofRequest::Range { .. } => 0.0,
+        }
+    }
+
+    /// Relayer fee attached to the request (zero for request types without one)
+    pub fn fee(&self) -> u64 {
+        match self {
+            ProofRequest::Withdrawal { fee, .. } => *fee,
+            _ => 0,
+        }
+    }
+
+    /// Check request invariants that can be verified before proving
+    pub fn validate(&self) -> Result<()> {
+        if let ProofRequest::Withdrawal {
+            outputs,
+            amount,
+            fee,
+            note_value,
+            change_commitment,
+            change_value,
+            ..
+        } = self
+        {
+            match change_commitment {
+                None if *change_value != 0 => {
+                    return Err(anyhow::anyhow!(
+                        "Change value {} given without a change commitment",
+                        change_value
+                    ));
+                }
+                Some(commitment) if *commitment == [0u8; 32] => {
+                    return Err(anyhow::anyhow!("Change commitment must be non-zero"));
+                }
+                Some(_) if *change_value == 0 => {
+                    return Err(anyhow::anyhow!(
+                        "Change commitment given with zero change value"
+                    ));
+                }
+                _ => {}
+            }
+
+            validate_outputs(outputs, *amount, *fee)?;
+
+            let total = amount
+                .checked_add(*change_value)
+                .ok_or_else(|| anyhow::anyhow!("Withdrawal amount plus change overflows"))?;
+            if total != *note_value {
+                return Err(anyhow::anyhow!(
+                    "Withdrawal amount {} plus change {} does not equal note value {}",
+                    amount,
+                    change_value,
+                    note_value
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Public inputs the circuit commits to, in circuit order
+    pub fn public_inputs(&self, encoding: &InputEncoding) -> Result<Vec<[u8; 32]>> {
+        Ok(match self {
+            ProofRequest::Withdrawal {
+                merkle_root,
+                nullifier,
+                outputs,
+                amount,
+                fee,
+                change_commitment,
+                ..
+            } => withdrawal_public_inputs(
+                *merkle_root,
+                *nullifier,
+                outputs,
+                *amount,
+                *fee,
+                *change_commitment,
+                encoding,
+            )?,
+            ProofRequest::Transfer {
+                merkle_root,
+                nullifier,
+                new_commitment_a,
+                new_commitment_b,
+                ..
+            } => vec![
+                *merkle_root,
+                *nullifier,
+                *new_commitment_a,
+                *new_commitment_b,
+            ],
+            // Bind the proof to the generators it was made for
+            ProofRequest::Consistency {
+                pedersen_commitment,
+                generators,
+                ..
+            } => vec![*pedersen_commitment, generators.g, generators.h],
+            ProofRequest::Range {
+                commitment,
+                min_value,
+                ..
+            } => vec![*commitment, encoding.encode_u64(*min_value)],
+        })
+    }
+
+    /// Check the Merkle path spans exactly `tree_depth` levels of 0/1 indices
+    ///
+    /// A wrong-length path always yields an invalid proof, so it is rejected
+    /// before any proving time is spent on it.
+    pub fn check_path_depth(&self, tree_depth: usize) -> Result<(), ProverError> {
+        let (path, indices) = match self {
+            ProofRequest::Withdrawal {
+                merkle_path,
+                merkle_indices,
+                ..
+            }
+            | ProofRequest::Transfer {
+                merkle_path,
+                merkle_indices,
+                ..
+            } => (merkle_path, merkle_indices),
+            _ => return Ok(()),
+        };
+
+        if path.len() != tree_depth || indices.len() != tree_depth {
+            return Err(ProverError::MerklePathLength {
+                tree_depth,
+                path_len: path.len(),
+                indices_len: indices.len(),
+            });
+        }
+        if let Some((level, &index)) = indices.iter().enumerate().find(|(_, &index)| index > 1) {
+            return Err(ProverError::MerkleIndex { level, index });
+        }
+        Ok(())
+    }
+}
+
+/// Check that withdrawal outputs are distinct, non-zero and sum to `amount - fee`
+fn validate_outputs(outputs: &[WithdrawalOutput], amount: u64, fee: u64) -> Result<()> {
+    if outputs.is_empty() {
+        return Err(anyhow::anyhow!("Withdrawal has no outputs"));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut total: u64 = 0;
+    for output in outputs {
+        if output.amount == 0 {
+            return Err(anyhow::anyhow!(
+                "Zero-amount output to 0x{}",
+                hex::encode(&output.recipient)
+            ));
+        }
+        if !seen.insert(&output.recipient) {
+            return Err(anyhow::anyhow!(
+                "Duplicate output recipient 0x{}",
+                hex::encode(&output.recipient)
+            ));
+        }
+        total = total
+            .checked_add(output.amount)
+            .ok_or_else(|| anyhow::anyhow!("Withdrawal outputs overflow"))?;
+    }
+
+    let payable = amount
+        .checked_sub(fee)
+        .ok_or_else(|| anyhow::anyhow!("Fee {} exceeds withdrawal amount {}", fee, amount))?;
+    if total != payable {
+        return Err(anyhow::anyhow!(
+            "Outputs total {} but amount {} minus fee {} is {}",
+            total,
+            amount,
+            fee,
+            payable
+        ));
+    }
+    Ok(())
+}
+
 /// Generated proof
 #[derive(Debug, Clone)]
 pub struct GeneratedProof {
     pub proof_type: String,
+    pub proof_system: ProofSystem,
     pub proof_data: Vec<u8>,
     pub public_inputs: Vec<[u8; 32]>,
     pub generation_time_ms: u64,
 }
 
+impl GeneratedProof {
+    /// Compressed proof and serialized public inputs, as sent over the wire
+    pub fn to_wire(&self) -> Result<(Vec<u8>, Vec<u8>), WireError> {
+        Ok((
+            compress_proof(&self.proof_type, self.proof_system, &self.proof_data)?,
+            serialize_public_inputs(&self.public_inputs),
+        ))
+    }
+
+    /// Rebuild a proof received in wire format
+    pub fn from_wire(proof: &[u8], public_inputs: &[u8]) -> Result<Self, WireError> {
+        let (proof_type, proof_system, proof_data) = decompress_proof(proof)?;
+        Ok(Self {
+            proof_type: proof_type.to_string(),
+            proof_system,
+            proof_data,
+            public_inputs: deserialize_public_inputs(public_inputs)?,
+            generation_time_ms: 0,
+        })
+    }
+}
+
+/// Backend that turns a proof request into a proof
+#[async_trait]
+pub trait ProofGenerator: Send + Sync {
+    /// Backend name reported in startup diagnostics
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Prove `request` in `system`, laying out public inputs per `encoding`
+    async fn generate(
+        &self,
+        request: ProofRequest,
+        encoding: &InputEncoding,
+        system: ProofSystem,
+    ) -> Result<GeneratedProof>;
+}
+
+/// Generator producing placeholder proofs, for running without the prover
+/// toolchain
+pub struct PlaceholderGenerator;
+
+#[async_trait]
+impl ProofGenerator for PlaceholderGenerator {
+    fn name(&self) -> &'static str {
+        "placeholder"
+    }
+
+    async fn generate(
+        &self,
+        request: ProofRequest,
+        encoding: &InputEncoding,
+        system: ProofSystem,
+    ) -> Result<GeneratedProof> {
+        generate_proof(request, encoding, system).await
+    }
+}
+
+/// A queued proof request, its target's input encoding, its circuit's proof
+/// system, the channel its result is delivered on, and its cancellation
+type ProofJob = (
+    ProofRequest,
+    InputEncoding,
+    ProofSystem,
+    mpsc::Sender<Result<GeneratedProof, ProverError>>,
+    CancellationToken,
+);
+
+/// A job waiting in the queue with its scheduling rank
+struct QueuedJob {
+    job: ProofJob,
+    /// Priority at enqueue time less the aging any later arrival has over
+    /// it, so ranks stay comparable without being recomputed as jobs wait
+    rank: f64,
+    /// Arrival order, breaking ties in rank
+    seq: u64,
+}
+
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank
+            .total_cmp(&other.rank)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for QueuedJob {}
+
+struct PendingJobs {
+    heap: BinaryHeap<QueuedJob>,
+    next_seq: u64,
+}
+
+/// Bounded proof request queue with a configurable overflow policy
+///
+/// Jobs are served highest effective priority first, with ties going to the
+/// earliest arrival. A job's effective priority grows by `aging_per_sec`
+/// while it waits; since every job ages at the same rate, the order only
+/// depends on each job's priority less its arrival time times that rate,
+/// which is fixed at enqueue and kept in a max-heap.
+struct RequestQueue {
+    jobs: Mutex<PendingJobs>,
+    /// Reference point for arrival times in ranks
+    epoch: Instant,
+    capacity: usize,
+    policy: QueueFullPolicy,
+    /// Priority gained per second of waiting, so low-fee jobs can't starve
+    aging_per_sec: f64,
+    closed: AtomicBool,
+    /// Signalled when a job is pushed or the queue closes
+    job_ready: Notify,
+    /// Signalled when a job is popped
+    space_ready: Notify,
+}
+
+impl RequestQueue {
+    fn new(capacity: usize, policy: QueueFullPolicy, aging_per_sec: f64) -> Self {
+        Self {
+            jobs: Mutex::new(PendingJobs {
+                heap: BinaryHeap::with_capacity(capacity),
+                next_seq: 0,
+            }),
+            epoch: Instant::now(),
+            capacity,
+            policy,
+            aging_per_sec,
+            closed: AtomicBool::new(false),
+            job_ready: Notify::new(),
+            space_ready: Notify::new(),
+        }
+    }
+
+    /// Enqueue a job, applying the overflow policy when full
+    async fn push(&self, job: ProofJob, priority: f64) -> Result<(), ProverError> {
+        let mut job = Some(job);
+        loop {
+            {
+                let mut jobs = self.jobs.lock().unwrap();
+                if jobs.heap.len() < self.capacity {
+                    self.insert(&mut jobs, job.take().unwrap(), priority);
+                    return Ok(());
+                }
+
+                match self.policy {
+                    QueueFullPolicy::Block => {}
+                    QueueFullPolicy::RejectNewest => {
+                        self.record_rejection();
+                        return Err(ProverError::QueueFull);
+                    }
+                    QueueFullPolicy::RejectOldest => {
+                        // Eviction is rare enough to pay for a heap rebuild
+                        let mut queued = std::mem::take(&mut jobs.heap).into_vec();
+                        if let Some(oldest) = (0..queued.len()).min_by_key(|&i| queued[i].seq) {
+                            let (evicted, _, _, evicted_tx, _) = queued.swap_remove(oldest).job;
+                            metrics::PROOF_QUEUE_DEPTH
+                                .with_label_values(&[evicted.proof_type()])
+                                .dec();
+                            let _ = evicted_tx.try_send(Err(ProverError::QueueFull));
+                        }
+                        jobs.heap = queued.into();
+                        self.insert(&mut jobs, job.take().unwrap(), priority);
+                        self.record_rejection();
+                        return Ok(());
+                    }
+                }
+            }
+
+            self.space_ready.notified().await;
+        }
+    }
+
+    fn insert(&self, jobs: &mut PendingJobs, job: ProofJob, priority: f64) {
+        let arrival = Instant::now()
+            .saturating_duration_since(self.epoch)
+            .as_secs_f64();
+        metrics::PROOF_QUEUE_DEPTH
+            .with_label_values(&[job.0.proof_type()])
+            .inc();
+        let seq = jobs.next_seq;
+        jobs.next_seq += 1;
+        jobs.heap.push(QueuedJob {
+            job,
+            rank: priority - arrival * self.aging_per_sec,
+            seq,
+        });
+        self.job_ready.notify_one();
+    }
+
+    /// Dequeue the next job, or `None` once the queue is closed
+    async fn pop(&self) -> Option<ProofJob> {
+        loop {
+            let job = self
+                .jobs
+                .lock()
+                .unwrap()
+                .heap
+                .pop()
+                .map(|queued| queued.job);
+            if let Some(job) = job {
+                metrics::PROOF_QUEUE_DEPTH
+                    .with_label_values(&[job.0.proof_type()])
+                    .dec();
+                self.space_ready.notify_one();
+                return Some(job);
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            self.job_ready.notified().await;
+        }
+    }
+
+    /// Wait until a job is queued, or return false once the queue is closed
+    /// and drained
+    async fn wait_for_job(&self) -> bool {
+        loop {
+            if self.len() > 0 {
+                return true;
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                return false;
+            }
+            self.job_ready.notified().await;
+        }
+    }
+
+    /// Stop the worker once the remaining jobs are drained
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.job_ready.notify_one();
+    }
+
+    fn len(&self) -> usize {
+        self.jobs.lock().unwrap().heap.len()
+    }
+
+    /// Waiting jobs of each proof type
+    fn len_by_type(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::new();
+        for queued in self.jobs.lock().unwrap().heap.iter() {
+            *counts.entry(queued.job.0.proof_type()).or_default() += 1;
+        }
+        counts
+    }
+
+    fn record_rejection(&self) {
+        warn!(
+            policy = self.policy.as_str(),
+            "Proof request queue full, request rejected"
+        );
+        metrics::PROOF_REQUESTS_REJECTED
+            .with_label_values(&[self.policy.as_str()])
+            .inc();
+    }
+}
+
+/// Delay before a dead prover worker is restarted
+const WORKER_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Liveness of the prover's queue worker, shared with health checks
+#[derive(Debug)]
+pub struct WorkerHealth {
+    alive: AtomicBool,
+}
+
+impl Default for WorkerHealth {
+    fn default() -> Self {
+        Self {
+            alive: AtomicBool::new(true),
+        }
+    }
+}
+
+impl WorkerHealth {
+    /// Whether the worker is currently running
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+}
+
+/// Run the worker produced by `spawn_worker`, restarting it whenever it dies
+///
+/// A worker that returns normally has drained a closed queue, so supervision
+/// ends. Jobs still queued when a worker dies are picked up by its
+/// replacement; the job it was handling fails with a closed channel.
+async fn supervise<F, Fut>(health: Arc<WorkerHealth>, mut spawn_worker: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    loop {
+        health.alive.store(true, Ordering::SeqCst);
+        match tokio::spawn(spawn_worker()).await {
+            Ok(()) => {
+                debug!("Prover worker stopped");
+                return;
+            }
+            Err(e) => {
+                health.alive.store(false, Ordering::SeqCst);
+                metrics::PROVER_WORKER_RESTARTS.inc();
+                error!(panicked = e.is_panic(), "Prover worker died, restarting");
+                tokio::time::sleep(WORKER_RESTART_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Pull jobs off the queue and run each under a concurrency permit
+///
+/// The job is only chosen once a permit is free, so a job arriving while
+/// every slot is busy still competes for the next one.
+async fn run_worker(
+    queue: Arc<RequestQueue>,
+    semaphore: Arc<Semaphore>,
+    generator: Arc<dyn ProofGenerator>,
+    latencies: Arc<LatencyTracker>,
+    timeout_secs: u64,
+) {
+    while queue.wait_for_job().await {
+        let Ok(permit) = semaphore.clone().acquire_owned().await else {
+            return;
+        };
+        let Some((request, encoding, system, response_tx, cancel)) = queue.pop().await else {
+            return;
+        };
+        // Cancelled while queued: answer without running, freeing the slot
+        if cancel.is_cancelled() {
+            record_cancelled(request.proof_type());
+            let _ = response_tx.try_send(Err(ProverError::Cancelled));
+            continue;
+        }
+
+        let generator = generator.clone();
+        let latencies = latencies.clone();
+        metrics::PROOFS_IN_FLIGHT.inc();
+        tokio::spawn(async move {
+            let proof_type = request.proof_type();
+            let result = tokio::select! {
+                result = run_job(generator.as_ref(), request, &encoding, system, timeout_secs) => {
+                    result
+                }
+                _ = cancel.cancelled() => {
+                    record_cancelled(proof_type);
+                    Err(ProverError::Cancelled)
+                }
+            };
+            metrics::PROOFS_IN_FLIGHT.dec();
+            if let Ok(proof) = &result {
+                latencies.record(proof_type, proof.generation_time_ms);
+            }
+            // The requester stops waiting when it times out or is cancelled
+            if crate::channel::send(&response_tx, "proof_results", result)
+                .await
+                .is_err()
+            {
+                debug!(
+                    proof_type = proof_type,
+                    "Proof requester gone, result discarded"
+                );
+            }
+            drop(permit);
+        });
+    }
+}
+
+fn record_cancelled(proof_type: &'static str) {
+    debug!(proof_type = proof_type, "Proof request cancelled");
+    metrics::PROOFS_CANCELLED
+        .with_label_values(&[proof_type])
+        .inc();
+}
+
 /// Prover service for generating ZK proofs
 pub struct ProverService {
     /// Configuration
     config: ProverConfig,
     /// Semaphore for concurrency control
     semaphore: Arc<Semaphore>,
-    /// Request channel
-    request_tx: mpsc::Sender<(ProofRequest, mpsc::Sender<Result<GeneratedProof>>)>,
+    /// Pending proof requests
+    queue: Arc<RequestQueue>,
+    /// Circuit artifacts loaded from config
+    circuits: Arc<CircuitRegistry>,
+    /// Fee worth one priority point, by chain id
+    fee_per_point: HashMap<u64, u64>,
+    /// Public-input layout by target chain id (EVM layout if absent)
+    encodings: HashMap<u64, InputEncoding>,
+    /// Pool Merkle tree depth by target chain id (`merkle::TREE_DEPTH` if absent)
+    tree_depths: HashMap<u64, usize>,
+    /// Generators and Paillier key of the consistency circuit, if configured
+    commitment_params: Option<CommitmentParams>,
+    /// Backend used to check proofs
+    verifier: Arc<dyn ProofVerifier>,
+    /// Real toolchain, which also checks UltraHonk proofs, if configured
+    barretenberg: Option<Arc<barretenberg::BarretenbergGenerator>>,
+    /// Liveness of the queue worker
+    health: Arc<WorkerHealth>,
+    /// Name of the proving backend
+    backend: &'static str,
+    /// Recently generated proofs, reused for identical requests
+    cache: ProofCache,
+    /// Recent generation times by proof type
+    latencies: Arc<LatencyTracker>,
+    /// Requests accepted and not yet answered
+    accepted: TaskTracker,
+    /// Bytes of batch members held at once while aggregating
+    aggregation_budget: usize,
 }
 
 impl ProverService {
     /// Create a new prover service
-    pub fn new(config: &ProverConfig) -> Result<Self> {
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrent));
-        let (request_tx, mut request_rx) = mpsc::channel::<(
-            ProofRequest,
-            mpsc::Sender<Result<GeneratedProof>>,
-        )>(100);
+    ///
+    /// Proves on the remote prover when one is configured, otherwise with
+    /// barretenberg when it is configured. Failing both, placeholder proofs
+    /// are generated only if `allow_placeholder_proofs` opts in (for running
+    /// without the prover toolchain); otherwise startup fails.
+    pub fn new(config: &ProverConfig) -> Result<Self, RelayerError> {
+        let bb = config
+            .barretenberg
+            .as_ref()
+            .map(barretenberg::BarretenbergGenerator::new)
+            .transpose()
+            .map_err(RelayerError::Config)?
+            .map(Arc::new);
+        let generator: Arc<dyn ProofGenerator> = match (&config.backend, &bb) {
+            (ProverBackend::RemoteHttp { url }, _) => Arc::new(
+                remote::RemoteGenerator::new(url.clone(), Duration::from_secs(config.timeout_secs))
+                    .map_err(RelayerError::Config)?,
+            ),
+            (ProverBackend::Local, Some(bb)) => bb.clone(),
+            // Nothing is proved, so there's nothing to fall back from
+            (ProverBackend::Local, None) if !config.enabled => Arc::new(PlaceholderGenerator),
+            (ProverBackend::Local, None) if config.allow_placeholder_proofs => {
+                warn!("No [prover.barretenberg] configured, generating placeholder proofs");
+                Arc::new(PlaceholderGenerator)
+            }
+            (ProverBackend::Local, None) => {
+                return Err(RelayerError::Config(anyhow::anyhow!(
+                    "No [prover.barretenberg] or remote prover configured; set \
+                     allow_placeholder_proofs to run with placeholder proofs"
+                )))
+            }
+        };
+        let mut service = Self::with_generator(config, generator)?;
+        service.barretenberg = bb;
+        Ok(service)
+    }
 
-        // Spawn worker task
-        let worker_semaphore = semaphore.clone();
-        let timeout_secs = config.timeout_secs;
+    /// Create a prover service over a specific proving backend
+    pub fn with_generator(
+        config: &ProverConfig,
+        generator: Arc<dyn ProofGenerator>,
+    ) -> Result<Self, RelayerError> {
+        let mut service = Self::without_worker(config).map_err(RelayerError::Config)?;
+        service.backend = generator.name();
 
-        tokio::spawn(async move {
-            while let Some((request, response_tx)) = request_rx.recv().await {
-                let permit = worker_semaphore.clone().acquire_owned().await;
-                if permit.is_err() {
-                    continue;
-                }
+        // Spawn supervised worker task
+        let queue = service.queue.clone();
+        let semaphore = service.semaphore.clone();
+        let latencies = service.latencies.clone();
+        let timeout_secs = config.timeout_secs;
+        tokio::spawn(supervise(service.health.clone(), move || {
+            run_worker(
+                queue.clone(),
+                semaphore.clone(),
+                generator.clone(),
+                latencies.clone(),
+                timeout_secs,
+            )
+        }));
 
-                tokio::spawn(async move {
-                    let result = tokio::time::timeout(
-                        std::time::Duration::from_secs(timeout_secs),
-                        generate_proof(request),
-                    )
-                    .await
-                    .map_err(|_| anyhow::anyhow!("Proof generation timeout"))
-                    .and_then(|r| r);
+        Ok(service)
+    }
 
-                    let _ = response_tx.send(result).await;
-                    drop(permit);
-                });
-            }
-        });
+    /// Service state with no worker consuming the queue yet
+    fn without_worker(config: &ProverConfig) -> Result<Self> {
+        let circuits = Arc::new(CircuitRegistry::load(&config.circuits)?);
+        let semaphore = Arc::new(Semaphore::new(config.max_concurrent));
+        let queue = Arc::new(RequestQueue::new(
+            config.queue_capacity,
+            config.queue_full_policy,
+            config.priority_aging_per_sec,
+        ));
+        let fee_per_point = config
+            .fee_priority
+            .iter()
+            .map(|entry| (entry.chain_id, entry.fee_per_point.max(1)))
+            .collect();
+        let encodings = config
+            .input_encoding
+            .iter()
+            .map(|entry| {
+                entry.encoding.validate()?;
+                Ok((entry.chain_id, entry.encoding))
+            })
+            .collect::<Result<_>>()?;
+        let tree_depths = config
+            .tree_depth
+            .iter()
+            .map(|entry| (entry.chain_id, entry.depth))
+            .collect();
+        let commitment_params = config
+            .consistency
+            .as_ref()
+            .map(CommitmentParams::from_config)
+            .transpose()?;
 
         Ok(Self {
             config: config.clone(),
             semaphore,
-            request_tx,
+            queue,
+            circuits,
+            fee_per_point,
+            encodings,
+            tree_depths,
+            commitment_params,
+            verifier: Arc::new(PlaceholderVerifier),
+            barretenberg: None,
+            health: Arc::new(WorkerHealth::default()),
+            backend: "none",
+            cache: ProofCache::new(config.cache_capacity),
+            latencies: Arc::default(),
+            accepted: TaskTracker::new(),
+            aggregation_budget: config.aggregation_memory_budget,
         })
     }
 
     /// Generate a proof asynchronously
-    pub async fn generate(&self, request: ProofRequest) -> Result<GeneratedProof> {
+    ///
+    /// Requests are scheduled by their attached fee, normalized for the
+    /// chain the proof will be submitted on, and their public inputs use
+    /// that chain's encoding.
+    pub async fn generate(
+        &self,
+        request: ProofRequest,
+        chain_id: u64,
+    ) -> Result<GeneratedProof, RelayerError> {
+        self.prove(request, chain_id, true, CancellationToken::new())
+            .await
+    }
+
+    /// Generate a proof, giving up once `cancel` is triggered
+    ///
+    /// A cancelled request fails with `ProverError::Cancelled`. If it is
+    /// still queued it never runs; if it is proving, the job is dropped and
+    /// its prover slot released.
+    pub async fn generate_cancellable(
+        &self,
+        request: ProofRequest,
+        chain_id: u64,
+        cancel: CancellationToken,
+    ) -> Result<GeneratedProof, RelayerError> {
+        self.prove(request, chain_id, true, cancel).await
+    }
+
+    /// Validate, queue and prove one request, optionally through the cache
+    ///
+    /// Batch members skip the cache: they're never requested twice, and
+    /// caching them would keep them in memory after aggregation drops them.
+    async fn prove(
+        &self,
+        request: ProofRequest,
+        chain_id: u64,
+        cached: bool,
+        cancel: CancellationToken,
+    ) -> Result<GeneratedProof, RelayerError> {
         if !self.config.enabled {
-            return Err(anyhow::anyhow!("Prover service is disabled"));
+            return Err(RelayerError::Config(anyhow::anyhow!(
+                "Prover service is disabled"
+            )));
+        }
+        // Taken before the check so a drain started in between waits for us
+        let _accepted = self.accepted.token();
+        if self.accepted.is_closed() {
+            return Err(ProverError::ShuttingDown.into());
+        }
+
+        request.validate().map_err(RelayerError::Validation)?;
+        request.check_path_depth(self.tree_depth(chain_id))?;
+        if matches!(request, ProofRequest::Consistency { .. }) {
+            self.commitment_params
+                .as_ref()
+                .ok_or_else(|| {
+                    RelayerError::Config(anyhow::anyhow!(
+                        "Consistency proofs need [prover.consistency]"
+                    ))
+                })?
+                .check_request(&request)?;
+        }
+        let encoding = self.encoding(chain_id);
+        if let ProofRequest::Withdrawal { outputs, .. } = &request {
+            for output in outputs {
+                encoding
+                    .check_address(&output.recipient)
+                    .map_err(RelayerError::Validation)?;
+            }
         }
 
+        // A hit skips the queue and the prover slots entirely
+        let cache_key = cached.then(|| cache::cache_key(&request, &encoding));
+        if let Some(proof) = cache_key.as_ref().and_then(|key| self.cache.get(key)) {
+            debug!(proof_type = request.proof_type(), "Proof served from cache");
+            return Ok(proof);
+        }
+
+        let priority = self.priority(&request, chain_id);
+        let system = self
+            .circuits
+            .proof_system(request.proof_type(), CURRENT_CIRCUIT_VERSION);
         let (response_tx, mut response_rx) = mpsc::channel(1);
-        self.request_tx.send((request, response_tx)).await?;
+        self.queue
+            .push(
+                (request, encoding, system, response_tx, cancel.clone()),
+                priority,
+            )
+            .await?;
 
-        response_rx
-            .recv()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Prover channel closed"))?
+        // The worker drops a cancelled job when it reaches it; the caller
+        // doesn't wait for that
+        let proof = tokio::select! {
+            result = response_rx.recv() => result.ok_or(ProverError::Dropped)??,
+            _ = cancel.cancelled() => return Err(ProverError::Cancelled.into()),
+        };
+        if let Some(key) = cache_key {
+            self.cache.insert(key, proof.clone());
+        }
+        Ok(proof)
+    }
+
+    /// Verify a proof against its public inputs with its circuit's key
+    pub async fn verify(&self, proof: &GeneratedProof) -> Result<bool, RelayerError> {
+        self.verify_circuit(proof, CURRENT_CIRCUIT_VERSION).await
+    }
+
+    /// Verify a proof against version `version` of its circuit
+    ///
+    /// UltraHonk proofs are checked by `bb` against the loaded verification
+    /// key when barretenberg is configured. Placeholder proofs are only
+    /// accepted under `allow_placeholder_proofs`; any other proof there's no
+    /// backend for fails with `ProverError::Verification` rather than being
+    /// taken on trust. Generation doesn't need to be enabled, so
+    /// validate-only nodes can use this.
+    pub async fn verify_circuit(
+        &self,
+        proof: &GeneratedProof,
+        version: &str,
+    ) -> Result<bool, RelayerError> {
+        let circuit = self.circuits.get(&proof.proof_type, version);
+        let system = circuit
+            .as_ref()
+            .map_or(ProofSystem::default(), |circuit| circuit.proof_system);
+        if proof.proof_system != system {
+            return Ok(false);
+        }
+        match (&self.barretenberg, circuit) {
+            (Some(bb), Some(circuit)) if system == ProofSystem::UltraHonk => {
+                bb.verify_with_key(proof, &circuit.vk).await.map_err(|e| {
+                    RelayerError::Prover(ProverError::Verification {
+                        proof_type: proof.proof_type.clone(),
+                        reason: format!("{:#}", e),
+                    })
+                })
+            }
+            _ if self.config.allow_placeholder_proofs => {
+                Ok(self
+                    .verifier
+                    .verify_with(system, &proof.proof_data, &proof.public_inputs))
+            }
+            _ => Err(ProverError::Verification {
+                proof_type: proof.proof_type.clone(),
+                reason: format!("no verifier is configured for {:?} proofs", system),
+            }
+            .into()),
+        }
+    }
+
+    /// Verify many proofs, returning one result per proof in order
+    ///
+    /// Uses the backend's batch check when it has one, bisecting failed
+    /// batches to find the invalid proofs; otherwise verifies sequentially.
+    pub fn verify_batch(&self, batch: Vec<(Vec<u8>, Vec<[u8; 32]>)>) -> Vec<bool> {
+        verifier::verify_batch(self.verifier.as_ref(), &batch)
+    }
+
+    /// Public-input layout for proofs targeting `chain_id`
+    fn encoding(&self, chain_id: u64) -> InputEncoding {
+        self.encodings.get(&chain_id).copied().unwrap_or_default()
+    }
+
+    /// Merkle tree depth of the pool on `chain_id`
+    pub fn tree_depth(&self, chain_id: u64) -> usize {
+        self.tree_depths
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(crate::merkle::TREE_DEPTH)
+    }
+
+    /// Scheduling priority for a request submitted on `chain_id`
+    fn priority(&self, request: &ProofRequest, chain_id: u64) -> f64 {
+        let fee_per_point = self.fee_per_point.get(&chain_id).copied().unwrap_or(1);
+        request.base_priority() + request.fee() as f64 / fee_per_point as f64
     }
 
     /// Check if prover is available
+    ///
+    /// False while the queue worker is down and being restarted.
     pub fn is_available(&self) -> bool {
-        self.config.enabled && self.semaphore.available_permits() > 0
+        self.config.enabled && self.health.is_alive() && self.semaphore.available_permits() > 0
+    }
+
+    /// Whether proving is enabled in config
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Name of the proving backend
+    pub fn backend(&self) -> &'static str {
+        self.backend
+    }
+
+    /// Liveness of the queue worker, for health checks
+    pub fn worker_health(&self) -> Arc<WorkerHealth> {
+        self.health.clone()
     }
 
     /// Get current queue depth
     pub fn queue_depth(&self) -> usize {
         self.config.max_concurrent - self.semaphore.available_permits()
     }
+
+    /// Circuit artifacts the prover was configured with
+    pub fn circuits(&self) -> Arc<CircuitRegistry> {
+        self.circuits.clone()
+    }
+
+    /// Number of requests waiting for a prover slot
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Turn new requests away and wait until accepted ones are answered
+    pub async fn drain(&self) {
+        self.accepted.close();
+        self.accepted.wait().await;
+    }
+
+    /// Load and generation-time percentiles of recent proofs, by type
+    pub fn stats(&self) -> ProverStats {
+        let mut by_type = self.latencies.by_type();
+        let queued = self.queue.len_by_type();
+        for (proof_type, waiting) in &queued {
+            by_type.entry(proof_type.to_string()).or_default().queued = *waiting;
+        }
+        ProverStats {
+            in_flight: self.queue_depth(),
+            queued: queued.values().sum(),
+            by_type,
+        }
+    }
+}
+
+impl Drop for ProverService {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
 }
 
-/// Generate a proof (actual implementation would use Noir prover)
-async fn generate_proof(request: ProofRequest) -> Result<GeneratedProof> {
+/// Run one proof job under the timeout, classifying how it ended
+async fn run_job(
+    generator: &dyn ProofGenerator,
+    request: ProofRequest,
+    encoding: &InputEncoding,
+    system: ProofSystem,
+    timeout_secs: u64,
+) -> Result<GeneratedProof, ProverError> {
+    let proof_type = request.proof_type();
+    let started = std::time::Instant::now();
+    match tokio::time::timeout(
+        Duration::from_secs(timeout_secs),
+        generator.generate(request, encoding, system),
+    )
+    .await
+    {
+        Ok(Ok(proof)) => {
+            metrics::PROOFS_GENERATED
+                .with_label_values(&[proof_type])
+                .inc();
+            metrics::PROOF_DURATION
+                .with_label_values(&[proof_type])
+                .observe(started.elapsed().as_secs_f64());
+            Ok(proof)
+        }
+        Ok(Err(e)) => {
+            error!(proof_type = proof_type, error = %e, "Proof generation failed");
+            metrics::PROOFS_FAILED
+                .with_label_values(&[proof_type])
+                .inc();
+            Err(ProverError::Failed {
+                proof_type,
+                reason: e.to_string(),
+            })
+        }
+        Err(_) => {
+            warn!(
+                proof_type = proof_type,
+                timeout_secs = timeout_secs,
+                "Proof generation timed out"
+            );
+            metrics::PROOFS_TIMEOUT
+                .with_label_values(&[proof_type])
+                .inc();
+            Err(ProverError::Timeout {
+                proof_type,
+                timeout_secs,
+            })
+        }
+    }
+}
+
+/// Generate a placeholder proof with the real public inputs
+async fn generate_proof(
+    request: ProofRequest,
+    encoding: &InputEncoding,
+    system: ProofSystem,
+) -> Result<GeneratedProof> {
     let start = std::time::Instant::now();
 
-    let (proof_type, proof_data, public_inputs) = match request {
+    let public_inputs = request.public_inputs(encoding)?;
+    let proof_data = match &request {
         ProofRequest::Withdrawal {
-            merkle_root,
-            nullifier,
-            recipient,
-            amount,
+            outputs,
+            change_commitment,
             secret,
             randomness,
             merkle_path,
-            merkle_indices,
+            ..
         } => {
-            info!("Generating withdrawal proof");
-
-            // In production, this would:
-            // 1. Load the compiled Noir circuit
-            // 2. Create witness from private inputs
-            // 3. Generate Groth16 proof using barretenberg
-            // 4. Serialize proof for on-chain verification
-
+            info!(
+                partial = change_commitment.is_some(),
+                outputs = outputs.len(),
+                "Generating withdrawal proof"
+            );
             // Placeholder proof
-            let proof = generate_dummy_proof(&secret, &randomness, &merkle_path);
-
-            let mut inputs = Vec::new();
-            inputs.push(merkle_root);
-            inputs.push(nullifier);
-
-            let mut recipient_padded = [0u8; 32];
-            recipient_padded[12..].copy_from_slice(&recipient);
-            inputs.push(recipient_padded);
-
-            let mut amount_bytes = [0u8; 32];
-            amount_bytes[24..].copy_from_slice(&amount.to_be_bytes());
-            inputs.push(amount_bytes);
-
-            ("withdrawal".to_string(), proof, inputs)
+            generate_dummy_proof(secret, randomness, merkle_path, &public_inputs, system)
         }
 
         ProofRequest::Transfer {
-            merkle_root,
-            nullifier,
-            new_commitment_a,
-            new_commitment_b,
             secret,
             randomness,
             merkle_path,
-            merkle_indices,
+            ..
         } => {
             info!("Generating transfer proof");
-
-            let proof = generate_dummy_proof(&secret, &randomness, &merkle_path);
-
-            let inputs = vec![merkle_root, nullifier, new_commitment_a, new_commitment_b];
-
-            ("transfer".to_string(), proof, inputs)
+            generate_dummy_proof(secret, randomness, merkle_path, &public_inputs, system)
         }
 
         ProofRequest::Consistency {
-            pedersen_commitment,
-            paillier_ciphertext,
-            value,
             pedersen_randomness,
-            paillier_randomness,
+            ..
         } => {
             info!("Generating consistency proof");
-
-            let proof = generate_dummy_proof(&pedersen_randomness, &[0u8; 32], &[]);
-
-            let inputs = vec![pedersen_commitment];
-
-            ("consistency".to_string(), proof, inputs)
+            generate_dummy_proof(pedersen_randomness, &[0u8; 32], &[], &public_inputs, system)
         }
 
-        ProofRequest::Range {
-            commitment,
-            min_value,
-            value,
-            randomness,
-        } => {
+        ProofRequest::Range { randomness, .. } => {
             info!("Generating range proof");
-
-            let proof = generate_dummy_proof(&randomness, &[0u8; 32], &[]);
-
-            let mut min_bytes = [0u8; 32];
-            min_bytes[24..].copy_from_slice(&min_value.to_be_bytes());
-
-            let inputs = vec![commitment, min_bytes];
-
-            ("range".to_string(), proof, inputs)
+            generate_dummy_proof(randomness, &[0u8; 32], &[], &public_inputs, system)
         }
     };
+    let proof_type = request.proof_type().to_string();
 
     let elapsed = start.elapsed();
     debug!(
@@ -243,26 +1354,74 @@ async fn generate_proof(request: ProofRequest) -> Result<GeneratedProof> {
 
     Ok(GeneratedProof {
         proof_type,
+        proof_system: system,
         proof_data,
         public_inputs,
         generation_time_ms: elapsed.as_millis() as u64,
     })
 }
 
+/// Assemble withdrawal public inputs in circuit order
+///
+/// A single output keeps the original four-input layout
+/// (root, nullifier, recipient, amount). Multiple outputs use
+/// (root, nullifier, amount, fee, count, recipient_0, amount_0, ...).
+/// Partial withdrawals append the change commitment in either case so the
+/// pool can insert it as a new leaf. Recipients and amounts are encoded per
+/// the target's `encoding`; a split recipient takes two elements.
+fn withdrawal_public_inputs(
+    merkle_root: [u8; 32],
+    nullifier: [u8; 32],
+    outputs: &[WithdrawalOutput],
+    amount: u64,
+    fee: u64,
+    change_commitment: Option<[u8; 32]>,
+    encoding: &InputEncoding,
+) -> Result<Vec<[u8; 32]>> {
+    let mut inputs = Vec::new();
+    inputs.push(merkle_root);
+    inputs.push(nullifier);
+
+    match outputs {
+        [single] => {
+            inputs.extend(encoding.encode_address(&single.recipient)?);
+            inputs.push(encoding.encode_u64(amount));
+        }
+        _ => {
+            inputs.push(encoding.encode_u64(amount));
+            inputs.push(encoding.encode_u64(fee));
+            inputs.push(encoding.encode_u64(outputs.len() as u64));
+            for output in outputs {
+                inputs.extend(encoding.encode_address(&output.recipient)?);
+                inputs.push(encoding.encode_u64(output.amount));
+            }
+        }
+    }
+
+    if let Some(change) = change_commitment {
+        inputs.push(change);
+    }
+
+    Ok(inputs)
+}
+
 /// Generate dummy proof for testing
 fn generate_dummy_proof(
     _secret: &[u8; 32],
     _randomness: &[u8; 32],
     _merkle_path: &[[u8; 32]],
+    public_inputs: &[[u8; 32]],
+    system: ProofSystem,
 ) -> Vec<u8> {
-    // In production, this would be a real Groth16 proof
-    // Groth16 proofs are 192 bytes (2 * 32 + 2 * 32 + 2 * 32)
-    vec![0u8; 192]
+    // The placeholder has the system's size and is bound to its inputs so
+    // the placeholder verifier can check it
+    verifier::placeholder_proof(system, public_inputs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::merkle::TREE_DEPTH;
 
     #[tokio::test]
     async fn test_prover_disabled() {
@@ -270,6 +1429,7 @@ mod tests {
             enabled: false,
             max_concurrent: 1,
             timeout_secs: 60,
+            ..ProverConfig::placeholder()
         };
 
         let prover = ProverService::new(&config).unwrap();
@@ -281,8 +1441,25 @@ mod tests {
             randomness: [0u8; 32],
         };
 
-        let result = prover.generate(request).await;
-        assert!(result.is_err());
+        let result = prover.generate(request, 1).await;
+        assert!(matches!(result, Err(RelayerError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request_is_validation_error() {
+        let config = ProverConfig {
+            enabled: true,
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+
+        // A zero-amount output never reaches the queue
+        let err = prover
+            .generate(withdrawal_request(0, 0, None, 0), 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RelayerError::Validation(_)));
+        assert!(err.to_string().contains("Zero-amount output"));
     }
 
     #[tokio::test]
@@ -291,10 +1468,965 @@ mod tests {
             enabled: true,
             max_concurrent: 2,
             timeout_secs: 60,
+            ..ProverConfig::placeholder()
         };
 
         let prover = ProverService::new(&config).unwrap();
         assert!(prover.is_available());
         assert_eq!(prover.queue_depth(), 0);
     }
+
+    #[tokio::test]
+    async fn test_placeholder_proofs_need_opt_in() {
+        let err = ProverService::new(&ProverConfig::default()).err().unwrap();
+        assert!(matches!(err, RelayerError::Config(_)));
+        assert!(err.to_string().contains("allow_placeholder_proofs"));
+
+        let prover = ProverService::new(&ProverConfig::placeholder()).unwrap();
+        assert_eq!(prover.backend(), "placeholder");
+    }
+
+    #[tokio::test]
+    async fn test_repeated_request_served_from_cache() {
+        let config = ProverConfig {
+            enabled: true,
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        let request = withdrawal_request(1_000, 1_000, None, 0);
+
+        let first = prover.generate(request.clone(), 1).await.unwrap();
+        let hits = metrics::PROOF_CACHE_HITS.get();
+        let started = std::time::Instant::now();
+        let second = prover.generate(request, 1).await.unwrap();
+
+        assert!(started.elapsed() < std::time::Duration::from_millis(50));
+        assert!(metrics::PROOF_CACHE_HITS.get() > hits);
+        assert_eq!(second.generation_time_ms, 0);
+        assert_eq!(second.proof_data, first.proof_data);
+        assert_eq!(second.public_inputs, first.public_inputs);
+    }
+
+    fn withdrawal_request(
+        amount: u64,
+        note_value: u64,
+        change_commitment: Option<[u8; 32]>,
+        change_value: u64,
+    ) -> ProofRequest {
+        ProofRequest::Withdrawal {
+            merkle_root: [1u8; 32],
+            nullifier: [2u8; 32],
+            outputs: vec![WithdrawalOutput {
+                recipient: vec![3u8; 20],
+                amount,
+            }],
+            amount,
+            fee: 0,
+            note_value,
+            change_commitment,
+            change_value,
+            secret: [0u8; 32],
+            randomness: [0u8; 32],
+            merkle_path: vec![[0u8; 32]; TREE_DEPTH],
+            merkle_indices: vec![0; TREE_DEPTH],
+        }
+    }
+
+    #[test]
+    fn test_full_withdrawal_public_inputs() {
+        assert!(withdrawal_request(500, 500, None, 0).validate().is_ok());
+
+        let outputs = [WithdrawalOutput {
+            recipient: vec![3u8; 20],
+            amount: 500,
+        }];
+        let inputs = withdrawal_public_inputs(
+            [1u8; 32],
+            [2u8; 32],
+            &outputs,
+            500,
+            0,
+            None,
+            &InputEncoding::default(),
+        )
+        .unwrap();
+        assert_eq!(inputs.len(), 4);
+        assert_eq!(inputs[2][..12], [0u8; 12]);
+        assert_eq!(inputs[2][12..], [3u8; 20]);
+        assert_eq!(inputs[3][24..], 500u64.to_be_bytes());
+    }
+
+    #[test]
+    fn test_partial_withdrawal_public_inputs() {
+        assert!(withdrawal_request(300, 500, Some([9u8; 32]), 200)
+            .validate()
+            .is_ok());
+
+        let outputs = [WithdrawalOutput {
+            recipient: vec![3u8; 20],
+            amount: 300,
+        }];
+        let inputs = withdrawal_public_inputs(
+            [1u8; 32],
+            [2u8; 32],
+            &outputs,
+            300,
+            0,
+            Some([9u8; 32]),
+            &InputEncoding::default(),
+        )
+        .unwrap();
+        assert_eq!(inputs.len(), 5);
+        assert_eq!(inputs[3][24..], 300u64.to_be_bytes());
+        assert_eq!(inputs[4], [9u8; 32]);
+    }
+
+    #[test]
+    fn test_inconsistent_change_rejected() {
+        // Amount plus change doesn't add up to the note
+        assert!(withdrawal_request(300, 500, Some([9u8; 32]), 100)
+            .validate()
+            .is_err());
+        // Change value without a commitment to hold it
+        assert!(withdrawal_request(300, 500, None, 200).validate().is_err());
+        // Change commitment with nothing in it
+        assert!(withdrawal_request(500, 500, Some([9u8; 32]), 0)
+            .validate()
+            .is_err());
+    }
+
+    fn multi_output_request(outputs: Vec<WithdrawalOutput>, amount: u64, fee: u64) -> ProofRequest {
+        ProofRequest::Withdrawal {
+            merkle_root: [1u8; 32],
+            nullifier: [2u8; 32],
+            outputs,
+            amount,
+            fee,
+            note_value: amount,
+            change_commitment: None,
+            change_value: 0,
+            secret: [0u8; 32],
+            randomness: [0u8; 32],
+            merkle_path: vec![[0u8; 32]; TREE_DEPTH],
+            merkle_indices: vec![0; TREE_DEPTH],
+        }
+    }
+
+    #[test]
+    fn test_multi_recipient_public_inputs() {
+        let outputs = vec![
+            WithdrawalOutput {
+                recipient: vec![3u8; 20],
+                amount: 400,
+            },
+            WithdrawalOutput {
+                recipient: vec![4u8; 20],
+                amount: 90,
+            },
+        ];
+        assert!(multi_output_request(outputs.clone(), 500, 10)
+            .validate()
+            .is_ok());
+
+        let inputs = withdrawal_public_inputs(
+            [1u8; 32],
+            [2u8; 32],
+            &outputs,
+            500,
+            10,
+            None,
+            &InputEncoding::default(),
+        )
+        .unwrap();
+        assert_eq!(inputs.len(), 9);
+        assert_eq!(inputs[2][24..], 500u64.to_be_bytes());
+        assert_eq!(inputs[3][24..], 10u64.to_be_bytes());
+        assert_eq!(inputs[4][24..], 2u64.to_be_bytes());
+        assert_eq!(inputs[5][12..], [3u8; 20]);
+        assert_eq!(inputs[6][24..], 400u64.to_be_bytes());
+        assert_eq!(inputs[7][12..], [4u8; 20]);
+        assert_eq!(inputs[8][24..], 90u64.to_be_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_target_chain_encoding_used() {
+        let config = ProverConfig {
+            input_encoding: vec![crate::InputEncodingConfig {
+                chain_id: 7,
+                encoding: InputEncoding {
+                    address_bytes: 32,
+                    endianness: encoding::Endianness::Little,
+                    address_packing: encoding::AddressPacking::Split,
+                },
+            }],
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        let wide_request = || {
+            multi_output_request(
+                vec![WithdrawalOutput {
+                    recipient: vec![5u8; 32],
+                    amount: 500,
+                }],
+                500,
+                0,
+            )
+        };
+
+        // Wide recipient: split into two limbs, amount little-endian
+        let proof = prover.generate(wide_request(), 7).await.unwrap();
+        assert_eq!(proof.public_inputs.len(), 5);
+        assert_eq!(proof.public_inputs[2][..16], [5u8; 16]);
+        assert_eq!(proof.public_inputs[3][..16], [5u8; 16]);
+        assert_eq!(proof.public_inputs[3][16..], [0u8; 16]);
+        assert_eq!(proof.public_inputs[4][..8], 500u64.to_le_bytes());
+
+        // Other chains keep the EVM layout and reject non-EVM recipients
+        assert!(prover.generate(wide_request(), 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wire_format_round_trips_all_proof_types() {
+        let requests = vec![
+            withdrawal_request(300, 500, Some([9u8; 32]), 200),
+            ProofRequest::Transfer {
+                merkle_root: [1u8; 32],
+                nullifier: [2u8; 32],
+                new_commitment_a: [3u8; 32],
+                new_commitment_b: [4u8; 32],
+                secret: [0u8; 32],
+                randomness: [0u8; 32],
+                merkle_path: vec![[0u8; 32]; TREE_DEPTH],
+                merkle_indices: vec![0; TREE_DEPTH],
+            },
+            ProofRequest::Consistency {
+                pedersen_commitment: [5u8; 32],
+                generators: PedersenGenerators {
+                    g: [0x11; 32],
+                    h: [0x22; 32],
+                },
+                paillier_ciphertext: vec![6u8; 64],
+                value: 10,
+                pedersen_randomness: [7u8; 32],
+                paillier_randomness: vec![8u8; 32],
+            },
+            range_request(),
+        ];
+
+        for request in requests {
+            let proof_type = request.proof_type();
+            let proof = generate_proof(request, &InputEncoding::default(), ProofSystem::Groth16)
+                .await
+                .unwrap();
+            let (compressed, inputs) = proof.to_wire().unwrap();
+            assert!(compressed.len() < proof.proof_data.len(), "{}", proof_type);
+
+            let decoded = GeneratedProof::from_wire(&compressed, &inputs).unwrap();
+            assert_eq!(decoded.proof_type, proof_type);
+            assert_eq!(decoded.proof_system, ProofSystem::Groth16);
+            assert_eq!(decoded.proof_data, proof.proof_data);
+            assert_eq!(decoded.public_inputs, proof.public_inputs);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_proof_system_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let vk_path = dir.path().join("vk");
+        std::fs::write(&vk_path, b"verification-key-bytes").unwrap();
+
+        for system in scheme::PROOF_SYSTEMS {
+            let config = ProverConfig {
+                circuits: vec![crate::CircuitConfig {
+                    proof_type: "range".to_string(),
+                    version: CURRENT_CIRCUIT_VERSION.to_string(),
+                    vk_path: vk_path.clone(),
+                    bytecode_path: None,
+                    proof_system: system,
+                }],
+                ..ProverConfig::placeholder()
+            };
+            let prover = ProverService::new(&config).unwrap();
+
+            let proof = prover.generate(range_request(), 1).await.unwrap();
+            assert_eq!(proof.proof_system, system);
+            assert_eq!(proof.proof_data.len(), system.proof_len());
+            assert!(prover.verify(&proof).await.unwrap());
+
+            let (compressed, inputs) = proof.to_wire().unwrap();
+            let decoded = GeneratedProof::from_wire(&compressed, &inputs).unwrap();
+            assert_eq!(decoded.proof_system, system);
+            assert_eq!(decoded.proof_data, proof.proof_data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consistency_generators_checked() {
+        let consistency =
+            |generators: PedersenGenerators, ciphertext_len: usize| ProofRequest::Consistency {
+                pedersen_commitment: [5u8; 32],
+                generators,
+                paillier_ciphertext: vec![6u8; ciphertext_len],
+                value: 10,
+                pedersen_randomness: [7u8; 32],
+                paillier_randomness: vec![8u8; 32],
+            };
+        let configured = PedersenGenerators {
+            g: [0x11; 32],
+            h: [0x22; 32],
+        };
+
+        // Refused outright without configured parameters
+        let unconfigured = ProverService::new(&ProverConfig::placeholder()).unwrap();
+        assert!(unconfigured
+            .generate(consistency(configured, 512), 1)
+            .await
+            .is_err());
+
+        let config = ProverConfig {
+            consistency: Some(consistency::test_config()),
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        let proof = prover
+            .generate(consistency(configured, 512), 1)
+            .await
+            .unwrap();
+        assert_eq!(proof.public_inputs[1..], [configured.g, configured.h]);
+
+        let swapped = PedersenGenerators {
+            g: configured.h,
+            h: configured.g,
+        };
+        let err = prover
+            .generate(consistency(swapped, 512), 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Prover(ProverError::GeneratorMismatch)
+        ));
+
+        // Ciphertexts must live modulo n² of the configured key
+        let err = prover
+            .generate(consistency(configured, 64), 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Prover(ProverError::CiphertextLength {
+                expected: 512,
+                got: 64
+            })
+        ));
+
+        // Bad parameters fail at startup
+        let mut invalid = consistency::test_config();
+        invalid.pedersen_h = invalid.pedersen_g.clone();
+        let config = ProverConfig {
+            consistency: Some(invalid),
+            ..ProverConfig::placeholder()
+        };
+        assert!(ProverService::new(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merkle_path_length_checked() {
+        let config = ProverConfig {
+            tree_depth: vec![crate::TreeDepthConfig {
+                chain_id: 7,
+                depth: 32,
+            }],
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        let with_path = |path_len: usize, indices_len: usize| {
+            let mut request = withdrawal_request(500, 500, None, 0);
+            if let ProofRequest::Withdrawal {
+                merkle_path,
+                merkle_indices,
+                ..
+            } = &mut request
+            {
+                *merkle_path = vec![[0u8; 32]; path_len];
+                *merkle_indices = vec![0; indices_len];
+            }
+            request
+        };
+        let path_error = |result: Result<GeneratedProof, RelayerError>| match result {
+            Err(RelayerError::Prover(err)) => Some(err),
+            _ => None,
+        };
+
+        // Too short
+        assert!(matches!(
+            path_error(
+                prover
+                    .generate(with_path(TREE_DEPTH - 1, TREE_DEPTH - 1), 1)
+                    .await
+            ),
+            Some(ProverError::MerklePathLength {
+                tree_depth: TREE_DEPTH,
+                path_len: 19,
+                indices_len: 19,
+            })
+        ));
+        // Too long
+        assert!(matches!(
+            path_error(
+                prover
+                    .generate(with_path(TREE_DEPTH + 1, TREE_DEPTH + 1), 1)
+                    .await
+            ),
+            Some(ProverError::MerklePathLength { path_len: 21, .. })
+        ));
+        // Indices must match the path
+        assert!(matches!(
+            path_error(
+                prover
+                    .generate(with_path(TREE_DEPTH, TREE_DEPTH - 1), 1)
+                    .await
+            ),
+            Some(ProverError::MerklePathLength {
+                indices_len: 19,
+                ..
+            })
+        ));
+
+        // Each index picks a side
+        let mut bad_index = with_path(TREE_DEPTH, TREE_DEPTH);
+        if let ProofRequest::Withdrawal { merkle_indices, .. } = &mut bad_index {
+            merkle_indices[3] = 2;
+        }
+        assert!(matches!(
+            path_error(prover.generate(bad_index, 1).await),
+            Some(ProverError::MerkleIndex { level: 3, index: 2 })
+        ));
+
+        // Depth follows the target pool
+        assert!(prover
+            .generate(with_path(TREE_DEPTH, TREE_DEPTH), 1)
+            .await
+            .is_ok());
+        assert!(prover.generate(with_path(32, 32), 7).await.is_ok());
+        assert!(path_error(prover.generate(with_path(TREE_DEPTH, TREE_DEPTH), 7).await).is_some());
+    }
+
+    #[test]
+    fn test_output_sum_validation() {
+        let output = |recipient: u8, amount| WithdrawalOutput {
+            recipient: vec![recipient; 20],
+            amount,
+        };
+
+        // Outputs must cover exactly amount - fee
+        assert!(
+            multi_output_request(vec![output(3, 400), output(4, 100)], 500, 10)
+                .validate()
+                .is_err()
+        );
+        // Fee larger than the withdrawal
+        assert!(multi_output_request(vec![output(3, 1)], 500, 600)
+            .validate()
+            .is_err());
+        // Duplicate recipient
+        assert!(
+            multi_output_request(vec![output(3, 245), output(3, 245)], 500, 10)
+                .validate()
+                .is_err()
+        );
+        // Zero-amount output
+        assert!(
+            multi_output_request(vec![output(3, 490), output(4, 0)], 500, 10)
+                .validate()
+                .is_err()
+        );
+        // No outputs at all
+        assert!(multi_output_request(vec![], 500, 500).validate().is_err());
+    }
+
+    fn range_job() -> (
+        ProofJob,
+        mpsc::Receiver<Result<GeneratedProof, ProverError>>,
+    ) {
+        let (response_tx, response_rx) = mpsc::channel(1);
+        let request = ProofRequest::Range {
+            commitment: [0u8; 32],
+            min_value: 0,
+            value: 1,
+            randomness: [0u8; 32],
+        };
+        (
+            (
+                request,
+                InputEncoding::default(),
+                ProofSystem::default(),
+                response_tx,
+                CancellationToken::new(),
+            ),
+            response_rx,
+        )
+    }
+
+    fn tagged_job(tag: u64) -> ProofJob {
+        let (response_tx, _) = mpsc::channel(1);
+        let request = ProofRequest::Range {
+            commitment: [0u8; 32],
+            min_value: 0,
+            value: tag,
+            randomness: [0u8; 32],
+        };
+        (
+            request,
+            InputEncoding::default(),
+            ProofSystem::default(),
+            response_tx,
+            CancellationToken::new(),
+        )
+    }
+
+    async fn pop_tag(queue: &RequestQueue) -> u64 {
+        match queue.pop().await.unwrap().0 {
+            ProofRequest::Range { value, .. } => value,
+            other => panic!("unexpected request {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_blocks() {
+        let queue = RequestQueue::new(2, QueueFullPolicy::Block, 0.0);
+        queue.push(range_job().0, 0.0).await.unwrap();
+        queue.push(range_job().0, 0.0).await.unwrap();
+
+        let blocked = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            queue.push(range_job().0, 0.0),
+        )
+        .await;
+        assert!(blocked.is_err(), "push should wait for space");
+
+        // Freeing a slot lets the blocked producer through
+        queue.pop().await.unwrap();
+        queue.push(range_job().0, 0.0).await.unwrap();
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_rejects_newest() {
+        let queue = RequestQueue::new(2, QueueFullPolicy::RejectNewest, 0.0);
+        queue.push(range_job().0, 0.0).await.unwrap();
+        queue.push(range_job().0, 0.0).await.unwrap();
+
+        let before = metrics::PROOF_REQUESTS_REJECTED
+            .with_label_values(&["reject_newest"])
+            .get();
+        let err = queue.push(range_job().0, 0.0).await.unwrap_err();
+        assert!(matches!(err, ProverError::QueueFull));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(
+            metrics::PROOF_REQUESTS_REJECTED
+                .with_label_values(&["reject_newest"])
+                .get(),
+            before + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_rejects_oldest() {
+        let queue = RequestQueue::new(2, QueueFullPolicy::RejectOldest, 0.0);
+        let (oldest, mut oldest_rx) = range_job();
+        queue.push(oldest, 0.0).await.unwrap();
+        queue.push(range_job().0, 0.0).await.unwrap();
+
+        queue.push(range_job().0, 0.0).await.unwrap();
+        assert_eq!(queue.len(), 2);
+
+        let evicted = oldest_rx.recv().await.unwrap().unwrap_err();
+        assert!(matches!(evicted, ProverError::QueueFull));
+    }
+
+    #[tokio::test]
+    async fn test_higher_fee_proves_first() {
+        let config = ProverConfig {
+            fee_priority: vec![crate::FeePriorityConfig {
+                chain_id: 42161,
+                fee_per_point: 10,
+            }],
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        let mut low_fee = withdrawal_request(500, 500, None, 0);
+        let mut high_fee = withdrawal_request(500, 500, None, 0);
+        if let ProofRequest::Withdrawal { fee, outputs, .. } = &mut low_fee {
+            *fee = 20;
+            outputs[0].amount = 480;
+        }
+        if let ProofRequest::Withdrawal { fee, outputs, .. } = &mut high_fee {
+            *fee = 200;
+            outputs[0].amount = 300;
+        }
+
+        // Fees are normalized per chain; unlisted chains use one unit per point
+        assert_eq!(prover.priority(&low_fee, 42161), WITHDRAWAL_PRIORITY + 2.0);
+        assert_eq!(
+            prover.priority(&high_fee, 42161),
+            WITHDRAWAL_PRIORITY + 20.0
+        );
+        assert_eq!(prover.priority(&high_fee, 1), WITHDRAWAL_PRIORITY + 200.0);
+
+        let queue = RequestQueue::new(10, QueueFullPolicy::Block, 0.0);
+        queue
+            .push(tagged_job(1), prover.priority(&low_fee, 42161))
+            .await
+            .unwrap();
+        queue
+            .push(tagged_job(2), prover.priority(&high_fee, 42161))
+            .await
+            .unwrap();
+
+        assert_eq!(pop_tag(&queue).await, 2);
+        assert_eq!(pop_tag(&queue).await, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_aged_low_fee_request_runs() {
+        let queue = RequestQueue::new(10, QueueFullPolicy::Block, 1.0);
+        queue.push(tagged_job(1), 0.0).await.unwrap();
+
+        // Waiting longer than the fee gap outranks a fresh high-fee job
+        tokio::time::advance(std::time::Duration::from_secs(60)).await;
+        queue.push(tagged_job(2), 50.0).await.unwrap();
+        queue.push(tagged_job(3), 50.0).await.unwrap();
+
+        assert_eq!(pop_tag(&queue).await, 1);
+        // Equal priority falls back to arrival order
+        assert_eq!(pop_tag(&queue).await, 2);
+        assert_eq!(pop_tag(&queue).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_generated_proofs_verify_in_batch() {
+        let prover = ProverService::new(&ProverConfig::placeholder()).unwrap();
+        let proof = prover
+            .generate(withdrawal_request(500, 500, None, 0), 1)
+            .await
+            .unwrap();
+        assert!(prover.verify(&proof).await.unwrap());
+
+        // Same proof claimed for a different recipient
+        let mut tampered = proof.public_inputs.clone();
+        tampered[2][31] ^= 1;
+
+        let results = prover.verify_batch(vec![
+            (proof.proof_data.clone(), proof.public_inputs.clone()),
+            (proof.proof_data.clone(), tampered),
+            (proof.proof_data, proof.public_inputs),
+        ]);
+        assert_eq!(results, vec![true, false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_altered_proof() {
+        // A node that only validates can still check proofs
+        let prover = ProverService::new(&ProverConfig::placeholder()).unwrap();
+        let proof = prover
+            .generate(withdrawal_request(500, 500, None, 0), 1)
+            .await
+            .unwrap();
+        let validator = ProverService::new(&ProverConfig {
+            enabled: false,
+            ..ProverConfig::placeholder()
+        })
+        .unwrap();
+        assert!(validator.verify(&proof).await.unwrap());
+
+        let mut flipped = proof.clone();
+        flipped.proof_data[0] ^= 1;
+        assert!(!validator.verify(&flipped).await.unwrap());
+
+        // Claimed in a proof system its circuit isn't deployed with
+        let mut wrong_system = proof.clone();
+        wrong_system.proof_system = ProofSystem::Plonk;
+        assert!(!validator.verify(&wrong_system).await.unwrap());
+
+        // Without the opt-in a placeholder is never taken for a real proof
+        let strict = ProverService::new(&ProverConfig {
+            enabled: false,
+            allow_placeholder_proofs: false,
+            ..ProverConfig::placeholder()
+        })
+        .unwrap();
+        assert!(matches!(
+            strict.verify(&proof).await,
+            Err(RelayerError::Prover(ProverError::Verification { .. }))
+        ));
+    }
+
+    /// Generator that never finishes
+    struct HangingGenerator;
+
+    #[async_trait]
+    impl ProofGenerator for HangingGenerator {
+        async fn generate(
+            &self,
+            _request: ProofRequest,
+            _encoding: &InputEncoding,
+            _system: ProofSystem,
+        ) -> Result<GeneratedProof> {
+            std::future::pending().await
+        }
+    }
+
+    /// Generator that always errors
+    struct FailingGenerator;
+
+    #[async_trait]
+    impl ProofGenerator for FailingGenerator {
+        async fn generate(
+            &self,
+            _request: ProofRequest,
+            _encoding: &InputEncoding,
+            _system: ProofSystem,
+        ) -> Result<GeneratedProof> {
+            Err(anyhow::anyhow!("constraint not satisfied"))
+        }
+    }
+
+    fn range_request() -> ProofRequest {
+        range_job().0 .0
+    }
+
+    /// Generator recording the order jobs start in, each held until released
+    struct GatedGenerator {
+        started: Mutex<Vec<&'static str>>,
+        gate: Semaphore,
+    }
+
+    #[async_trait]
+    impl ProofGenerator for GatedGenerator {
+        async fn generate(
+            &self,
+            request: ProofRequest,
+            encoding: &InputEncoding,
+            system: ProofSystem,
+        ) -> Result<GeneratedProof> {
+            self.started.lock().unwrap().push(request.proof_type());
+            self.gate.acquire().await?.forget();
+            generate_proof(request, encoding, system).await
+        }
+    }
+
+    async fn wait_until(done: impl Fn() -> bool) {
+        while !done() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_withdrawal_dispatched_before_queued_range_proofs() {
+        let config = ProverConfig {
+            max_concurrent: 1,
+            cache_capacity: 0,
+            ..ProverConfig::placeholder()
+        };
+        let generator = Arc::new(GatedGenerator {
+            started: Mutex::default(),
+            gate: Semaphore::new(0),
+        });
+        let prover = Arc::new(ProverService::with_generator(&config, generator.clone()).unwrap());
+        let submit = |request: ProofRequest| {
+            let prover = prover.clone();
+            tokio::spawn(async move { prover.generate(request, 1).await })
+        };
+
+        // The only slot is busy with a range proof while the rest queue up
+        let mut jobs = vec![submit(range_request())];
+        wait_until(|| generator.started.lock().unwrap().len() == 1).await;
+        for _ in 0..3 {
+            jobs.push(submit(range_request()));
+        }
+        wait_until(|| prover.queue.len() == 3).await;
+        jobs.push(submit(withdrawal_request(500, 500, None, 0)));
+        wait_until(|| prover.queue.len() == 4).await;
+
+        generator.gate.add_permits(jobs.len());
+        for job in jobs {
+            job.await.unwrap().unwrap();
+        }
+        assert_eq!(
+            *generator.started.lock().unwrap(),
+            vec!["range", "withdrawal", "range", "range", "range"]
+        );
+    }
+
+    /// Generator reporting a range proof's value as its generation time
+    struct TimedGenerator;
+
+    #[async_trait]
+    impl ProofGenerator for TimedGenerator {
+        async fn generate(
+            &self,
+            request: ProofRequest,
+            encoding: &InputEncoding,
+            system: ProofSystem,
+        ) -> Result<GeneratedProof> {
+            let ProofRequest::Range { value, .. } = request else {
+                anyhow::bail!("only range proofs are timed");
+            };
+            let proof = generate_proof(request, encoding, system).await?;
+            Ok(GeneratedProof {
+                generation_time_ms: value,
+                ..proof
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_report_latency_percentiles() {
+        let prover =
+            ProverService::with_generator(&ProverConfig::placeholder(), Arc::new(TimedGenerator))
+                .unwrap();
+        assert_eq!(prover.stats(), ProverStats::default());
+
+        for ms in (10..=200).step_by(10) {
+            let request = ProofRequest::Range {
+                commitment: [0u8; 32],
+                min_value: 0,
+                value: ms,
+                randomness: [0u8; 32],
+            };
+            prover.generate(request, 1).await.unwrap();
+        }
+
+        // Slots are released just after results are delivered
+        wait_until(|| prover.queue_depth() == 0).await;
+        let stats = prover.stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.queued, 0);
+        let range = stats.by_type["range"];
+        assert_eq!(range.count, 20);
+        assert_eq!((range.p50_ms, range.p95_ms, range.p99_ms), (100, 190, 200));
+        assert!(!stats.by_type.contains_key("withdrawal"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_proof_releases_permit() {
+        let config = ProverConfig {
+            max_concurrent: 1,
+            ..ProverConfig::placeholder()
+        };
+        let generator = Arc::new(GatedGenerator {
+            started: Mutex::default(),
+            gate: Semaphore::new(0),
+        });
+        let prover = Arc::new(ProverService::with_generator(&config, generator.clone()).unwrap());
+        let cancelled_before = metrics::PROOFS_CANCELLED
+            .with_label_values(&["range"])
+            .get();
+
+        let cancel = CancellationToken::new();
+        let job = tokio::spawn({
+            let (prover, cancel) = (prover.clone(), cancel.clone());
+            async move {
+                prover
+                    .generate_cancellable(range_request(), 1, cancel)
+                    .await
+            }
+        });
+        wait_until(|| generator.started.lock().unwrap().len() == 1).await;
+        assert_eq!(prover.queue_depth(), 1);
+        assert!(!prover.is_available());
+
+        cancel.cancel();
+        let err = job.await.unwrap().unwrap_err();
+        assert!(matches!(err, RelayerError::Prover(ProverError::Cancelled)));
+
+        // The slot frees up without the generator ever finishing
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            wait_until(|| prover.queue_depth() == 0),
+        )
+        .await
+        .expect("permit released");
+        assert!(prover.is_available());
+        assert!(
+            metrics::PROOFS_CANCELLED
+                .with_label_values(&["range"])
+                .get()
+                > cancelled_before
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_counted_separately_from_failure() {
+        let config = ProverConfig {
+            timeout_secs: 5,
+            ..ProverConfig::placeholder()
+        };
+        let timeouts = metrics::PROOFS_TIMEOUT.with_label_values(&["range"]);
+        let failures = metrics::PROOFS_FAILED.with_label_values(&["range"]);
+
+        let (timeouts_before, failures_before) = (timeouts.get(), failures.get());
+        let prover = ProverService::with_generator(&config, Arc::new(HangingGenerator)).unwrap();
+        let err = prover.generate(range_request(), 1).await.unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Prover(ProverError::Timeout {
+                proof_type: "range",
+                timeout_secs: 5
+            })
+        ));
+        assert_eq!(timeouts.get(), timeouts_before + 1);
+        assert_eq!(failures.get(), failures_before);
+
+        let (timeouts_before, failures_before) = (timeouts.get(), failures.get());
+        let prover = ProverService::with_generator(&config, Arc::new(FailingGenerator)).unwrap();
+        let err = prover.generate(range_request(), 1).await.unwrap_err();
+        assert!(matches!(
+            err,
+            RelayerError::Prover(ProverError::Failed {
+                proof_type: "range",
+                ..
+            })
+        ));
+        assert_eq!(failures.get(), failures_before + 1);
+        assert_eq!(timeouts.get(), timeouts_before);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_worker_panic_detected_and_restarted() {
+        let prover = ProverService::without_worker(&ProverConfig::placeholder()).unwrap();
+        let restarts = metrics::PROVER_WORKER_RESTARTS.get();
+
+        // First worker panics straight away; its replacement is the real one
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (queue, semaphore) = (prover.queue.clone(), prover.semaphore.clone());
+        tokio::spawn(supervise(prover.worker_health(), move || {
+            let first = attempts.fetch_add(1, Ordering::SeqCst) == 0;
+            let worker = run_worker(
+                queue.clone(),
+                semaphore.clone(),
+                Arc::new(PlaceholderGenerator),
+                Arc::default(),
+                60,
+            );
+            async move {
+                if first {
+                    panic!("forced worker panic");
+                }
+                worker.await
+            }
+        }));
+
+        while prover.is_available() {
+            tokio::task::yield_now().await;
+        }
+        assert!(!prover.worker_health().is_alive());
+        assert!(metrics::PROVER_WORKER_RESTARTS.get() > restarts);
+
+        // Requests made while the worker is down are served after the restart
+        let proof = prover.generate(range_request(), 1).await.unwrap();
+        assert!(!proof.proof_data.is_empty());
+        assert!(prover.is_available());
+    }
 }
@@ -0,0 +1,309 @@
+//! Proving on an external HTTP prover
+//!
+//! The request is POSTed as JSON together with the target's input encoding
+//! and the circuit's proof system, and the prover answers with the proof.
+//! The full witness (secret, randomness, Merkle path) goes over the wire, so
+//! the remote must be trusted and reached over TLS or a private network.
+//! Server errors are retried with exponential backoff while the job's time
+//! budget lasts; anything else fails the job with the remote's error body.
+//! A proof is only accepted if its public inputs are the ones the request
+//! encodes to.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use ethers::types::{Bytes, H256};
+use reqwest::StatusCode;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use super::encoding::InputEncoding;
+use super::scheme::ProofSystem;
+use super::{GeneratedProof, ProofGenerator, ProofRequest};
+
+/// Attempts made before a failing remote is given up on
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Body POSTed to the remote prover
+#[derive(Debug, serde::Serialize)]
+struct RemoteProofRequest<'a> {
+    request: &'a ProofRequest,
+    encoding: &'a InputEncoding,
+    proof_system: ProofSystem,
+}
+
+/// Proof returned by the remote prover
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RemoteProof {
+    pub proof_type: String,
+    pub proof_system: ProofSystem,
+    pub proof: Bytes,
+    pub public_inputs: Vec<H256>,
+    #[serde(default)]
+    pub generation_time_ms: u64,
+}
+
+impl From<RemoteProof> for GeneratedProof {
+    fn from(remote: RemoteProof) -> Self {
+        Self {
+            proof_type: remote.proof_type,
+            proof_system: remote.proof_system,
+            proof_data: remote.proof.to_vec(),
+            public_inputs: remote
+                .public_inputs
+                .into_iter()
+                .map(|input| input.0)
+                .collect(),
+            generation_time_ms: remote.generation_time_ms,
+        }
+    }
+}
+
+/// Generator forwarding requests to an external prover
+pub struct RemoteGenerator {
+    client: reqwest::Client,
+    url: String,
+    /// Time for all attempts and backoff together
+    budget: Duration,
+}
+
+impl RemoteGenerator {
+    /// All attempts at a proof, retries included, share `budget`
+    pub fn new(url: impl Into<String>, budget: Duration) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().build()?,
+            url: url.into(),
+            budget,
+        })
+    }
+}
+
+#[async_trait]
+impl ProofGenerator for RemoteGenerator {
+    fn name(&self) -> &'static str {
+        "remote_http"
+    }
+
+    async fn generate(
+        &self,
+        request: ProofRequest,
+        encoding: &InputEncoding,
+        system: ProofSystem,
+    ) -> Result<GeneratedProof> {
+        let expected_inputs = request.public_inputs(encoding)?;
+        let body = RemoteProofRequest {
+            request: &request,
+            encoding,
+            proof_system: system,
+        };
+
+        let deadline = Instant::now() + self.budget;
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        let response = loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .timeout(deadline.saturating_duration_since(Instant::now()))
+                .send()
+                .await?;
+            let status = response.status();
+            if status.is_success() {
+                break response;
+            }
+
+            let error = response.text().await.unwrap_or_default();
+            // Only retry if the backoff leaves time for another attempt
+            let retry_in_time = deadline.saturating_duration_since(Instant::now()) > backoff;
+            if status.is_server_error() && attempt < MAX_ATTEMPTS && retry_in_time {
+                warn!(
+                    status = %status,
+                    attempt = attempt,
+                    error = %error,
+                    "Remote prover failed, retrying"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+                continue;
+            }
+            bail!("Remote prover returned {}: {}", status, error);
+        };
+
+        let proof: GeneratedProof = response.json::<RemoteProof>().await?.into();
+        if proof.proof_type != request.proof_type() || proof.proof_system != system {
+            bail!(
+                "Remote prover answered a {} {:?} proof for a {} {:?} request",
+                proof.proof_type,
+                proof.proof_system,
+                request.proof_type(),
+                system
+            );
+        }
+        if proof.public_inputs != expected_inputs {
+            bail!(
+                "Remote prover answered a {} proof for other public inputs than requested",
+                proof.proof_type
+            );
+        }
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::{ProverBackend, ProverService};
+    use crate::ProverConfig;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// Queued responses, replayed in order, and the bodies received
+    #[derive(Clone, Default)]
+    struct MockProver {
+        responses: Arc<Mutex<Vec<(StatusCode, String)>>>,
+        received: Arc<Mutex<Vec<serde_json::Value>>>,
+    }
+
+    async fn mock_prover(responses: Vec<(StatusCode, String)>) -> (String, MockProver) {
+        let mock = MockProver {
+            responses: Arc::new(Mutex::new(responses)),
+            ..MockProver::default()
+        };
+        let app = Router::new()
+            .route(
+                "/prove",
+                post(
+                    |State(mock): State<MockProver>, Json(body): Json<serde_json::Value>| async move {
+                        mock.received.lock().unwrap().push(body);
+                        mock.responses.lock().unwrap().remove(0)
+                    },
+                ),
+            )
+            .with_state(mock.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/prove", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, mock)
+    }
+
+    fn range_request() -> ProofRequest {
+        ProofRequest::Range {
+            commitment: [4u8; 32],
+            min_value: 100,
+            value: 200,
+            randomness: [0u8; 32],
+        }
+    }
+
+    fn canned_proof(public_inputs: Vec<[u8; 32]>) -> String {
+        serde_json::to_string(&RemoteProof {
+            proof_type: "range".to_string(),
+            proof_system: ProofSystem::Groth16,
+            proof: Bytes::from(vec![9u8; 256]),
+            public_inputs: public_inputs.into_iter().map(H256).collect(),
+            generation_time_ms: 1_500,
+        })
+        .unwrap()
+    }
+
+    fn range_inputs() -> Vec<[u8; 32]> {
+        range_request()
+            .public_inputs(&InputEncoding::default())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_remote_proof_after_server_error() {
+        let (url, mock) = mock_prover(vec![
+            (StatusCode::SERVICE_UNAVAILABLE, "warming up".to_string()),
+            (StatusCode::OK, canned_proof(range_inputs())),
+        ])
+        .await;
+        let config = ProverConfig {
+            enabled: true,
+            backend: ProverBackend::RemoteHttp { url },
+            ..ProverConfig::placeholder()
+        };
+        let prover = ProverService::new(&config).unwrap();
+        assert_eq!(prover.backend(), "remote_http");
+
+        let proof = prover.generate(range_request(), 1).await.unwrap();
+        assert_eq!(proof.proof_data, vec![9u8; 256]);
+        assert_eq!(proof.public_inputs, range_inputs());
+        assert_eq!(proof.generation_time_ms, 1_500);
+
+        let received = mock.received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1]["request"]["type"], "range");
+        assert_eq!(received[1]["proof_system"], "groth16");
+    }
+
+    #[tokio::test]
+    async fn test_remote_client_error_not_retried() {
+        let (url, mock) = mock_prover(vec![(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "witness does not satisfy the circuit".to_string(),
+        )])
+        .await;
+        let generator = RemoteGenerator::new(url, Duration::from_secs(5)).unwrap();
+
+        let err = generator
+            .generate(
+                range_request(),
+                &InputEncoding::default(),
+                ProofSystem::Groth16,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("422"));
+        assert!(err
+            .to_string()
+            .contains("witness does not satisfy the circuit"));
+        assert_eq!(mock.received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_remote_proof_for_other_inputs_rejected() {
+        let (url, _mock) = mock_prover(vec![(StatusCode::OK, canned_proof(vec![[4u8; 32]]))]).await;
+        let generator = RemoteGenerator::new(url, Duration::from_secs(5)).unwrap();
+
+        let err = generator
+            .generate(
+                range_request(),
+                &InputEncoding::default(),
+                ProofSystem::Groth16,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("other public inputs"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_retries_stop_at_budget() {
+        let (url, mock) = mock_prover(vec![
+            (StatusCode::SERVICE_UNAVAILABLE, "warming up".to_string()),
+            (StatusCode::OK, canned_proof(range_inputs())),
+        ])
+        .await;
+        // No room for the first backoff, so the server error is final
+        let generator = RemoteGenerator::new(url, INITIAL_BACKOFF / 2).unwrap();
+
+        let err = generator
+            .generate(
+                range_request(),
+                &InputEncoding::default(),
+                ProofSystem::Groth16,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("503"));
+        assert_eq!(mock.received.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Proof systems a circuit can be deployed with
+//!
+//! Each circuit in the registry names the proof system its on-chain verifier
+//! expects. The backend proves in that system and the verifier only accepts
+//! proofs of its shape; proofs are fixed-size per system, so a proof of the
+//! wrong size is rejected before any cryptography runs.
+
+use serde::{Deserialize, Serialize};
+
+/// Proof system used by a circuit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofSystem {
+    /// Groth16 over BN254, with uncompressed affine points as the
+    /// Solidity verifier's `uint256[8]`
+    #[default]
+    Groth16,
+    /// PLONK over BN254: 9 G1 commitments and 6 evaluations
+    Plonk,
+    /// Barretenberg UltraHonk with the keccak transcript, padded to the
+    /// fixed size the Solidity verifier reads
+    UltraHonk,
+}
+
+/// Every supported proof system
+pub const PROOF_SYSTEMS: [ProofSystem; 3] = [
+    ProofSystem::Groth16,
+    ProofSystem::Plonk,
+    ProofSystem::UltraHonk,
+];
+
+impl ProofSystem {
+    /// Size of one proof in bytes
+    pub fn proof_len(&self) -> usize {
+        match self {
+            // A and C in G1 (x, y), B in G2 (x and y in Fp2)
+            ProofSystem::Groth16 => 8 * 32,
+            ProofSystem::Plonk => (9 * 2 + 6) * 32,
+            ProofSystem::UltraHonk => 456 * 32,
+        }
+    }
+
+    /// Tag identifying the system in the proof wire format
+    pub fn tag(&self) -> u8 {
+        match self {
+            ProofSystem::Groth16 => 1,
+            ProofSystem::Plonk => 2,
+            ProofSystem::UltraHonk => 3,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        PROOF_SYSTEMS.into_iter().find(|system| system.tag() == tag)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofSystem::Groth16 => "groth16",
+            ProofSystem::Plonk => "plonk",
+            ProofSystem::UltraHonk => "ultra_honk",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::verifier::ProofVerifier;
+    use crate::prover::wire::{compress_proof, decompress_proof};
+    use ethers::types::{U256, U512};
+
+    /// Groth16 proof of knowledge of x with x^3 = 27 (constraints x * x = s,
+    /// s * x = y, public y), from a throwaway BN254 setup and checked with the
+    /// pairing equation before being copied here. Laid out as
+    /// WithdrawalVerifier.sol reads it: A.x, A.y, B.x (imaginary, real),
+    /// B.y (imaginary, real), C.x, C.y.
+    const GROTH16_PROOF: &str = "\
+         28c3f362fcb85b1e3979d0cf391e7de9076eb2d732b5bb4a3edc51d36fd0c2cb\
+         0ec47f94ff0fbf6ef8fbc5a7c7f87c06ddd551180c42f737e7d704a4b689e57c\
+         20c48993e6e22536d55e15bff892f6fdd103177eae6052fb1a90a294697a058e\
+         2f47f731a687df120071c4eb2e06858f59c36d663111b83029c3a778919091f3\
+         2a5883def1e5fc16e4e8ea03b2756b92a2192b14e9fee0cdc43c73e1ba7419d3\
+         1f56dc091bb3d8926efd81e8af8531e6e36edda5b19cc72b55d9607240971296\
+         2314f4db65d6584c6d5e80c9ca33a58a467795f69415641b6986fa213e08f9b2\
+         0fec42ecb4e7a43998952277b16dbb61707ce82405da1be64bcc945360b306ec";
+
+    /// BN254 base field modulus
+    const FIELD_MODULUS: &str = "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47";
+
+    /// Backend that accepts anything, leaving only the length check
+    struct AcceptAll;
+
+    impl ProofVerifier for AcceptAll {
+        fn verify(&self, _proof: &[u8], _public_inputs: &[[u8; 32]]) -> bool {
+            true
+        }
+    }
+
+    fn on_g1(x: U256, y: U256) -> bool {
+        let p = U512::from(U256::from_str_radix(FIELD_MODULUS, 16).unwrap());
+        let mulmod = |a: U512, b: U512| (a * b) % p;
+        let (x, y) = (U512::from(x), U512::from(y));
+        mulmod(y, y) == (mulmod(mulmod(x, x), x) + 3) % p
+    }
+
+    #[test]
+    fn test_groth16_proof_matches_verifier_layout() {
+        let proof = ethers::utils::hex::decode(GROTH16_PROOF).unwrap();
+        assert_eq!(proof.len(), ProofSystem::Groth16.proof_len());
+
+        let words: Vec<U256> = proof.chunks(32).map(U256::from_big_endian).collect();
+        assert!(on_g1(words[0], words[1]), "A");
+        assert!(on_g1(words[6], words[7]), "C");
+
+        let compressed = compress_proof("withdrawal", ProofSystem::Groth16, &proof).unwrap();
+        assert_eq!(
+            decompress_proof(&compressed).unwrap(),
+            ("withdrawal", ProofSystem::Groth16, proof.clone())
+        );
+
+        // Dropping C leaves a proof the length check turns away
+        let mut input = [0u8; 32];
+        U256::from(27).to_big_endian(&mut input);
+        assert!(AcceptAll.verify_with(ProofSystem::Groth16, &proof, &[input]));
+        assert!(!AcceptAll.verify_with(ProofSystem::Groth16, &proof[..192], &[input]));
+    }
+}
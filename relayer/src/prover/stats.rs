@@ -0,0 +1,123 @@
+//! Rolling proving statistics for operators
+//!
+//! Keeps the generation times of the most recent proofs of each type so
+//! `ProverService::stats` can report current percentiles on `/status`
+//! without scraping Prometheus. The Prometheus side gets the same durations through the
+//! `laundry_proof_duration_seconds` histogram.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Generation times kept per proof type
+pub const LATENCY_WINDOW: usize = 1024;
+
+/// Snapshot of the prover's load and recent latencies
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ProverStats {
+    /// Proofs being generated right now
+    pub in_flight: usize,
+    /// Requests waiting for a prover slot
+    pub queued: usize,
+    pub by_type: BTreeMap<String, ProofTypeStats>,
+}
+
+/// Latency of one proof type over the rolling window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ProofTypeStats {
+    /// Proofs generated since startup
+    pub count: u64,
+    /// Requests of this type waiting for a prover slot
+    pub queued: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+#[derive(Default)]
+struct Window {
+    samples: VecDeque<u64>,
+    count: u64,
+}
+
+/// Recent generation times by proof type
+#[derive(Default)]
+pub struct LatencyTracker {
+    windows: Mutex<HashMap<&'static str, Window>>,
+}
+
+impl LatencyTracker {
+    pub fn record(&self, proof_type: &'static str, generation_time_ms: u64) {
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(proof_type).or_default();
+        if window.samples.len() == LATENCY_WINDOW {
+            window.samples.pop_front();
+        }
+        window.samples.push_back(generation_time_ms);
+        window.count += 1;
+    }
+
+    /// Percentiles of every proof type seen
+    pub fn by_type(&self) -> BTreeMap<String, ProofTypeStats> {
+        let windows = self.windows.lock().unwrap();
+        windows
+            .iter()
+            .map(|(proof_type, window)| {
+                let mut samples: Vec<u64> = window.samples.iter().copied().collect();
+                samples.sort_unstable();
+                let stats = ProofTypeStats {
+                    count: window.count,
+                    queued: 0,
+                    p50_ms: percentile(&samples, 0.50),
+                    p95_ms: percentile(&samples, 0.95),
+                    p99_ms: percentile(&samples, 0.99),
+                };
+                (proof_type.to_string(), stats)
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted samples (0 if there are none)
+fn percentile(sorted: &[u64], q: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_over_window() {
+        let tracker = LatencyTracker::default();
+        for ms in 1..=100 {
+            tracker.record("withdrawal", ms);
+        }
+        tracker.record("range", 7);
+
+        let stats = tracker.by_type();
+        assert_eq!(
+            stats["withdrawal"],
+            ProofTypeStats {
+                count: 100,
+                queued: 0,
+                p50_ms: 50,
+                p95_ms: 95,
+                p99_ms: 99,
+            }
+        );
+        assert_eq!(stats["range"].p50_ms, 7);
+        assert_eq!(stats["range"].p99_ms, 7);
+
+        // Old samples roll out of the window, the count keeps going
+        for _ in 0..LATENCY_WINDOW {
+            tracker.record("withdrawal", 1_000);
+        }
+        let withdrawal = tracker.by_type()["withdrawal"];
+        assert_eq!(withdrawal.count, 100 + LATENCY_WINDOW as u64);
+        assert_eq!(withdrawal.p50_ms, 1_000);
+    }
+}
@@ -0,0 +1,200 @@
+//! Proof verification backends
+//!
+//! Backends that can check many proofs in one pass (e.g. via a random
+//! linear combination of pairing checks) expose it through
+//! `ProofVerifier::verify_batch`; `verify_batch` below falls back to
+//! per-proof checks for backends that can't.
+
+use ethers::utils::keccak256;
+
+use super::scheme::ProofSystem;
+
+/// A proof and its public inputs
+pub type ProofWithInputs = (Vec<u8>, Vec<[u8; 32]>);
+
+/// Proof verification backend
+pub trait ProofVerifier: Send + Sync {
+    /// Verify one proof
+    fn verify(&self, proof: &[u8], public_inputs: &[[u8; 32]]) -> bool;
+
+    /// Verify one proof for a circuit deployed with `system`, turning away
+    /// proofs that aren't the system's size
+    fn verify_with(&self, system: ProofSystem, proof: &[u8], public_inputs: &[[u8; 32]]) -> bool {
+        proof.len() == system.proof_len() && self.verify(proof, public_inputs)
+    }
+
+    /// Check a whole batch at once: `true` only if every proof is valid
+    ///
+    /// Returns `None` when the backend has no batch mode.
+    fn verify_all(&self, _batch: &[ProofWithInputs]) -> Option<bool> {
+        None
+    }
+}
+
+/// Verify each proof in `batch`, returning results in order
+///
+/// A failed batch check is split in half and retried so the invalid
+/// proofs are pinned down in O(k log n) batch checks.
+pub fn verify_batch(verifier: &dyn ProofVerifier, batch: &[ProofWithInputs]) -> Vec<bool> {
+    match batch {
+        [] => Vec::new(),
+        [(proof, inputs)] => vec![verifier.verify(proof, inputs)],
+        _ => match verifier.verify_all(batch) {
+            Some(true) => vec![true; batch.len()],
+            Some(false) => {
+                let (left, right) = batch.split_at(batch.len() / 2);
+                let mut results = verify_batch(verifier, left);
+                results.extend(verify_batch(verifier, right));
+                results
+            }
+            None => batch
+                .iter()
+                .map(|(proof, inputs)| verifier.verify(proof, inputs))
+                .collect(),
+        },
+    }
+}
+
+/// Commitment to the public inputs embedded in placeholder proofs
+pub fn placeholder_binding(public_inputs: &[[u8; 32]]) -> [u8; 32] {
+    keccak256(public_inputs.concat())
+}
+
+/// Placeholder proof in `system`: its size, opening with the input binding
+pub fn placeholder_proof(system: ProofSystem, public_inputs: &[[u8; 32]]) -> Vec<u8> {
+    let mut proof = vec![0u8; system.proof_len()];
+    proof[..32].copy_from_slice(&placeholder_binding(public_inputs));
+    proof
+}
+
+/// Verifier for the placeholder proofs produced until the Noir backend lands
+///
+/// Accepts a placeholder of any supported system; `verify_with` pins the
+/// system down.
+pub struct PlaceholderVerifier;
+
+impl ProofVerifier for PlaceholderVerifier {
+    fn verify(&self, proof: &[u8], public_inputs: &[[u8; 32]]) -> bool {
+        super::scheme::PROOF_SYSTEMS
+            .iter()
+            .any(|system| proof.len() == system.proof_len())
+            && proof[..32] == placeholder_binding(public_inputs)
+    }
+
+    fn verify_all(&self, batch: &[ProofWithInputs]) -> Option<bool> {
+        Some(
+            batch
+                .iter()
+                .all(|(proof, inputs)| self.verify(proof, inputs)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Placeholder verifier that counts calls and can disable batching
+    struct CountingVerifier {
+        batching: bool,
+        single_calls: AtomicUsize,
+        batch_calls: AtomicUsize,
+    }
+
+    impl CountingVerifier {
+        fn new(batching: bool) -> Self {
+            Self {
+                batching,
+                single_calls: AtomicUsize::new(0),
+                batch_calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl ProofVerifier for CountingVerifier {
+        fn verify(&self, proof: &[u8], public_inputs: &[[u8; 32]]) -> bool {
+            self.single_calls.fetch_add(1, Ordering::SeqCst);
+            PlaceholderVerifier.verify(proof, public_inputs)
+        }
+
+        fn verify_all(&self, batch: &[ProofWithInputs]) -> Option<bool> {
+            if !self.batching {
+                return None;
+            }
+            self.batch_calls.fetch_add(1, Ordering::SeqCst);
+            PlaceholderVerifier.verify_all(batch)
+        }
+    }
+
+    fn proof_for(inputs: &[[u8; 32]]) -> Vec<u8> {
+        placeholder_proof(ProofSystem::Groth16, inputs)
+    }
+
+    fn mixed_batch(invalid: &[usize]) -> Vec<ProofWithInputs> {
+        (0..8u8)
+            .map(|i| {
+                let inputs = vec![[i; 32], [i + 1; 32]];
+                let mut proof = proof_for(&inputs);
+                if invalid.contains(&(i as usize)) {
+                    proof[0] ^= 0xff;
+                }
+                (proof, inputs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_agrees_with_individual_verification() {
+        for invalid in [vec![], vec![5], vec![0, 3, 7]] {
+            let batch = mixed_batch(&invalid);
+            let individual: Vec<bool> = batch
+                .iter()
+                .map(|(proof, inputs)| PlaceholderVerifier.verify(proof, inputs))
+                .collect();
+
+            assert_eq!(verify_batch(&PlaceholderVerifier, &batch), individual);
+            assert_eq!(
+                individual.iter().filter(|valid| !**valid).count(),
+                invalid.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_valid_batch_uses_single_batch_check() {
+        let verifier = CountingVerifier::new(true);
+        assert!(verify_batch(&verifier, &mixed_batch(&[]))
+            .iter()
+            .all(|valid| *valid));
+        assert_eq!(verifier.batch_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(verifier.single_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_falls_back_to_sequential_without_batch_support() {
+        let verifier = CountingVerifier::new(false);
+        let results = verify_batch(&verifier, &mixed_batch(&[2]));
+        assert_eq!(results.iter().position(|valid| !*valid), Some(2));
+        assert_eq!(verifier.single_calls.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn test_each_system_verified_at_its_size() {
+        let inputs = vec![[3u8; 32], [4u8; 32]];
+        for system in crate::prover::scheme::PROOF_SYSTEMS {
+            let proof = placeholder_proof(system, &inputs);
+            assert_eq!(proof.len(), system.proof_len());
+            assert!(PlaceholderVerifier.verify_with(system, &proof, &inputs));
+
+            // Another system's proof for the same inputs is the wrong shape
+            for other in crate::prover::scheme::PROOF_SYSTEMS {
+                if other != system {
+                    let proof = placeholder_proof(other, &inputs);
+                    assert!(!PlaceholderVerifier.verify_with(system, &proof, &inputs));
+                }
+            }
+        }
+        assert!(!PlaceholderVerifier.verify(&[0u8; 100], &inputs));
+    }
+}
@@ -0,0 +1,268 @@
+//! Proof wire format
+//!
+//! Stable encodings for proofs and public inputs exchanged with clients and
+//! on-chain tooling. All integers are big-endian.
+//!
+//! Compressed proof (version 2):
+//!
+//! | field        | size            | contents                                      |
+//! |--------------|-----------------|-----------------------------------------------|
+//! | version      | 1               | `2`                                           |
+//! | proof type   | 1               | 1 withdrawal, 2 transfer, 3 consistency, 4 range |
+//! | proof system | 1               | 1 Groth16, 2 PLONK, 3 UltraHonk               |
+//! | length       | 4               | uncompressed proof length in bytes            |
+//! | bitmap       | ceil(words / 8) | bit `i` (MSB first) set if word `i` is non-zero |
+//! | words        | 32 each         | the non-zero 32-byte words, in order          |
+//!
+//! The proof is split into 32-byte words, the last one zero-padded. All-zero
+//! words are omitted and restored from the bitmap on decompression.
+//!
+//! Version 1 is the same without the proof system byte, and is still
+//! decoded, as Groth16.
+//!
+//! Public inputs: a 4-byte element count followed by the 32-byte elements.
+
+use thiserror::Error;
+
+use super::scheme::ProofSystem;
+
+/// Current compressed proof format version
+pub const WIRE_VERSION: u8 = 2;
+
+/// Version before proofs were tagged with their proof system
+const WIRE_VERSION_GROTH16_ONLY: u8 = 1;
+
+const WORD: usize = 32;
+
+/// Proof types and their wire tags
+const PROOF_TYPES: [(&str, u8); 4] = [
+    ("withdrawal", 1),
+    ("transfer", 2),
+    ("consistency", 3),
+    ("range", 4),
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WireError {
+    #[error("Unsupported proof format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unknown proof type {0:?}")]
+    UnknownProofType(String),
+    #[error("Unknown proof type tag {0}")]
+    UnknownProofTag(u8),
+    #[error("Unknown proof system tag {0}")]
+    UnknownSystemTag(u8),
+    #[error("Encoding truncated: needed {needed} bytes, got {got}")]
+    Truncated { needed: usize, got: usize },
+    #[error("{0} trailing bytes after encoding")]
+    TrailingBytes(usize),
+    #[error("Proof of {0} bytes is too large to encode")]
+    TooLarge(usize),
+}
+
+/// Compress `proof` of the given type and system into the wire format
+pub fn compress_proof(
+    proof_type: &str,
+    system: ProofSystem,
+    proof: &[u8],
+) -> Result<Vec<u8>, WireError> {
+    let tag = PROOF_TYPES
+        .iter()
+        .find(|(name, _)| *name == proof_type)
+        .map(|(_, tag)| *tag)
+        .ok_or_else(|| WireError::UnknownProofType(proof_type.to_string()))?;
+    let length = u32::try_from(proof.len()).map_err(|_| WireError::TooLarge(proof.len()))?;
+
+    let words: Vec<[u8; WORD]> = proof
+        .chunks(WORD)
+        .map(|chunk| {
+            let mut word = [0u8; WORD];
+            word[..chunk.len()].copy_from_slice(chunk);
+            word
+        })
+        .collect();
+
+    let mut bitmap = vec![0u8; words.len().div_ceil(8)];
+    let mut body = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        if *word != [0u8; WORD] {
+            bitmap[i / 8] |= 0x80 >> (i % 8);
+            body.extend_from_slice(word);
+        }
+    }
+
+    let mut out = Vec::with_capacity(7 + bitmap.len() + body.len());
+    out.push(WIRE_VERSION);
+    out.push(tag);
+    out.push(system.tag());
+    out.extend_from_slice(&length.to_be_bytes());
+    out.extend_from_slice(&bitmap);
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// Decompress a wire-format proof, returning its type, system and raw bytes
+pub fn decompress_proof(bytes: &[u8]) -> Result<(&'static str, ProofSystem, Vec<u8>), WireError> {
+    let version = take(bytes, 0, 1)?[0];
+    let (system, header_len) = match version {
+        WIRE_VERSION => {
+            let tag = take(bytes, 2, 1)?[0];
+            let system = ProofSystem::from_tag(tag).ok_or(WireError::UnknownSystemTag(tag))?;
+            (system, 7)
+        }
+        WIRE_VERSION_GROTH16_ONLY => (ProofSystem::Groth16, 6),
+        other => return Err(WireError::UnsupportedVersion(other)),
+    };
+    let header = take(bytes, 0, header_len)?;
+    let proof_type = PROOF_TYPES
+        .iter()
+        .find(|(_, tag)| *tag == header[1])
+        .map(|(name, _)| *name)
+        .ok_or(WireError::UnknownProofTag(header[1]))?;
+    let length = &header[header_len - 4..];
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+
+    let word_count = length.div_ceil(WORD);
+    let bitmap = take(bytes, header_len, word_count.div_ceil(8))?;
+    let mut offset = header_len + bitmap.len();
+
+    // The claimed length is untrusted, so only what the remaining words can
+    // fill is reserved up front; elided zero words grow the buffer as needed
+    let mut proof = Vec::with_capacity(length.min(bytes.len() - offset));
+    for i in 0..word_count {
+        if bitmap[i / 8] & (0x80 >> (i % 8)) != 0 {
+            proof.extend_from_slice(take(bytes, offset, WORD)?);
+            offset += WORD;
+        } else {
+            proof.extend_from_slice(&[0u8; WORD]);
+        }
+    }
+    if offset != bytes.len() {
+        return Err(WireError::TrailingBytes(bytes.len() - offset));
+    }
+
+    proof.truncate(length);
+    Ok((proof_type, system, proof))
+}
+
+/// Serialize public inputs as a count followed by the elements
+pub fn serialize_public_inputs(inputs: &[[u8; 32]]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + inputs.len() * WORD);
+    out.extend_from_slice(&(inputs.len() as u32).to_be_bytes());
+    for input in inputs {
+        out.extend_from_slice(input);
+    }
+    out
+}
+
+/// Parse public inputs written by `serialize_public_inputs`
+pub fn deserialize_public_inputs(bytes: &[u8]) -> Result<Vec<[u8; 32]>, WireError> {
+    let count = take(bytes, 0, 4)?;
+    let count = u32::from_be_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    let body = take(bytes, 4, count.saturating_mul(WORD))?;
+    if bytes.len() != 4 + body.len() {
+        return Err(WireError::TrailingBytes(bytes.len() - 4 - body.len()));
+    }
+
+    Ok(body
+        .chunks_exact(WORD)
+        .map(|chunk| {
+            let mut input = [0u8; WORD];
+            input.copy_from_slice(chunk);
+            input
+        })
+        .collect())
+}
+
+/// `len` bytes at `offset`, or a truncation error
+fn take(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8], WireError> {
+    let needed = offset.saturating_add(len);
+    bytes.get(offset..needed).ok_or(WireError::Truncated {
+        needed,
+        got: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_elides_zero_words() {
+        let mut proof = vec![0u8; ProofSystem::Groth16.proof_len()];
+        proof[..32].copy_from_slice(&[7u8; 32]);
+        *proof.last_mut().unwrap() = 1;
+
+        let compressed = compress_proof("withdrawal", ProofSystem::Groth16, &proof).unwrap();
+        // Header, one bitmap byte, two non-zero words
+        assert_eq!(compressed.len(), 7 + 1 + 64);
+        assert_eq!(
+            decompress_proof(&compressed).unwrap(),
+            ("withdrawal", ProofSystem::Groth16, proof.clone())
+        );
+
+        // Version 1 encodings predate the system tag and are Groth16
+        let mut legacy = compressed.clone();
+        legacy.remove(2);
+        legacy[0] = 1;
+        assert_eq!(
+            decompress_proof(&legacy).unwrap(),
+            ("withdrawal", ProofSystem::Groth16, proof)
+        );
+    }
+
+    #[test]
+    fn test_proof_system_tagged() {
+        for system in crate::prover::scheme::PROOF_SYSTEMS {
+            let proof = vec![0x5a; system.proof_len()];
+            let compressed = compress_proof("transfer", system, &proof).unwrap();
+            assert_eq!(compressed[2], system.tag());
+            assert_eq!(
+                decompress_proof(&compressed).unwrap(),
+                ("transfer", system, proof)
+            );
+        }
+
+        let mut unknown = compress_proof("transfer", ProofSystem::Plonk, &[1u8; 32]).unwrap();
+        unknown[2] = 9;
+        assert_eq!(
+            decompress_proof(&unknown),
+            Err(WireError::UnknownSystemTag(9))
+        );
+    }
+
+    #[test]
+    fn test_malformed_encodings_rejected() {
+        let compressed = compress_proof("range", ProofSystem::Groth16, &[1u8; 40]).unwrap();
+        assert!(matches!(
+            decompress_proof(&compressed[..compressed.len() - 1]),
+            Err(WireError::Truncated { .. })
+        ));
+
+        let mut trailing = compressed.clone();
+        trailing.push(0);
+        assert_eq!(
+            decompress_proof(&trailing),
+            Err(WireError::TrailingBytes(1))
+        );
+
+        let mut future = compressed;
+        future[0] = 3;
+        assert_eq!(
+            decompress_proof(&future),
+            Err(WireError::UnsupportedVersion(3))
+        );
+
+        // A huge claimed length needs its bitmap before anything is allocated
+        let mut huge = vec![WIRE_VERSION, 1, 1];
+        huge.extend_from_slice(&u32::MAX.to_be_bytes());
+        huge.push(0);
+        assert!(matches!(
+            decompress_proof(&huge),
+            Err(WireError::Truncated { .. })
+        ));
+
+        assert!(compress_proof("mint", ProofSystem::Groth16, &[]).is_err());
+        assert!(deserialize_public_inputs(&[0, 0, 0, 2, 1]).is_err());
+    }
+}
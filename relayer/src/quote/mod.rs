@@ -0,0 +1,588 @@
+//! Binding fee quotes
+//!
+//! Quotes are signed with the relayer's transaction key, so a client can
+//! prove what it was offered. Once `/relay` honors a quote the relayer is
+//! bound to it: the fee deducted at submission never exceeds the quoted fee.
+//! Gas spikes are absorbed up to a configured buffer; beyond that the
+//! submitter turns the relay away rather than over-charge it.
+//!
+//! The quoted fee is a withdrawal's gas limit at the most per gas the
+//! submitter would pay on the chain right now, plus a margin: the gas price
+//! for legacy transactions, the fee cap for EIP-1559 ones. Pools take fees in the chain's native token, so the
+//! fee is in the same wei as the gas it pays for.
+
+use ethers::prelude::*;
+use ethers::utils::hash_message;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+use crate::store::{AuditEntry, Store};
+use crate::submitter::{FeeSettings, GasOracle};
+
+/// Default time a quote stays valid after issue
+pub const DEFAULT_QUOTE_VALIDITY: Duration = Duration::from_secs(300);
+
+/// Default gas cost overrun absorbed before deferring, in basis points (10%)
+pub const DEFAULT_ABSORB_BUFFER_BPS: u64 = 1_000;
+
+/// Default margin added to the estimated gas cost, in basis points (20%)
+pub const DEFAULT_FEE_MARGIN_BPS: u64 = 2_000;
+
+/// Fee terms offered to a client
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Quote {
+    pub quote_id: String,
+    pub chain_id: u64,
+    /// Fee in wei deducted from the withdrawal
+    pub fee: U256,
+    /// Gas the fee was priced for
+    pub gas_limit: u64,
+    /// Gas price in wei the fee was priced at
+    pub gas_price: U256,
+    /// Unix timestamp after which the quote can no longer be honored
+    pub valid_until: i64,
+    /// Address that signed the quote
+    pub relayer: Address,
+}
+
+impl Quote {
+    /// Message signed (EIP-191) to bind the relayer to these terms
+    pub fn signing_message(&self) -> String {
+        format!(
+            "laundry-quote\n{}\n{}\n{}\n{}\n{}\n{}\n{:?}",
+            self.quote_id,
+            self.chain_id,
+            self.fee,
+            self.gas_limit,
+            self.gas_price,
+            self.valid_until,
+            self.relayer
+        )
+    }
+}
+
+/// Quote plus the relayer's signature over it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignedQuote {
+    #[serde(flatten)]
+    pub quote: Quote,
+    /// Hex signature over `Quote::signing_message`
+    pub signature: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuoteError {
+    #[error("Quote was not signed by this relayer")]
+    InvalidSignature,
+    #[error("Quote expired at {valid_until}")]
+    Expired { valid_until: i64 },
+    #[error("Quote is for chain {quoted}, not {requested}")]
+    WrongChain { quoted: u64, requested: u64 },
+    #[error("Quote {0} has already been honored")]
+    AlreadyHonored(String),
+    #[error("No gas price source for chain {0}")]
+    UnknownChain(u64),
+    #[error("Failed to fetch gas price: {0:#}")]
+    GasPrice(anyhow::Error),
+    #[error("Failed to sign quote: {0}")]
+    Signing(#[from] WalletError),
+    #[error("Failed to record quote: {0}")]
+    Audit(#[from] anyhow::Error),
+    #[error("Relayer is shutting down and takes no new relays")]
+    ShuttingDown,
+}
+
+impl QuoteError {
+    /// Stable reason code reported to API callers
+    pub fn code(&self) -> &'static str {
+        match self {
+            QuoteError::InvalidSignature => "quote_invalid_signature",
+            QuoteError::Expired { .. } => "quote_expired",
+            QuoteError::WrongChain { .. } => "quote_wrong_chain",
+            QuoteError::AlreadyHonored(_) => "quote_already_honored",
+            QuoteError::UnknownChain(_) => "chain_not_served",
+            QuoteError::GasPrice(_) => "gas_price_unavailable",
+            QuoteError::ShuttingDown => "shutting_down",
+            QuoteError::Signing(_) | QuoteError::Audit(_) => "internal",
+        }
+    }
+}
+
+/// Fee to deduct at submission time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeDecision {
+    /// Submit, deducting this fee
+    Charge(U256),
+    /// Gas cost is beyond what the quote can absorb; the relay isn't
+    /// submitted, and the quote can be presented again while it is valid
+    Defer { quoted: U256, cost: U256 },
+}
+
+/// Decide the fee for a relay bound to `quoted`, given the current gas cost
+///
+/// The quoted fee is charged whenever the cost is within `absorb_buffer_bps`
+/// of it; the relayer eats the difference. Never charges more than quoted.
+pub fn settle(quoted: U256, cost: U256, absorb_buffer_bps: u64) -> FeeDecision {
+    let absorbable = quoted + quoted * U256::from(absorb_buffer_bps) / U256::from(10_000u64);
+    if cost <= absorbable {
+        FeeDecision::Charge(quoted)
+    } else {
+        FeeDecision::Defer { quoted, cost }
+    }
+}
+
+/// Gas terms of a quoted fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub gas_limit: u64,
+    pub gas_price: U256,
+    pub fee: U256,
+}
+
+/// Fee covering `gas_limit` at `gas_price`, plus `margin_bps`
+pub fn quoted_fee(gas_limit: u64, gas_price: U256, margin_bps: u64) -> U256 {
+    gas_price * U256::from(gas_limit) * U256::from(10_000 + margin_bps) / U256::from(10_000u64)
+}
+
+/// How withdrawals on one chain are priced
+pub struct ChainPricing {
+    pub oracle: Arc<dyn GasOracle>,
+    /// How the chain's submitter prices withdrawal transactions
+    pub fees: FeeSettings,
+    /// Gas limit the chain's withdrawals are sent with
+    pub gas_limit: u64,
+}
+
+/// Prices quotes from each chain's current fees
+pub struct FeePricing {
+    chains: HashMap<u64, ChainPricing>,
+    margin_bps: u64,
+}
+
+impl FeePricing {
+    pub fn new(chains: HashMap<u64, ChainPricing>, margin_bps: u64) -> Self {
+        Self { chains, margin_bps }
+    }
+
+    /// Price a withdrawal on `chain_id` as the submitter would send it now
+    pub async fn estimate(&self, chain_id: u64) -> Result<FeeEstimate, QuoteError> {
+        let chain = self
+            .chains
+            .get(&chain_id)
+            .ok_or(QuoteError::UnknownChain(chain_id))?;
+        let gas_price = chain
+            .oracle
+            .gas_price()
+            .await
+            .map_err(QuoteError::GasPrice)?;
+        let gas_price = chain
+            .fees
+            .price(chain.oracle.as_ref(), gas_price)
+            .await
+            .max_per_gas();
+        Ok(FeeEstimate {
+            gas_limit: chain.gas_limit,
+            gas_price,
+            fee: quoted_fee(chain.gas_limit, gas_price, self.margin_bps),
+        })
+    }
+}
+
+/// A relay's hold on an honored quote
+struct Binding {
+    quote_id: String,
+    fee: U256,
+    /// When the binding lapses if the relay is never submitted
+    lapses_at: i64,
+}
+
+/// Issues signed quotes and tracks the ones relays are bound to
+pub struct QuoteBook {
+    signer: LocalWallet,
+    validity: Duration,
+    absorb_buffer_bps: u64,
+    pricing: FeePricing,
+    store: Arc<dyn Store>,
+    /// Quote each relay is bound to, by relay ID
+    bindings: Mutex<HashMap<String, Binding>>,
+    /// Honored quote IDs and their expiry, so a quote binds one relay only
+    honored: Mutex<HashMap<String, i64>>,
+}
+
+impl QuoteBook {
+    pub fn new(
+        signer: LocalWallet,
+        validity: Duration,
+        absorb_buffer_bps: u64,
+        pricing: FeePricing,
+        store: Arc<dyn Store>,
+    ) -> Self {
+        Self {
+            signer,
+            validity,
+            absorb_buffer_bps,
+            pricing,
+            store,
+            bindings: Mutex::new(HashMap::new()),
+            honored: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Price and sign a quote on `chain_id`, valid for the quote validity
+    /// from now
+    ///
+    /// Validity runs on the local clock, the same one `verify` checks expiry
+    /// against, so a quote is honored for the full period it advertises.
+    pub async fn quote(&self, chain_id: u64) -> Result<SignedQuote, QuoteError> {
+        let estimate = self.pricing.estimate(chain_id).await?;
+        Ok(self.issue(chain_id, estimate, chrono::Utc::now().timestamp())?)
+    }
+
+    /// Sign a quote for `estimate` on `chain_id`
+    pub fn issue(
+        &self,
+        chain_id: u64,
+        estimate: FeeEstimate,
+        issued_at: i64,
+    ) -> Result<SignedQuote, WalletError> {
+        let quote = Quote {
+            quote_id: uuid::Uuid::new_v4().to_string(),
+            chain_id,
+            fee: estimate.fee,
+            gas_limit: estimate.gas_limit,
+            gas_price: estimate.gas_price,
+            valid_until: issued_at + self.validity.as_secs() as i64,
+            relayer: self.signer.address(),
+        };
+        self.sign(quote)
+    }
+
+    fn sign(&self, quote: Quote) -> Result<SignedQuote, WalletError> {
+        let signature = self
+            .signer
+            .sign_hash(hash_message(quote.signing_message()))?;
+
+        Ok(SignedQuote {
+            quote,
+            signature: signature.to_string(),
+        })
+    }
+
+    /// Check a quote presented with a relay request
+    pub fn verify(&self, signed: &SignedQuote, chain_id: u64) -> Result<(), QuoteError> {
+        let quote = &signed.quote;
+        if quote.relayer != self.signer.address() {
+            return Err(QuoteError::InvalidSignature);
+        }
+        let signature =
+            Signature::from_str(&signed.signature).map_err(|_| QuoteError::InvalidSignature)?;
+        signature
+            .verify(quote.signing_message(), quote.relayer)
+            .map_err(|_| QuoteError::InvalidSignature)?;
+
+        if chrono::Utc::now().timestamp() > quote.valid_until {
+            return Err(QuoteError::Expired {
+                valid_until: quote.valid_until,
+            });
+        }
+        if quote.chain_id != chain_id {
+            return Err(QuoteError::WrongChain {
+                quoted: quote.chain_id,
+                requested: chain_id,
+            });
+        }
+        Ok(())
+    }
+
+    /// Bind `relay_id` to a valid quote and record it in the audit log
+    pub async fn honor(
+        &self,
+        signed: &SignedQuote,
+        chain_id: u64,
+        relay_id: &str,
+    ) -> Result<(), QuoteError> {
+        self.verify(signed, chain_id)?;
+
+        let quote = &signed.quote;
+        let now = chrono::Utc::now().timestamp();
+        // Reserved up front so concurrent relays can't both honor the quote,
+        // and kept only once the audit log records it
+        {
+            let mut honored = self.honored.lock().unwrap();
+            honored.retain(|_, valid_until| *valid_until >= now);
+            if honored.contains_key(&quote.quote_id) {
+                return Err(QuoteError::AlreadyHonored(quote.quote_id.clone()));
+            }
+            honored.insert(quote.quote_id.clone(), quote.valid_until);
+        }
+
+        let audited = self
+            .store
+            .append_audit(&AuditEntry {
+                seq: 0,
+                timestamp: now,
+                actor: "node".to_string(),
+                action: "quote_honored".to_string(),
+                details: serde_json::json!({
+                    "relay_id": relay_id,
+                    "quote": signed,
+                })
+                .to_string(),
+            })
+            .await;
+        if let Err(e) = audited {
+            self.honored.lock().unwrap().remove(&quote.quote_id);
+            return Err(e.into());
+        }
+
+        // A relay honored late in the quote's validity still gets a full
+        // validity period to be submitted
+        let lapses_at = quote.valid_until + self.validity.as_secs() as i64;
+        let mut bindings = self.bindings.lock().unwrap();
+        bindings.retain(|_, binding| binding.lapses_at >= now);
+        let binding = Binding {
+            quote_id: quote.quote_id.clone(),
+            fee: quote.fee,
+            lapses_at,
+        };
+        bindings.insert(relay_id.to_string(), binding);
+        info!(relay_id = relay_id, quote_id = %quote.quote_id, fee = %quote.fee, "Quote honored");
+        Ok(())
+    }
+
+    /// Quoted fee a relay is bound to, if it was submitted with a quote that
+    /// hasn't lapsed
+    pub fn binding(&self, relay_id: &str) -> Option<U256> {
+        let now = chrono::Utc::now().timestamp();
+        self.bindings
+            .lock()
+            .unwrap()
+            .get(relay_id)
+            .filter(|binding| binding.lapses_at >= now)
+            .map(|binding| binding.fee)
+    }
+
+    /// Fee to deduct when submitting `relay_id` at the current gas `cost`
+    ///
+    /// Relays without a quote are charged the fee they `offered`, which
+    /// validation already held to the minimum.
+    pub fn fee_for(&self, relay_id: &str, offered: U256, cost: U256) -> FeeDecision {
+        match self.binding(relay_id) {
+            Some(quoted) => settle(quoted, cost, self.absorb_buffer_bps),
+            None => FeeDecision::Charge(offered),
+        }
+    }
+
+    /// Forget a relay's binding once it is submitted
+    pub fn release(&self, relay_id: &str) {
+        self.bindings.lock().unwrap().remove(relay_id);
+    }
+
+    /// Forget the binding of a relay that was never submitted, and un-honor
+    /// its quote so the quote can be presented again while it is valid
+    pub fn restore(&self, relay_id: &str) {
+        let Some(binding) = self.bindings.lock().unwrap().remove(relay_id) else {
+            return;
+        };
+        self.honored.lock().unwrap().remove(&binding.quote_id);
+        info!(relay_id = relay_id, quote_id = %binding.quote_id, "Quote restored");
+    }
+}
+
+/// Gas price source that never changes, for tests across the crate
+#[cfg(test)]
+pub(crate) struct FixedGasPrice(pub U256);
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl GasOracle for FixedGasPrice {
+    async fn gas_price(&self) -> anyhow::Result<U256> {
+        Ok(self.0)
+    }
+}
+
+/// Pricing at 1 gwei on each of `chain_ids`, for tests across the crate
+#[cfg(test)]
+pub(crate) fn test_pricing(chain_ids: &[u64]) -> FeePricing {
+    let chains = chain_ids
+        .iter()
+        .map(|chain_id| {
+            let pricing = ChainPricing {
+                oracle: Arc::new(FixedGasPrice(U256::exp10(9))),
+                fees: FeeSettings::default(),
+                gas_limit: crate::submitter::withdraw::DEFAULT_WITHDRAW_GAS_LIMIT,
+            };
+            (*chain_id, pricing)
+        })
+        .collect();
+    FeePricing::new(chains, DEFAULT_FEE_MARGIN_BPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn book() -> (QuoteBook, Arc<dyn Store>) {
+        let store = crate::store::memory().await;
+        let book = QuoteBook::new(
+            LocalWallet::new(&mut rand::thread_rng()),
+            DEFAULT_QUOTE_VALIDITY,
+            DEFAULT_ABSORB_BUFFER_BPS,
+            test_pricing(&[1]),
+            store.clone(),
+        );
+        (book, store)
+    }
+
+    fn estimate(fee: u64) -> FeeEstimate {
+        FeeEstimate {
+            gas_limit: 21_000,
+            gas_price: U256::one(),
+            fee: U256::from(fee),
+        }
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[tokio::test]
+    async fn test_quote_priced_from_gas_price() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(25_000_000_000u64)).unwrap();
+        let pricing = ChainPricing {
+            oracle: Arc::new(provider),
+            fees: FeeSettings {
+                strategy: crate::submitter::FeeStrategy::Legacy,
+                ..Default::default()
+            },
+            gas_limit: 500_000,
+        };
+        let book = QuoteBook::new(
+            LocalWallet::new(&mut rand::thread_rng()),
+            DEFAULT_QUOTE_VALIDITY,
+            DEFAULT_ABSORB_BUFFER_BPS,
+            FeePricing::new(HashMap::from([(1, pricing)]), 2_000),
+            crate::store::memory().await,
+        );
+
+        let before = now();
+        let quote = book.quote(1).await.unwrap();
+        // 500k gas at 25 gwei is 0.0125 ETH; plus 20% is 0.015 ETH
+        assert_eq!(quote.quote.fee, U256::from(15_000_000_000_000_000u64));
+        assert_eq!(quote.quote.gas_limit, 500_000);
+        assert_eq!(quote.quote.gas_price, U256::from(25_000_000_000u64));
+        // Valid for the full period from the local clock
+        let validity = DEFAULT_QUOTE_VALIDITY.as_secs() as i64;
+        assert!(quote.quote.valid_until >= before + validity);
+        assert!(quote.quote.valid_until <= now() + validity);
+
+        assert!(matches!(
+            book.quote(42161).await,
+            Err(QuoteError::UnknownChain(42161))
+        ));
+    }
+
+    #[test]
+    fn test_settlement_never_exceeds_quote() {
+        let quoted = U256::from(1_000u64);
+        for cost in (0..=2_000u64).step_by(50) {
+            match settle(quoted, U256::from(cost), 1_000) {
+                FeeDecision::Charge(fee) => {
+                    assert!(fee <= quoted, "charged {} for quote {}", fee, quoted);
+                    assert!(cost <= 1_100);
+                }
+                FeeDecision::Defer { .. } => assert!(cost > 1_100),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_honored_quote_binds_submission_fee() {
+        let (book, store) = book().await;
+        let quote = book.issue(1, estimate(1_000), now()).unwrap();
+
+        book.honor(&quote, 1, "relay-1").await.unwrap();
+        assert_eq!(book.binding("relay-1"), Some(U256::from(1_000u64)));
+
+        // Cheaper gas: still the quoted fee. Spike within buffer: absorbed.
+        // Spike beyond it: deferred, never over-charged.
+        // Whatever the relay offered on top of the quote isn't taken either.
+        let offered = U256::from(2_000u64);
+        let quoted = FeeDecision::Charge(U256::from(1_000u64));
+        assert_eq!(book.fee_for("relay-1", offered, U256::from(400u64)), quoted);
+        assert_eq!(
+            book.fee_for("relay-1", offered, U256::from(1_050u64)),
+            quoted
+        );
+        assert!(matches!(
+            book.fee_for("relay-1", offered, U256::from(5_000u64)),
+            FeeDecision::Defer { .. }
+        ));
+        // Unquoted relays pay what they offered
+        assert_eq!(
+            book.fee_for("relay-2", offered, U256::from(400u64)),
+            FeeDecision::Charge(offered)
+        );
+
+        let audit = store.audit_entries(0, 10).await.unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, "quote_honored");
+        assert!(audit[0].details.contains(&quote.quote.quote_id));
+
+        // A quote binds a single relay
+        assert!(matches!(
+            book.honor(&quote, 1, "relay-2").await,
+            Err(QuoteError::AlreadyHonored(_))
+        ));
+
+        // unless the relay it bound is never submitted
+        book.restore("relay-1");
+        assert_eq!(book.binding("relay-1"), None);
+        book.honor(&quote, 1, "relay-2").await.unwrap();
+        book.release("relay-2");
+        assert!(matches!(
+            book.honor(&quote, 1, "relay-3").await,
+            Err(QuoteError::AlreadyHonored(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_foreign_quotes_rejected() {
+        let (book, _) = book().await;
+
+        let mut tampered = book.issue(1, estimate(1_000), now()).unwrap();
+        tampered.quote.fee = U256::from(1u64);
+        assert!(matches!(
+            book.verify(&tampered, 1),
+            Err(QuoteError::InvalidSignature)
+        ));
+
+        let (other, _) = book().await;
+        let foreign = other.issue(1, estimate(1_000), now()).unwrap();
+        assert!(matches!(
+            book.verify(&foreign, 1),
+            Err(QuoteError::InvalidSignature)
+        ));
+
+        let quote = book.issue(1, estimate(1_000), now()).unwrap();
+        assert!(matches!(
+            book.verify(&quote, 42161),
+            Err(QuoteError::WrongChain { .. })
+        ));
+
+        let expired = book
+            .sign(Quote {
+                valid_until: now() - 10,
+                ..quote.quote
+            })
+            .unwrap();
+        assert!(matches!(
+            book.verify(&expired, 1),
+            Err(QuoteError::Expired { .. })
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+//! Deduplication of gossiped relay requests by what they spend
+//!
+//! Gossipsub drops byte-identical messages by their content hash, but the
+//! same withdrawal can be re-encoded endlessly: reordered JSON fields,
+//! whitespace, explicit defaults. Each variant is a new gossip message and
+//! would be validated, proof included, and relayed again. Requests are
+//! therefore also keyed by the fields that decide what they spend (chain,
+//! root and nullifier), and once a request with a key has validated, later
+//! requests with the same key are dropped for `window`.
+//!
+//! Only validated requests claim a key, so an invalid request can't shadow
+//! the valid one it imitates.
+
+use ethers::types::H256;
+use ethers::utils::keccak256;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::validate::{INPUT_NULLIFIER, INPUT_ROOT};
+use crate::api::types::RelayRequest;
+
+/// Default time a validated request's key suppresses equivalent requests
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(600);
+
+/// Default number of keys remembered; the oldest are forgotten first
+pub const DEFAULT_DEDUP_CAPACITY: usize = 100_000;
+
+/// Key identifying what a relay request spends, if it has the inputs to say
+pub fn dedup_key(request: &RelayRequest) -> Option<H256> {
+    let root = request.public_inputs.get(INPUT_ROOT)?;
+    let nullifier = request.public_inputs.get(INPUT_NULLIFIER)?;
+    let mut data = Vec::with_capacity(8 + 64);
+    data.extend_from_slice(&request.chain_id.to_be_bytes());
+    data.extend_from_slice(root.as_bytes());
+    data.extend_from_slice(nullifier.as_bytes());
+    Some(H256(keccak256(data)))
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    /// When each key was claimed
+    keys: HashMap<H256, Instant>,
+    /// Keys in the order they were claimed
+    order: VecDeque<(H256, Instant)>,
+}
+
+/// Recently validated relay request keys
+#[derive(Debug)]
+pub struct RelayDedup {
+    window: Duration,
+    capacity: usize,
+    seen: Mutex<Seen>,
+}
+
+impl Default for RelayDedup {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW, DEFAULT_DEDUP_CAPACITY)
+    }
+}
+
+impl RelayDedup {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            seen: Mutex::new(Seen::default()),
+        }
+    }
+
+    /// Whether a request with `key` has validated within the window
+    pub fn is_duplicate(&self, key: H256) -> bool {
+        self.is_duplicate_at(key, Instant::now())
+    }
+
+    /// Claim `key` for a validated request; false if it was already claimed
+    pub fn claim(&self, key: H256) -> bool {
+        self.claim_at(key, Instant::now())
+    }
+
+    fn is_duplicate_at(&self, key: H256, now: Instant) -> bool {
+        let seen = self.seen.lock().unwrap();
+        seen.keys
+            .get(&key)
+            .is_some_and(|at| now.duration_since(*at) < self.window)
+    }
+
+    fn claim_at(&self, key: H256, now: Instant) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        while let Some(&(oldest, at)) = seen.order.front() {
+            if now.duration_since(at) < self.window && seen.order.len() < self.capacity {
+                break;
+            }
+            seen.order.pop_front();
+            if seen.keys.get(&oldest) == Some(&at) {
+                seen.keys.remove(&oldest);
+            }
+        }
+        if seen.keys.contains_key(&key) {
+            return false;
+        }
+        seen.keys.insert(key, now);
+        seen.order.push_back((key, now));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_expire_and_respect_capacity() {
+        let dedup = RelayDedup::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+        let (a, b, c) = (
+            H256::repeat_byte(1),
+            H256::repeat_byte(2),
+            H256::repeat_byte(3),
+        );
+
+        assert!(dedup.claim_at(a, now));
+        assert!(!dedup.claim_at(a, now));
+        assert!(dedup.is_duplicate_at(a, now));
+
+        // Over capacity the oldest key is forgotten
+        assert!(dedup.claim_at(b, now));
+        assert!(dedup.claim_at(c, now));
+        assert!(!dedup.is_duplicate_at(a, now));
+        assert!(dedup.is_duplicate_at(c, now));
+
+        // Keys stop suppressing once the window has passed
+        let later = now + Duration::from_secs(60);
+        assert!(!dedup.is_duplicate_at(c, later));
+        assert!(dedup.claim_at(c, later));
+    }
+}
@@ -0,0 +1,109 @@
+//! Relay request tracking
+//!
+//! Keeps the status of every relay the node has accepted and lets callers
+//! wait for a relay to reach a terminal state without polling.
+
+pub mod dedup;
+pub mod validate;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub use dedup::RelayDedup;
+pub use validate::{
+    accept_gossiped, validate_relay_request, RelayContext, RelayRejection, ServedPools,
+};
+
+/// Lifecycle of a relay request
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RelayStatus {
+    /// Accepted, waiting for a proof or submission slot
+    Pending,
+    /// Transaction broadcast, not yet confirmed
+    Submitted { tx_hash: String },
+    /// Transaction confirmed on-chain
+    Confirmed { tx_hash: String },
+    /// Transaction mined but reverted
+    Reverted { tx_hash: String, reason: String },
+    /// Relay abandoned
+    Failed { reason: String },
+}
+
+impl RelayStatus {
+    /// Whether the relay will not change status again
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            RelayStatus::Confirmed { .. }
+                | RelayStatus::Reverted { .. }
+                | RelayStatus::Failed { .. }
+        )
+    }
+}
+
+/// Status of every known relay, with change notification
+#[derive(Default)]
+pub struct RelayTracker {
+    relays: Mutex<HashMap<String, watch::Sender<RelayStatus>>>,
+}
+
+impl RelayTracker {
+    /// Start tracking a relay in the pending state
+    pub fn register(&self, id: impl Into<String>) {
+        let (status_tx, _) = watch::channel(RelayStatus::Pending);
+        self.relays.lock().unwrap().insert(id.into(), status_tx);
+    }
+
+    /// Move a relay to a new status, waking anyone waiting on it
+    pub fn update(&self, id: &str, status: RelayStatus) {
+        if let Some(status_tx) = self.relays.lock().unwrap().get(id) {
+            status_tx.send_replace(status);
+        }
+    }
+
+    /// Current status, if the relay is known
+    pub fn status(&self, id: &str) -> Option<RelayStatus> {
+        self.relays
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|status_tx| status_tx.borrow().clone())
+    }
+
+    /// Wait up to `timeout` for the relay to reach a terminal state
+    ///
+    /// Returns the status at that point (terminal or not), or `None` if the
+    /// relay is unknown.
+    pub async fn wait(&self, id: &str, timeout: Duration) -> Option<RelayStatus> {
+        let mut status_rx = self.relays.lock().unwrap().get(id)?.subscribe();
+
+        let _ = tokio::time::timeout(timeout, status_rx.wait_for(RelayStatus::is_terminal)).await;
+        let status = status_rx.borrow().clone();
+        Some(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_returns_terminal_status() {
+        let tracker = RelayTracker::default();
+        tracker.register("r1");
+        tracker.update(
+            "r1",
+            RelayStatus::Failed {
+                reason: "nullifier spent".to_string(),
+            },
+        );
+
+        // Already terminal: no waiting
+        let status = tracker.wait("r1", Duration::from_secs(60)).await;
+        assert!(status.unwrap().is_terminal());
+        assert_eq!(tracker.wait("unknown", Duration::ZERO).await, None);
+    }
+}
@@ -0,0 +1,847 @@
+//! Relay request validation
+//!
+//! Every relay request, whether posted to `/relay` or gossiped by a peer, goes
+//! through `validate_relay_request` before the relayer spends any gas on it.
+//! Checks run cheapest first, so junk is turned away before the store is
+//! queried or the proof verifier runs.
+//!
+//! Requests carry a single-output withdrawal proof whose public inputs are
+//! `(root, nullifier, recipient, amount)`, plus the change commitment on
+//! partial withdrawals.
+
+use ethers::types::{Address, H256, U256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+use super::dedup::{dedup_key, RelayDedup};
+use crate::api::types::RelayRequest;
+use crate::light_client::FinalityHandle;
+use crate::merkle::roots::PoolRoots;
+use crate::metrics;
+use crate::prover::circuits::CircuitRegistry;
+use crate::prover::{GeneratedProof, ProverService};
+use crate::quote::{QuoteBook, QuoteError};
+use crate::store::Store;
+use crate::submitter::{InputCommitment, InputHashError};
+use crate::watcher::LogSource;
+
+/// Circuit version assumed when a request doesn't name one
+pub const CURRENT_CIRCUIT_VERSION: &str = "v1";
+
+/// Reputation a peer loses for each invalid relay request it gossips
+pub const INVALID_REQUEST_PENALTY: i64 = 10;
+
+/// BN254 scalar field modulus; public inputs must be reduced below it
+pub(crate) const SCALAR_FIELD_MODULUS: &str =
+    "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+
+pub(super) const INPUT_ROOT: usize = 0;
+pub(super) const INPUT_NULLIFIER: usize = 1;
+const INPUT_RECIPIENT: usize = 2;
+
+/// Why a relay request was turned away
+#[derive(Debug, thiserror::Error)]
+pub enum RelayRejection {
+    #[error("Malformed relay request: {0}")]
+    Malformed(String),
+    #[error("Circuit version {0} is not supported")]
+    UnsupportedCircuit(String),
+    #[error(
+        "Relayer does not serve {} on chain {chain_id}",
+        .pool.map_or("requests naming no pool".to_string(), |pool| format!("pool {:?}", pool))
+    )]
+    PoolNotServed {
+        chain_id: u64,
+        pool: Option<Address>,
+    },
+    #[error("Expected 4 or 5 public inputs, got {0}")]
+    WrongInputCount(usize),
+    #[error("Public input {index} is not a canonical field element")]
+    NonCanonicalInput { index: usize },
+    #[error("{0}")]
+    InputHash(#[from] InputHashError),
+    #[error("Recipient {0:?} is not a valid address")]
+    InvalidRecipient(H256),
+    #[error("Recipient {given:?} does not match the proof's recipient {proven:?}")]
+    RecipientMismatch { given: Address, proven: Address },
+    #[error("Deadline {deadline} passed at {now}")]
+    DeadlinePassed { deadline: i64, now: i64 },
+    #[error("Quote rejected: {0}")]
+    Quote(#[from] QuoteError),
+    #[error("Fee {offered} is below the required {required}")]
+    InsufficientFee { offered: U256, required: U256 },
+    #[error("Root {0:?} is not a known pool root")]
+    UnknownRoot(H256),
+    #[error("Root {root:?} from block {block_number} is not finalized")]
+    RootNotFinalized { root: H256, block_number: u64 },
+    #[error("Nullifier {0:?} has already been spent")]
+    NullifierSpent(H256),
+    #[error("Proof does not verify against its public inputs")]
+    InvalidProof,
+    #[error("Relay request {0:?} duplicates one already accepted")]
+    Duplicate(H256),
+    #[error("Failed to check relay request: {0}")]
+    Store(#[from] anyhow::Error),
+}
+
+impl RelayRejection {
+    /// Stable reason code reported to API callers
+    pub fn code(&self) -> &'static str {
+        match self {
+            RelayRejection::Malformed(_) => "malformed",
+            RelayRejection::UnsupportedCircuit(_) => "unsupported_circuit",
+            RelayRejection::PoolNotServed { .. } => "pool_not_served",
+            RelayRejection::WrongInputCount(_) => "wrong_input_count",
+            RelayRejection::NonCanonicalInput { .. } => "non_canonical_input",
+            RelayRejection::InputHash(_) => "input_hash_mismatch",
+            RelayRejection::InvalidRecipient(_) => "invalid_recipient",
+            RelayRejection::RecipientMismatch { .. } => "recipient_mismatch",
+            RelayRejection::DeadlinePassed { .. } => "deadline_passed",
+            RelayRejection::Quote(e) => e.code(),
+            RelayRejection::InsufficientFee { .. } => "insufficient_fee",
+            RelayRejection::UnknownRoot(_) => "unknown_root",
+            RelayRejection::RootNotFinalized { .. } => "root_not_finalized",
+            RelayRejection::NullifierSpent(_) => "nullifier_spent",
+            RelayRejection::InvalidProof => "invalid_proof",
+            RelayRejection::Duplicate(_) => "duplicate",
+            RelayRejection::Store(_) => "internal",
+        }
+    }
+
+    /// Whether the request itself is at fault, rather than the relayer
+    pub fn is_sender_fault(&self) -> bool {
+        // Honest peers forward equivalent requests they received separately,
+        // and requests for pools other relayers serve
+        !matches!(
+            self,
+            RelayRejection::Store(_)
+                | RelayRejection::Duplicate(_)
+                | RelayRejection::PoolNotServed { .. }
+        )
+    }
+}
+
+/// Pools the relayer serves on one chain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServedPools {
+    /// Pool targeted by requests that don't name one
+    pub default: Option<Address>,
+    /// Pools requests may target besides the default; empty serves every pool
+    pub allowed: Vec<Address>,
+}
+
+impl ServedPools {
+    /// Pool a request naming `requested` targets, if it is served
+    pub fn resolve(&self, requested: Option<Address>) -> Option<Address> {
+        requested.or(self.default).filter(|pool| {
+            self.allowed.is_empty() || Some(*pool) == self.default || self.allowed.contains(pool)
+        })
+    }
+}
+
+/// Everything relay validation reads
+#[derive(Clone)]
+pub struct RelayContext {
+    /// Checks relay proofs with the prover's verification backend
+    pub prover: Arc<ProverService>,
+    /// Circuits loaded besides `CURRENT_CIRCUIT_VERSION`
+    pub circuits: Arc<CircuitRegistry>,
+    /// Checks quotes attached to requests before their fee is trusted
+    pub quotes: Arc<QuoteBook>,
+    /// Recent roots of each watched pool, keyed by chain ID and pool
+    pub roots: Arc<HashMap<(u64, Address), Arc<RwLock<PoolRoots>>>>,
+    /// Reads the on-chain root of pools served without a local tree, keyed
+    /// by chain ID
+    pub sources: Arc<HashMap<u64, Arc<dyn LogSource>>>,
+    /// Finality of each tracked chain, keyed by chain ID
+    pub chains: Arc<HashMap<u64, FinalityHandle>>,
+    pub store: Arc<dyn Store>,
+    /// Fee required from requests that don't carry a quote
+    pub min_fee: U256,
+    /// How each chain's verifier takes public inputs (in full if absent)
+    pub input_commitments: Arc<HashMap<u64, InputCommitment>>,
+    /// Pools served on each chain; chains absent here are not served
+    pub pools: Arc<HashMap<u64, ServedPools>>,
+    /// Keys of recently accepted gossiped requests
+    pub dedup: Arc<RelayDedup>,
+}
+
+/// Check a relay request end to end, as of unix time `now`
+///
+/// Checks, in order: circuit version, target pool, public input count and encoding (and
+/// hash, if one is given), recipient (matching the request's, if it names one), deadline, fee (the
+/// quoted fee if a valid quote is attached), root known to the target pool and
+/// finalized (for a pool without a local tree, its root at the finalized
+/// block), nullifier unspent in that pool, and finally the proof itself,
+/// in the circuit's proof system.
+pub async fn validate_relay_request(
+    request: &RelayRequest,
+    ctx: &RelayContext,
+    now: i64,
+) -> Result<(), RelayRejection> {
+    let version = request
+        .circuit_version
+        .as_deref()
+        .unwrap_or(CURRENT_CIRCUIT_VERSION);
+    if version != CURRENT_CIRCUIT_VERSION && ctx.circuits.get("withdrawal", version).is_none() {
+        return Err(RelayRejection::UnsupportedCircuit(version.to_string()));
+    }
+
+    let served = ctx.pools.get(&request.chain_id);
+    let Some(pool) = served.and_then(|served| served.resolve(request.pool)) else {
+        return Err(RelayRejection::PoolNotServed {
+            chain_id: request.chain_id,
+            pool: request.pool.or(served.and_then(|served| served.default)),
+        });
+    };
+
+    let inputs = &request.public_inputs;
+    if !(4..=5).contains(&inputs.len()) {
+        return Err(RelayRejection::WrongInputCount(inputs.len()));
+    }
+    let modulus = U256::from_str_radix(SCALAR_FIELD_MODULUS, 16).expect("valid modulus");
+    if let Some(index) = inputs
+        .iter()
+        .position(|input| U256::from_big_endian(input.as_bytes()) >= modulus)
+    {
+        return Err(RelayRejection::NonCanonicalInput { index });
+    }
+    // The submitter recomputes the hash it commits to; checking here turns a
+    // bad one away before the relay is accepted
+    let elements: Vec<[u8; 32]> = inputs.iter().map(|input| input.0).collect();
+    ctx.input_commitments
+        .get(&request.chain_id)
+        .copied()
+        .unwrap_or_default()
+        .committed_hash(&elements, request.public_inputs_hash)?;
+
+    let recipient = inputs[INPUT_RECIPIENT];
+    if recipient[..12] != [0u8; 12] || Address::from_slice(&recipient[12..]).is_zero() {
+        return Err(RelayRejection::InvalidRecipient(recipient));
+    }
+    let proven = Address::from_slice(&recipient[12..]);
+    if let Some(given) = request.recipient {
+        if given.0 != proven {
+            return Err(RelayRejection::RecipientMismatch {
+                given: given.0,
+                proven,
+            });
+        }
+    }
+
+    if let Some(deadline) = request.deadline {
+        if now > deadline {
+            return Err(RelayRejection::DeadlinePassed { deadline, now });
+        }
+    }
+
+    // A quote only lowers the fee floor once it is known to be one this
+    // relayer signed for this chain and still honors
+    let required = match &request.quote {
+        Some(quote) => {
+            ctx.quotes.verify(quote, request.chain_id)?;
+            quote.quote.fee
+        }
+        None => ctx.min_fee,
+    };
+    if request.fee < required {
+        return Err(RelayRejection::InsufficientFee {
+            offered: request.fee,
+            required,
+        });
+    }
+
+    let root = inputs[INPUT_ROOT];
+    let finalized = ctx
+        .chains
+        .get(&request.chain_id)
+        .map_or(0, |chain| chain.finalized());
+    match ctx.roots.get(&(request.chain_id, pool)) {
+        Some(roots) => {
+            let known = roots
+                .read()
+                .unwrap()
+                .roots()
+                .into_iter()
+                .find(|known| known.root == root);
+            let Some(known) = known else {
+                return Err(RelayRejection::UnknownRoot(root));
+            };
+            if known.block_number > finalized {
+                return Err(RelayRejection::RootNotFinalized {
+                    root,
+                    block_number: known.block_number,
+                });
+            }
+        }
+        // A pool served without a local tree has no root history, so only
+        // its root as of the finalized block is known
+        None => {
+            let Some(source) = ctx.sources.get(&request.chain_id) else {
+                return Err(RelayRejection::UnknownRoot(root));
+            };
+            if source.fetch_pool_root(pool, finalized).await? != root {
+                return Err(RelayRejection::UnknownRoot(root));
+            }
+        }
+    }
+
+    let nullifier = inputs[INPUT_NULLIFIER];
+    if ctx
+        .store
+        .has_nullifier(request.chain_id, pool, nullifier)
+        .await?
+    {
+        return Err(RelayRejection::NullifierSpent(nullifier));
+    }
+
+    let proof = GeneratedProof {
+        proof_type: "withdrawal".to_string(),
+        proof_system: ctx.circuits.proof_system("withdrawal", version),
+        proof_data: request.proof.to_vec(),
+        public_inputs: elements,
+        generation_time_ms: 0,
+    };
+    // A proof the relayer has no way to check is its own failing, not the
+    // sender's
+    let verified = ctx
+        .prover
+        .verify_circuit(&proof, version)
+        .await
+        .map_err(|e| RelayRejection::Store(e.into()))?;
+    if !verified {
+        return Err(RelayRejection::InvalidProof);
+    }
+
+    Ok(())
+}
+
+/// Decode and validate a relay request gossiped by `peer_id`
+///
+/// A request that is the sender's fault costs the peer
+/// `INVALID_REQUEST_PENALTY` reputation. A request spending the same chain,
+/// root and nullifier as one accepted recently is dropped as a duplicate
+/// before validation, however differently it is encoded.
+pub async fn accept_gossiped(
+    ctx: &RelayContext,
+    peer_id: &str,
+    data: &[u8],
+    now: i64,
+) -> Result<RelayRequest, RelayRejection> {
+    let result = match serde_json::from_slice::<RelayRequest>(data) {
+        Ok(request) => match dedup_key(&request) {
+            Some(key) if ctx.dedup.is_duplicate(key) => {
+                metrics::RELAY_DUPLICATES_DROPPED.inc();
+                Err(RelayRejection::Duplicate(key))
+            }
+            key => validate_relay_request(&request, ctx, now)
+                .await
+                .and_then(|()| match key {
+                    Some(key) if !ctx.dedup.claim(key) => {
+                        metrics::RELAY_DUPLICATES_DROPPED.inc();
+                        Err(RelayRejection::Duplicate(key))
+                    }
+                    _ => Ok(request),
+                }),
+        },
+        Err(e) => Err(RelayRejection::Malformed(e.to_string())),
+    };
+
+    if let Err(rejection) = &result {
+        if rejection.is_sender_fault() {
+            if let Err(e) = penalize(ctx.store.as_ref(), peer_id).await {
+                warn!(peer_id = peer_id, error = %e, "Failed to record peer penalty");
+            }
+        }
+    }
+    result
+}
+
+async fn penalize(store: &dyn Store, peer_id: &str) -> anyhow::Result<()> {
+    let score = store.get_reputation(peer_id).await?.unwrap_or(0) - INVALID_REQUEST_PENALTY;
+    store
+        .set_reputation(peer_id, score, chrono::Utc::now().timestamp())
+        .await?;
+    warn!(
+        peer_id = peer_id,
+        score = score,
+        "Peer sent an invalid relay request"
+    );
+    Ok(())
+}
+
+/// Default pool of the chain `valid_relay_fixture` serves
+#[cfg(test)]
+pub(crate) const FIXTURE_POOL: Address = Address::repeat_byte(0x50);
+
+/// A context and a request it accepts, for tests across the crate
+#[cfg(test)]
+pub(crate) async fn valid_relay_fixture(chain_id: u64) -> (RelayContext, RelayRequest) {
+    use crate::merkle::{IncrementalMerkleTree, TREE_DEPTH};
+    use crate::prover::verifier::placeholder_proof;
+    use crate::prover::ProofSystem;
+
+    let mut pool = PoolRoots::new(IncrementalMerkleTree::new(TREE_DEPTH), 30);
+    let root = pool
+        .apply_deposit(H256::repeat_byte(1), 0, 100)
+        .unwrap()
+        .unwrap();
+
+    let public_inputs = vec![
+        root,
+        H256::repeat_byte(0x22),
+        H256::from(Address::repeat_byte(0x33)),
+        H256::from_low_u64_be(1_000_000),
+    ];
+    let elements: Vec<[u8; 32]> = public_inputs.iter().map(|input| input.0).collect();
+    let proof = placeholder_proof(ProofSystem::Groth16, &elements);
+
+    let ctx = RelayContext {
+        prover: Arc::new(ProverService::new(&crate::ProverConfig::placeholder()).unwrap()),
+        circuits: Arc::default(),
+        quotes: Arc::new(QuoteBook::new(
+            ethers::signers::LocalWallet::new(&mut rand::thread_rng()),
+            crate::quote::DEFAULT_QUOTE_VALIDITY,
+            crate::quote::DEFAULT_ABSORB_BUFFER_BPS,
+            crate::quote::test_pricing(&[chain_id]),
+            crate::store::memory().await,
+        )),
+        roots: Arc::new(HashMap::from([(
+            (chain_id, FIXTURE_POOL),
+            Arc::new(RwLock::new(pool)),
+        )])),
+        sources: Arc::default(),
+        chains: Arc::new(HashMap::from([(
+            chain_id,
+            FinalityHandle::with_headers(vec![], 101),
+        )])),
+        store: crate::store::memory().await,
+        min_fee: U256::from(1_000u64),
+        input_commitments: Arc::default(),
+        pools: Arc::new(HashMap::from([(
+            chain_id,
+            ServedPools {
+                default: Some(FIXTURE_POOL),
+                allowed: vec![],
+            },
+        )])),
+        dedup: Arc::default(),
+    };
+    let request = RelayRequest {
+        chain_id,
+        proof: proof.into(),
+        public_inputs,
+        quote: None,
+        fee: U256::from(1_000u64),
+        deadline: Some(2_000_000_000),
+        circuit_version: None,
+        recipient: None,
+        public_inputs_hash: None,
+        pool: None,
+    };
+    (ctx, request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::types::Recipient;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn rejection(ctx: &RelayContext, request: &RelayRequest) -> RelayRejection {
+        validate_relay_request(request, ctx, NOW).await.unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn test_valid_request_passes() {
+        let (ctx, request) = valid_relay_fixture(1).await;
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+
+        // With a change commitment and no deadline
+        let (ctx, mut request) = valid_relay_fixture(1).await;
+        request.public_inputs.push(H256::repeat_byte(0x05));
+        request.deadline = None;
+        let elements: Vec<[u8; 32]> = request.public_inputs.iter().map(|i| i.0).collect();
+        let mut proof = request.proof.to_vec();
+        proof[..32].copy_from_slice(&crate::prover::verifier::placeholder_binding(&elements));
+        request.proof = proof.into();
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_each_rejection_reason() {
+        let (ctx, valid) = valid_relay_fixture(1).await;
+
+        let mut request = valid.clone();
+        request.circuit_version = Some("v9".to_string());
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::UnsupportedCircuit(v) if v == "v9"
+        ));
+
+        let mut request = valid.clone();
+        request.public_inputs.truncate(3);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::WrongInputCount(3)
+        ));
+
+        let mut request = valid.clone();
+        request.public_inputs[3] = H256::repeat_byte(0xff);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::NonCanonicalInput { index: 3 }
+        ));
+
+        let mut request = valid.clone();
+        request.public_inputs[2] = H256::zero();
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::InvalidRecipient(_)
+        ));
+        request.public_inputs[2] = H256::from_low_u64_be(1);
+        request.public_inputs[2].0[0] = 0x01;
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::InvalidRecipient(_)
+        ));
+
+        let mut request = valid.clone();
+        request.recipient = Some(Recipient(Address::repeat_byte(0x44)));
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::RecipientMismatch { .. }
+        ));
+        request.recipient = Some(Recipient(Address::repeat_byte(0x33)));
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+
+        // A public input hash is only taken by chains whose verifier uses one
+        let mut request = valid.clone();
+        request.public_inputs_hash = Some(H256::repeat_byte(0x09));
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::InputHash(InputHashError::NotHashed)
+        ));
+        let mut hashed_ctx = ctx.clone();
+        hashed_ctx.input_commitments = Arc::new(HashMap::from([(1, InputCommitment::Keccak256)]));
+        assert!(matches!(
+            rejection(&hashed_ctx, &request).await,
+            RelayRejection::InputHash(InputHashError::Mismatch { .. })
+        ));
+        let elements: Vec<[u8; 32]> = request.public_inputs.iter().map(|i| i.0).collect();
+        request.public_inputs_hash = InputCommitment::Keccak256.hash(&elements);
+        validate_relay_request(&request, &hashed_ctx, NOW)
+            .await
+            .unwrap();
+
+        let mut request = valid.clone();
+        request.deadline = Some(NOW - 1);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::DeadlinePassed { .. }
+        ));
+
+        let mut request = valid.clone();
+        request.fee = U256::from(999u64);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::InsufficientFee { .. }
+        ));
+
+        let mut request = valid.clone();
+        request.public_inputs[0] = H256::repeat_byte(0x07);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::UnknownRoot(_)
+        ));
+        // Chains without served pools are turned away before their roots
+        request.chain_id = 5;
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::PoolNotServed {
+                chain_id: 5,
+                pool: None
+            }
+        ));
+
+        // A root from a block past the finalized height (101)
+        let fresh = ctx.roots[&(1, FIXTURE_POOL)]
+            .write()
+            .unwrap()
+            .apply_deposit(H256::repeat_byte(2), 1, 150)
+            .unwrap()
+            .unwrap();
+        let mut request = valid.clone();
+        request.public_inputs[0] = fresh;
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::RootNotFinalized {
+                block_number: 150,
+                ..
+            }
+        ));
+
+        let mut request = valid.clone();
+        let mut proof = request.proof.to_vec();
+        proof[0] ^= 0xff;
+        request.proof = proof.into();
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::InvalidProof
+        ));
+
+        ctx.store
+            .insert_nullifier(1, FIXTURE_POOL, valid.public_inputs[1])
+            .await
+            .unwrap();
+        assert!(matches!(
+            rejection(&ctx, &valid).await,
+            RelayRejection::NullifierSpent(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_proof_rejected_unless_allowed() {
+        let (mut ctx, request) = valid_relay_fixture(1).await;
+        ctx.prover = Arc::new(
+            ProverService::new(&crate::ProverConfig {
+                allow_placeholder_proofs: false,
+                enabled: false,
+                ..crate::ProverConfig::placeholder()
+            })
+            .unwrap(),
+        );
+
+        // Bound to its inputs, but nothing that could check a real proof
+        // of the system is configured
+        let rejected = rejection(&ctx, &request).await;
+        assert!(matches!(rejected, RelayRejection::Store(_)));
+        assert!(!rejected.is_sender_fault());
+    }
+
+    #[tokio::test]
+    async fn test_quote_checked_before_its_fee_is_taken() {
+        let (ctx, valid) = valid_relay_fixture(1).await;
+        let estimate = crate::quote::FeeEstimate {
+            gas_limit: 100_000,
+            gas_price: U256::one(),
+            fee: U256::from(10u64),
+        };
+        let quote = ctx
+            .quotes
+            .issue(1, estimate, chrono::Utc::now().timestamp())
+            .unwrap();
+
+        // A quote this relayer signed lowers the fee floor to its fee
+        let mut request = valid.clone();
+        request.fee = U256::from(10u64);
+        request.quote = Some(quote.clone());
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+
+        // One whose fee was edited after signing doesn't
+        let mut forged = quote.clone();
+        forged.quote.fee = U256::zero();
+        request.fee = U256::zero();
+        request.quote = Some(forged);
+        let rejected = rejection(&ctx, &request).await;
+        assert!(matches!(
+            rejected,
+            RelayRejection::Quote(QuoteError::InvalidSignature)
+        ));
+        assert!(rejected.is_sender_fault());
+
+        // Nor does one signed for another chain
+        let mut request = valid.clone();
+        request.fee = U256::from(10u64);
+        request.quote = Some(
+            ctx.quotes
+                .issue(2, estimate, chrono::Utc::now().timestamp())
+                .unwrap(),
+        );
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::Quote(QuoteError::WrongChain {
+                quoted: 2,
+                requested: 1
+            })
+        ));
+    }
+
+    /// Pool contract whose root is always the one it holds
+    struct FixedRoot(H256);
+
+    #[async_trait::async_trait]
+    impl LogSource for FixedRoot {
+        async fn fetch_block_number(&self) -> anyhow::Result<u64> {
+            Ok(0)
+        }
+
+        async fn fetch_logs(
+            &self,
+            _address: Address,
+            _from: u64,
+            _to: u64,
+        ) -> anyhow::Result<Vec<ethers::types::Log>> {
+            Ok(Vec::new())
+        }
+
+        async fn fetch_pool_root(
+            &self,
+            _pool: Address,
+            _block_number: u64,
+        ) -> anyhow::Result<H256> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_served_pools_follow_allowlist() {
+        let (mut ctx, valid) = valid_relay_fixture(1).await;
+        let (trusted, other) = (Address::repeat_byte(0xa1), Address::repeat_byte(0xa3));
+        let onchain_root = |root: H256| {
+            let source: Arc<dyn LogSource> = Arc::new(FixedRoot(root));
+            Arc::new(HashMap::from([(1, source)]))
+        };
+
+        // Requests naming no pool, or the default, target the default pool
+        let mut request = valid.clone();
+        request.pool = Some(FIXTURE_POOL);
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+        validate_relay_request(&valid, &ctx, NOW).await.unwrap();
+
+        // Without an allowlist any pool is served, against its root at the
+        // finalized block since it has no local tree
+        request.pool = Some(other);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::UnknownRoot(_)
+        ));
+        ctx.sources = onchain_root(valid.public_inputs[0]);
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+        ctx.sources = onchain_root(H256::repeat_byte(0x0f));
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::UnknownRoot(_)
+        ));
+        ctx.sources = Arc::default();
+
+        // An allowlist serves only the default and the pools on it
+        ctx.pools = Arc::new(HashMap::from([(
+            1,
+            ServedPools {
+                default: Some(FIXTURE_POOL),
+                allowed: vec![trusted],
+            },
+        )]));
+        let rejected = rejection(&ctx, &request).await;
+        assert!(matches!(
+            rejected,
+            RelayRejection::PoolNotServed {
+                chain_id: 1,
+                pool: Some(pool),
+            } if pool == other
+        ));
+        assert!(rejected.to_string().contains(&format!("{:?}", other)));
+        assert!(!rejected.is_sender_fault());
+
+        // An allowed pool is checked against its own roots and nullifiers
+        request.pool = Some(trusted);
+        assert!(matches!(
+            rejection(&ctx, &request).await,
+            RelayRejection::UnknownRoot(_)
+        ));
+        let mut roots = (*ctx.roots).clone();
+        roots.insert((1, trusted), roots[&(1, FIXTURE_POOL)].clone());
+        ctx.roots = Arc::new(roots);
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+        ctx.store
+            .insert_nullifier(1, FIXTURE_POOL, valid.public_inputs[1])
+            .await
+            .unwrap();
+        validate_relay_request(&request, &ctx, NOW).await.unwrap();
+        assert!(matches!(
+            rejection(&ctx, &valid).await,
+            RelayRejection::NullifierSpent(_)
+        ));
+
+        // Without a default, a request must name an allowed pool
+        ctx.pools = Arc::new(HashMap::from([(
+            1,
+            ServedPools {
+                default: None,
+                allowed: vec![trusted],
+            },
+        )]));
+        assert!(matches!(
+            rejection(&ctx, &valid).await,
+            RelayRejection::PoolNotServed { pool: None, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_gossip_scores_sender_down() {
+        let (ctx, valid) = valid_relay_fixture(1).await;
+
+        let data = serde_json::to_vec(&valid).unwrap();
+        assert!(accept_gossiped(&ctx, "good-peer", &data, NOW).await.is_ok());
+        assert_eq!(ctx.store.get_reputation("good-peer").await.unwrap(), None);
+
+        let mut expired = valid.clone();
+        expired.deadline = Some(NOW - 1);
+        let data = serde_json::to_vec(&expired).unwrap();
+        assert!(accept_gossiped(&ctx, "bad-peer", &data, NOW).await.is_err());
+        assert!(matches!(
+            accept_gossiped(&ctx, "bad-peer", b"not json", NOW).await,
+            Err(RelayRejection::Malformed(_))
+        ));
+        assert_eq!(
+            ctx.store.get_reputation("bad-peer").await.unwrap(),
+            Some(-2 * INVALID_REQUEST_PENALTY)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_gossip_deduplicated() {
+        let (ctx, valid) = valid_relay_fixture(1).await;
+        let data = serde_json::to_vec(&valid).unwrap();
+        accept_gossiped(&ctx, "peer-a", &data, NOW).await.unwrap();
+
+        // Same request with fields reordered, padded with whitespace and
+        // null defaults left out: a different gossip message, same withdrawal
+        let mut fields: Vec<(String, serde_json::Value)> = serde_json::to_value(&valid)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect();
+        fields.reverse();
+        let reordered = fields
+            .iter()
+            .map(|(name, value)| format!("  \"{}\" :  {}", name, value))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let variant = format!("{{\n{}\n}}\n", reordered);
+        assert_ne!(variant.as_bytes(), data.as_slice());
+        assert_eq!(
+            serde_json::from_str::<RelayRequest>(&variant).unwrap(),
+            valid
+        );
+
+        let key = dedup_key(&valid).unwrap();
+        assert!(matches!(
+            accept_gossiped(&ctx, "peer-b", variant.as_bytes(), NOW).await,
+            Err(RelayRejection::Duplicate(k)) if k == key
+        ));
+        // Forwarding a duplicate isn't held against the peer
+        assert_eq!(ctx.store.get_reputation("peer-b").await.unwrap(), None);
+
+        // A different nullifier is a different withdrawal
+        let mut other = valid.clone();
+        other.public_inputs[1] = H256::repeat_byte(0x23);
+        assert_ne!(dedup_key(&other), Some(key));
+    }
+}
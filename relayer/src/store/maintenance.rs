@@ -0,0 +1,295 @@
+//! Periodic store maintenance
+//!
+//! Persisted headers and audit entries are only needed for a while; left
+//! alone the database grows without bound. Each pass prunes headers that
+//! fall further than `header_blocks` behind their chain's finalized block,
+//! expires audit entries older than `audit_age`, reorg records older than
+//! `reorg_age` and reputation scores left unchanged for `reputation_age`,
+//! then reports the store's size. Compaction (a full `VACUUM` on SQLite)
+//! rewrites the whole database, so it only runs once `compact_interval` has
+//! passed since the last one. Nullifiers are never pruned: forgetting one
+//! would let a spent note be relayed again.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::Store;
+use crate::light_client::FinalityHandle;
+use crate::metrics;
+
+/// Default time between maintenance passes
+pub const DEFAULT_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Default headers kept below each chain's finalized block
+pub const DEFAULT_HEADER_RETENTION_BLOCKS: u64 = 50_000;
+
+/// Default age after which audit entries expire
+pub const DEFAULT_AUDIT_RETENTION: Duration = Duration::from_secs(90 * 24 * 3600);
+
+/// Default age after which reorg records expire
+pub const DEFAULT_REORG_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Default time a peer's reputation score is kept after it last changed
+pub const DEFAULT_REPUTATION_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Default time between compactions
+pub const DEFAULT_COMPACT_INTERVAL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// How long each kind of record is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Headers kept below the finalized block
+    pub header_blocks: u64,
+    pub audit_age: Duration,
+    pub reorg_age: Duration,
+    pub reputation_age: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            header_blocks: DEFAULT_HEADER_RETENTION_BLOCKS,
+            audit_age: DEFAULT_AUDIT_RETENTION,
+            reorg_age: DEFAULT_REORG_RETENTION,
+            reputation_age: DEFAULT_REPUTATION_RETENTION,
+        }
+    }
+}
+
+/// What a maintenance pass removed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub headers_pruned: u64,
+    pub audit_pruned: u64,
+    pub reorgs_pruned: u64,
+    pub reputation_pruned: u64,
+    /// Whether the store was compacted this pass
+    pub compacted: bool,
+    pub size_bytes: u64,
+}
+
+/// Prunes, compacts and measures the store
+pub struct StoreMaintenance {
+    store: Arc<dyn Store>,
+    /// Finality of each tracked chain, keyed by chain ID
+    chains: HashMap<u64, FinalityHandle>,
+    policy: RetentionPolicy,
+    compact_interval: Duration,
+    /// Unix time of the last compaction, counted from the first pass
+    last_compacted: AtomicI64,
+}
+
+impl StoreMaintenance {
+    pub fn new(
+        store: Arc<dyn Store>,
+        chains: HashMap<u64, FinalityHandle>,
+        policy: RetentionPolicy,
+        compact_interval: Duration,
+    ) -> Self {
+        Self {
+            store,
+            chains,
+            policy,
+            compact_interval,
+            last_compacted: AtomicI64::new(i64::MIN),
+        }
+    }
+
+    /// Run one pass as of unix time `now`
+    pub async fn run_once(&self, now: i64) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+
+        for (chain_id, finality) in &self.chains {
+            let keep_from = finality
+                .finalized()
+                .saturating_sub(self.policy.header_blocks);
+            if keep_from == 0 {
+                continue;
+            }
+            report.headers_pruned += self.store.prune_headers(*chain_id, keep_from).await?;
+        }
+
+        let cutoff = now.saturating_sub(self.policy.audit_age.as_secs() as i64);
+        report.audit_pruned = self.store.prune_audit(cutoff).await?;
+        let cutoff = now.saturating_sub(self.policy.reorg_age.as_secs() as i64);
+        report.reorgs_pruned = self.store.prune_reorgs(cutoff).await?;
+        let cutoff = now.saturating_sub(self.policy.reputation_age.as_secs() as i64);
+        report.reputation_pruned = self.store.prune_reputation(cutoff).await?;
+
+        metrics::STORE_RECORDS_PRUNED
+            .with_label_values(&["headers"])
+            .inc_by(report.headers_pruned);
+        metrics::STORE_RECORDS_PRUNED
+            .with_label_values(&["audit"])
+            .inc_by(report.audit_pruned);
+        metrics::STORE_RECORDS_PRUNED
+            .with_label_values(&["reorgs"])
+            .inc_by(report.reorgs_pruned);
+        metrics::STORE_RECORDS_PRUNED
+            .with_label_values(&["reputation"])
+            .inc_by(report.reputation_pruned);
+
+        // A restart waits a full interval rather than compacting on startup
+        let _ =
+            self.last_compacted
+                .compare_exchange(i64::MIN, now, Ordering::SeqCst, Ordering::SeqCst);
+        let last_compacted = self.last_compacted.load(Ordering::SeqCst);
+        if now.saturating_sub(last_compacted) >= self.compact_interval.as_secs() as i64 {
+            self.store.compact().await?;
+            self.last_compacted.store(now, Ordering::SeqCst);
+            report.compacted = true;
+        }
+        report.size_bytes = self.store.size_bytes().await?;
+        metrics::STORE_SIZE_BYTES.set(report.size_bytes as i64);
+
+        Ok(report)
+    }
+
+    /// Run a pass every `interval`, forever
+    pub async fn run(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.run_once(chrono::Utc::now().timestamp()).await {
+                Ok(report) => info!(
+                    headers_pruned = report.headers_pruned,
+                    audit_pruned = report.audit_pruned,
+                    reorgs_pruned = report.reorgs_pruned,
+                    reputation_pruned = report.reputation_pruned,
+                    compacted = report.compacted,
+                    size_bytes = report.size_bytes,
+                    "Store maintenance complete"
+                ),
+                Err(e) => warn!(error = %e, "Store maintenance failed"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_client::{ReorgRecord, StoredHeader};
+    use crate::store::AuditEntry;
+    use ethers::types::H256;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 24 * 3600;
+
+    fn header(block_number: u64) -> StoredHeader {
+        StoredHeader {
+            block_number,
+            block_hash: H256::from_low_u64_be(block_number),
+            parent_hash: H256::from_low_u64_be(block_number.saturating_sub(1)),
+            state_root: H256::zero(),
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+            timestamp: 1_700_000_000 + block_number * 12,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prunes_only_beyond_retention() {
+        let store = crate::store::memory().await;
+        for block_number in 90..=110 {
+            store.put_header(1, &header(block_number)).await.unwrap();
+            store.put_header(10, &header(block_number)).await.unwrap();
+        }
+        for (age_days, action) in [(40, "old"), (29, "recent"), (0, "today")] {
+            store
+                .append_audit(&AuditEntry {
+                    seq: 0,
+                    timestamp: NOW - age_days * DAY,
+                    actor: "node".to_string(),
+                    action: action.to_string(),
+                    details: "{}".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for age_days in [40, 0] {
+            store
+                .record_reorg(&ReorgRecord {
+                    chain_id: 1,
+                    detected_at: NOW - age_days * DAY,
+                    fork_block: 100,
+                    depth: 1,
+                    orphaned: vec![H256::repeat_byte(1)],
+                    replacement: vec![H256::repeat_byte(2)],
+                })
+                .await
+                .unwrap();
+        }
+        for (peer_id, age_days) in [("stale-peer", 40), ("active-peer", 1)] {
+            store
+                .set_reputation(peer_id, -5, NOW - age_days * DAY)
+                .await
+                .unwrap();
+        }
+
+        // Chain 1 is finalized at 110, chain 10 hasn't passed its window yet
+        let chains = HashMap::from([
+            (1, FinalityHandle::with_headers(vec![], 110)),
+            (10, FinalityHandle::with_headers(vec![], 8)),
+        ]);
+        let policy = RetentionPolicy {
+            header_blocks: 10,
+            audit_age: Duration::from_secs(30 * DAY as u64),
+            reorg_age: Duration::from_secs(30 * DAY as u64),
+            reputation_age: Duration::from_secs(30 * DAY as u64),
+        };
+        let maintenance = StoreMaintenance::new(
+            store.clone(),
+            chains,
+            policy,
+            Duration::from_secs(DAY as u64),
+        );
+
+        let report = maintenance.run_once(NOW).await.unwrap();
+        assert_eq!(report.headers_pruned, 10);
+        assert_eq!(report.audit_pruned, 1);
+        assert_eq!(report.reorgs_pruned, 1);
+        assert_eq!(report.reputation_pruned, 1);
+        assert!(!report.compacted);
+        assert!(report.size_bytes > 0);
+
+        assert_eq!(store.get_header(1, 99).await.unwrap(), None);
+        assert_eq!(store.get_header(1, 100).await.unwrap(), Some(header(100)));
+        assert_eq!(store.get_header(1, 110).await.unwrap(), Some(header(110)));
+        assert_eq!(store.get_header(10, 90).await.unwrap(), Some(header(90)));
+        let actions: Vec<_> = store
+            .audit_entries(0, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert_eq!(actions, vec!["recent", "today"]);
+        let reorgs = store.reorgs(1, 10).await.unwrap();
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!(reorgs[0].detected_at, NOW);
+        assert_eq!(store.get_reputation("stale-peer").await.unwrap(), None);
+        assert_eq!(store.get_reputation("active-peer").await.unwrap(), Some(-5));
+
+        // Nothing left to prune on a second pass
+        let report = maintenance.run_once(NOW).await.unwrap();
+        assert_eq!(
+            (
+                report.headers_pruned,
+                report.audit_pruned,
+                report.reorgs_pruned,
+                report.reputation_pruned
+            ),
+            (0, 0, 0, 0)
+        );
+        assert!(!report.compacted);
+
+        // Compaction waits for its own interval
+        assert!(maintenance.run_once(NOW + DAY).await.unwrap().compacted);
+        assert!(!maintenance.run_once(NOW + DAY + 1).await.unwrap().compacted);
+    }
+}
@@ -0,0 +1,342 @@
+//! Persistent storage for the relayer node
+//!
+//! Everything the node persists goes through the `Store` trait so the rest
+//! of the code doesn't depend on a particular database. The backend is
+//! picked from the configured URL scheme.
+
+pub mod maintenance;
+mod rocks;
+mod sqlite;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::light_client::{ReorgRecord, StoredHeader};
+
+pub use rocks::RocksStore;
+pub use sqlite::SqliteStore;
+
+/// Operator-visible record of something the node did
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    /// Sequence number assigned by the store on append
+    pub seq: u64,
+    /// Unix timestamp (seconds)
+    pub timestamp: i64,
+    /// Who performed the action (peer ID, admin address, "node", ...)
+    pub actor: String,
+    pub action: String,
+    pub details: String,
+}
+
+/// Storage operations used by the relayer
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Store a header, replacing any previous header at the same height
+    async fn put_header(&self, chain_id: u64, header: &StoredHeader) -> Result<()>;
+    /// Header at `block_number`, if stored
+    async fn get_header(&self, chain_id: u64, block_number: u64) -> Result<Option<StoredHeader>>;
+    /// Header with `block_hash`, if stored
+    async fn get_header_by_hash(
+        &self,
+        chain_id: u64,
+        block_hash: H256,
+    ) -> Result<Option<StoredHeader>>;
+    /// Up to `limit` of a chain's highest headers at or below `block_number`,
+    /// oldest first
+    async fn latest_headers(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+        limit: usize,
+    ) -> Result<Vec<StoredHeader>>;
+    /// Record a chain's finalized height
+    async fn set_finalized(&self, chain_id: u64, block_number: u64) -> Result<()>;
+    async fn get_finalized(&self, chain_id: u64) -> Result<Option<u64>>;
+
+    /// Record a nullifier spent in `pool`; returns `false` if it was already
+    /// recorded
+    async fn insert_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256)
+        -> Result<bool>;
+    async fn has_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<bool>;
+    /// Forget a nullifier reserved for a withdrawal that was never broadcast
+    async fn remove_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<()>;
+
+    /// Append an audit entry, returning its assigned sequence number
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<u64>;
+    /// Up to `limit` audit entries with sequence numbers after `after`, oldest first
+    async fn audit_entries(&self, after: u64, limit: usize) -> Result<Vec<AuditEntry>>;
+
+    /// Set a peer's score, last changed at `updated_at`
+    async fn set_reputation(&self, peer_id: &str, score: i64, updated_at: i64) -> Result<()>;
+    async fn get_reputation(&self, peer_id: &str) -> Result<Option<i64>>;
+
+    /// Record a reorg the light client handled
+    async fn record_reorg(&self, reorg: &ReorgRecord) -> Result<()>;
+    /// Up to `limit` of a chain's recorded reorgs, newest first
+    async fn reorgs(&self, chain_id: u64, limit: usize) -> Result<Vec<ReorgRecord>>;
+
+    /// Delete a chain's headers below `block_number`, returning how many went
+    async fn prune_headers(&self, chain_id: u64, block_number: u64) -> Result<u64>;
+    /// Delete audit entries timestamped before `timestamp`, returning how many went
+    async fn prune_audit(&self, timestamp: i64) -> Result<u64>;
+    /// Delete reorgs detected before `timestamp`, returning how many went
+    async fn prune_reorgs(&self, timestamp: i64) -> Result<u64>;
+    /// Delete scores last changed before `timestamp`, returning how many went
+    async fn prune_reputation(&self, timestamp: i64) -> Result<u64>;
+    /// Reclaim space freed by deletions
+    async fn compact(&self) -> Result<()>;
+    /// Approximate on-disk size in bytes
+    async fn size_bytes(&self) -> Result<u64>;
+}
+
+/// Open the store named by `url`
+///
+/// Supported schemes are `sqlite://` and `rocksdb://`. A bare path is
+/// treated as a SQLite file for compatibility with `database_path`.
+pub async fn open(url: &str) -> Result<Arc<dyn Store>> {
+    let store: Arc<dyn Store> = match url.split_once("://") {
+        Some(("sqlite", path)) => Arc::new(SqliteStore::open(path).await?),
+        Some(("rocksdb", path)) => Arc::new(RocksStore::open(path)?),
+        Some((scheme, _)) => anyhow::bail!("Unsupported database scheme: {}", scheme),
+        None => Arc::new(SqliteStore::open(url).await?),
+    };
+    info!(url = url, "Opened database");
+    Ok(store)
+}
+
+/// Throwaway in-memory store for tests of other components
+#[cfg(test)]
+pub(crate) async fn memory() -> Arc<dyn Store> {
+    Arc::new(SqliteStore::open_in_memory().await.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(block_number: u64) -> StoredHeader {
+        StoredHeader {
+            block_number,
+            block_hash: H256::from_low_u64_be(block_number),
+            parent_hash: H256::from_low_u64_be(block_number.saturating_sub(1)),
+            state_root: H256::repeat_byte(1),
+            transactions_root: H256::repeat_byte(2),
+            receipts_root: H256::repeat_byte(3),
+            timestamp: 1_700_000_000 + block_number,
+        }
+    }
+
+    fn audit(action: &str) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp: 1_700_000_000,
+            actor: "node".to_string(),
+            action: action.to_string(),
+            details: "{}".to_string(),
+        }
+    }
+
+    /// Behaviour every backend must share
+    async fn check_store_contract(store: Arc<dyn Store>) {
+        // Headers are keyed by chain and height
+        store.put_header(1, &header(10)).await.unwrap();
+        store.put_header(42161, &header(10)).await.unwrap();
+        assert_eq!(store.get_header(1, 10).await.unwrap(), Some(header(10)));
+        assert_eq!(store.get_header(1, 11).await.unwrap(), None);
+        let mut replacement = header(10);
+        replacement.block_hash = H256::repeat_byte(9);
+        store.put_header(1, &replacement).await.unwrap();
+        assert_eq!(store.get_header(1, 10).await.unwrap(), Some(replacement));
+        assert_eq!(store.get_header(42161, 10).await.unwrap(), Some(header(10)));
+        assert_eq!(
+            store
+                .get_header_by_hash(1, replacement.block_hash)
+                .await
+                .unwrap(),
+            Some(replacement.clone())
+        );
+        assert_eq!(
+            store
+                .get_header_by_hash(42161, replacement.block_hash)
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Latest headers come back oldest first, capped at the given height
+        for block_number in 11..=14 {
+            store.put_header(1, &header(block_number)).await.unwrap();
+        }
+        let latest = store.latest_headers(1, 13, 2).await.unwrap();
+        assert_eq!(latest, vec![header(12), header(13)]);
+        assert_eq!(store.latest_headers(1, 100, 10).await.unwrap().len(), 5);
+        assert!(store.latest_headers(5, 100, 10).await.unwrap().is_empty());
+
+        // Finalized heights overwrite per chain
+        assert_eq!(store.get_finalized(1).await.unwrap(), None);
+        store.set_finalized(1, 10).await.unwrap();
+        store.set_finalized(1, 12).await.unwrap();
+        assert_eq!(store.get_finalized(1).await.unwrap(), Some(12));
+        assert_eq!(store.get_finalized(42161).await.unwrap(), None);
+
+        // Nullifiers are insert-once per chain and pool
+        let nullifier = H256::repeat_byte(0xab);
+        let (pool, other_pool) = (Address::repeat_byte(0x50), Address::repeat_byte(0x51));
+        assert!(!store.has_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(store.insert_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(!store.insert_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(store.has_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(!store.has_nullifier(42161, pool, nullifier).await.unwrap());
+        assert!(!store.has_nullifier(1, other_pool, nullifier).await.unwrap());
+        store.remove_nullifier(1, pool, nullifier).await.unwrap();
+        assert!(!store.has_nullifier(1, pool, nullifier).await.unwrap());
+        assert!(store.insert_nullifier(1, pool, nullifier).await.unwrap());
+
+        // Audit log is append-only with increasing sequence numbers
+        let first = store.append_audit(&audit("start")).await.unwrap();
+        let second = store.append_audit(&audit("relay")).await.unwrap();
+        let third = store.append_audit(&audit("stop")).await.unwrap();
+        assert!(first < second && second < third);
+        let entries = store.audit_entries(first, 10).await.unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.action.as_str())
+                .collect::<Vec<_>>(),
+            vec!["relay", "stop"]
+        );
+        assert_eq!(entries[0].seq, second);
+        assert_eq!(store.audit_entries(0, 1).await.unwrap()[0].action, "start");
+
+        // Reputation scores overwrite
+        assert_eq!(store.get_reputation("peer-a").await.unwrap(), None);
+        store
+            .set_reputation("peer-a", 10, 1_700_000_000)
+            .await
+            .unwrap();
+        store
+            .set_reputation("peer-a", -5, 1_700_000_100)
+            .await
+            .unwrap();
+        assert_eq!(store.get_reputation("peer-a").await.unwrap(), Some(-5));
+
+        // Reorgs are listed per chain, newest first
+        let reorg = |chain_id, detected_at, fork_block| ReorgRecord {
+            chain_id,
+            detected_at,
+            fork_block,
+            depth: 1,
+            orphaned: vec![H256::repeat_byte(0x0a)],
+            replacement: vec![H256::repeat_byte(0x0b), H256::repeat_byte(0x0c)],
+        };
+        store
+            .record_reorg(&reorg(1, 1_700_000_000, 20))
+            .await
+            .unwrap();
+        store
+            .record_reorg(&reorg(1, 1_700_000_000, 21))
+            .await
+            .unwrap();
+        store
+            .record_reorg(&reorg(1, 1_700_000_100, 30))
+            .await
+            .unwrap();
+        store
+            .record_reorg(&reorg(42161, 1_700_000_000, 5))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.reorgs(1, 10).await.unwrap(),
+            vec![
+                reorg(1, 1_700_000_100, 30),
+                reorg(1, 1_700_000_000, 21),
+                reorg(1, 1_700_000_000, 20),
+            ]
+        );
+        assert_eq!(store.reorgs(1, 1).await.unwrap().len(), 1);
+        assert!(store.reorgs(10, 10).await.unwrap().is_empty());
+        assert_eq!(store.prune_reorgs(1_700_000_050).await.unwrap(), 3);
+        assert_eq!(
+            store.reorgs(1, 10).await.unwrap(),
+            vec![reorg(1, 1_700_000_100, 30)]
+        );
+
+        // Pruning only touches old records of the named chain
+        for block_number in 11..15 {
+            store.put_header(1, &header(block_number)).await.unwrap();
+        }
+        assert_eq!(store.prune_headers(1, 13).await.unwrap(), 3);
+        assert_eq!(store.get_header(1, 12).await.unwrap(), None);
+        assert_eq!(store.get_header(1, 13).await.unwrap(), Some(header(13)));
+        assert_eq!(store.get_header(42161, 10).await.unwrap(), Some(header(10)));
+
+        let mut recent = audit("recent");
+        recent.timestamp += 100;
+        store.append_audit(&recent).await.unwrap();
+        assert_eq!(store.prune_audit(1_700_000_050).await.unwrap(), 3);
+        let entries = store.audit_entries(0, 10).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "recent");
+
+        store
+            .set_reputation("peer-b", -5, 1_700_000_000)
+            .await
+            .unwrap();
+        assert_eq!(store.prune_reputation(1_700_000_050).await.unwrap(), 1);
+        assert_eq!(store.get_reputation("peer-b").await.unwrap(), None);
+        assert_eq!(store.get_reputation("peer-a").await.unwrap(), Some(-5));
+
+        store.compact().await.unwrap();
+        assert!(store.size_bytes().await.unwrap() > 0);
+        assert!(store.has_nullifier(1, pool, nullifier).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("relayer.db").display());
+        check_store_contract(open(&url).await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_store_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("rocksdb://{}", dir.path().join("relayer").display());
+        check_store_contract(open(&url).await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_reorgs_kept_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("rocksdb://{}", dir.path().join("relayer").display());
+        let reorg = |fork_block| ReorgRecord {
+            chain_id: 1,
+            detected_at: 1_700_000_000,
+            fork_block,
+            depth: 1,
+            orphaned: vec![],
+            replacement: vec![],
+        };
+
+        let store = open(&url).await.unwrap();
+        store.record_reorg(&reorg(20)).await.unwrap();
+        drop(store);
+
+        // Same chain and second after a restart: recorded alongside, not over
+        let store = open(&url).await.unwrap();
+        store.record_reorg(&reorg(21)).await.unwrap();
+        assert_eq!(
+            store.reorgs(1, 10).await.unwrap(),
+            vec![reorg(21), reorg(20)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_scheme_rejected() {
+        assert!(open("postgres://localhost/relayer").await.is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! RocksDB store backend
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+use rocksdb::{ColumnFamily, Direction, IteratorMode, Options, WriteBatch, DB};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::{AuditEntry, Store};
+use crate::light_client::{ReorgRecord, StoredHeader};
+
+const CF_HEADERS: &str = "headers";
+const CF_NULLIFIERS: &str = "nullifiers";
+const CF_AUDIT: &str = "audit";
+const CF_REPUTATION: &str = "reputation";
+const CF_REORGS: &str = "reorgs";
+const CF_FINALIZED: &str = "finalized";
+const COLUMN_FAMILIES: [&str; 6] = [
+    CF_HEADERS,
+    CF_NULLIFIERS,
+    CF_AUDIT,
+    CF_REPUTATION,
+    CF_REORGS,
+    CF_FINALIZED,
+];
+
+/// Store backed by a RocksDB directory, one column family per record type
+pub struct RocksStore {
+    db: DB,
+    /// Last assigned audit sequence number
+    audit_seq: AtomicU64,
+    /// Next tiebreaker for reorgs recorded on one chain in the same second
+    reorg_seq: AtomicU64,
+    /// Serializes read-modify-write operations (nullifier insertion)
+    write_lock: Mutex<()>,
+}
+
+impl RocksStore {
+    /// Open (creating if needed) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let db = DB::open_cf(&options, path, COLUMN_FAMILIES)?;
+
+        let last_seq = match db.iterator_cf(cf(&db, CF_AUDIT), IteratorMode::End).next() {
+            Some(item) => decode_u64(&item?.0)?,
+            None => 0,
+        };
+        // Keys sort by chain and time, not tiebreaker, so every one is read;
+        // restarting from zero would overwrite reorgs recorded before
+        let mut next_reorg_seq = 0;
+        for item in db.iterator_cf(cf(&db, CF_REORGS), IteratorMode::Start) {
+            let seq = decode_u64(&item?.0[16..24])?;
+            next_reorg_seq = next_reorg_seq.max(seq + 1);
+        }
+
+        Ok(Self {
+            db,
+            audit_seq: AtomicU64::new(last_seq),
+            reorg_seq: AtomicU64::new(next_reorg_seq),
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    fn cf(&self, name: &str) -> &ColumnFamily {
+        cf(&self.db, name)
+    }
+}
+
+fn cf<'a>(db: &'a DB, name: &str) -> &'a ColumnFamily {
+    db.cf_handle(name).expect("column family created on open")
+}
+
+/// Key made of a chain ID followed by a suffix, so keys sort by chain then suffix
+fn chain_key(chain_id: u64, suffix: &[u8]) -> Vec<u8> {
+    let mut key = chain_id.to_be_bytes().to_vec();
+    key.extend_from_slice(suffix);
+    key
+}
+
+/// Key of a nullifier spent in `pool`: chain ID, pool address, nullifier
+fn nullifier_key(chain_id: u64, pool: Address, nullifier: H256) -> Vec<u8> {
+    chain_key(chain_id, &[pool.as_bytes(), nullifier.as_bytes()].concat())
+}
+
+fn decode_u64(bytes: &[u8]) -> Result<u64> {
+    Ok(u64::from_be_bytes(bytes.try_into()?))
+}
+
+#[async_trait]
+impl Store for RocksStore {
+    async fn put_header(&self, chain_id: u64, header: &StoredHeader) -> Result<()> {
+        self.db.put_cf(
+            self.cf(CF_HEADERS),
+            chain_key(chain_id, &header.block_number.to_be_bytes()),
+            serde_json::to_vec(header)?,
+        )?;
+        Ok(())
+    }
+
+    async fn get_header(&self, chain_id: u64, block_number: u64) -> Result<Option<StoredHeader>> {
+        self.db
+            .get_cf(
+                self.cf(CF_HEADERS),
+                chain_key(chain_id, &block_number.to_be_bytes()),
+            )?
+            .map(|bytes| -> Result<StoredHeader> { Ok(serde_json::from_slice(&bytes)?) })
+            .transpose()
+    }
+
+    async fn get_header_by_hash(
+        &self,
+        chain_id: u64,
+        block_hash: H256,
+    ) -> Result<Option<StoredHeader>> {
+        let prefix = chain_id.to_be_bytes();
+        for item in self.db.iterator_cf(
+            self.cf(CF_HEADERS),
+            IteratorMode::From(&prefix, Direction::Forward),
+        ) {
+            let (key, value) = item?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let header: StoredHeader = serde_json::from_slice(&value)?;
+            if header.block_hash == block_hash {
+                return Ok(Some(header));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn latest_headers(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+        limit: usize,
+    ) -> Result<Vec<StoredHeader>> {
+        let prefix = chain_id.to_be_bytes();
+        let end = chain_key(chain_id, &block_number.to_be_bytes());
+        let mut headers = self
+            .db
+            .iterator_cf(
+                self.cf(CF_HEADERS),
+                IteratorMode::From(&end, Direction::Reverse),
+            )
+            .take_while(|item| {
+                item.as_ref()
+                    .map_or(true, |(key, _)| key.starts_with(&prefix))
+            })
+            .take(limit)
+            .map(|item| -> Result<StoredHeader> { Ok(serde_json::from_slice(&item?.1)?) })
+            .collect::<Result<Vec<_>>>()?;
+        headers.reverse();
+        Ok(headers)
+    }
+
+    async fn set_finalized(&self, chain_id: u64, block_number: u64) -> Result<()> {
+        self.db.put_cf(
+            self.cf(CF_FINALIZED),
+            chain_id.to_be_bytes(),
+            block_number.to_be_bytes(),
+        )?;
+        Ok(())
+    }
+
+    async fn get_finalized(&self, chain_id: u64) -> Result<Option<u64>> {
+        self.db
+            .get_cf(self.cf(CF_FINALIZED), chain_id.to_be_bytes())?
+            .map(|bytes| decode_u64(&bytes))
+            .transpose()
+    }
+
+    async fn insert_nullifier(
+        &self,
+        chain_id: u64,
+        pool: Address,
+        nullifier: H256,
+    ) -> Result<bool> {
+        let key = nullifier_key(chain_id, pool, nullifier);
+        let _guard = self.write_lock.lock().unwrap();
+        if self.db.get_cf(self.cf(CF_NULLIFIERS), &key)?.is_some() {
+            return Ok(false);
+        }
+        self.db.put_cf(self.cf(CF_NULLIFIERS), &key, b"")?;
+        Ok(true)
+    }
+
+    async fn has_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<bool> {
+        let key = nullifier_key(chain_id, pool, nullifier);
+        Ok(self.db.get_cf(self.cf(CF_NULLIFIERS), key)?.is_some())
+    }
+
+    async fn remove_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<()> {
+        let key = nullifier_key(chain_id, pool, nullifier);
+        let _guard = self.write_lock.lock().unwrap();
+        self.db.delete_cf(self.cf(CF_NULLIFIERS), key)?;
+        Ok(())
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<u64> {
+        let seq = self.audit_seq.fetch_add(1, Ordering::SeqCst) + 1;
+        let entry = AuditEntry {
+            seq,
+            ..entry.clone()
+        };
+        self.db.put_cf(
+            self.cf(CF_AUDIT),
+            seq.to_be_bytes(),
+            serde_json::to_vec(&entry)?,
+        )?;
+        Ok(seq)
+    }
+
+    async fn audit_entries(&self, after: u64, limit: usize) -> Result<Vec<AuditEntry>> {
+        let start = (after + 1).to_be_bytes();
+        self.db
+            .iterator_cf(
+                self.cf(CF_AUDIT),
+                IteratorMode::From(&start, Direction::Forward),
+            )
+            .take(limit)
+            .map(|item| -> Result<AuditEntry> { Ok(serde_json::from_slice(&item?.1)?) })
+            .collect()
+    }
+
+    async fn set_reputation(&self, peer_id: &str, score: i64, updated_at: i64) -> Result<()> {
+        // Score followed by the time it was last changed
+        let mut value = score.to_be_bytes().to_vec();
+        value.extend_from_slice(&updated_at.to_be_bytes());
+        self.db.put_cf(self.cf(CF_REPUTATION), peer_id, value)?;
+        Ok(())
+    }
+
+    async fn get_reputation(&self, peer_id: &str) -> Result<Option<i64>> {
+        self.db
+            .get_cf(self.cf(CF_REPUTATION), peer_id)?
+            .map(|bytes| -> Result<i64> { Ok(decode_u64(&bytes[..8])? as i64) })
+            .transpose()
+    }
+
+    async fn record_reorg(&self, reorg: &ReorgRecord) -> Result<()> {
+        // Keyed by chain, then detection time, so a chain's reorgs sort in
+        // the order they happened
+        let mut suffix = (reorg.detected_at as u64).to_be_bytes().to_vec();
+        suffix.extend_from_slice(&self.reorg_seq.fetch_add(1, Ordering::SeqCst).to_be_bytes());
+        self.db.put_cf(
+            self.cf(CF_REORGS),
+            chain_key(reorg.chain_id, &suffix),
+            serde_json::to_vec(reorg)?,
+        )?;
+        Ok(())
+    }
+
+    async fn reorgs(&self, chain_id: u64, limit: usize) -> Result<Vec<ReorgRecord>> {
+        let prefix = chain_id.to_be_bytes();
+        let end = chain_key(chain_id, &[0xff; 16]);
+        self.db
+            .iterator_cf(
+                self.cf(CF_REORGS),
+                IteratorMode::From(&end, Direction::Reverse),
+            )
+            .take_while(|item| {
+                item.as_ref()
+                    .map_or(true, |(key, _)| key.starts_with(&prefix))
+            })
+            .take(limit)
+            .map(|item| -> Result<ReorgRecord> { Ok(serde_json::from_slice(&item?.1)?) })
+            .collect()
+    }
+
+    async fn prune_headers(&self, chain_id: u64, block_number: u64) -> Result<u64> {
+        let start = chain_key(chain_id, &0u64.to_be_bytes());
+        let end = chain_key(chain_id, &block_number.to_be_bytes());
+        let mut batch = WriteBatch::default();
+        let mut pruned = 0;
+        for item in self.db.iterator_cf(
+            self.cf(CF_HEADERS),
+            IteratorMode::From(&start, Direction::Forward),
+        ) {
+            let (key, _) = item?;
+            if *key >= *end {
+                break;
+            }
+            batch.delete_cf(self.cf(CF_HEADERS), key);
+            pruned += 1;
+        }
+        self.db.write(batch)?;
+        Ok(pruned)
+    }
+
+    async fn prune_audit(&self, timestamp: i64) -> Result<u64> {
+        // Entries are appended in time order, so the old ones are a prefix
+        let mut batch = WriteBatch::default();
+        let mut pruned = 0;
+        for item in self.db.iterator_cf(self.cf(CF_AUDIT), IteratorMode::Start) {
+            let (key, value) = item?;
+            let entry: AuditEntry = serde_json::from_slice(&value)?;
+            if entry.timestamp >= timestamp {
+                break;
+            }
+            batch.delete_cf(self.cf(CF_AUDIT), key);
+            pruned += 1;
+        }
+        self.db.write(batch)?;
+        Ok(pruned)
+    }
+
+    async fn prune_reorgs(&self, timestamp: i64) -> Result<u64> {
+        let mut batch = WriteBatch::default();
+        let mut pruned = 0;
+        for item in self.db.iterator_cf(self.cf(CF_REORGS), IteratorMode::Start) {
+            let (key, _) = item?;
+            if (decode_u64(&key[8..16])? as i64) < timestamp {
+                batch.delete_cf(self.cf(CF_REORGS), key);
+                pruned += 1;
+            }
+        }
+        self.db.write(batch)?;
+        Ok(pruned)
+    }
+
+    async fn prune_reputation(&self, timestamp: i64) -> Result<u64> {
+        let mut batch = WriteBatch::default();
+        let mut pruned = 0;
+        for item in self
+            .db
+            .iterator_cf(self.cf(CF_REPUTATION), IteratorMode::Start)
+        {
+            let (key, value) = item?;
+            if (decode_u64(&value[8..16])? as i64) < timestamp {
+                batch.delete_cf(self.cf(CF_REPUTATION), key);
+                pruned += 1;
+            }
+        }
+        self.db.write(batch)?;
+        Ok(pruned)
+    }
+
+    async fn compact(&self) -> Result<()> {
+        for name in COLUMN_FAMILIES {
+            self.db
+                .compact_range_cf(self.cf(name), None::<&[u8]>, None::<&[u8]>);
+        }
+        Ok(())
+    }
+
+    async fn size_bytes(&self) -> Result<u64> {
+        let mut total = 0;
+        for name in COLUMN_FAMILIES {
+            for property in [
+                "rocksdb.total-sst-files-size",
+                "rocksdb.cur-size-all-mem-tables",
+            ] {
+                total += self
+                    .db
+                    .property_int_value_cf(self.cf(name), property)?
+                    .unwrap_or(0);
+            }
+        }
+        Ok(total)
+    }
+}
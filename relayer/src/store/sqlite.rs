@@ -0,0 +1,357 @@
+//! SQLite store backend
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::Path;
+#[cfg(test)]
+use std::str::FromStr;
+
+use super::{AuditEntry, Store};
+use crate::light_client::{ReorgRecord, StoredHeader};
+
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS headers (
+        chain_id INTEGER NOT NULL,
+        block_number INTEGER NOT NULL,
+        header TEXT NOT NULL,
+        PRIMARY KEY (chain_id, block_number)
+    )",
+    "CREATE TABLE IF NOT EXISTS finalized (
+        chain_id INTEGER PRIMARY KEY,
+        block_number INTEGER NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS nullifiers (
+        chain_id INTEGER NOT NULL,
+        pool BLOB NOT NULL,
+        nullifier BLOB NOT NULL,
+        PRIMARY KEY (chain_id, pool, nullifier)
+    )",
+    "CREATE TABLE IF NOT EXISTS audit_log (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        actor TEXT NOT NULL,
+        action TEXT NOT NULL,
+        details TEXT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS reputation (
+        peer_id TEXT PRIMARY KEY,
+        score INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS reputation_by_age ON reputation (updated_at)",
+    "CREATE TABLE IF NOT EXISTS reorgs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        chain_id INTEGER NOT NULL,
+        detected_at INTEGER NOT NULL,
+        reorg TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS reorgs_by_chain ON reorgs (chain_id, detected_at)",
+];
+
+/// Store backed by a single SQLite file
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Open (creating if needed) the database at `path`
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+
+        Self::with_pool(pool).await
+    }
+
+    /// Private in-memory database, kept on a single long-lived connection
+    pub async fn open_in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(SqliteConnectOptions::from_str("sqlite::memory:")?)
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    async fn with_pool(pool: SqlitePool) -> Result<Self> {
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn put_header(&self, chain_id: u64, header: &StoredHeader) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO headers (chain_id, block_number, header) VALUES (?, ?, ?)",
+        )
+        .bind(chain_id as i64)
+        .bind(header.block_number as i64)
+        .bind(serde_json::to_string(header)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_header(&self, chain_id: u64, block_number: u64) -> Result<Option<StoredHeader>> {
+        let row = sqlx::query("SELECT header FROM headers WHERE chain_id = ? AND block_number = ?")
+            .bind(chain_id as i64)
+            .bind(block_number as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| -> Result<StoredHeader> {
+            let header: String = row.try_get("header")?;
+            Ok(serde_json::from_str(&header)?)
+        })
+        .transpose()
+    }
+
+    async fn get_header_by_hash(
+        &self,
+        chain_id: u64,
+        block_hash: H256,
+    ) -> Result<Option<StoredHeader>> {
+        let row = sqlx::query(
+            "SELECT header FROM headers
+             WHERE chain_id = ? AND json_extract(header, '$.block_hash') = ?",
+        )
+        .bind(chain_id as i64)
+        .bind(format!("{:?}", block_hash))
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| -> Result<StoredHeader> {
+            let header: String = row.try_get("header")?;
+            Ok(serde_json::from_str(&header)?)
+        })
+        .transpose()
+    }
+
+    async fn latest_headers(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+        limit: usize,
+    ) -> Result<Vec<StoredHeader>> {
+        let rows = sqlx::query(
+            "SELECT header FROM headers WHERE chain_id = ? AND block_number <= ?
+             ORDER BY block_number DESC LIMIT ?",
+        )
+        .bind(chain_id as i64)
+        .bind(block_number as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .rev()
+            .map(|row| {
+                let header: String = row.try_get("header")?;
+                Ok(serde_json::from_str(&header)?)
+            })
+            .collect()
+    }
+
+    async fn set_finalized(&self, chain_id: u64, block_number: u64) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO finalized (chain_id, block_number) VALUES (?, ?)")
+            .bind(chain_id as i64)
+            .bind(block_number as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_finalized(&self, chain_id: u64) -> Result<Option<u64>> {
+        let row = sqlx::query("SELECT block_number FROM finalized WHERE chain_id = ?")
+            .bind(chain_id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row
+            .map(|row| row.try_get::<i64, _>("block_number"))
+            .transpose()?
+            .map(|block_number| block_number as u64))
+    }
+
+    async fn insert_nullifier(
+        &self,
+        chain_id: u64,
+        pool: Address,
+        nullifier: H256,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO nullifiers (chain_id, pool, nullifier) VALUES (?, ?, ?)",
+        )
+        .bind(chain_id as i64)
+        .bind(pool.as_bytes())
+        .bind(nullifier.as_bytes())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn has_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT 1 FROM nullifiers WHERE chain_id = ? AND pool = ? AND nullifier = ?",
+        )
+        .bind(chain_id as i64)
+        .bind(pool.as_bytes())
+        .bind(nullifier.as_bytes())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn remove_nullifier(&self, chain_id: u64, pool: Address, nullifier: H256) -> Result<()> {
+        sqlx::query("DELETE FROM nullifiers WHERE chain_id = ? AND pool = ? AND nullifier = ?")
+            .bind(chain_id as i64)
+            .bind(pool.as_bytes())
+            .bind(nullifier.as_bytes())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<u64> {
+        let result = sqlx::query(
+            "INSERT INTO audit_log (timestamp, actor, action, details) VALUES (?, ?, ?, ?)",
+        )
+        .bind(entry.timestamp)
+        .bind(&entry.actor)
+        .bind(&entry.action)
+        .bind(&entry.details)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid() as u64)
+    }
+
+    async fn audit_entries(&self, after: u64, limit: usize) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT seq, timestamp, actor, action, details FROM audit_log
+             WHERE seq > ? ORDER BY seq LIMIT ?",
+        )
+        .bind(after as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditEntry {
+                    seq: row.try_get::<i64, _>("seq")? as u64,
+                    timestamp: row.try_get("timestamp")?,
+                    actor: row.try_get("actor")?,
+                    action: row.try_get("action")?,
+                    details: row.try_get("details")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn set_reputation(&self, peer_id: &str, score: i64, updated_at: i64) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO reputation (peer_id, score, updated_at) VALUES (?, ?, ?)",
+        )
+        .bind(peer_id)
+        .bind(score)
+        .bind(updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_reputation(&self, peer_id: &str) -> Result<Option<i64>> {
+        let row = sqlx::query("SELECT score FROM reputation WHERE peer_id = ?")
+            .bind(peer_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.try_get("score")).transpose()?)
+    }
+
+    async fn record_reorg(&self, reorg: &ReorgRecord) -> Result<()> {
+        sqlx::query("INSERT INTO reorgs (chain_id, detected_at, reorg) VALUES (?, ?, ?)")
+            .bind(reorg.chain_id as i64)
+            .bind(reorg.detected_at)
+            .bind(serde_json::to_string(reorg)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn reorgs(&self, chain_id: u64, limit: usize) -> Result<Vec<ReorgRecord>> {
+        let rows = sqlx::query(
+            "SELECT reorg FROM reorgs WHERE chain_id = ?
+             ORDER BY detected_at DESC, id DESC LIMIT ?",
+        )
+        .bind(chain_id as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let reorg: String = row.try_get("reorg")?;
+                Ok(serde_json::from_str(&reorg)?)
+            })
+            .collect()
+    }
+
+    async fn prune_headers(&self, chain_id: u64, block_number: u64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM headers WHERE chain_id = ? AND block_number < ?")
+            .bind(chain_id as i64)
+            .bind(block_number as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn prune_audit(&self, timestamp: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM audit_log WHERE timestamp < ?")
+            .bind(timestamp)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn prune_reorgs(&self, timestamp: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM reorgs WHERE detected_at < ?")
+            .bind(timestamp)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn prune_reputation(&self, timestamp: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM reputation WHERE updated_at < ?")
+            .bind(timestamp)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn compact(&self) -> Result<()> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn size_bytes(&self) -> Result<u64> {
+        let row = sqlx::query(
+            "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get::<i64, _>("size")? as u64)
+    }
+}
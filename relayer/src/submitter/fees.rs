@@ -0,0 +1,242 @@
+//! Transaction fee pricing
+//!
+//! Transactions are either legacy, paying a single gas price, or EIP-1559
+//! (type 2), paying the block's base fee plus a priority tip up to a cap.
+//! EIP-1559 fees come from the chain's recent fee history: the tip is the
+//! median of recent blocks' median tips, and the cap leaves room for the
+//! base fee to double. Chains whose history reports no base fee don't
+//! support EIP-1559 and fall back to legacy pricing.
+
+use ethers::types::{FeeHistory, U256};
+use tracing::debug;
+
+use super::{bumped, GasOracle, GasPolicy};
+
+/// Blocks of fee history sampled for EIP-1559 pricing
+pub const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Percentile of each block's tips sampled
+const REWARD_PERCENTILE: f64 = 50.0;
+
+/// Tip used when recent blocks paid none (1 gwei)
+const DEFAULT_PRIORITY_FEE: u64 = 1_000_000_000;
+
+/// Transaction envelope relayer transactions are sent in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeStrategy {
+    /// Untyped transactions with a single gas price
+    Legacy,
+    /// Type-2 transactions priced from fee history
+    #[default]
+    Eip1559,
+}
+
+/// How relayer transactions are priced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeSettings {
+    pub strategy: FeeStrategy,
+    /// Highest priority tip offered, if capped
+    pub max_priority_fee: Option<U256>,
+    /// Highest total fee per gas paid, if capped
+    pub max_fee: Option<U256>,
+}
+
+/// Fees a transaction is sent with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxFees {
+    Legacy {
+        gas_price: U256,
+    },
+    Eip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl TxFees {
+    /// Most the transaction can pay per gas: its gas price or fee cap
+    pub fn max_per_gas(&self) -> U256 {
+        match self {
+            TxFees::Legacy { gas_price } => *gas_price,
+            TxFees::Eip1559 {
+                max_fee_per_gas, ..
+            } => *max_fee_per_gas,
+        }
+    }
+}
+
+impl FeeSettings {
+    /// Settings for a chain, whose gas ceiling caps EIP-1559 fees unless a
+    /// cap is already set
+    pub fn for_chain(self, gas: &GasPolicy) -> Self {
+        Self {
+            max_fee: self.max_fee.or(gas.max_gas_price),
+            ..self
+        }
+    }
+
+    /// Fees for a transaction, given the legacy `gas_price` already cleared
+    /// against the gas ceiling
+    pub async fn price(&self, oracle: &dyn GasOracle, gas_price: U256) -> TxFees {
+        let legacy = TxFees::Legacy { gas_price };
+        if self.strategy == FeeStrategy::Legacy {
+            return legacy;
+        }
+
+        match oracle
+            .fee_history(FEE_HISTORY_BLOCKS, REWARD_PERCENTILE)
+            .await
+        {
+            Ok(history) => self.eip1559(&history).unwrap_or_else(|| {
+                debug!("Chain reports no base fee, using legacy pricing");
+                legacy
+            }),
+            Err(e) => {
+                debug!(error = %e, "No fee history, using legacy pricing");
+                legacy
+            }
+        }
+    }
+
+    /// Fees replacing a stuck transaction sent with `fees`
+    ///
+    /// Nodes only accept a replacement that raises every fee by the minimum
+    /// bump, so `None` when a cap or the gas ceiling leaves no room for one.
+    pub fn bump(&self, fees: TxFees, gas: &GasPolicy) -> Option<TxFees> {
+        match fees {
+            TxFees::Legacy { gas_price } => gas
+                .bump(gas_price)
+                .map(|gas_price| TxFees::Legacy { gas_price }),
+            TxFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                let max_fee_per_gas = bumped(max_fee_per_gas);
+                let max_priority_fee_per_gas = bumped(max_priority_fee_per_gas);
+                let over = |cap: Option<U256>, fee: U256| cap.is_some_and(|cap| fee > cap);
+                if over(self.max_fee, max_fee_per_gas)
+                    || over(self.max_priority_fee, max_priority_fee_per_gas)
+                {
+                    return None;
+                }
+                Some(TxFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                })
+            }
+        }
+    }
+
+    fn eip1559(&self, history: &FeeHistory) -> Option<TxFees> {
+        // The last entry is the base fee of the next block
+        let base_fee = *history.base_fee_per_gas.last()?;
+        if base_fee.is_zero() {
+            return None;
+        }
+
+        let mut tips: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|rewards| rewards.first().copied())
+            .filter(|tip| !tip.is_zero())
+            .collect();
+        tips.sort_unstable();
+        let mut priority = tips
+            .get(tips.len() / 2)
+            .copied()
+            .unwrap_or_else(|| U256::from(DEFAULT_PRIORITY_FEE));
+        if let Some(cap) = self.max_priority_fee {
+            priority = priority.min(cap);
+        }
+
+        let mut max_fee = base_fee * 2 + priority;
+        if let Some(cap) = self.max_fee {
+            max_fee = max_fee.min(cap);
+            priority = priority.min(max_fee);
+        }
+        Some(TxFees::Eip1559 {
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::Provider;
+
+    fn gwei(n: u64) -> U256 {
+        U256::from(n) * U256::exp10(9)
+    }
+
+    fn history(base_fees: &[u64], tips: &[u64]) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: base_fees.iter().map(|fee| gwei(*fee)).collect(),
+            gas_used_ratio: vec![0.5; tips.len()],
+            oldest_block: U256::from(100u64),
+            reward: tips.iter().map(|tip| vec![gwei(*tip)]).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eip1559_priced_from_fee_history() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(history(&[20, 22, 24, 30], &[1, 3, 2])).unwrap();
+        let settings = FeeSettings {
+            strategy: FeeStrategy::Eip1559,
+            ..Default::default()
+        };
+
+        // Median tip 2 gwei; cap twice the next base fee of 30 gwei, plus the tip
+        assert_eq!(
+            settings.price(&provider, gwei(25)).await,
+            TxFees::Eip1559 {
+                max_fee_per_gas: gwei(62),
+                max_priority_fee_per_gas: gwei(2),
+            }
+        );
+
+        // Caps bound both fees
+        mock.push(history(&[20, 22, 24, 30], &[1, 3, 2])).unwrap();
+        let capped = FeeSettings {
+            max_priority_fee: Some(gwei(1)),
+            max_fee: Some(gwei(50)),
+            ..settings
+        };
+        assert_eq!(
+            capped.price(&provider, gwei(25)).await,
+            TxFees::Eip1559 {
+                max_fee_per_gas: gwei(50),
+                max_priority_fee_per_gas: gwei(1),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_legacy_without_base_fee() {
+        let (provider, mock) = Provider::mocked();
+        let legacy = TxFees::Legacy {
+            gas_price: gwei(25),
+        };
+
+        // Pre-London chains report zero base fees
+        mock.push(history(&[0, 0, 0], &[0, 0])).unwrap();
+        let settings = FeeSettings {
+            strategy: FeeStrategy::Eip1559,
+            ..Default::default()
+        };
+        assert_eq!(settings.price(&provider, gwei(25)).await, legacy);
+
+        // Nothing queued: the fee history call fails
+        assert_eq!(settings.price(&provider, gwei(25)).await, legacy);
+
+        // Legacy never asks for fee history
+        mock.push(history(&[20], &[])).unwrap();
+        assert_eq!(
+            FeeSettings::default().price(&provider, gwei(25)).await,
+            legacy
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Public inputs as submitted on-chain
+//!
+//! Verifiers take either every public input as calldata or a single hash of
+//! the inputs, computed on-chain, which is much cheaper when there are many
+//! inputs. The scheme is set per chain to match its verifier, and picks the
+//! pool's `withdraw` overload the hash is passed to. A hash is always
+//! recomputed from the inputs, and any hash supplied alongside them must
+//! agree, before anything is submitted.
+
+use ethers::types::{H256, U256};
+use ethers::utils::keccak256;
+
+use crate::relay::validate::SCALAR_FIELD_MODULUS;
+
+/// How a chain's verifier takes public inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputCommitment {
+    /// Every input as a 32-byte calldata word
+    #[default]
+    Full,
+    /// `keccak256` of the inputs packed as 32-byte words
+    Keccak256,
+    /// `keccak256` of the packed inputs reduced modulo the BN254 scalar
+    /// field, for verifiers that feed the hash to the circuit as its only
+    /// public input
+    Keccak256Field,
+}
+
+/// A supplied public-input hash that can't be submitted
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InputHashError {
+    #[error("Public input hash {given:?} does not match the inputs, which hash to {computed:?}")]
+    Mismatch { given: H256, computed: H256 },
+    #[error("Verifier takes full public inputs, not a hash")]
+    NotHashed,
+}
+
+impl InputCommitment {
+    /// Hash of `inputs` under this scheme; `None` when they go in full
+    pub fn hash(&self, inputs: &[[u8; 32]]) -> Option<H256> {
+        let digest = keccak256(inputs.concat());
+        match self {
+            InputCommitment::Full => None,
+            InputCommitment::Keccak256 => Some(H256(digest)),
+            InputCommitment::Keccak256Field => {
+                let modulus =
+                    U256::from_str_radix(SCALAR_FIELD_MODULUS, 16).expect("valid modulus");
+                let mut reduced = [0u8; 32];
+                (U256::from_big_endian(&digest) % modulus).to_big_endian(&mut reduced);
+                Some(H256(reduced))
+            }
+        }
+    }
+
+    /// Hash to submit in place of `inputs`; `None` when they go in full
+    ///
+    /// A `given` hash, such as one sent with a relay request, must match
+    /// the hash recomputed from `inputs`.
+    pub fn committed_hash(
+        &self,
+        inputs: &[[u8; 32]],
+        given: Option<H256>,
+    ) -> Result<Option<H256>, InputHashError> {
+        let Some(computed) = self.hash(inputs) else {
+            return match given {
+                Some(_) => Err(InputHashError::NotHashed),
+                None => Ok(None),
+            };
+        };
+        match given {
+            Some(given) if given != computed => Err(InputHashError::Mismatch { given, computed }),
+            _ => Ok(Some(computed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    fn inputs() -> Vec<[u8; 32]> {
+        vec![[0x11; 32], H256::from_low_u64_be(42).0, [0x05; 32]]
+    }
+
+    #[test]
+    fn test_hash_matches_verifier_encoding() {
+        // keccak256(bytes32(0)), the well-known empty storage slot hash
+        assert_eq!(
+            InputCommitment::Keccak256.hash(&[[0u8; 32]]),
+            Some(
+                "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+                    .parse()
+                    .unwrap()
+            )
+        );
+
+        // Same as hashing `abi.encodePacked(uint256[])` in the verifier
+        let tokens: Vec<Token> = inputs()
+            .iter()
+            .map(|input| Token::Uint(U256::from_big_endian(input)))
+            .collect();
+        let packed = H256(keccak256(encode(&tokens)));
+        assert_eq!(InputCommitment::Keccak256.hash(&inputs()), Some(packed));
+        assert_eq!(InputCommitment::Full.hash(&inputs()), None);
+
+        // The field variant is the same digest reduced below the modulus
+        let modulus = U256::from_str_radix(SCALAR_FIELD_MODULUS, 16).unwrap();
+        let mut reduced_any = false;
+        for n in 0..32u64 {
+            let inputs = [H256::from_low_u64_be(n).0];
+            let raw =
+                U256::from_big_endian(InputCommitment::Keccak256.hash(&inputs).unwrap().as_bytes());
+            let field = U256::from_big_endian(
+                InputCommitment::Keccak256Field
+                    .hash(&inputs)
+                    .unwrap()
+                    .as_bytes(),
+            );
+            assert!(field < modulus);
+            assert_eq!(field, raw % modulus);
+            reduced_any |= raw >= modulus;
+        }
+        assert!(reduced_any);
+    }
+
+    #[test]
+    fn test_given_hash_must_match_inputs() {
+        let scheme = InputCommitment::Keccak256Field;
+        let hash = scheme.hash(&inputs()).unwrap();
+        assert_eq!(scheme.committed_hash(&inputs(), None), Ok(Some(hash)));
+        assert_eq!(scheme.committed_hash(&inputs(), Some(hash)), Ok(Some(hash)));
+
+        // The hash commits to the order of the inputs, not just the set
+        let mut reordered = inputs();
+        reordered.swap(0, 2);
+        assert_eq!(
+            scheme.committed_hash(&reordered, Some(hash)),
+            Err(InputHashError::Mismatch {
+                given: hash,
+                computed: scheme.hash(&reordered).unwrap(),
+            })
+        );
+
+        // Chains taking full inputs submit no hash and refuse one
+        let full = InputCommitment::Full;
+        assert_eq!(full.committed_hash(&inputs(), None), Ok(None));
+        assert_eq!(
+            full.committed_hash(&inputs(), Some(hash)),
+            Err(InputHashError::NotHashed)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Transaction submission
+//!
+//! Gas pricing rules applied before relayer transactions are broadcast,
+//! the routes they are broadcast through, per-chain nonce ordering,
+//! tracking of the transactions until they are mined, and the withdrawal
+//! calls relays are submitted as, replaced at a higher fee while stuck.
+
+pub mod fees;
+pub mod inputs;
+pub mod nonce;
+pub mod receipts;
+pub mod route;
+pub mod status;
+pub mod withdraw;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::ChainEndpoints;
+
+pub use fees::{FeeSettings, FeeStrategy, TxFees};
+pub use inputs::{InputCommitment, InputHashError};
+pub use nonce::{ChainSubmitters, InFlight, NonceManager, NonceSource};
+pub use receipts::{decode_revert_reason, ReceiptSource, RevertReason, SubmissionTracker};
+pub use route::{Broadcast, RoutedBroadcaster, SubmissionRoute, TxBroadcaster};
+pub use status::{TxStatus, TxStatuses};
+pub use withdraw::{ChainWithdrawals, WithdrawError, WithdrawalSubmitter};
+
+/// How often a deferred submission re-checks the gas price
+const GAS_RECHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Longest a submission may be deferred waiting for gas to drop
+const MAX_GAS_DEFERRAL: Duration = Duration::from_secs(600);
+
+/// Minimum replacement bump, in basis points (12.5%)
+const MIN_BUMP_BPS: u64 = 1_250;
+
+/// Submission errors callers may want to match on
+#[derive(Debug, thiserror::Error)]
+pub enum SubmitError {
+    /// Network gas price is above the chain's configured ceiling
+    #[error("Gas price {gas_price} exceeds ceiling {ceiling}")]
+    GasTooHigh { gas_price: U256, ceiling: U256 },
+}
+
+/// What to do when gas is above the ceiling
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasCeilingAction {
+    /// Hold the submission and retry once gas drops
+    #[default]
+    Defer,
+    /// Fail immediately with `GasTooHigh`
+    Reject,
+}
+
+/// Source of the current network gas price
+#[async_trait]
+pub trait GasOracle: Send + Sync {
+    async fn gas_price(&self) -> Result<U256>;
+
+    /// Base fees and tips (at `percentile`) of the latest `blocks`
+    async fn fee_history(&self, _blocks: u64, _percentile: f64) -> Result<FeeHistory> {
+        anyhow::bail!("Fee history not available")
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> GasOracle for Provider<P> {
+    async fn gas_price(&self) -> Result<U256> {
+        Ok(self.get_gas_price().await?)
+    }
+
+    async fn fee_history(&self, blocks: u64, percentile: f64) -> Result<FeeHistory> {
+        Ok(Middleware::fee_history(self, blocks, BlockNumber::Latest, &[percentile]).await?)
+    }
+}
+
+/// Per-chain gas price ceiling
+#[derive(Debug, Clone)]
+pub struct GasPolicy {
+    /// Highest gas price the relayer will pay, if any
+    pub max_gas_price: Option<U256>,
+    /// Behaviour when the network price exceeds `max_gas_price`
+    pub action: GasCeilingAction,
+    /// Interval between re-checks while deferred
+    pub recheck_interval: Duration,
+    /// Give up deferring after this long
+    pub max_deferral: Duration,
+}
+
+impl From<&ChainEndpoints> for GasPolicy {
+    fn from(endpoints: &ChainEndpoints) -> Self {
+        Self {
+            max_gas_price: endpoints
+                .max_gas_price_gwei
+                .map(|gwei| U256::from(gwei) * U256::exp10(9)),
+            action: endpoints.gas_ceiling_action,
+            recheck_interval: GAS_RECHECK_INTERVAL,
+            max_deferral: MAX_GAS_DEFERRAL,
+        }
+    }
+}
+
+impl GasPolicy {
+    /// Wait until gas is within the ceiling and return the price to use
+    ///
+    /// With `Reject` (or once `max_deferral` elapses under `Defer`) an
+    /// over-ceiling price fails with `SubmitError::GasTooHigh`.
+    pub async fn acquire(&self, oracle: &dyn GasOracle) -> Result<U256> {
+        let started = Instant::now();
+
+        loop {
+            let gas_price = oracle.gas_price().await?;
+            let Some(ceiling) = self.max_gas_price else {
+                return Ok(gas_price);
+            };
+            if gas_price <= ceiling {
+                return Ok(gas_price);
+            }
+
+            if self.action == GasCeilingAction::Reject
+                || started.elapsed() + self.recheck_interval > self.max_deferral
+            {
+                warn!(%gas_price, %ceiling, "Gas price above ceiling, rejecting submission");
+                return Err(SubmitError::GasTooHigh { gas_price, ceiling }.into());
+            }
+
+            info!(%gas_price, %ceiling, "Gas price above ceiling, deferring submission");
+            tokio::time::sleep(self.recheck_interval).await;
+        }
+    }
+
+    /// Replacement gas price for a stuck transaction
+    ///
+    /// Bumps by at least 12.5% but never past the ceiling; returns `None` when
+    /// the ceiling leaves no room for a valid replacement.
+    pub fn bump(&self, current: U256) -> Option<U256> {
+        let bumped = bumped(current);
+        match self.max_gas_price {
+            Some(ceiling) if bumped > ceiling => None,
+            _ => Some(bumped),
+        }
+    }
+}
+
+/// `current` raised by the minimum replacement bump
+fn bumped(current: U256) -> U256 {
+    current + current * U256::from(MIN_BUMP_BPS) / U256::from(10_000u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Oracle returning a scripted sequence of prices (last one repeats)
+    struct ScriptedOracle {
+        prices: Mutex<Vec<u64>>,
+    }
+
+    impl ScriptedOracle {
+        fn new(prices: &[u64]) -> Self {
+            Self {
+                prices: Mutex::new(prices.iter().rev().copied().collect()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl GasOracle for ScriptedOracle {
+        async fn gas_price(&self) -> Result<U256> {
+            let mut prices = self.prices.lock().unwrap();
+            let price = if prices.len() > 1 {
+                prices.pop().unwrap()
+            } else {
+                prices[0]
+            };
+            Ok(U256::from(price))
+        }
+    }
+
+    fn policy(action: GasCeilingAction) -> GasPolicy {
+        GasPolicy {
+            max_gas_price: Some(U256::from(100u64)),
+            action,
+            recheck_interval: Duration::from_millis(5),
+            max_deferral: Duration::from_millis(200),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_when_gas_above_ceiling() {
+        let oracle = ScriptedOracle::new(&[150]);
+        let err = policy(GasCeilingAction::Reject)
+            .acquire(&oracle)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SubmitError>(),
+            Some(SubmitError::GasTooHigh { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_defer_until_gas_drops() {
+        let oracle = ScriptedOracle::new(&[150, 120, 90]);
+        let price = policy(GasCeilingAction::Defer)
+            .acquire(&oracle)
+            .await
+            .unwrap();
+        assert_eq!(price, U256::from(90u64));
+    }
+
+    #[tokio::test]
+    async fn test_deferral_gives_up_eventually() {
+        let oracle = ScriptedOracle::new(&[150]);
+        let err = policy(GasCeilingAction::Defer)
+            .acquire(&oracle)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<SubmitError>().is_some());
+    }
+
+    #[test]
+    fn test_bump_never_exceeds_ceiling() {
+        let policy = policy(GasCeilingAction::Defer);
+        assert_eq!(policy.bump(U256::from(80u64)), Some(U256::from(90u64)));
+        assert_eq!(policy.bump(U256::from(95u64)), None);
+
+        let uncapped = GasPolicy {
+            max_gas_price: None,
+            ..policy
+        };
+        assert_eq!(uncapped.bump(U256::from(800u64)), Some(U256::from(900u64)));
+    }
+}
@@ -0,0 +1,401 @@
+//! Nonce assignment and per-chain submission ordering
+//!
+//! Each chain has one `NonceManager`. Submissions on a chain take the next
+//! nonce and broadcast while holding the chain's lock, so transactions reach
+//! the network in nonce order; chains never share a lock and submit
+//! concurrently. A broadcast transaction holds one of the chain's in-flight
+//! slots until its `InFlight` guard is dropped (once it is mined or
+//! abandoned), bounding how many unconfirmed transactions a chain can have.
+//! Managers are tied to one signer address; draining one waits out its
+//! in-flight transactions and retires it. A broadcast the node rejects over
+//! its nonce means the cached nonce has drifted from the account's (another
+//! client sent from the key, or a transaction was dropped), so the next
+//! submission refetches it.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, warn};
+
+use super::Broadcast;
+use crate::metrics;
+use crate::ChainEndpoints;
+
+/// Default unconfirmed transactions allowed per chain
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+/// Source of the signer's next usable nonce
+#[async_trait]
+pub trait NonceSource: Send + Sync {
+    async fn pending_nonce(&self, address: Address) -> Result<U256>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> NonceSource for Provider<P> {
+    async fn pending_nonce(&self, address: Address) -> Result<U256> {
+        Ok(self
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await?)
+    }
+}
+
+/// Hands out one chain's nonces in order and bounds its in-flight count
+pub struct NonceManager {
+    chain_id: u64,
+    address: Address,
+    source: Arc<dyn NonceSource>,
+    /// Next nonce to assign, fetched on first use
+    next: Mutex<Option<U256>>,
+    in_flight: Arc<Semaphore>,
+    max_in_flight: usize,
+}
+
+impl NonceManager {
+    pub fn new(
+        chain_id: u64,
+        address: Address,
+        source: Arc<dyn NonceSource>,
+        max_in_flight: usize,
+    ) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        Self {
+            chain_id,
+            address,
+            source,
+            next: Mutex::new(None),
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight,
+        }
+    }
+
+    /// Build the manager for a chain from its endpoint config
+    pub fn from_endpoints(endpoints: &ChainEndpoints, address: Address) -> Result<Self> {
+        let source = Arc::new(crate::light_client::http_provider(endpoints)?);
+        Ok(Self::new(
+            endpoints.chain_id,
+            address,
+            source,
+            endpoints.max_in_flight,
+        ))
+    }
+
+    /// Wait for an in-flight slot, then broadcast with the next nonce
+    ///
+    /// `send` signs and broadcasts a transaction at the given nonce. The
+    /// nonce is only consumed if it succeeds; on failure the next
+    /// submission reuses it, or refetches it if the node rejected the nonce.
+    pub async fn submit<F, Fut>(&self, send: F) -> Result<InFlight>
+    where
+        F: FnOnce(U256) -> Fut,
+        Fut: Future<Output = Result<Broadcast>>,
+    {
+        let permit = self
+            .in_flight
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| anyhow::anyhow!("Signer {:?} has been retired", self.address))?;
+
+        let mut next = self.next.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => self.source.pending_nonce(self.address).await?,
+        };
+        let broadcast = match send(nonce).await {
+            Ok(broadcast) => broadcast,
+            Err(e) => {
+                if is_nonce_rejection(&e) {
+                    warn!(chain_id = self.chain_id, %nonce, error = %e, "Nonce rejected, resyncing");
+                    *next = None;
+                }
+                return Err(e);
+            }
+        };
+        *next = Some(nonce + 1);
+        drop(next);
+
+        debug!(chain_id = self.chain_id, %nonce, tx_hash = ?broadcast.tx_hash, "Transaction in flight");
+        Ok(InFlight::new(self.chain_id, nonce, broadcast, permit))
+    }
+
+    /// Forget the cached nonce so the next submission refetches it
+    ///
+    /// Needed after a transaction is dropped without being mined, which
+    /// leaves a gap the network won't fill.
+    pub async fn reset(&self) {
+        *self.next.lock().await = None;
+    }
+
+    /// Wait for every in-flight transaction to finish, then refuse further
+    /// submissions
+    pub async fn drain(&self) {
+        // Queued behind submissions already waiting for a slot; fails only
+        // if already drained
+        if let Ok(_all) = self.in_flight.acquire_many(self.max_in_flight as u32).await {
+            self.in_flight.close();
+        }
+    }
+}
+
+/// Whether a broadcast failed because the node disagrees about the nonce
+fn is_nonce_rejection(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error).to_lowercase();
+    ["nonce too low", "nonce too high", "invalid nonce"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// A broadcast transaction occupying one of its chain's in-flight slots
+#[derive(Debug)]
+pub struct InFlight {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub broadcast: Broadcast,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl InFlight {
+    fn new(chain_id: u64, nonce: U256, broadcast: Broadcast, permit: OwnedSemaphorePermit) -> Self {
+        metrics::SUBMISSIONS_IN_FLIGHT
+            .with_label_values(&[&chain_id.to_string()])
+            .inc();
+        Self {
+            chain_id,
+            nonce,
+            broadcast,
+            _permit: permit,
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        metrics::SUBMISSIONS_IN_FLIGHT
+            .with_label_values(&[&self.chain_id.to_string()])
+            .dec();
+    }
+}
+
+/// Nonce managers for every chain the relayer submits to
+#[derive(Default)]
+pub struct ChainSubmitters {
+    chains: HashMap<u64, Arc<NonceManager>>,
+}
+
+impl ChainSubmitters {
+    pub fn insert(&mut self, manager: NonceManager) {
+        self.chains.insert(manager.chain_id, Arc::new(manager));
+    }
+
+    /// Submit on `chain_id`, queued behind that chain's earlier submissions
+    pub async fn submit<F, Fut>(&self, chain_id: u64, send: F) -> Result<InFlight>
+    where
+        F: FnOnce(U256) -> Fut,
+        Fut: Future<Output = Result<Broadcast>>,
+    {
+        let manager = self
+            .chains
+            .get(&chain_id)
+            .ok_or_else(|| anyhow::anyhow!("No submitter for chain {}", chain_id))?;
+        manager.submit(send).await
+    }
+
+    /// Drain every chain's manager; see `NonceManager::drain`
+    pub async fn drain(&self) {
+        futures::future::join_all(self.chains.values().map(|manager| manager.drain())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::submitter::route::BroadcastRoute;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex as StdMutex;
+    use std::time::Duration;
+    use tokio::sync::Barrier;
+
+    struct FixedNonce(u64);
+
+    #[async_trait]
+    impl NonceSource for FixedNonce {
+        async fn pending_nonce(&self, _address: Address) -> Result<U256> {
+            Ok(U256::from(self.0))
+        }
+    }
+
+    fn broadcast(chain_id: u64, nonce: U256) -> Broadcast {
+        Broadcast {
+            tx_hash: H256::from_low_u64_be(chain_id * 1_000 + nonce.as_u64()),
+            route: BroadcastRoute::Public,
+            expires_at_block: None,
+        }
+    }
+
+    fn submitters(max_in_flight: usize) -> Arc<ChainSubmitters> {
+        let mut submitters = ChainSubmitters::default();
+        for (chain_id, first_nonce) in [(1, 7), (42161, 0)] {
+            submitters.insert(NonceManager::new(
+                chain_id,
+                Address::repeat_byte(0x11),
+                Arc::new(FixedNonce(first_nonce)),
+                max_in_flight,
+            ));
+        }
+        Arc::new(submitters)
+    }
+
+    #[tokio::test]
+    async fn test_chains_concurrent_same_chain_ordered() {
+        let submitters = submitters(8);
+
+        // Each chain's broadcast waits for the other's: this only completes
+        // if the two chains are submitting at the same time
+        let barrier = Arc::new(Barrier::new(2));
+        let both = [1u64, 42161].map(|chain_id| {
+            let submitters = submitters.clone();
+            let barrier = barrier.clone();
+            tokio::spawn(async move {
+                submitters
+                    .submit(chain_id, |nonce| async move {
+                        barrier.wait().await;
+                        Ok(broadcast(chain_id, nonce))
+                    })
+                    .await
+                    .unwrap()
+            })
+        });
+        let mut done = Vec::new();
+        for handle in both {
+            done.push(
+                tokio::time::timeout(Duration::from_secs(5), handle)
+                    .await
+                    .expect("chains serialized behind each other")
+                    .unwrap(),
+            );
+        }
+        assert_eq!(done[0].nonce, U256::from(7u64));
+        assert_eq!(done[1].nonce, U256::from(0u64));
+
+        // Concurrent submissions on one chain broadcast in nonce order
+        let sent = Arc::new(StdMutex::new(Vec::new()));
+        let handles: Vec<_> = (0..5)
+            .map(|_| {
+                let submitters = submitters.clone();
+                let sent = sent.clone();
+                tokio::spawn(async move {
+                    submitters
+                        .submit(1, |nonce| async move {
+                            tokio::task::yield_now().await;
+                            sent.lock().unwrap().push(nonce.as_u64());
+                            Ok(broadcast(1, nonce))
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            done.push(handle.await.unwrap());
+        }
+        assert_eq!(*sent.lock().unwrap(), vec![8, 9, 10, 11, 12]);
+    }
+
+    #[tokio::test]
+    async fn test_many_relays_get_contiguous_nonces() {
+        let submitters = submitters(64);
+        let handles: Vec<_> = (0..50)
+            .map(|_| {
+                let submitters = submitters.clone();
+                tokio::spawn(async move {
+                    submitters
+                        .submit(1, |nonce| async move {
+                            tokio::task::yield_now().await;
+                            Ok(broadcast(1, nonce))
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        let mut nonces = Vec::new();
+        let mut in_flight = Vec::new();
+        for handle in handles {
+            let submitted = handle.await.unwrap();
+            nonces.push(submitted.nonce.as_u64());
+            in_flight.push(submitted);
+        }
+        nonces.sort_unstable();
+        assert_eq!(nonces, (7..57).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_rejection_resyncs() {
+        /// Account nonce moved on by another client sending from the key
+        struct Moving(AtomicU64);
+
+        #[async_trait]
+        impl NonceSource for Moving {
+            async fn pending_nonce(&self, _address: Address) -> Result<U256> {
+                Ok(U256::from(self.0.load(Ordering::SeqCst)))
+            }
+        }
+
+        let source = Arc::new(Moving(AtomicU64::new(3)));
+        let manager = NonceManager::new(1, Address::repeat_byte(0x11), source.clone(), 8);
+        let send_ok = |nonce| async move { Ok(broadcast(1, nonce)) };
+        assert_eq!(
+            manager.submit(send_ok).await.unwrap().nonce,
+            U256::from(3u64)
+        );
+
+        source.0.store(10, Ordering::SeqCst);
+        let err = manager
+            .submit(|_| async { Err::<Broadcast, _>(anyhow::anyhow!("nonce too low")) })
+            .await;
+        assert!(err.is_err());
+        assert_eq!(
+            manager.submit(send_ok).await.unwrap().nonce,
+            U256::from(10u64)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_limit_and_failed_broadcast() {
+        let submitters = submitters(2);
+        let send_ok = |nonce| async move { Ok(broadcast(1, nonce)) };
+
+        let first = submitters.submit(1, send_ok).await.unwrap();
+        let _second = submitters.submit(1, send_ok).await.unwrap();
+        let gauge = metrics::SUBMISSIONS_IN_FLIGHT.with_label_values(&["1"]);
+        assert!(gauge.get() >= 2);
+
+        // The chain is full; the other chain is unaffected
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), submitters.submit(1, send_ok))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            submitters.submit(42161, send_ok).await.unwrap().nonce,
+            U256::zero()
+        );
+
+        // A confirmed transaction frees its slot; a failed broadcast
+        // leaves the nonce for the next submission
+        drop(first);
+        let err = submitters
+            .submit(1, |_| async {
+                Err::<Broadcast, _>(anyhow::anyhow!("rejected"))
+            })
+            .await;
+        assert!(err.is_err());
+        let third = submitters.submit(1, send_ok).await.unwrap();
+        assert_eq!(third.nonce, U256::from(9u64));
+    }
+}
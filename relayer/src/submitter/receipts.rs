@@ -0,0 +1,384 @@
+//! Outcome of broadcast relay transactions
+//!
+//! A relay transaction can pass every off-chain check and still revert once
+//! mined, typically because another relayer spent the nullifier first or the
+//! root aged out of the pool's history. The tracker waits for the receipt;
+//! on a revert it replays the transaction at its block to recover the revert
+//! data, decodes a reason, and records it against the relay so the requester
+//! waiting on it is told why.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::abi::{decode, ParamType, Token};
+use ethers::prelude::*;
+use ethers::utils::id;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::metrics;
+use crate::relay::{RelayStatus, RelayTracker};
+use crate::store::{AuditEntry, Store};
+
+/// Default interval between receipt lookups
+pub const DEFAULT_RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Custom errors the pool contracts revert with
+const POOL_ERRORS: &[&str] = &[
+    "InvalidCommitment",
+    "InvalidDepositAmount",
+    "InvalidProof",
+    "NullifierAlreadySpent",
+    "InvalidRecipient",
+    "InvalidAmount",
+    "InsufficientFee",
+    "TransferFailed",
+    "TreeFull",
+    "Paused",
+    "NotOwner",
+    "InvalidRelayer",
+    "RelayerNotActive",
+    "RateLimitExceeded",
+    "CooldownActive",
+];
+
+/// Selector of `Error(string)`, used by `require` with a message
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of `Panic(uint256)`, used by failed asserts and overflows
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a transaction reverted, decoded from its revert data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// One of the pool's custom errors, by name
+    Custom(&'static str),
+    /// `require` or `revert` with a message
+    Message(String),
+    /// Solidity panic code
+    Panic(U256),
+    /// Revert data that matched nothing known, possibly empty
+    Unknown(Bytes),
+}
+
+impl RevertReason {
+    /// Bounded label for metrics: the custom error name or the kind of revert
+    pub fn label(&self) -> &'static str {
+        match self {
+            RevertReason::Custom(name) => *name,
+            RevertReason::Message(_) => "message",
+            RevertReason::Panic(_) => "panic",
+            RevertReason::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevertReason::Custom(name) => write!(f, "{}", name),
+            RevertReason::Message(message) => write!(f, "{}", message),
+            RevertReason::Panic(code) => write!(f, "panic 0x{:x}", code),
+            RevertReason::Unknown(data) if data.is_empty() => write!(f, "no revert data"),
+            RevertReason::Unknown(data) => write!(f, "unknown revert {}", data),
+        }
+    }
+}
+
+/// Decode revert data returned by a failed call
+pub fn decode_revert_reason(data: &[u8]) -> RevertReason {
+    let unknown = || RevertReason::Unknown(Bytes::from(data.to_vec()));
+    if data.len() < 4 {
+        return unknown();
+    }
+    let (selector, args) = data.split_at(4);
+
+    if selector == ERROR_STRING_SELECTOR {
+        if let Ok(tokens) = decode(&[ParamType::String], args) {
+            if let Some(Token::String(message)) = tokens.into_iter().next() {
+                return RevertReason::Message(message);
+            }
+        }
+        return unknown();
+    }
+    if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = decode(&[ParamType::Uint(256)], args) {
+            if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                return RevertReason::Panic(code);
+            }
+        }
+        return unknown();
+    }
+    POOL_ERRORS
+        .iter()
+        .find(|name| id(format!("{}()", name)) == selector)
+        .map_or_else(unknown, |name| RevertReason::Custom(*name))
+}
+
+/// Where receipts and revert data come from
+#[async_trait]
+pub trait ReceiptSource: Send + Sync {
+    /// Receipt of a mined transaction; `None` while it is pending
+    async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>>;
+    /// Revert data from re-executing the transaction at the block it was
+    /// mined in
+    async fn revert_data(&self, tx_hash: H256, block_number: U64) -> Result<Bytes>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> ReceiptSource for Provider<P> {
+    async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>> {
+        Ok(self.get_transaction_receipt(tx_hash).await?)
+    }
+
+    async fn revert_data(&self, tx_hash: H256, block_number: U64) -> Result<Bytes> {
+        let tx = self
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Transaction {:?} not found", tx_hash))?;
+        let call: TypedTransaction = (&tx).into();
+        match self.call(&call, Some(block_number.into())).await {
+            // Replays can succeed if state the revert depended on differs
+            Ok(_) => Ok(Bytes::new()),
+            Err(err) => Ok(err
+                .as_error_response()
+                .and_then(|response| response.as_revert_data())
+                .unwrap_or_default()),
+        }
+    }
+}
+
+/// Follows broadcast relay transactions until they are mined
+pub struct SubmissionTracker {
+    source: Arc<dyn ReceiptSource>,
+    relays: Arc<RelayTracker>,
+    store: Arc<dyn Store>,
+    poll_interval: Duration,
+}
+
+impl SubmissionTracker {
+    pub fn new(
+        source: Arc<dyn ReceiptSource>,
+        relays: Arc<RelayTracker>,
+        store: Arc<dyn Store>,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            source,
+            relays,
+            store,
+            poll_interval,
+        }
+    }
+
+    /// Interval between receipt lookups
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Wait for relay `relay_id`'s transaction to be mined and record the
+    /// outcome, `Confirmed` or `Reverted` with the decoded reason
+    pub async fn track(&self, relay_id: &str, chain_id: u64, tx_hash: H256) -> Result<RelayStatus> {
+        let receipt = loop {
+            if let Some(receipt) = self.mined(&[tx_hash]).await? {
+                break receipt;
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        };
+        self.record(relay_id, chain_id, receipt).await
+    }
+
+    /// Receipt of whichever of `tx_hashes` was mined, if any
+    ///
+    /// Replacements share a nonce, so at most one of them is ever mined.
+    pub async fn mined(&self, tx_hashes: &[H256]) -> Result<Option<TransactionReceipt>> {
+        for tx_hash in tx_hashes {
+            if let Some(receipt) = self.source.receipt(*tx_hash).await? {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Record the outcome of relay `relay_id`'s mined transaction
+    pub async fn record(
+        &self,
+        relay_id: &str,
+        chain_id: u64,
+        receipt: TransactionReceipt,
+    ) -> Result<RelayStatus> {
+        let tx = format!("{:?}", receipt.transaction_hash);
+
+        let status = if receipt.status == Some(U64::one()) {
+            info!(relay_id = relay_id, tx_hash = %tx, "Relay confirmed");
+            RelayStatus::Confirmed { tx_hash: tx }
+        } else {
+            let block_number = receipt.block_number.unwrap_or_default();
+            let reason = match self
+                .source
+                .revert_data(receipt.transaction_hash, block_number)
+                .await
+            {
+                Ok(data) => decode_revert_reason(&data),
+                Err(e) => {
+                    warn!(error = %e, tx_hash = %tx, "Could not replay reverted relay");
+                    RevertReason::Unknown(Bytes::new())
+                }
+            };
+            metrics::RELAY_REVERTED
+                .with_label_values(&[reason.label()])
+                .inc();
+            warn!(
+                relay_id = relay_id,
+                chain_id = chain_id,
+                tx_hash = %tx,
+                reason = %reason,
+                "Relay reverted on-chain"
+            );
+            self.store
+                .append_audit(&AuditEntry {
+                    seq: 0,
+                    timestamp: chrono::Utc::now().timestamp(),
+                    actor: "node".to_string(),
+                    action: "relay_reverted".to_string(),
+                    details: serde_json::json!({
+                        "relay_id": relay_id,
+                        "chain_id": chain_id,
+                        "tx_hash": tx,
+                        "block_number": block_number.as_u64(),
+                        "reason": reason.to_string(),
+                    })
+                    .to_string(),
+                })
+                .await?;
+            RelayStatus::Reverted {
+                tx_hash: tx,
+                reason: reason.to_string(),
+            }
+        };
+
+        // Wakes anyone waiting on the relay
+        self.relays.update(relay_id, status.clone());
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::encode;
+    use std::sync::Mutex;
+
+    /// Reports the transaction pending for a number of polls, then mined
+    struct MockChain {
+        pending_polls: Mutex<u32>,
+        status: u64,
+        revert_data: Bytes,
+    }
+
+    #[async_trait]
+    impl ReceiptSource for MockChain {
+        async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>> {
+            let mut pending = self.pending_polls.lock().unwrap();
+            if *pending > 0 {
+                *pending -= 1;
+                return Ok(None);
+            }
+            Ok(Some(TransactionReceipt {
+                transaction_hash: tx_hash,
+                block_number: Some(U64::from(1_234)),
+                status: Some(U64::from(self.status)),
+                ..Default::default()
+            }))
+        }
+
+        async fn revert_data(&self, _tx_hash: H256, _block_number: U64) -> Result<Bytes> {
+            Ok(self.revert_data.clone())
+        }
+    }
+
+    #[test]
+    fn test_decode_revert_reasons() {
+        let nullifier_spent = id("NullifierAlreadySpent()");
+        assert_eq!(
+            decode_revert_reason(&nullifier_spent),
+            RevertReason::Custom("NullifierAlreadySpent")
+        );
+
+        let mut message = ERROR_STRING_SELECTOR.to_vec();
+        message.extend(encode(&[Token::String("fee too low".to_string())]));
+        assert_eq!(
+            decode_revert_reason(&message),
+            RevertReason::Message("fee too low".to_string())
+        );
+
+        let mut panic = PANIC_SELECTOR.to_vec();
+        panic.extend(encode(&[Token::Uint(U256::from(0x11))]));
+        let reason = decode_revert_reason(&panic);
+        assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(reason.to_string(), "panic 0x11");
+
+        assert_eq!(decode_revert_reason(&[]).label(), "unknown");
+        assert_eq!(
+            decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]).label(),
+            "unknown"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverted_receipt_marks_relay_reverted() {
+        let chain = Arc::new(MockChain {
+            pending_polls: Mutex::new(2),
+            status: 0,
+            revert_data: Bytes::from(id("NullifierAlreadySpent()").to_vec()),
+        });
+        let relays = Arc::new(RelayTracker::default());
+        let store = crate::store::memory().await;
+        let tracker = SubmissionTracker::new(
+            chain,
+            relays.clone(),
+            store.clone(),
+            Duration::from_millis(1),
+        );
+        relays.register("r1");
+        let tx_hash = H256::repeat_byte(0xab);
+        relays.update(
+            "r1",
+            RelayStatus::Submitted {
+                tx_hash: format!("{:?}", tx_hash),
+            },
+        );
+
+        let before = metrics::RELAY_REVERTED
+            .with_label_values(&["NullifierAlreadySpent"])
+            .get();
+        let waiter = {
+            let relays = relays.clone();
+            tokio::spawn(async move { relays.wait("r1", Duration::from_secs(10)).await })
+        };
+        let status = tracker.track("r1", 1, tx_hash).await.unwrap();
+
+        let expected = RelayStatus::Reverted {
+            tx_hash: format!("{:?}", tx_hash),
+            reason: "NullifierAlreadySpent".to_string(),
+        };
+        assert_eq!(status, expected);
+        assert!(status.is_terminal());
+        assert_eq!(relays.status("r1"), Some(expected.clone()));
+        assert_eq!(waiter.await.unwrap(), Some(expected));
+        assert_eq!(
+            metrics::RELAY_REVERTED
+                .with_label_values(&["NullifierAlreadySpent"])
+                .get(),
+            before + 1
+        );
+
+        let audit = store.audit_entries(0, 10).await.unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, "relay_reverted");
+        let details: serde_json::Value = serde_json::from_str(&audit[0].details).unwrap();
+        assert_eq!(details["relay_id"], "r1");
+        assert_eq!(details["reason"], "NullifierAlreadySpent");
+        assert_eq!(details["block_number"], 1_234);
+    }
+}
@@ -0,0 +1,336 @@
+//! Broadcast routes for signed transactions
+//!
+//! Transactions go to the public mempool by default. A chain can instead
+//! route through a private relay (Flashbots-style
+//! `eth_sendPrivateTransaction`) so withdrawals can't be front-run, falling
+//! back to the public mempool if the relay is unreachable.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::ChainEndpoints;
+
+/// Header carrying the relay authentication signature
+pub const FLASHBOTS_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+/// Default number of blocks a private transaction stays eligible for inclusion
+const DEFAULT_PRIVATE_MAX_BLOCKS: u64 = 25;
+
+/// How a chain's transactions are broadcast
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SubmissionRoute {
+    /// `eth_sendRawTransaction` on the chain's RPC endpoint
+    #[default]
+    Public,
+    /// Private relay accepting `eth_sendPrivateTransaction`
+    PrivateRelay {
+        url: String,
+        /// Environment variable holding the relay authentication key
+        /// (a random key is used if unset)
+        #[serde(default)]
+        auth_key_env: Option<String>,
+        /// Blocks after submission before the relay drops the transaction
+        #[serde(default = "default_private_max_blocks")]
+        max_blocks: u64,
+        /// Use the public mempool when the relay rejects or is unreachable
+        #[serde(default = "default_true")]
+        fallback_to_public: bool,
+    },
+}
+
+fn default_private_max_blocks() -> u64 {
+    DEFAULT_PRIVATE_MAX_BLOCKS
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Where a broadcast transaction ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastRoute {
+    Public,
+    Private,
+}
+
+/// Result of broadcasting a signed transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broadcast {
+    pub tx_hash: H256,
+    pub route: BroadcastRoute,
+    /// Last block the transaction can be included in (private route only)
+    ///
+    /// Private transactions never appear in the public mempool; if they
+    /// aren't mined by this block the relay has dropped them and they must
+    /// be resubmitted.
+    pub expires_at_block: Option<u64>,
+}
+
+impl Broadcast {
+    /// Whether an unmined transaction should be treated as dropped
+    pub fn is_expired(&self, head: u64) -> bool {
+        self.expires_at_block.is_some_and(|last| head > last)
+    }
+}
+
+/// Sends signed raw transactions somewhere they can be mined
+#[async_trait]
+pub trait TxBroadcaster: Send + Sync {
+    async fn broadcast(&self, raw_tx: &Bytes, current_block: u64) -> Result<Broadcast>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> TxBroadcaster for Provider<P> {
+    async fn broadcast(&self, raw_tx: &Bytes, _current_block: u64) -> Result<Broadcast> {
+        let pending = self.send_raw_transaction(raw_tx.clone()).await?;
+        Ok(Broadcast {
+            tx_hash: pending.tx_hash(),
+            route: BroadcastRoute::Public,
+            expires_at_block: None,
+        })
+    }
+}
+
+/// Flashbots-compatible private transaction relay
+pub struct PrivateRelay {
+    client: reqwest::Client,
+    url: String,
+    /// Key identifying the relayer to the private relay (not the tx key)
+    auth_signer: LocalWallet,
+    max_blocks: u64,
+}
+
+impl PrivateRelay {
+    pub fn new(url: impl Into<String>, auth_signer: LocalWallet, max_blocks: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            auth_signer,
+            max_blocks,
+        }
+    }
+
+    /// `address:signature` over the hex keccak of the request body
+    async fn auth_header(&self, body: &[u8]) -> Result<String> {
+        let digest = format!("0x{}", hex::encode(keccak256(body)));
+        let signature = self.auth_signer.sign_message(digest).await?;
+        Ok(format!("{:?}:0x{}", self.auth_signer.address(), signature))
+    }
+}
+
+#[async_trait]
+impl TxBroadcaster for PrivateRelay {
+    async fn broadcast(&self, raw_tx: &Bytes, current_block: u64) -> Result<Broadcast> {
+        let max_block = current_block + self.max_blocks;
+        let body = serde_json::to_vec(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendPrivateTransaction",
+            "params": [{
+                "tx": raw_tx,
+                "maxBlockNumber": format!("{:#x}", max_block),
+                "preferences": { "fast": true },
+            }],
+        }))?;
+
+        let response: serde_json::Value = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(FLASHBOTS_SIGNATURE_HEADER, self.auth_header(&body).await?)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("Private relay rejected transaction: {}", error);
+        }
+        let tx_hash: H256 = serde_json::from_value(
+            response
+                .get("result")
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Private relay response has no result"))?,
+        )?;
+
+        Ok(Broadcast {
+            tx_hash,
+            route: BroadcastRoute::Private,
+            expires_at_block: Some(max_block),
+        })
+    }
+}
+
+/// Broadcasts through the chain's configured route
+pub struct RoutedBroadcaster {
+    public: Arc<dyn TxBroadcaster>,
+    private: Option<Arc<dyn TxBroadcaster>>,
+    fallback_to_public: bool,
+}
+
+impl RoutedBroadcaster {
+    pub fn new(
+        public: Arc<dyn TxBroadcaster>,
+        private: Option<Arc<dyn TxBroadcaster>>,
+        fallback_to_public: bool,
+    ) -> Self {
+        Self {
+            public,
+            private,
+            fallback_to_public,
+        }
+    }
+
+    /// Build the broadcaster for a chain from its endpoint config
+    pub fn from_endpoints(endpoints: &ChainEndpoints) -> Result<Self> {
+        let public = Arc::new(crate::light_client::http_provider(endpoints)?);
+        match &endpoints.submission_route {
+            SubmissionRoute::Public => Ok(Self::new(public, None, false)),
+            SubmissionRoute::PrivateRelay {
+                url,
+                auth_key_env,
+                max_blocks,
+                fallback_to_public,
+            } => {
+                let auth_signer = match auth_key_env {
+                    Some(var) => std::env::var(var)
+                        .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", var))?
+                        .parse::<LocalWallet>()?,
+                    None => LocalWallet::new(&mut rand::thread_rng()),
+                };
+                let private = Arc::new(PrivateRelay::new(url.clone(), auth_signer, *max_blocks));
+                Ok(Self::new(public, Some(private), *fallback_to_public))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TxBroadcaster for RoutedBroadcaster {
+    async fn broadcast(&self, raw_tx: &Bytes, current_block: u64) -> Result<Broadcast> {
+        let Some(private) = &self.private else {
+            return self.public.broadcast(raw_tx, current_block).await;
+        };
+
+        match private.broadcast(raw_tx, current_block).await {
+            Ok(broadcast) => {
+                info!(tx_hash = ?broadcast.tx_hash, "Submitted via private relay");
+                Ok(broadcast)
+            }
+            Err(e) if self.fallback_to_public => {
+                warn!(error = %e, "Private relay submission failed, falling back to public mempool");
+                self.public.broadcast(raw_tx, current_block).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use std::sync::Mutex;
+
+    type Captured = Arc<Mutex<Option<(HeaderMap, serde_json::Value)>>>;
+
+    /// Start a fake private relay that records the last request it received
+    async fn mock_relay() -> (String, Captured) {
+        let captured: Captured = Arc::default();
+        let app = Router::new()
+            .route(
+                "/",
+                post(
+                    |State(captured): State<Captured>,
+                     headers: HeaderMap,
+                     Json(body): Json<serde_json::Value>| async move {
+                        *captured.lock().unwrap() = Some((headers, body));
+                        Json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "result": format!("{:?}", H256::repeat_byte(0x77)),
+                        }))
+                    },
+                ),
+            )
+            .with_state(captured.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, captured)
+    }
+
+    /// Public route stand-in that counts broadcasts
+    #[derive(Default)]
+    struct CountingPublic {
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl TxBroadcaster for CountingPublic {
+        async fn broadcast(&self, raw_tx: &Bytes, _current_block: u64) -> Result<Broadcast> {
+            *self.calls.lock().unwrap() += 1;
+            Ok(Broadcast {
+                tx_hash: H256(keccak256(raw_tx)),
+                route: BroadcastRoute::Public,
+                expires_at_block: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_route_posts_to_relay() {
+        let (url, captured) = mock_relay().await;
+        let auth = LocalWallet::new(&mut rand::thread_rng());
+        let public = Arc::new(CountingPublic::default());
+        let broadcaster = RoutedBroadcaster::new(
+            public.clone(),
+            Some(Arc::new(PrivateRelay::new(url, auth.clone(), 25))),
+            true,
+        );
+
+        let raw_tx = Bytes::from(vec![0x02, 0xf8, 0x01]);
+        let broadcast = broadcaster.broadcast(&raw_tx, 1_000).await.unwrap();
+        assert_eq!(broadcast.route, BroadcastRoute::Private);
+        assert_eq!(broadcast.tx_hash, H256::repeat_byte(0x77));
+        assert_eq!(broadcast.expires_at_block, Some(1_025));
+        assert!(!broadcast.is_expired(1_025));
+        assert!(broadcast.is_expired(1_026));
+        assert_eq!(*public.calls.lock().unwrap(), 0);
+
+        let (headers, body) = captured.lock().unwrap().take().unwrap();
+        assert_eq!(body["method"], "eth_sendPrivateTransaction");
+        assert_eq!(body["params"][0]["tx"], "0x02f801");
+        assert_eq!(body["params"][0]["maxBlockNumber"], "0x401");
+
+        // Auth header is signed by the relay key, not left blank
+        let signature = headers[FLASHBOTS_SIGNATURE_HEADER].to_str().unwrap();
+        let (address, _) = signature.split_once(':').unwrap();
+        assert_eq!(address.parse::<Address>().unwrap(), auth.address());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_relay_falls_back_to_public() {
+        let auth = LocalWallet::new(&mut rand::thread_rng());
+        // Nothing listens on port 1
+        let private = Arc::new(PrivateRelay::new("http://127.0.0.1:1", auth, 25));
+        let public = Arc::new(CountingPublic::default());
+
+        let fallback = RoutedBroadcaster::new(public.clone(), Some(private.clone()), true);
+        let broadcast = fallback.broadcast(&Bytes::from(vec![1]), 1).await.unwrap();
+        assert_eq!(broadcast.route, BroadcastRoute::Public);
+        assert_eq!(*public.calls.lock().unwrap(), 1);
+
+        let strict = RoutedBroadcaster::new(public.clone(), Some(private), false);
+        assert!(strict.broadcast(&Bytes::from(vec![1]), 1).await.is_err());
+        assert_eq!(*public.calls.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,82 @@
+//! Outcome of submitted withdrawals, by request
+//!
+//! A request is `Pending` from submission until one of its transactions is
+//! mined (`Mined`, with the block and the hash that made it in) or it is
+//! given up on (`Failed`). Entries are forgotten once they go unchanged for
+//! the configured TTL, so the map only holds recent requests.
+
+use ethers::types::H256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default time a status is kept after its last change
+pub const DEFAULT_TX_STATUS_TTL: Duration = Duration::from_secs(3600);
+
+/// Where a request's withdrawal stands
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxStatus {
+    /// Broadcast (or about to be), not yet mined
+    Pending,
+    /// Mined and successful
+    Mined { block: u64, tx_hash: H256 },
+    /// Not submitted, reverted, or lost track of
+    Failed { reason: String },
+}
+
+/// Recent requests' withdrawal status
+pub struct TxStatuses {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (TxStatus, Instant)>>,
+}
+
+impl TxStatuses {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Record `status` for request `id`, restarting its TTL
+    pub fn set(&self, id: &str, status: TxStatus) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, updated)| now.duration_since(*updated) < self.ttl);
+        entries.insert(id.to_string(), (status, now));
+    }
+
+    /// Status of request `id`, unless unknown or expired
+    pub fn get(&self, id: &str) -> Option<TxStatus> {
+        let entries = self.entries.lock().unwrap();
+        let (status, updated) = entries.get(id)?;
+        (updated.elapsed() < self.ttl).then(|| status.clone())
+    }
+}
+
+impl Default for TxStatuses {
+    fn default() -> Self {
+        Self::new(DEFAULT_TX_STATUS_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statuses_expire() {
+        let statuses = TxStatuses::new(Duration::from_millis(20));
+        statuses.set("r1", TxStatus::Pending);
+        assert_eq!(statuses.get("r1"), Some(TxStatus::Pending));
+        assert_eq!(statuses.get("r2"), None);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(statuses.get("r1"), None);
+
+        // Setting a new entry drops expired ones
+        statuses.set("r2", TxStatus::Pending);
+        assert_eq!(statuses.entries.lock().unwrap().len(), 1);
+    }
+}
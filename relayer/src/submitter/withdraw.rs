@@ -0,0 +1,1122 @@
+//! Withdrawal transactions for accepted relay requests
+//!
+//! Builds the pool's `withdraw` call from a validated request, signs it with
+//! the active signer at the chain's next nonce, and broadcasts it through the
+//! chain's route once gas is within the ceiling, as a legacy or EIP-1559
+//! transaction per the fee strategy. The transaction keeps its in-flight
+//! slot until the tracker sees it mined. One left unmined for too many
+//! blocks is replaced at the same nonce with fees raised by the minimum
+//! bump, and the relay reports whichever hash is the latest, then the one
+//! mined. Each request's outcome is also kept in `TxStatuses` for a while.
+//! Relays are dispatched with their nullifier reserved in the store, and
+//! released again if the withdrawal is never broadcast. A relay bound to a
+//! quote pays the quoted fee, and is turned away when gas costs more than
+//! the quote can absorb, handing the quote back for a later attempt. On
+//! shutdown `drain`
+//! turns new requests away and waits for those already accepted to be
+//! broadcast; following them until mined is not waited on.
+
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::id;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+use super::{
+    FeeSettings, GasOracle, GasPolicy, InputCommitment, InputHashError, ReceiptSource,
+    RoutedBroadcaster, SubmissionTracker, SubmitError, TxBroadcaster, TxFees, TxStatus, TxStatuses,
+    DEFAULT_RECEIPT_POLL_INTERVAL,
+};
+use crate::api::types::RelayRequest;
+use crate::keys::ActiveSigner;
+use crate::light_client::FinalityHandle;
+use crate::quote::{FeeDecision, QuoteBook};
+use crate::relay::{RelayStatus, RelayTracker};
+use crate::store::Store;
+use crate::ChainEndpoints;
+
+/// Pool entry point relays are submitted to
+pub const WITHDRAW_SIGNATURE: &str = "withdraw(bytes,bytes32,address,uint256,address,uint256)";
+
+/// Pool entry point on chains whose verifier takes a hash of the public
+/// inputs, passed after the proof
+pub const WITHDRAW_HASHED_SIGNATURE: &str =
+    "withdraw(bytes,bytes32,bytes32,address,uint256,address,uint256)";
+
+/// Default gas limit of a withdrawal transaction
+pub const DEFAULT_WITHDRAW_GAS_LIMIT: u64 = 600_000;
+
+/// Default blocks a withdrawal may stay unmined before it is replaced
+pub const DEFAULT_RESUBMIT_AFTER_BLOCKS: u64 = 5;
+
+/// Why a withdrawal wasn't broadcast
+#[derive(Debug, thiserror::Error)]
+pub enum WithdrawError {
+    #[error("Relayer does not submit on chain {0}")]
+    ChainNotServed(u64),
+    #[error("No pool to submit to on chain {0}")]
+    NoPool(u64),
+    #[error("Expected at least 4 public inputs, got {0}")]
+    MissingInputs(usize),
+    #[error("Nullifier {0:?} is already being relayed or spent")]
+    NullifierReserved(H256),
+    #[error(transparent)]
+    InputHash(#[from] InputHashError),
+    #[error("Gas costs {cost} wei, beyond what the quoted fee of {quoted} wei absorbs")]
+    OverQuote { quoted: U256, cost: U256 },
+    #[error("Relayer is shutting down")]
+    ShuttingDown,
+    #[error("Failed to submit withdrawal: {0:#}")]
+    Failed(anyhow::Error),
+}
+
+impl WithdrawError {
+    /// Stable reason code reported to API callers
+    pub fn code(&self) -> &'static str {
+        match self {
+            WithdrawError::ChainNotServed(_) => "chain_not_served",
+            WithdrawError::NoPool(_) => "no_pool",
+            WithdrawError::MissingInputs(_) => "wrong_input_count",
+            WithdrawError::NullifierReserved(_) => "nullifier_spent",
+            WithdrawError::InputHash(_) => "input_hash_mismatch",
+            WithdrawError::OverQuote { .. } => "gas_too_high",
+            WithdrawError::ShuttingDown => "shutting_down",
+            WithdrawError::Failed(e) if e.downcast_ref::<SubmitError>().is_some() => "gas_too_high",
+            WithdrawError::Failed(_) => "submission_failed",
+        }
+    }
+}
+
+/// Everything needed to submit withdrawals on one chain
+pub struct ChainWithdrawals {
+    /// Pool targeted by requests that don't name one
+    pub pool: Option<Address>,
+    pub broadcaster: Arc<dyn TxBroadcaster>,
+    pub gas_oracle: Arc<dyn GasOracle>,
+    pub gas: GasPolicy,
+    pub fees: FeeSettings,
+    /// Gas limit withdrawals are sent, and quoted, with
+    pub gas_limit: u64,
+    /// How the chain's verifier takes public inputs
+    pub commitment: InputCommitment,
+    /// Blocks a withdrawal may stay unmined before it is replaced at a
+    /// higher fee; 0 never replaces
+    pub resubmit_after_blocks: u64,
+    /// Headers of the chain; the head bounds private-route inclusion
+    pub chain: FinalityHandle,
+    pub tracker: Arc<SubmissionTracker>,
+}
+
+impl ChainWithdrawals {
+    /// Build a chain's submission path from its endpoint config
+    ///
+    /// The chain's gas ceiling also caps EIP-1559 fees unless `fees` sets a
+    /// cap of its own.
+    pub fn from_endpoints(
+        endpoints: &ChainEndpoints,
+        fees: FeeSettings,
+        chain: FinalityHandle,
+        relays: Arc<RelayTracker>,
+        store: Arc<dyn Store>,
+    ) -> anyhow::Result<Self> {
+        let provider = Arc::new(crate::light_client::http_provider(endpoints)?);
+        let receipts: Arc<dyn ReceiptSource> = provider.clone();
+        let gas = GasPolicy::from(endpoints);
+        Ok(Self {
+            pool: endpoints.pool_address,
+            broadcaster: Arc::new(RoutedBroadcaster::from_endpoints(endpoints)?),
+            gas_oracle: provider,
+            fees: fees.for_chain(&gas),
+            gas_limit: endpoints.withdraw_gas_limit,
+            gas,
+            commitment: endpoints.public_input_commitment,
+            resubmit_after_blocks: endpoints.resubmit_after_blocks,
+            chain,
+            tracker: Arc::new(SubmissionTracker::new(
+                receipts,
+                relays,
+                store,
+                DEFAULT_RECEIPT_POLL_INTERVAL,
+            )),
+        })
+    }
+
+    fn head_number(&self) -> u64 {
+        self.chain.head().map_or(0, |head| head.block_number)
+    }
+
+    /// Sign `tx` with `wallet` and broadcast it
+    async fn send(
+        &self,
+        wallet: &LocalWallet,
+        tx: &TypedTransaction,
+        current_block: u64,
+    ) -> anyhow::Result<super::Broadcast> {
+        let chain_id = tx.chain_id().unwrap_or_default().as_u64();
+        let signature = wallet.with_chain_id(chain_id).sign_transaction(tx).await?;
+        self.broadcaster
+            .broadcast(&tx.rlp_signed(&signature), current_block)
+            .await
+    }
+}
+
+/// Submits accepted relay requests on their target chain
+pub struct WithdrawalSubmitter {
+    signer: Arc<ActiveSigner>,
+    relays: Arc<RelayTracker>,
+    chains: HashMap<u64, Arc<ChainWithdrawals>>,
+    statuses: Arc<TxStatuses>,
+    /// Quotes relays are bound to
+    quotes: Arc<QuoteBook>,
+    /// Submissions accepted but not yet broadcast
+    accepted: TaskTracker,
+}
+
+impl WithdrawalSubmitter {
+    pub fn new(
+        signer: Arc<ActiveSigner>,
+        relays: Arc<RelayTracker>,
+        chains: HashMap<u64, ChainWithdrawals>,
+        statuses: Arc<TxStatuses>,
+        quotes: Arc<QuoteBook>,
+    ) -> Self {
+        Self {
+            signer,
+            relays,
+            chains: chains
+                .into_iter()
+                .map(|(chain_id, chain)| (chain_id, Arc::new(chain)))
+                .collect(),
+            statuses,
+            quotes,
+            accepted: TaskTracker::new(),
+        }
+    }
+
+    /// Turn new submissions away and wait until accepted ones are broadcast
+    /// (or have failed)
+    pub async fn drain(&self) {
+        self.accepted.close();
+        self.accepted.wait().await;
+    }
+
+    /// Whether a drain has started, so new withdrawals are refused
+    pub fn is_draining(&self) -> bool {
+        self.accepted.is_closed()
+    }
+
+    /// Outcome of request `id`'s withdrawal, while still remembered
+    pub fn tx_status(&self, id: &str) -> Option<TxStatus> {
+        self.statuses.get(id)
+    }
+
+    /// Broadcast relay `relay_id`'s withdrawal and return its transaction hash
+    ///
+    /// The request must already have passed validation. The relay is marked
+    /// `Submitted` once broadcast and followed until it is mined, replacing
+    /// the transaction whenever it gets stuck. Its quote binding, if any, is
+    /// released once broadcast; a relay that isn't broadcast gives its quote
+    /// back, so it can be presented again.
+    pub async fn submit(
+        &self,
+        relay_id: &str,
+        request: &RelayRequest,
+    ) -> Result<H256, WithdrawError> {
+        // Taken before the check so a drain started in between waits for us
+        let _accepted = self.accepted.token();
+        if self.accepted.is_closed() {
+            self.quotes.restore(relay_id);
+            return Err(WithdrawError::ShuttingDown);
+        }
+        let submitted = self.send_withdrawal(relay_id, request).await;
+        if submitted.is_ok() {
+            self.quotes.release(relay_id);
+        } else {
+            self.quotes.restore(relay_id);
+        }
+        if let Err(e) = &submitted {
+            self.statuses.set(
+                relay_id,
+                TxStatus::Failed {
+                    reason: e.to_string(),
+                },
+            );
+        }
+        submitted
+    }
+
+    /// Track relay `relay_id` as pending, reserve its nullifier in `store`,
+    /// then submit its withdrawal on a task of its own
+    ///
+    /// Returns once the nullifier is reserved, so callers aren't held while
+    /// gas is deferred; `drain` still waits for the task. A nullifier another
+    /// relay already reserved is turned away. A relay turned away, or whose
+    /// withdrawal isn't broadcast, is marked `Failed`; in the latter case the
+    /// nullifier is released so the note can be relayed again.
+    pub async fn dispatch(
+        self: &Arc<Self>,
+        relay_id: &str,
+        request: RelayRequest,
+        store: Arc<dyn Store>,
+    ) -> Result<(), WithdrawError> {
+        self.relays.register(relay_id);
+        let reserved = async {
+            let accepted = self.accepted.token();
+            if self.accepted.is_closed() {
+                return Err(WithdrawError::ShuttingDown);
+            }
+            let pool = self.pool(&request)?;
+            let Some(&nullifier) = request.public_inputs.get(1) else {
+                return Err(WithdrawError::MissingInputs(request.public_inputs.len()));
+            };
+            match store
+                .insert_nullifier(request.chain_id, pool, nullifier)
+                .await
+            {
+                Ok(true) => Ok((accepted, pool, nullifier)),
+                Ok(false) => Err(WithdrawError::NullifierReserved(nullifier)),
+                Err(e) => Err(WithdrawError::Failed(e)),
+            }
+        };
+        let (accepted, pool, nullifier) = match reserved.await {
+            Ok(reserved) => reserved,
+            Err(e) => {
+                self.quotes.restore(relay_id);
+                self.relays.update(
+                    relay_id,
+                    RelayStatus::Failed {
+                        reason: e.to_string(),
+                    },
+                );
+                return Err(e);
+            }
+        };
+
+        let submitter = self.clone();
+        let relay_id = relay_id.to_string();
+        tokio::spawn(async move {
+            let _accepted = accepted;
+            let Err(e) = submitter.submit(&relay_id, &request).await else {
+                return;
+            };
+            warn!(relay_id = %relay_id, error = %e, "Relay submission failed");
+            submitter.relays.update(
+                &relay_id,
+                RelayStatus::Failed {
+                    reason: e.to_string(),
+                },
+            );
+            if let Err(e) = store
+                .remove_nullifier(request.chain_id, pool, nullifier)
+                .await
+            {
+                warn!(relay_id = %relay_id, error = %e, "Failed to release nullifier");
+            }
+        });
+        Ok(())
+    }
+
+    /// Pool a request's withdrawal goes to
+    fn pool(&self, request: &RelayRequest) -> Result<Address, WithdrawError> {
+        let chain_id = request.chain_id;
+        let chain = self
+            .chains
+            .get(&chain_id)
+            .ok_or(WithdrawError::ChainNotServed(chain_id))?;
+        request
+            .pool
+            .or(chain.pool)
+            .ok_or(WithdrawError::NoPool(chain_id))
+    }
+
+    async fn send_withdrawal(
+        &self,
+        relay_id: &str,
+        request: &RelayRequest,
+    ) -> Result<H256, WithdrawError> {
+        let chain_id = request.chain_id;
+        let pool = self.pool(request)?;
+        let chain = &self.chains[&chain_id];
+        if request.public_inputs.len() < 4 {
+            return Err(WithdrawError::MissingInputs(request.public_inputs.len()));
+        }
+        let elements: Vec<[u8; 32]> = request.public_inputs.iter().map(|input| input.0).collect();
+        let input_hash = chain
+            .commitment
+            .committed_hash(&elements, request.public_inputs_hash)?;
+
+        let gas_price = chain
+            .gas
+            .acquire(chain.gas_oracle.as_ref())
+            .await
+            .map_err(WithdrawError::Failed)?;
+        let fees = chain.fees.price(chain.gas_oracle.as_ref(), gas_price).await;
+        let cost = fees.max_per_gas() * U256::from(chain.gas_limit);
+        let fee = match self.quotes.fee_for(relay_id, request.fee, cost) {
+            FeeDecision::Charge(fee) => fee,
+            FeeDecision::Defer { quoted, cost } => {
+                return Err(WithdrawError::OverQuote { quoted, cost })
+            }
+        };
+        let current_block = chain.head_number();
+
+        // The wallet is kept to sign replacements, even after a rotation
+        let signed_with = Mutex::new(None);
+        let in_flight = self
+            .signer
+            .submit(chain_id, |wallet, nonce| {
+                let signed_with = &signed_with;
+                async move {
+                    let data = withdraw_calldata(request, input_hash, wallet.address(), fee);
+                    let tx =
+                        withdraw_tx(chain_id, pool, data.clone(), nonce, chain.gas_limit, fees);
+                    let broadcast = chain.send(&wallet, &tx, current_block).await?;
+                    *signed_with.lock().unwrap() = Some((wallet, data));
+                    Ok(broadcast)
+                }
+            })
+            .await
+            .map_err(WithdrawError::Failed)?;
+        let (wallet, data) = signed_with
+            .into_inner()
+            .unwrap()
+            .expect("set by a successful broadcast");
+
+        let tx_hash = in_flight.broadcast.tx_hash;
+        info!(relay_id = relay_id, chain_id = chain_id, tx_hash = ?tx_hash, "Withdrawal submitted");
+        self.relays.update(
+            relay_id,
+            RelayStatus::Submitted {
+                tx_hash: format!("{:?}", tx_hash),
+            },
+        );
+        self.statuses.set(relay_id, TxStatus::Pending);
+
+        let relay_id = relay_id.to_string();
+        let pending = Pending {
+            relay_id: relay_id.clone(),
+            wallet,
+            pool,
+            data,
+            fees,
+            hashes: vec![tx_hash],
+            sent_at: current_block,
+        };
+        let chain = chain.clone();
+        let relays = self.relays.clone();
+        let statuses = self.statuses.clone();
+        tokio::spawn(async move {
+            let key = (chain_id, in_flight.nonce);
+            if let Err(e) = follow(&chain, &relays, &statuses, key, pending).await {
+                warn!(relay_id = %relay_id, error = %e, "Lost track of withdrawal");
+                statuses.set(
+                    &relay_id,
+                    TxStatus::Failed {
+                        reason: format!("Lost track of withdrawal: {:#}", e),
+                    },
+                );
+            }
+            // Frees the chain's in-flight slot
+            drop(in_flight);
+        });
+        Ok(tx_hash)
+    }
+}
+
+/// A broadcast withdrawal, kept so it can be replaced while unmined
+struct Pending {
+    relay_id: String,
+    wallet: LocalWallet,
+    pool: Address,
+    data: Bytes,
+    /// Fees of the latest transaction sent at the nonce
+    fees: TxFees,
+    /// Every hash sent at the nonce; any of them may be the one mined
+    hashes: Vec<H256>,
+    /// Head when the latest transaction was sent
+    sent_at: u64,
+}
+
+/// Wait for `pending` to be mined, replacing it each time it sits unmined
+/// for the chain's `resubmit_after_blocks`, and record the outcome
+async fn follow(
+    chain: &ChainWithdrawals,
+    relays: &RelayTracker,
+    statuses: &TxStatuses,
+    (chain_id, nonce): (u64, U256),
+    mut pending: Pending,
+) -> anyhow::Result<RelayStatus> {
+    loop {
+        if let Some(receipt) = chain.tracker.mined(&pending.hashes).await? {
+            let block = receipt.block_number.unwrap_or_default().as_u64();
+            let tx_hash = receipt.transaction_hash;
+            let status = chain
+                .tracker
+                .record(&pending.relay_id, chain_id, receipt)
+                .await?;
+            let outcome = match &status {
+                RelayStatus::Reverted { reason, .. } => TxStatus::Failed {
+                    reason: format!("Reverted: {}", reason),
+                },
+                _ => TxStatus::Mined { block, tx_hash },
+            };
+            statuses.set(&pending.relay_id, outcome);
+            return Ok(status);
+        }
+
+        let head = chain.head_number();
+        if chain.resubmit_after_blocks > 0 && head >= pending.sent_at + chain.resubmit_after_blocks
+        {
+            // Waits another window before trying again, sent or not
+            pending.sent_at = head;
+            if let Some(tx_hash) = replace(chain, &mut pending, chain_id, nonce, head).await {
+                relays.update(
+                    &pending.relay_id,
+                    RelayStatus::Submitted {
+                        tx_hash: format!("{:?}", tx_hash),
+                    },
+                );
+            }
+        }
+
+        tokio::time::sleep(chain.tracker.poll_interval()).await;
+    }
+}
+
+/// Resend `pending` at `nonce` with bumped fees, returning the new hash
+async fn replace(
+    chain: &ChainWithdrawals,
+    pending: &mut Pending,
+    chain_id: u64,
+    nonce: U256,
+    head: u64,
+) -> Option<H256> {
+    let Some(fees) = chain.fees.bump(pending.fees, &chain.gas) else {
+        warn!(
+            relay_id = %pending.relay_id,
+            chain_id = chain_id,
+            %nonce,
+            "Stuck withdrawal can't be bumped within the fee caps"
+        );
+        return None;
+    };
+    let tx = withdraw_tx(
+        chain_id,
+        pending.pool,
+        pending.data.clone(),
+        nonce,
+        chain.gas_limit,
+        fees,
+    );
+    let tx_hash = match chain.send(&pending.wallet, &tx, head).await {
+        Ok(broadcast) => broadcast.tx_hash,
+        Err(e) => {
+            warn!(relay_id = %pending.relay_id, error = %e, "Failed to replace stuck withdrawal");
+            return None;
+        }
+    };
+
+    info!(
+        relay_id = %pending.relay_id,
+        chain_id = chain_id,
+        %nonce,
+        tx_hash = ?tx_hash,
+        "Stuck withdrawal replaced"
+    );
+    pending.fees = fees;
+    pending.hashes.push(tx_hash);
+    Some(tx_hash)
+}
+
+/// Unsigned `withdraw` transaction at `nonce`, legacy or EIP-1559 per `fees`
+fn withdraw_tx(
+    chain_id: u64,
+    pool: Address,
+    data: Bytes,
+    nonce: U256,
+    gas_limit: u64,
+    fees: TxFees,
+) -> TypedTransaction {
+    match fees {
+        TxFees::Legacy { gas_price } => TransactionRequest::new()
+            .to(pool)
+            .data(data)
+            .gas(gas_limit)
+            .gas_price(gas_price)
+            .nonce(nonce)
+            .chain_id(chain_id)
+            .into(),
+        TxFees::Eip1559 {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        } => Eip1559TransactionRequest::new()
+            .to(pool)
+            .data(data)
+            .gas(gas_limit)
+            .max_fee_per_gas(max_fee_per_gas)
+            .max_priority_fee_per_gas(max_priority_fee_per_gas)
+            .nonce(nonce)
+            .chain_id(chain_id)
+            .into(),
+    }
+}
+
+/// `withdraw` calldata paying `fee` to `relayer`
+///
+/// Public inputs are `(root, nullifier, recipient, amount, ..)`; the pool
+/// reads the root from the proof itself. An `input_hash` selects the
+/// overload that hands it to the verifier.
+fn withdraw_calldata(
+    request: &RelayRequest,
+    input_hash: Option<H256>,
+    relayer: Address,
+    fee: U256,
+) -> Bytes {
+    let inputs = &request.public_inputs;
+    let (signature, mut tokens) = match input_hash {
+        Some(hash) => (
+            WITHDRAW_HASHED_SIGNATURE,
+            vec![Token::FixedBytes(hash.as_bytes().to_vec())],
+        ),
+        None => (WITHDRAW_SIGNATURE, Vec::new()),
+    };
+    tokens.insert(0, Token::Bytes(request.proof.to_vec()));
+    tokens.extend([
+        Token::FixedBytes(inputs[1].as_bytes().to_vec()),
+        Token::Address(Address::from_slice(&inputs[2][12..])),
+        Token::Uint(U256::from_big_endian(inputs[3].as_bytes())),
+        Token::Address(relayer),
+        Token::Uint(fee),
+    ]);
+    let mut data = id(signature).to_vec();
+    data.extend(encode(&tokens));
+    data.into()
+}
+
+/// Broadcaster that keeps every transaction it is given, none of which are
+/// mined until `mine` is called
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingBroadcaster {
+    pub sent: std::sync::Mutex<Vec<Bytes>>,
+    /// Block each mined transaction was included in
+    mined: std::sync::Mutex<HashMap<H256, u64>>,
+    /// Whether broadcasts are refused, as by an unreachable RPC
+    pub offline: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+impl RecordingBroadcaster {
+    /// Include every transaction sent so far in `block_number`
+    pub fn mine(&self, block_number: u64) {
+        let sent = self.sent.lock().unwrap();
+        let mut mined = self.mined.lock().unwrap();
+        for raw_tx in sent.iter() {
+            mined
+                .entry(H256(ethers::utils::keccak256(raw_tx)))
+                .or_insert(block_number);
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl TxBroadcaster for RecordingBroadcaster {
+    async fn broadcast(
+        &self,
+        raw_tx: &Bytes,
+        _current_block: u64,
+    ) -> anyhow::Result<super::Broadcast> {
+        if self.offline.load(std::sync::atomic::Ordering::SeqCst) {
+            anyhow::bail!("Broadcaster offline");
+        }
+        self.sent.lock().unwrap().push(raw_tx.clone());
+        Ok(super::Broadcast {
+            tx_hash: H256(ethers::utils::keccak256(raw_tx)),
+            route: super::route::BroadcastRoute::Public,
+            expires_at_block: None,
+        })
+    }
+}
+
+/// 30 gwei gas price; fee history with a 10 gwei base fee and 2 gwei tips
+#[cfg(test)]
+struct StubFees;
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl GasOracle for StubFees {
+    async fn gas_price(&self) -> anyhow::Result<U256> {
+        Ok(U256::from(30_000_000_000u64))
+    }
+
+    async fn fee_history(&self, _blocks: u64, _percentile: f64) -> anyhow::Result<FeeHistory> {
+        Ok(FeeHistory {
+            base_fee_per_gas: vec![U256::from(10_000_000_000u64)],
+            gas_used_ratio: vec![],
+            oldest_block: U256::zero(),
+            reward: vec![vec![U256::from(2_000_000_000u64)]],
+        })
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ReceiptSource for RecordingBroadcaster {
+    async fn receipt(&self, tx_hash: H256) -> anyhow::Result<Option<TransactionReceipt>> {
+        let mined = self.mined.lock().unwrap();
+        Ok(mined.get(&tx_hash).map(|block_number| TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some((*block_number).into()),
+            status: Some(U64::one()),
+            ..Default::default()
+        }))
+    }
+
+    async fn revert_data(&self, _tx_hash: H256, _block_number: U64) -> anyhow::Result<Bytes> {
+        Ok(Bytes::new())
+    }
+}
+
+/// Blocks the test submitter leaves a withdrawal unmined before replacing it
+#[cfg(test)]
+pub(crate) const TEST_RESUBMIT_AFTER_BLOCKS: u64 = 3;
+
+/// Submitter for `chain_id` whose RPC is a mock provider and whose head is
+/// `chain`'s, with the wallet it signs with and the broadcaster
+/// transactions end up in (and are mined from)
+#[cfg(test)]
+pub(crate) async fn test_withdrawals(
+    chain_id: u64,
+    pool: Address,
+    fees: FeeSettings,
+    chain: FinalityHandle,
+    relays: Arc<RelayTracker>,
+) -> (
+    Arc<WithdrawalSubmitter>,
+    LocalWallet,
+    Arc<RecordingBroadcaster>,
+) {
+    let (provider, mock) = Provider::mocked();
+    mock.push(U256::from(7u64)).unwrap();
+    let nonces = Arc::new(provider);
+
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let signer = Arc::new(
+        ActiveSigner::new(
+            wallet.clone(),
+            Box::new(move |address| {
+                let mut submitters = super::ChainSubmitters::default();
+                submitters.insert(super::NonceManager::new(
+                    chain_id,
+                    address,
+                    nonces.clone(),
+                    4,
+                ));
+                Ok(submitters)
+            }),
+            Arc::new(crate::keys::rotation::AcceptAll),
+            crate::keys::rotation::DEFAULT_DRAIN_TIMEOUT,
+        )
+        .unwrap(),
+    );
+
+    let broadcaster = Arc::new(RecordingBroadcaster::default());
+    let withdrawals = ChainWithdrawals {
+        pool: Some(pool),
+        broadcaster: broadcaster.clone(),
+        gas_oracle: Arc::new(StubFees),
+        gas: GasPolicy {
+            max_gas_price: None,
+            action: Default::default(),
+            recheck_interval: std::time::Duration::from_millis(5),
+            max_deferral: std::time::Duration::from_millis(50),
+        },
+        fees,
+        gas_limit: DEFAULT_WITHDRAW_GAS_LIMIT,
+        commitment: InputCommitment::Full,
+        resubmit_after_blocks: TEST_RESUBMIT_AFTER_BLOCKS,
+        chain,
+        tracker: Arc::new(SubmissionTracker::new(
+            broadcaster.clone(),
+            relays.clone(),
+            crate::store::memory().await,
+            std::time::Duration::from_millis(5),
+        )),
+    };
+    let quotes = crate::quote::QuoteBook::new(
+        wallet.clone(),
+        crate::quote::DEFAULT_QUOTE_VALIDITY,
+        crate::quote::DEFAULT_ABSORB_BUFFER_BPS,
+        crate::quote::test_pricing(&[chain_id]),
+        crate::store::memory().await,
+    );
+    let submitter = WithdrawalSubmitter::new(
+        signer,
+        relays,
+        HashMap::from([(chain_id, withdrawals)]),
+        Arc::default(),
+        Arc::new(quotes),
+    );
+    (Arc::new(submitter), wallet, broadcaster)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_client::StoredHeader;
+    use crate::submitter::FeeStrategy;
+    use ethers::abi::{decode, ParamType};
+    use ethers::utils::rlp::Rlp;
+
+    /// Untyped transactions at the stub's 30 gwei gas price
+    fn legacy() -> FeeSettings {
+        FeeSettings {
+            strategy: FeeStrategy::Legacy,
+            ..Default::default()
+        }
+    }
+
+    fn head() -> FinalityHandle {
+        FinalityHandle::with_headers(vec![], 0)
+    }
+
+    fn header(block_number: u64) -> StoredHeader {
+        StoredHeader {
+            block_number,
+            block_hash: H256::from_low_u64_be(block_number),
+            parent_hash: H256::from_low_u64_be(block_number.saturating_sub(1)),
+            state_root: H256::zero(),
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+            timestamp: block_number * 12,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submits_signed_withdraw_call() {
+        let pool = Address::repeat_byte(0x50);
+        let relays = Arc::new(RelayTracker::default());
+        let (submitter, wallet, broadcaster) =
+            test_withdrawals(1, pool, legacy(), head(), relays.clone()).await;
+        let (_, mut request) = crate::relay::validate::valid_relay_fixture(1).await;
+        request.fee = U256::from(1_000u64);
+
+        relays.register("r1");
+        let tx_hash = submitter.submit("r1", &request).await.unwrap();
+        assert_eq!(
+            relays.status("r1"),
+            Some(RelayStatus::Submitted {
+                tx_hash: format!("{:?}", tx_hash)
+            })
+        );
+
+        let sent = broadcaster.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&sent[0])).unwrap();
+        assert_eq!(signature.recover(tx.sighash()).unwrap(), wallet.address());
+        assert_eq!(tx.to_addr(), Some(&pool));
+        assert_eq!(tx.nonce(), Some(&U256::from(7u64)));
+        assert_eq!(tx.chain_id(), Some(U64::one()));
+        assert_eq!(tx.gas_price(), Some(U256::from(30_000_000_000u64)));
+
+        let data = tx.data().unwrap();
+        assert_eq!(data[..4], id(WITHDRAW_SIGNATURE));
+        let tokens = decode(
+            &[
+                ParamType::Bytes,
+                ParamType::FixedBytes(32),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+            &data[4..],
+        )
+        .unwrap();
+        assert_eq!(tokens[0], Token::Bytes(request.proof.to_vec()));
+        assert_eq!(
+            tokens[1],
+            Token::FixedBytes(request.public_inputs[1].as_bytes().to_vec())
+        );
+        assert_eq!(tokens[2], Token::Address(Address::repeat_byte(0x33)));
+        assert_eq!(tokens[3], Token::Uint(U256::from(1_000_000u64)));
+        assert_eq!(tokens[4], Token::Address(wallet.address()));
+        assert_eq!(tokens[5], Token::Uint(request.fee));
+
+        // Verifiers taking a hash get the one the inputs commit to
+        let elements: Vec<[u8; 32]> = request.public_inputs.iter().map(|i| i.0).collect();
+        let hash = InputCommitment::Keccak256.hash(&elements).unwrap();
+        let hashed = withdraw_calldata(&request, Some(hash), wallet.address(), request.fee);
+        assert_eq!(hashed[..4], id(WITHDRAW_HASHED_SIGNATURE));
+        let hashed_tokens = decode(
+            &[
+                ParamType::Bytes,
+                ParamType::FixedBytes(32),
+                ParamType::FixedBytes(32),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+            &hashed[4..],
+        )
+        .unwrap();
+        assert_eq!(
+            hashed_tokens[1],
+            Token::FixedBytes(hash.as_bytes().to_vec())
+        );
+        assert_eq!(hashed_tokens[0], tokens[0]);
+        assert_eq!(hashed_tokens[2..], tokens[1..]);
+
+        // Nothing to submit on an unknown chain
+        request.chain_id = 5;
+        assert_eq!(
+            submitter.submit("r2", &request).await.unwrap_err().code(),
+            "chain_not_served"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_reserves_nullifier_until_broadcast_fails() {
+        let relays = Arc::new(RelayTracker::default());
+        let (submitter, _, broadcaster) = test_withdrawals(
+            1,
+            Address::repeat_byte(0x50),
+            legacy(),
+            head(),
+            relays.clone(),
+        )
+        .await;
+        let (ctx, mut request) = crate::relay::validate::valid_relay_fixture(1).await;
+        let pool = Address::repeat_byte(0x50);
+        let nullifier = request.public_inputs[1];
+
+        submitter
+            .dispatch("r1", request.clone(), ctx.store.clone())
+            .await
+            .unwrap();
+        assert!(ctx.store.has_nullifier(1, pool, nullifier).await.unwrap());
+        // A second relay of the same note is turned away at once
+        assert_eq!(
+            submitter
+                .dispatch("r2", request.clone(), ctx.store.clone())
+                .await
+                .unwrap_err()
+                .code(),
+            "nullifier_spent"
+        );
+        submitter.drain().await;
+        assert_eq!(broadcaster.sent.lock().unwrap().len(), 1);
+        assert!(matches!(
+            relays.status("r1"),
+            Some(RelayStatus::Submitted { .. })
+        ));
+
+        // A withdrawal that is never broadcast frees its nullifier
+        let (submitter, _, broadcaster) =
+            test_withdrawals(1, pool, legacy(), head(), relays.clone()).await;
+        broadcaster
+            .offline
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        request.public_inputs[1] = H256::repeat_byte(0x23);
+        submitter
+            .dispatch("r3", request.clone(), ctx.store.clone())
+            .await
+            .unwrap();
+        submitter.drain().await;
+        assert!(matches!(
+            relays.status("r3"),
+            Some(RelayStatus::Failed { .. })
+        ));
+        assert!(!ctx
+            .store
+            .has_nullifier(1, pool, request.public_inputs[1])
+            .await
+            .unwrap());
+
+        // Nothing can be submitted on an unknown chain, so nothing is reserved
+        request.chain_id = 5;
+        assert_eq!(
+            submitter
+                .dispatch("r4", request, ctx.store.clone())
+                .await
+                .unwrap_err()
+                .code(),
+            "chain_not_served"
+        );
+        assert!(matches!(
+            relays.status("r4"),
+            Some(RelayStatus::Failed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quoted_relay_turned_away_beyond_buffer() {
+        let relays = Arc::new(RelayTracker::default());
+        let (submitter, _, broadcaster) = test_withdrawals(
+            1,
+            Address::repeat_byte(0x50),
+            legacy(),
+            head(),
+            relays.clone(),
+        )
+        .await;
+        let (_, mut request) = crate::relay::validate::valid_relay_fixture(1).await;
+        // Offering more than either quote asks
+        request.fee = U256::from(20_000_000_000_000_000u64);
+        let now = chrono::Utc::now().timestamp();
+        let quoted = |fee: u64| {
+            let estimate = crate::quote::FeeEstimate {
+                gas_limit: DEFAULT_WITHDRAW_GAS_LIMIT,
+                gas_price: U256::from(30_000_000_000u64),
+                fee: U256::from(fee),
+            };
+            submitter.quotes.issue(1, estimate, now).unwrap()
+        };
+
+        // 600k gas at 30 gwei costs 0.018 ETH, more than 10% over 0.015
+        let low = quoted(15_000_000_000_000_000);
+        submitter.quotes.honor(&low, 1, "r1").await.unwrap();
+        let err = submitter.submit("r1", &request).await.unwrap_err();
+        assert!(matches!(err, WithdrawError::OverQuote { .. }));
+        assert_eq!(err.code(), "gas_too_high");
+        assert!(broadcaster.sent.lock().unwrap().is_empty());
+        assert_eq!(submitter.quotes.binding("r1"), None);
+        // The quote wasn't used up, so it can be presented again
+        submitter.quotes.honor(&low, 1, "r1-retry").await.unwrap();
+        submitter.quotes.release("r1-retry");
+
+        // but within 10% of 0.017, which absorbs the difference
+        submitter
+            .quotes
+            .honor(&quoted(17_000_000_000_000_000), 1, "r2")
+            .await
+            .unwrap();
+        submitter.submit("r2", &request).await.unwrap();
+        let sent = broadcaster.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(submitter.quotes.binding("r2"), None);
+
+        // The quoted fee is what's deducted, not what the request offered
+        let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(&sent[0])).unwrap();
+        let tokens = decode(
+            &[
+                ParamType::Bytes,
+                ParamType::FixedBytes(32),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+            &tx.data().unwrap()[4..],
+        )
+        .unwrap();
+        let Token::Uint(fee) = tokens[5] else {
+            panic!("expected a fee, got {:?}", tokens[5]);
+        };
+        let quote = U256::from(17_000_000_000_000_000u64);
+        assert!(fee <= quote, "charged {} for quote {}", fee, quote);
+        assert_eq!(fee, quote);
+    }
+
+    #[tokio::test]
+    async fn test_eip1559_envelope() {
+        let relays = Arc::new(RelayTracker::default());
+        let fees = FeeSettings {
+            strategy: FeeStrategy::Eip1559,
+            ..Default::default()
+        };
+        let (submitter, _, broadcaster) =
+            test_withdrawals(1, Address::repeat_byte(0x50), fees, head(), relays.clone()).await;
+        let (_, request) = crate::relay::validate::valid_relay_fixture(1).await;
+
+        relays.register("r1");
+        submitter.submit("r1", &request).await.unwrap();
+
+        let sent = broadcaster.sent.lock().unwrap().clone();
+        // Type-2 envelope
+        assert_eq!(sent[0][0], 0x02);
+        let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(&sent[0])).unwrap();
+        let TypedTransaction::Eip1559(tx) = tx else {
+            panic!("expected an EIP-1559 transaction, got {:?}", tx);
+        };
+        assert_eq!(tx.max_fee_per_gas, Some(U256::from(22_000_000_000u64)));
+        assert_eq!(
+            tx.max_priority_fee_per_gas,
+            Some(U256::from(2_000_000_000u64))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stuck_withdrawal_replaced_at_same_nonce() {
+        let relays = Arc::new(RelayTracker::default());
+        let chain = head();
+        chain.push_header(header(100));
+        let (submitter, _, broadcaster) = test_withdrawals(
+            1,
+            Address::repeat_byte(0x50),
+            legacy(),
+            chain.clone(),
+            relays.clone(),
+        )
+        .await;
+        let (_, request) = crate::relay::validate::valid_relay_fixture(1).await;
+        let sent = |n: usize| {
+            let sent = broadcaster.sent.lock().unwrap();
+            sent.get(n)
+                .map(|raw| TypedTransaction::decode_signed(&Rlp::new(raw)).unwrap().0)
+        };
+
+        relays.register("r1");
+        let first = submitter.submit("r1", &request).await.unwrap();
+        let nonce = U256::from(7u64);
+        let submitted = |tx_hash: H256| {
+            Some(RelayStatus::Submitted {
+                tx_hash: format!("{:?}", tx_hash),
+            })
+        };
+        assert_eq!(relays.status("r1"), submitted(first));
+
+        // Not yet stuck
+        chain.push_header(header(100 + TEST_RESUBMIT_AFTER_BLOCKS - 1));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(sent(1).is_none());
+
+        // Never mined, so replaced once enough blocks pass
+        chain.push_header(header(100 + TEST_RESUBMIT_AFTER_BLOCKS));
+        let replacement = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                if let Some(tx) = sent(1) {
+                    break tx;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let original = sent(0).unwrap();
+        assert_eq!(replacement.nonce(), Some(&nonce));
+        assert_eq!(original.nonce(), replacement.nonce());
+        assert_eq!(original.data(), replacement.data());
+        // 30 gwei plus 12.5%
+        assert_eq!(replacement.gas_price(), Some(U256::from(33_750_000_000u64)));
+
+        let replaced = H256(ethers::utils::keccak256(
+            &broadcaster.sent.lock().unwrap()[1],
+        ));
+        assert_ne!(replaced, first);
+        // The relay reports the replacement's hash once it is broadcast
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while relays.status("r1") != submitted(replaced) {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(sent(2).is_none());
+    }
+}
@@ -0,0 +1,686 @@
+//! Pool contract event watcher
+//!
+//! Follows the pool contract's logs and turns `Deposit` and `Withdrawal`
+//! events into relay triggers once their block is finalized by the light
+//! client. Withdrawals may instead go once they have enough confirmations,
+//! when a chain is configured with `min_confirmations`; deposits always wait
+//! for finality, since they are applied to the tree proofs are checked
+//! against and nothing takes them back out. Logs are only fetched up to that height, so a block
+//! reorged out above it is never seen, and its replacement's logs are read
+//! once they are deep enough. Events whose block is no longer canonical
+//! are dropped.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::channel;
+use crate::light_client::FinalityHandle;
+use crate::merkle::roots::PoolRoots;
+use crate::merkle::IncrementalMerkleTree;
+
+/// Default interval between log polls
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Largest block range requested in a single `eth_getLogs` call
+const MAX_LOG_RANGE: u64 = 2_000;
+
+/// Pool event that can trigger a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// A new commitment was inserted into the tree
+    Deposit { commitment: H256, leaf_index: U256 },
+    /// A note was spent to an external recipient
+    Withdrawal {
+        nullifier: H256,
+        recipient: Address,
+        amount: U256,
+    },
+}
+
+impl PoolEvent {
+    /// Decode a pool log, ignoring events the relayer doesn't act on
+    fn decode(log: &Log) -> Option<Self> {
+        let signature = *log.topics.first()?;
+        if signature == event_topic("Deposit(bytes32,uint256,uint256)") {
+            Some(PoolEvent::Deposit {
+                commitment: *log.topics.get(1)?,
+                leaf_index: U256::from_big_endian(log.topics.get(2)?.as_bytes()),
+            })
+        } else if signature == event_topic("Withdrawal(bytes32,address,uint256)") {
+            let data = log.data.as_ref();
+            if data.len() < 32 {
+                return None;
+            }
+            Some(PoolEvent::Withdrawal {
+                nullifier: *log.topics.get(1)?,
+                recipient: Address::from_slice(&log.topics.get(2)?.as_bytes()[12..]),
+                amount: U256::from_big_endian(&data[..32]),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Topic hash of an event signature
+fn event_topic(signature: &str) -> H256 {
+    H256(keccak256(signature.as_bytes()))
+}
+
+/// When an observed event's block is deep enough to act on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfirmationPolicy {
+    /// Wait for the light client to finalize the block
+    #[default]
+    Finalized,
+    /// Wait until the block has this many confirmations (the block itself
+    /// counts as one), or is finalized if that happens first
+    MinConfirmations(u64),
+}
+
+impl ConfirmationPolicy {
+    /// Policy for a chain's optional `min_confirmations` setting
+    pub fn from_min_confirmations(min_confirmations: Option<u64>) -> Self {
+        min_confirmations.map_or(Self::Finalized, Self::MinConfirmations)
+    }
+
+    /// Highest block whose events may be acted on
+    fn confirmed_height(&self, head: u64, finalized: u64) -> u64 {
+        match self {
+            Self::Finalized => finalized,
+            Self::MinConfirmations(min) => finalized.max((head + 1).saturating_sub((*min).max(1))),
+        }
+    }
+
+    /// Whether an event at `block_number` may be acted on
+    fn is_actionable(&self, block_number: u64, head: u64, finalized: u64) -> bool {
+        block_number <= self.confirmed_height(head, finalized)
+    }
+}
+
+/// Confirmed pool event ready to be relayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayTrigger {
+    pub chain_id: u64,
+    /// Pool contract that emitted the event
+    pub pool: Address,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub tx_hash: H256,
+    pub log_index: U256,
+    pub event: PoolEvent,
+}
+
+/// Source of contract logs for a single chain
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    /// Current head block number
+    async fn fetch_block_number(&self) -> Result<u64>;
+    /// Logs emitted by `address` in the inclusive block range
+    async fn fetch_logs(&self, address: Address, from: u64, to: u64) -> Result<Vec<Log>>;
+    /// The pool's `merkleRoot()` as of `block_number`
+    async fn fetch_pool_root(&self, pool: Address, block_number: u64) -> Result<H256>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> LogSource for Provider<P> {
+    async fn fetch_block_number(&self) -> Result<u64> {
+        Ok(self.get_block_number().await?.as_u64())
+    }
+
+    async fn fetch_logs(&self, address: Address, from: u64, to: u64) -> Result<Vec<Log>> {
+        let filter = Filter::new().address(address).from_block(from).to_block(to);
+        Ok(self.get_logs(&filter).await?)
+    }
+
+    async fn fetch_pool_root(&self, pool: Address, block_number: u64) -> Result<H256> {
+        let call: TypedTransaction = TransactionRequest::new()
+            .to(pool)
+            .data(ethers::utils::id("merkleRoot()").to_vec())
+            .into();
+        let output = self.call(&call, Some(block_number.into())).await?;
+        if output.len() != 32 {
+            anyhow::bail!("Malformed merkleRoot() result from pool {:?}", pool);
+        }
+        Ok(H256::from_slice(&output))
+    }
+}
+
+/// Rebuild a pool's `tree_depth`-level tree from its deposits in `from..=to`
+///
+/// The pool's `merkleRoot()` at `to` must match the rebuilt root, otherwise
+/// proofs would be checked against roots the pool never had.
+pub async fn rebuild_roots(
+    source: &dyn LogSource,
+    pool: Address,
+    tree_depth: usize,
+    from: u64,
+    to: u64,
+    window: usize,
+) -> Result<PoolRoots> {
+    let mut roots = PoolRoots::new(IncrementalMerkleTree::new(tree_depth), window);
+    let mut start = from;
+    while start <= to {
+        let end = to.min(start + MAX_LOG_RANGE - 1);
+        for log in source.fetch_logs(pool, start, end).await? {
+            let Some(PoolEvent::Deposit {
+                commitment,
+                leaf_index,
+            }) = PoolEvent::decode(&log)
+            else {
+                continue;
+            };
+            let Some(block_number) = log.block_number else {
+                continue;
+            };
+            let leaf_index = leaf_index
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Deposit leaf index {} out of range", leaf_index))?;
+            roots.apply_deposit(commitment, leaf_index, block_number.as_u64())?;
+        }
+        start = end + 1;
+    }
+
+    let onchain = source.fetch_pool_root(pool, to).await?;
+    if roots.root() != onchain {
+        anyhow::bail!(
+            "Rebuilt tree of pool {:?} has root {:?} at block {}, the pool has {:?}",
+            pool,
+            roots.root(),
+            to,
+            onchain
+        );
+    }
+    Ok(roots)
+}
+
+/// Finality information the watcher gates triggers on
+pub trait FinalityView: Send + Sync {
+    /// Latest finalized block number
+    fn finalized(&self) -> u64;
+    /// Canonical hash at `block_number`, if known
+    fn canonical_hash(&self, block_number: u64) -> Option<H256>;
+}
+
+impl FinalityView for FinalityHandle {
+    fn finalized(&self) -> u64 {
+        FinalityHandle::finalized(self)
+    }
+
+    fn canonical_hash(&self, block_number: u64) -> Option<H256> {
+        FinalityHandle::canonical_hash(self, block_number)
+    }
+}
+
+/// Watches one chain's pool contract and emits confirmed relay triggers
+pub struct PoolWatcher {
+    chain_id: u64,
+    pool: Address,
+    source: Arc<dyn LogSource>,
+    finality: Arc<dyn FinalityView>,
+    /// Depth an event's block must reach before it triggers a relay
+    policy: ConfirmationPolicy,
+    /// Next block whose logs haven't been fetched yet
+    next_block: u64,
+    /// Observed events waiting for their block to finalize
+    pending: Vec<RelayTrigger>,
+    trigger_tx: mpsc::Sender<RelayTrigger>,
+}
+
+impl PoolWatcher {
+    /// Create a watcher starting just after the current finalized block
+    pub fn new(
+        chain_id: u64,
+        pool: Address,
+        source: Arc<dyn LogSource>,
+        finality: Arc<dyn FinalityView>,
+        trigger_tx: mpsc::Sender<RelayTrigger>,
+    ) -> Self {
+        let next_block = finality.finalized() + 1;
+        Self {
+            chain_id,
+            pool,
+            source,
+            finality,
+            policy: ConfirmationPolicy::default(),
+            next_block,
+            pending: Vec::new(),
+            trigger_tx,
+        }
+    }
+
+    /// Gate withdrawal triggers on `policy` instead of finality
+    pub fn with_confirmation_policy(mut self, policy: ConfirmationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Start scanning at `block_number` instead of after the finalized block
+    pub fn with_start_block(mut self, block_number: u64) -> Self {
+        self.next_block = block_number;
+        self
+    }
+
+    /// Poll for new logs until the trigger receiver is dropped
+    pub async fn run(mut self, interval: Duration) {
+        info!(chain_id = self.chain_id, pool = ?self.pool, "Pool watcher started");
+        loop {
+            if let Err(e) = self.poll().await {
+                warn!(chain_id = self.chain_id, error = %e, "Pool log poll failed");
+            }
+            if self.trigger_tx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Fetch logs up to the confirmed height and emit any triggers that are
+    /// now confirmed
+    pub async fn poll(&mut self) -> Result<()> {
+        let head = self.source.fetch_block_number().await?;
+        let confirmed = self
+            .policy
+            .confirmed_height(head, self.finality.finalized());
+        if confirmed >= self.next_block {
+            let to = confirmed.min(self.next_block + MAX_LOG_RANGE - 1);
+            let logs = self
+                .source
+                .fetch_logs(self.pool, self.next_block, to)
+                .await?;
+            for log in &logs {
+                self.observe(log);
+            }
+            self.next_block = to + 1;
+        }
+
+        for trigger in self.release(head) {
+            if let Err(e) = channel::send(&self.trigger_tx, "relay_triggers", trigger).await {
+                warn!(chain_id = self.chain_id, error = %e, "Relay trigger dropped");
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a log as pending, or retract it if the node marked it removed
+    fn observe(&mut self, log: &Log) {
+        let (Some(block_number), Some(block_hash), Some(tx_hash), Some(log_index)) = (
+            log.block_number,
+            log.block_hash,
+            log.transaction_hash,
+            log.log_index,
+        ) else {
+            return;
+        };
+
+        if log.removed == Some(true) {
+            self.pending
+                .retain(|t| !(t.block_hash == block_hash && t.log_index == log_index));
+            return;
+        }
+
+        let Some(event) = PoolEvent::decode(log) else {
+            return;
+        };
+        let already_seen = self
+            .pending
+            .iter()
+            .any(|t| t.block_hash == block_hash && t.log_index == log_index);
+        if !already_seen {
+            debug!(
+                chain_id = self.chain_id,
+                block = block_number.as_u64(),
+                ?event,
+                "Pool event observed"
+            );
+            self.pending.push(RelayTrigger {
+                chain_id: self.chain_id,
+                pool: self.pool,
+                block_number: block_number.as_u64(),
+                block_hash,
+                tx_hash,
+                log_index,
+                event,
+            });
+        }
+    }
+
+    /// Policy gating `event`: deposits always wait for finality
+    fn policy_for(&self, event: &PoolEvent) -> ConfirmationPolicy {
+        match event {
+            PoolEvent::Deposit { .. } => ConfirmationPolicy::Finalized,
+            PoolEvent::Withdrawal { .. } => self.policy,
+        }
+    }
+
+    /// Take pending events whose block is deep enough and still canonical
+    ///
+    /// Events that don't yet satisfy their confirmation policy are deferred.
+    fn release(&mut self, head: u64) -> Vec<RelayTrigger> {
+        let finalized = self.finality.finalized();
+        let mut confirmed = Vec::new();
+        let mut lowest_reorged = None;
+
+        for trigger in std::mem::take(&mut self.pending) {
+            if !self
+                .policy_for(&trigger.event)
+                .is_actionable(trigger.block_number, head, finalized)
+            {
+                self.pending.push(trigger);
+                continue;
+            }
+            match self.finality.canonical_hash(trigger.block_number) {
+                Some(hash) if hash == trigger.block_hash => confirmed.push(trigger),
+                Some(_) => {
+                    info!(
+                        chain_id = self.chain_id,
+                        block = trigger.block_number,
+                        "Dropping pool event from reorged block"
+                    );
+                    lowest_reorged =
+                        Some(lowest_reorged.unwrap_or(u64::MAX).min(trigger.block_number));
+                }
+                None => warn!(
+                    chain_id = self.chain_id,
+                    block = trigger.block_number,
+                    "Dropping pool event whose block header is no longer retained"
+                ),
+            }
+        }
+
+        // Re-scan from the reorged height so the replacement block's logs are picked up
+        if let Some(block) = lowest_reorged {
+            self.next_block = self.next_block.min(block);
+        }
+
+        confirmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::TREE_DEPTH;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    const POOL: Address = H160([0x11; 20]);
+
+    struct StubLogs {
+        head: AtomicU64,
+        logs: Mutex<Vec<Log>>,
+        pool_root: H256,
+    }
+
+    #[async_trait]
+    impl LogSource for StubLogs {
+        async fn fetch_block_number(&self) -> Result<u64> {
+            Ok(self.head.load(Ordering::SeqCst))
+        }
+
+        async fn fetch_logs(&self, _address: Address, from: u64, to: u64) -> Result<Vec<Log>> {
+            Ok(self
+                .logs
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|log| (from..=to).contains(&log.block_number.unwrap().as_u64()))
+                .cloned()
+                .collect())
+        }
+
+        async fn fetch_pool_root(&self, _pool: Address, _block_number: u64) -> Result<H256> {
+            Ok(self.pool_root)
+        }
+    }
+
+    struct StubFinality {
+        finalized: AtomicU64,
+        /// Block numbers whose canonical hash differs from the stub hash
+        reorged: Mutex<Vec<u64>>,
+    }
+
+    impl FinalityView for StubFinality {
+        fn finalized(&self) -> u64 {
+            self.finalized.load(Ordering::SeqCst)
+        }
+
+        fn canonical_hash(&self, block_number: u64) -> Option<H256> {
+            if self.reorged.lock().unwrap().contains(&block_number) {
+                Some(H256::repeat_byte(0xee))
+            } else {
+                Some(block_hash(block_number))
+            }
+        }
+    }
+
+    fn block_hash(block_number: u64) -> H256 {
+        H256::from_low_u64_be(block_number)
+    }
+
+    fn deposit_log(block_number: u64, commitment: H256) -> Log {
+        Log {
+            address: POOL,
+            topics: vec![
+                event_topic("Deposit(bytes32,uint256,uint256)"),
+                commitment,
+                H256::from_low_u64_be(7),
+            ],
+            data: Bytes::from(vec![0u8; 32]),
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash(block_number)),
+            transaction_hash: Some(H256::repeat_byte(block_number as u8)),
+            log_index: Some(U256::zero()),
+            ..Default::default()
+        }
+    }
+
+    fn withdrawal_log(block_number: u64, nullifier: H256) -> Log {
+        Log {
+            topics: vec![
+                event_topic("Withdrawal(bytes32,address,uint256)"),
+                nullifier,
+                H256::from(Address::repeat_byte(0x42)),
+            ],
+            log_index: Some(U256::one()),
+            ..deposit_log(block_number, nullifier)
+        }
+    }
+
+    fn setup(
+        logs: Vec<Log>,
+        head: u64,
+        finalized: u64,
+    ) -> (PoolWatcher, Arc<StubFinality>, mpsc::Receiver<RelayTrigger>) {
+        let source = Arc::new(StubLogs {
+            head: AtomicU64::new(head),
+            logs: Mutex::new(logs),
+            pool_root: H256::zero(),
+        });
+        let finality = Arc::new(StubFinality {
+            finalized: AtomicU64::new(0),
+            reorged: Mutex::new(Vec::new()),
+        });
+        let (trigger_tx, trigger_rx) = mpsc::channel(16);
+        let watcher = PoolWatcher::new(1, POOL, source, finality.clone(), trigger_tx);
+        finality.finalized.store(finalized, Ordering::SeqCst);
+        (watcher, finality, trigger_rx)
+    }
+
+    #[test]
+    fn test_decode_withdrawal() {
+        let recipient = Address::repeat_byte(0x42);
+        let mut amount = [0u8; 32];
+        U256::from(1_000u64).to_big_endian(&mut amount);
+        let log = Log {
+            topics: vec![
+                event_topic("Withdrawal(bytes32,address,uint256)"),
+                H256::repeat_byte(0x01),
+                H256::from(recipient),
+            ],
+            data: Bytes::from(amount.to_vec()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PoolEvent::decode(&log),
+            Some(PoolEvent::Withdrawal {
+                nullifier: H256::repeat_byte(0x01),
+                recipient,
+                amount: U256::from(1_000u64),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_event_triggers_relay() {
+        let commitment = H256::repeat_byte(0xaa);
+        let (mut watcher, _, mut triggers) = setup(vec![deposit_log(10, commitment)], 30, 15);
+
+        watcher.poll().await.unwrap();
+        let trigger = triggers.try_recv().unwrap();
+        assert_eq!(trigger.block_number, 10);
+        assert_eq!(
+            trigger.event,
+            PoolEvent::Deposit {
+                commitment,
+                leaf_index: U256::from(7),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_event_waits_for_finality() {
+        let (mut watcher, finality, mut triggers) =
+            setup(vec![deposit_log(20, H256::repeat_byte(0xbb))], 30, 15);
+
+        watcher.poll().await.unwrap();
+        assert!(triggers.try_recv().is_err());
+
+        finality.finalized.store(20, Ordering::SeqCst);
+        watcher.poll().await.unwrap();
+        assert_eq!(triggers.try_recv().unwrap().block_number, 20);
+    }
+
+    #[tokio::test]
+    async fn test_under_confirmed_event_deferred() {
+        let source = Arc::new(StubLogs {
+            head: AtomicU64::new(22),
+            logs: Mutex::new(vec![
+                withdrawal_log(20, H256::repeat_byte(0xdd)),
+                deposit_log(20, H256::repeat_byte(0xde)),
+            ]),
+            pool_root: H256::zero(),
+        });
+        let finality = Arc::new(StubFinality {
+            finalized: AtomicU64::new(15),
+            reorged: Mutex::new(Vec::new()),
+        });
+        let (trigger_tx, mut triggers) = mpsc::channel(16);
+        let mut watcher = PoolWatcher::new(1, POOL, source.clone(), finality.clone(), trigger_tx)
+            .with_confirmation_policy(ConfirmationPolicy::from_min_confirmations(Some(5)));
+
+        // Three confirmations at head 22: not even fetched yet
+        watcher.poll().await.unwrap();
+        assert!(triggers.try_recv().is_err());
+        assert!(watcher.pending.is_empty());
+        assert_eq!(watcher.next_block, 19);
+
+        // Five confirmations at head 24, still short of finality: the
+        // withdrawal is released, the deposit waits for finality
+        source.head.store(24, Ordering::SeqCst);
+        watcher.poll().await.unwrap();
+        let trigger = triggers.try_recv().unwrap();
+        assert!(matches!(trigger.event, PoolEvent::Withdrawal { .. }));
+        assert!(triggers.try_recv().is_err());
+        assert_eq!(watcher.pending.len(), 1);
+
+        finality.finalized.store(20, Ordering::SeqCst);
+        watcher.poll().await.unwrap();
+        let trigger = triggers.try_recv().unwrap();
+        assert!(matches!(trigger.event, PoolEvent::Deposit { .. }));
+        assert!(watcher.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_logs_fetched_only_up_to_finalized() {
+        let (mut watcher, finality, _triggers) =
+            setup(vec![deposit_log(20, H256::repeat_byte(0xbb))], 30, 15);
+
+        watcher.poll().await.unwrap();
+        assert!(watcher.pending.is_empty());
+        assert_eq!(watcher.next_block, 16);
+
+        finality.finalized.store(18, Ordering::SeqCst);
+        watcher.poll().await.unwrap();
+        assert_eq!(watcher.next_block, 19);
+    }
+
+    #[tokio::test]
+    async fn test_reorged_event_dropped() {
+        let (mut watcher, finality, mut triggers) =
+            setup(vec![deposit_log(20, H256::repeat_byte(0xcc))], 30, 15);
+
+        watcher.poll().await.unwrap();
+        finality.reorged.lock().unwrap().push(20);
+        finality.finalized.store(25, Ordering::SeqCst);
+        watcher.poll().await.unwrap();
+
+        assert!(triggers.try_recv().is_err());
+        assert!(watcher.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_roots_rebuilt_and_checked_against_pool() {
+        let commitments: Vec<H256> = (1..=3).map(H256::from_low_u64_be).collect();
+        let logs: Vec<Log> = commitments
+            .iter()
+            .enumerate()
+            .map(|(index, commitment)| Log {
+                topics: vec![
+                    event_topic("Deposit(bytes32,uint256,uint256)"),
+                    *commitment,
+                    H256::from_low_u64_be(index as u64),
+                ],
+                ..deposit_log(10 + index as u64 * MAX_LOG_RANGE, *commitment)
+            })
+            .collect();
+        // A pool shallower than the default depth
+        let mut tree = IncrementalMerkleTree::new(TREE_DEPTH - 4);
+        for commitment in &commitments {
+            tree.insert(*commitment).unwrap();
+        }
+        let source = StubLogs {
+            head: AtomicU64::new(10_000),
+            logs: Mutex::new(logs),
+            pool_root: tree.root(),
+        };
+
+        // Deposits spread over several log ranges are all replayed
+        let roots = rebuild_roots(&source, POOL, TREE_DEPTH - 4, 0, 9_000, 30)
+            .await
+            .unwrap();
+        assert_eq!(roots.root(), tree.root());
+        assert!(roots.is_known(tree.root()));
+        assert_eq!(roots.roots()[0].block_number, 10 + 2 * MAX_LOG_RANGE);
+
+        // Stopping short of a deposit leaves a root the pool doesn't have
+        let err = rebuild_roots(&source, POOL, TREE_DEPTH - 4, 0, 2_000, 30)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("the pool has"));
+
+        // So does rebuilding at a depth other than the pool's
+        let err = rebuild_roots(&source, POOL, TREE_DEPTH, 0, 9_000, 30)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("the pool has"));
+    }
+}